@@ -0,0 +1,367 @@
+// Drives a raise through propose -> accept -> capital call -> distribution on a
+// provenance localnet using the provenanced cli, then checks the resulting state
+// and balances. Run with:
+//
+//   cargo run --example localnet --features localnet
+//
+// Configure with env vars (defaults suit `make localnet-start` in provenance):
+//   PROVENANCED    path to the provenanced binary
+//   CHAIN_ID       chain id of the localnet
+//   NODE           rpc address of a localnet node
+//   HOME_DIR       provenanced home holding the keys below
+//   GP_KEY         key acting as the general partner
+//   LP_KEY         key acting as the limited partner
+//   CAPITAL_DENOM  denom the raise calls capital in
+//   RAISE_WASM     optimized raise contract wasm
+//   SUB_WASM       optimized subscription contract wasm
+//
+// The lp side of settling exchanges goes through the subscription contract, so
+// this flow stops once the gp has issued them and checks the raise's view.
+
+use std::env;
+use std::process::Command;
+
+use cosmwasm_std::{from_slice, to_vec, Addr};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use marketpalace_raise_contract::msg::{
+    AcceptSubscription, AssetExchange, HandleMsg, InstantiateMsg, IssueAssetExchange, QueryMsg,
+    RaiseState,
+};
+use marketpalace_raise_contract::state::FundMetadata;
+
+const COMMITMENT: u64 = 10_000;
+const DEPOSIT: u64 = 1_000;
+
+struct Localnet {
+    binary: String,
+    chain_id: String,
+    node: String,
+    home: String,
+}
+
+#[derive(Deserialize)]
+struct TxResponse {
+    code: u32,
+    raw_log: String,
+    logs: Vec<TxLog>,
+}
+
+#[derive(Deserialize)]
+struct TxLog {
+    events: Vec<TxEvent>,
+}
+
+#[derive(Deserialize)]
+struct TxEvent {
+    #[serde(rename = "type")]
+    ty: String,
+    attributes: Vec<TxAttribute>,
+}
+
+#[derive(Deserialize)]
+struct TxAttribute {
+    key: String,
+    value: String,
+}
+
+#[derive(Deserialize)]
+struct QueryData<T> {
+    data: T,
+}
+
+#[derive(Deserialize)]
+struct Balances {
+    balances: Vec<Balance>,
+}
+
+#[derive(Deserialize)]
+struct Balance {
+    denom: String,
+    amount: String,
+}
+
+#[derive(Deserialize)]
+struct KeyInfo {
+    address: String,
+}
+
+fn var(name: &str, default: &str) -> String {
+    env::var(name).unwrap_or_else(|_| String::from(default))
+}
+
+impl Localnet {
+    fn from_env() -> Self {
+        Localnet {
+            binary: var("PROVENANCED", "provenanced"),
+            chain_id: var("CHAIN_ID", "chain-local"),
+            node: var("NODE", "tcp://localhost:26657"),
+            home: var("HOME_DIR", "build/node0"),
+        }
+    }
+
+    fn run(&self, args: &[&str]) -> Vec<u8> {
+        let output = Command::new(&self.binary)
+            .args(["-t", "--home", &self.home])
+            .args(args)
+            .args(["--node", &self.node, "--output", "json"])
+            .output()
+            .expect("failed to run provenanced");
+        if !output.status.success() {
+            panic!(
+                "provenanced {} failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+        output.stdout
+    }
+
+    fn address(&self, key: &str) -> Addr {
+        let output = Command::new(&self.binary)
+            .args(["-t", "--home", &self.home, "keys", "show", key])
+            .args(["--keyring-backend", "test", "--output", "json"])
+            .output()
+            .expect("failed to run provenanced");
+        let info: KeyInfo = from_slice(&output.stdout).expect("unexpected key output");
+        Addr::unchecked(info.address)
+    }
+
+    fn tx(&self, from: &str, args: &[&str]) -> TxResponse {
+        let mut full = vec!["tx"];
+        full.extend_from_slice(args);
+        full.extend_from_slice(&[
+            "--from",
+            from,
+            "--keyring-backend",
+            "test",
+            "--chain-id",
+            &self.chain_id,
+            "--gas",
+            "auto",
+            "--gas-prices",
+            "1905nhash",
+            "--gas-adjustment",
+            "2",
+            "--broadcast-mode",
+            "block",
+            "--yes",
+        ]);
+        let response: TxResponse = from_slice(&self.run(&full)).expect("unexpected tx output");
+        if response.code != 0 {
+            panic!("tx {} failed: {}", args.join(" "), response.raw_log);
+        }
+        response
+    }
+
+    fn execute<T: Serialize>(&self, from: &str, contract: &Addr, msg: &T, funds: Option<String>) {
+        let msg = json(msg);
+        let mut args = vec!["wasm", "execute", contract.as_str(), &msg];
+        if let Some(funds) = funds.as_deref() {
+            args.extend_from_slice(&["--amount", funds]);
+        }
+        self.tx(from, &args);
+    }
+
+    fn query<T: DeserializeOwned>(&self, contract: &Addr, msg: &QueryMsg) -> T {
+        let msg = json(msg);
+        let output = self.run(&[
+            "query",
+            "wasm",
+            "contract-state",
+            "smart",
+            contract.as_str(),
+            &msg,
+        ]);
+        let response: QueryData<T> = from_slice(&output).expect("unexpected query output");
+        response.data
+    }
+
+    fn balance(&self, address: &Addr, denom: &str) -> u128 {
+        let output = self.run(&["query", "bank", "balances", address.as_str()]);
+        let balances: Balances = from_slice(&output).expect("unexpected balances output");
+        balances
+            .balances
+            .into_iter()
+            .find(|balance| balance.denom == denom)
+            .map(|balance| balance.amount.parse().expect("invalid amount"))
+            .unwrap_or(0)
+    }
+}
+
+impl TxResponse {
+    fn attribute(&self, ty: &str, key: &str) -> String {
+        self.logs
+            .iter()
+            .flat_map(|log| log.events.iter())
+            .filter(|event| event.ty == ty)
+            .flat_map(|event| event.attributes.iter())
+            .find(|attribute| attribute.key == key)
+            .map(|attribute| attribute.value.clone())
+            .unwrap_or_else(|| panic!("no {} attribute in {} event", key, ty))
+    }
+}
+
+fn json<T: Serialize>(msg: &T) -> String {
+    String::from_utf8(to_vec(msg).expect("failed to serialize msg")).unwrap()
+}
+
+fn exchange(
+    investment: Option<i64>,
+    commitment_in_shares: Option<i64>,
+    capital: Option<i64>,
+) -> AssetExchange {
+    AssetExchange {
+        investment,
+        commitment_in_shares,
+        capital,
+        date: None,
+        required_attestation: None,
+        exchange_id: None,
+        late_fee_bps: None,
+        class: None,
+        provenance: None,
+        price: None,
+        denom: None,
+        late_fee_paid: false,
+    }
+}
+
+fn main() {
+    let localnet = Localnet::from_env();
+    let gp_key = var("GP_KEY", "validator");
+    let lp_key = var("LP_KEY", "lp");
+    let capital_denom = var("CAPITAL_DENOM", "stablecoin");
+    let gp = localnet.address(&gp_key);
+    let lp = localnet.address(&lp_key);
+
+    // store both contracts, the raise instantiates subs from the stored code id
+    let store = |path: String| {
+        localnet
+            .tx(&gp_key, &["wasm", "store", &path])
+            .attribute("store_code", "code_id")
+    };
+    let raise_code_id = store(var(
+        "RAISE_WASM",
+        "artifacts/marketpalace_raise_contract.wasm",
+    ));
+    let sub_code_id = store(var(
+        "SUB_WASM",
+        "artifacts/marketpalace_subscription_contract.wasm",
+    ));
+    println!(
+        "stored raise {} and subscription {}",
+        raise_code_id, sub_code_id
+    );
+
+    let instantiate = InstantiateMsg {
+        subscription_code_id: sub_code_id.parse().expect("invalid code id"),
+        recovery_admin: gp.clone(),
+        required_attestations: vec![],
+        capital_denom: capital_denom.clone(),
+        capital_per_share: 1,
+        required_capital_attribute: None,
+        due_grace_seconds: None,
+        escrow_capital: true,
+        permissionless_eligibility: false,
+        share_classes: vec![],
+        initial_accepted_subscriptions: vec![],
+        metadata: FundMetadata::default(),
+        min_initial_commitment: None,
+        denom_decimals: vec![],
+    };
+    let raise = Addr::unchecked(
+        localnet
+            .tx(
+                &gp_key,
+                &[
+                    "wasm",
+                    "instantiate",
+                    &raise_code_id,
+                    &json(&instantiate),
+                    "--label",
+                    "localnet raise",
+                    "--admin",
+                    gp.as_str(),
+                ],
+            )
+            .attribute("instantiate", "_contract_address"),
+    );
+    println!("instantiated raise at {}", raise);
+
+    // the lp proposes with a deposit the raise holds in escrow until acceptance
+    localnet.execute(
+        &lp_key,
+        &raise,
+        &HandleMsg::ProposeSubscription {
+            initial_commitment: Some(COMMITMENT),
+            owner: None,
+            forward_funds: false,
+        },
+        Some(format!("{}{}", DEPOSIT, capital_denom)),
+    );
+    let subs: Vec<Addr> =
+        localnet.query(&raise, &QueryMsg::GetSubscriptionForLp { lp: lp.clone() });
+    let sub = subs.last().expect("lp has no subscription").clone();
+    println!("lp {} proposed subscription {}", lp, sub);
+
+    // escrowed raises also hold the accepted commitment, attached by the gp
+    localnet.execute(
+        &gp_key,
+        &raise,
+        &HandleMsg::AcceptSubscriptions {
+            subscriptions: vec![AcceptSubscription {
+                subscription: sub.clone(),
+                commitment_in_capital: COMMITMENT,
+            }],
+        },
+        Some(format!("{}{}", COMMITMENT, capital_denom)),
+    );
+    println!("gp accepted {}", sub);
+
+    // a capital call for half the commitment, then a distribution against it
+    let call = exchange(Some(5_000), Some(-5_000), Some(-5_000));
+    let distribution = exchange(Some(-1_000), None, Some(1_000));
+    for issued in [call.clone(), distribution.clone()] {
+        localnet.execute(
+            &gp_key,
+            &raise,
+            &HandleMsg::IssueAssetExchanges {
+                asset_exchanges: vec![IssueAssetExchange {
+                    subscription: sub.clone(),
+                    exchanges: vec![issued],
+                }],
+                notify_subscriptions: true,
+                verify_capital_denom: false,
+            },
+            None,
+        );
+    }
+    println!("gp issued a capital call and a distribution");
+
+    let state: RaiseState = localnet.query(&raise, &QueryMsg::GetState {});
+    assert_eq!(vec![sub.clone()], state.accepted_subscriptions);
+    assert!(state.pending_subscriptions.is_empty());
+
+    let outstanding: Vec<AssetExchange> = localnet.query(
+        &raise,
+        &QueryMsg::GetAssetExchangesForSubscription {
+            subscription: sub.clone(),
+        },
+    );
+    // acceptance leaves the initial commitment outstanding ahead of the issued exchanges
+    let commitment = exchange(None, Some(COMMITMENT as i64), None);
+    assert_eq!(vec![commitment, call, distribution], outstanding);
+
+    // the deposit and accepted commitment stay with the raise until exchanges settle
+    let escrowed = DEPOSIT + COMMITMENT;
+    assert_eq!(
+        u128::from(escrowed),
+        localnet.balance(&raise, &capital_denom)
+    );
+    println!(
+        "raise holds {}{} in escrow for {}",
+        escrowed, capital_denom, sub
+    );
+}
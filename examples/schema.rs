@@ -3,8 +3,19 @@ use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
-use marketpalace_raise_contract::msg::{HandleMsg, InstantiateMsg, MigrateMsg, QueryMsg};
-use marketpalace_raise_contract::state::State;
+use marketpalace_raise_contract::compliance_msg::{ComplianceQueryMsg, EligibilityResponse};
+use marketpalace_raise_contract::msg::{
+    ArchiveCheckpoint, AssetExchange, CapitalAccount, CapitalTransferCheck, Changes,
+    ClaimableItems, Claimables, ClosedSubscription, CompletedAssetExchange, CompletionSimulation,
+    ConsentProposal, DueCapitalCall, HandleMsg, Holdings, InstantiateMsg, MarkerPermissionAudit,
+    MarkerSetup, MigrateMsg, MigrationFailure, PendingOperation, PerformanceFeeAccrual, QueryMsg,
+    RaiseInfo, RaiseState, RaiseTotals, Receipt, SubscriptionAssetExchanges, SubscriptionAudit,
+    SubscriptionCodeId, SubscriptionSettlementWindows, SubscriptionStats, SubscriptionVersion,
+};
+use marketpalace_raise_contract::state::{InstantiateTerms, NetAssetValue, State};
+use marketpalace_raise_contract::sub_msg::{
+    SubExecuteMsg, SubInstantiateMsg, SubQueryMsg, SubState,
+};
 
 fn main() {
     let mut out_dir = current_dir().unwrap();
@@ -17,4 +28,46 @@ fn main() {
     export_schema(&schema_for!(HandleMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
     export_schema(&schema_for!(State), &out_dir);
+
+    // query responses
+    export_schema(&schema_for!(RaiseState), &out_dir);
+    export_schema(&schema_for!(SubscriptionAssetExchanges), &out_dir);
+    export_schema(&schema_for!(AssetExchange), &out_dir);
+    export_schema(&schema_for!(CompletedAssetExchange), &out_dir);
+    export_schema(&schema_for!(Claimables), &out_dir);
+    export_schema(&schema_for!(ClaimableItems), &out_dir);
+    export_schema(&schema_for!(InstantiateTerms), &out_dir);
+    export_schema(&schema_for!(SubscriptionVersion), &out_dir);
+    export_schema(&schema_for!(SubscriptionSettlementWindows), &out_dir);
+    export_schema(&schema_for!(DueCapitalCall), &out_dir);
+    export_schema(&schema_for!(CapitalAccount), &out_dir);
+    export_schema(&schema_for!(ClosedSubscription), &out_dir);
+    export_schema(&schema_for!(CapitalTransferCheck), &out_dir);
+    export_schema(&schema_for!(MigrationFailure), &out_dir);
+    export_schema(&schema_for!(Changes), &out_dir);
+    export_schema(&schema_for!(RaiseTotals), &out_dir);
+    export_schema(&schema_for!(Holdings), &out_dir);
+    export_schema(&schema_for!(RaiseInfo), &out_dir);
+    export_schema(&schema_for!(NetAssetValue), &out_dir);
+    export_schema(&schema_for!(PerformanceFeeAccrual), &out_dir);
+    export_schema(&schema_for!(ConsentProposal), &out_dir);
+    export_schema(&schema_for!(SubscriptionStats), &out_dir);
+    export_schema(&schema_for!(SubscriptionCodeId), &out_dir);
+    export_schema(&schema_for!(CompletionSimulation), &out_dir);
+    export_schema(&schema_for!(Receipt), &out_dir);
+    export_schema(&schema_for!(ArchiveCheckpoint), &out_dir);
+    export_schema(&schema_for!(MarkerPermissionAudit), &out_dir);
+    export_schema(&schema_for!(MarkerSetup), &out_dir);
+    export_schema(&schema_for!(SubscriptionAudit), &out_dir);
+    export_schema(&schema_for!(PendingOperation), &out_dir);
+
+    // subscription contract messages
+    export_schema(&schema_for!(SubInstantiateMsg), &out_dir);
+    export_schema(&schema_for!(SubExecuteMsg), &out_dir);
+    export_schema(&schema_for!(SubQueryMsg), &out_dir);
+    export_schema(&schema_for!(SubState), &out_dir);
+
+    // compliance contract messages
+    export_schema(&schema_for!(ComplianceQueryMsg), &out_dir);
+    export_schema(&schema_for!(EligibilityResponse), &out_dir);
 }
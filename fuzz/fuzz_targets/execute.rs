@@ -0,0 +1,21 @@
+#![no_main]
+
+use cosmwasm_std::testing::{mock_env, mock_info};
+use cosmwasm_std::{coins, from_slice};
+use libfuzzer_sys::fuzz_target;
+use marketpalace_raise_contract::contract::execute;
+use marketpalace_raise_contract::HandleMsg;
+use marketpalace_raise_contract_fuzz::{instantiated_deps, split_sender};
+
+// any well formed message must return an error rather than panic
+fuzz_target!(|data: &[u8]| {
+    let (sender, msg) = match split_sender(data) {
+        Some(split) => split,
+        None => return,
+    };
+    if let Ok(msg) = from_slice::<HandleMsg>(msg) {
+        let mut deps = instantiated_deps();
+        let funds = coins(1_000, "stable_coin");
+        let _ = execute(deps.as_mut(), mock_env(), mock_info(sender, &funds), msg);
+    }
+});
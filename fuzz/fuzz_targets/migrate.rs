@@ -0,0 +1,15 @@
+#![no_main]
+
+use cosmwasm_std::from_slice;
+use cosmwasm_std::testing::mock_env;
+use libfuzzer_sys::fuzz_target;
+use marketpalace_raise_contract::migrate::migrate;
+use marketpalace_raise_contract::MigrateMsg;
+use marketpalace_raise_contract_fuzz::instantiated_deps;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(msg) = from_slice::<MigrateMsg>(data) {
+        let mut deps = instantiated_deps();
+        let _ = migrate(deps.as_mut(), mock_env(), msg);
+    }
+});
@@ -0,0 +1,15 @@
+#![no_main]
+
+use cosmwasm_std::from_slice;
+use cosmwasm_std::testing::mock_env;
+use libfuzzer_sys::fuzz_target;
+use marketpalace_raise_contract::query::query;
+use marketpalace_raise_contract::QueryMsg;
+use marketpalace_raise_contract_fuzz::instantiated_deps;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(msg) = from_slice::<QueryMsg>(data) {
+        let deps = instantiated_deps();
+        let _ = query(deps.as_ref(), mock_env(), msg);
+    }
+});
@@ -0,0 +1,46 @@
+use cosmwasm_std::testing::{mock_env, mock_info, MockApi, MockStorage};
+use cosmwasm_std::{Addr, OwnedDeps};
+use marketpalace_raise_contract::instantiate::instantiate;
+use marketpalace_raise_contract::state::FundMetadata;
+use marketpalace_raise_contract::InstantiateMsg;
+use provwasm_mocks::{mock_dependencies, ProvenanceMockQuerier};
+use provwasm_std::ProvenanceQuery;
+
+pub type FuzzDeps = OwnedDeps<MockStorage, MockApi, ProvenanceMockQuerier, ProvenanceQuery>;
+
+// the parties a fuzzed message may be sent from
+pub const SENDERS: [&str; 4] = ["gp", "marketpalace", "sub_1", "lp"];
+
+// a freshly instantiated raise for each input, so runs never depend on each other
+pub fn instantiated_deps() -> FuzzDeps {
+    let mut deps = mock_dependencies(&[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("gp", &[]),
+        InstantiateMsg {
+            subscription_code_id: 1,
+            recovery_admin: Addr::unchecked("marketpalace"),
+            required_attestations: vec![],
+            capital_denom: String::from("stable_coin"),
+            capital_per_share: 100,
+            required_capital_attribute: None,
+            due_grace_seconds: None,
+            escrow_capital: false,
+            permissionless_eligibility: false,
+            share_classes: vec![],
+            initial_accepted_subscriptions: vec![],
+            metadata: FundMetadata::default(),
+            min_initial_commitment: None,
+            denom_decimals: vec![],
+        },
+    )
+    .expect("fuzz raise failed to instantiate");
+    deps
+}
+
+// the first byte picks the sender, the rest is the json message
+pub fn split_sender(data: &[u8]) -> Option<(&'static str, &[u8])> {
+    let (first, msg) = data.split_first()?;
+    Some((SENDERS[usize::from(*first) % SENDERS.len()], msg))
+}
@@ -0,0 +1,819 @@
+use cosmwasm_std::{Addr, DepsMut, Env, MessageInfo, Response};
+use provwasm_std::ProvenanceQuery;
+
+use crate::consent::has_consent;
+use crate::contract::{check_marker_setup, dispatch, ContractResponse};
+use crate::error::ContractError;
+use crate::msg::{ChangedEntity, HandleMsg, PendingOperation};
+use crate::state::{
+    config, config_read, operation_sequence, pending_operation_storage, record_change,
+};
+use crate::state::{management_fee_change, ManagementFee, ManagementFeeChange};
+use crate::state::{term_extension, TermExtension};
+
+// capital a message would move, if it is subject to committee approval
+pub fn operation_capital(msg: &HandleMsg) -> Option<u64> {
+    match msg {
+        HandleMsg::IssueWithdrawal { amount, .. } => Some(*amount),
+        HandleMsg::IssueAssetExchanges {
+            asset_exchanges, ..
+        } => Some(
+            asset_exchanges
+                .iter()
+                .flat_map(|issuance| issuance.exchanges.iter())
+                .map(|exchange| exchange.capital.unwrap_or_default().unsigned_abs())
+                .fold(0u64, u64::saturating_add),
+        ),
+        _ => None,
+    }
+}
+
+pub fn try_update_gp_committee(
+    deps: DepsMut<ProvenanceQuery>,
+    info: MessageInfo,
+    gp_committee: Vec<Addr>,
+    approval_threshold: u32,
+    approval_capital_threshold: Option<u64>,
+) -> ContractResponse {
+    let mut state = config(deps.storage).load()?;
+
+    // the gp can't loosen the controls placed on itself
+    if info.sender != state.recovery_admin {
+        return Err(ContractError::Unauthorized {
+            required: "admin",
+            action: "update gp committee",
+        });
+    }
+
+    if approval_threshold as usize > gp_committee.len() {
+        return Err(ContractError::InvalidRequest {
+            reason: "approval threshold exceeds committee size",
+        });
+    }
+
+    state.gp_committee = gp_committee;
+    state.approval_threshold = approval_threshold;
+    state.approval_capital_threshold = approval_capital_threshold;
+    config(deps.storage).save(&state)?;
+    record_change(deps.storage, ChangedEntity::State, "config")?;
+
+    Ok(Response::default())
+}
+
+// the gp sets the initial term, but extending it takes either the admin's agreement
+// or lp consent to the same extension
+pub fn try_extend_fund_term(
+    deps: DepsMut<ProvenanceQuery>,
+    info: MessageInfo,
+    term_end: u64,
+) -> ContractResponse {
+    let mut state = config(deps.storage).load()?;
+
+    if info.sender != state.gp && info.sender != state.recovery_admin {
+        return Err(ContractError::Unauthorized {
+            required: "gp or admin",
+            action: "extend fund term",
+        });
+    }
+
+    match state.term_end {
+        None if info.sender == state.gp => {}
+        None => {
+            return Err(ContractError::InvalidRequest {
+                reason: "the gp sets the initial fund term",
+            })
+        }
+        Some(current) if term_end <= current => {
+            return Err(ContractError::InvalidRequest {
+                reason: "fund term can only be extended",
+            })
+        }
+        Some(_) => {
+            let consented = info.sender == state.gp
+                && has_consent(deps.storage, &HandleMsg::ExtendFundTerm { term_end })?;
+            if !consented {
+                let mut extension = term_extension(deps.storage)
+                    .may_load()?
+                    .filter(|extension| extension.term_end == term_end)
+                    .unwrap_or(TermExtension {
+                        term_end,
+                        approvals: vec![],
+                    });
+                if !extension.approvals.contains(&info.sender) {
+                    extension.approvals.push(info.sender);
+                }
+                if !(extension.approvals.contains(&state.gp)
+                    && extension.approvals.contains(&state.recovery_admin))
+                {
+                    term_extension(deps.storage).save(&extension)?;
+                    return Ok(
+                        Response::new().add_attribute("term_extension", term_end.to_string())
+                    );
+                }
+            }
+            term_extension(deps.storage).remove();
+        }
+    }
+
+    state.term_end = Some(term_end);
+    config(deps.storage).save(&state)?;
+    record_change(deps.storage, ChangedEntity::State, "config")?;
+
+    Ok(Response::new().add_attribute("term_end", term_end.to_string()))
+}
+
+// the gp's fee change takes effect once the admin approves the same change
+pub fn try_update_management_fee(
+    deps: DepsMut<ProvenanceQuery>,
+    info: MessageInfo,
+    management_fee: Option<ManagementFee>,
+) -> ContractResponse {
+    let mut state = config(deps.storage).load()?;
+
+    if info.sender != state.gp && info.sender != state.recovery_admin {
+        return Err(ContractError::Unauthorized {
+            required: "gp or admin",
+            action: "update management fee",
+        });
+    }
+    if management_fee
+        .as_ref()
+        .is_some_and(|fee| fee.fee_bps > 10_000)
+    {
+        return Err(ContractError::InvalidRequest {
+            reason: "management fee can't exceed 10000 bps",
+        });
+    }
+
+    let mut change = management_fee_change(deps.storage)
+        .may_load()?
+        .filter(|change| change.management_fee == management_fee)
+        .unwrap_or(ManagementFeeChange {
+            management_fee,
+            approvals: vec![],
+        });
+    if !change.approvals.contains(&info.sender) {
+        change.approvals.push(info.sender);
+    }
+    if !(change.approvals.contains(&state.gp) && change.approvals.contains(&state.recovery_admin)) {
+        management_fee_change(deps.storage).save(&change)?;
+        return Ok(Response::new().add_attribute("management_fee_change", "pending"));
+    }
+    management_fee_change(deps.storage).remove();
+
+    state.management_fee = change.management_fee;
+    config(deps.storage).save(&state)?;
+    record_change(deps.storage, ChangedEntity::State, "config")?;
+
+    Ok(Response::default())
+}
+
+pub fn try_propose_operation(
+    deps: DepsMut<ProvenanceQuery>,
+    env: Env,
+    info: MessageInfo,
+    operation: HandleMsg,
+) -> ContractResponse {
+    let state = config_read(deps.storage).load()?;
+
+    if info.sender != state.gp && !state.gp_committee.contains(&info.sender) {
+        return Err(ContractError::Unauthorized {
+            required: "gp or committee member",
+            action: "propose high value operations",
+        });
+    }
+    if !info.funds.is_empty() {
+        return Err(ContractError::InvalidRequest {
+            reason: "funds cannot be attached to a proposed operation",
+        });
+    }
+
+    let id = operation_sequence(deps.storage)
+        .may_load()?
+        .unwrap_or_default()
+        + 1;
+    operation_sequence(deps.storage).save(&id)?;
+
+    let approvals = if state.gp_committee.contains(&info.sender) {
+        vec![info.sender.clone()]
+    } else {
+        vec![]
+    };
+    let pending = PendingOperation {
+        id,
+        operation,
+        proposed_by: info.sender,
+        approvals,
+    };
+
+    let response = execute_if_approved(deps, env, pending)?;
+    Ok(response.add_attribute("operation", id.to_string()))
+}
+
+pub fn try_approve_operation(
+    deps: DepsMut<ProvenanceQuery>,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+) -> ContractResponse {
+    let state = config_read(deps.storage).load()?;
+
+    if !state.gp_committee.contains(&info.sender) {
+        return Err(ContractError::Unauthorized {
+            required: "committee member",
+            action: "approve operations",
+        });
+    }
+
+    let mut pending = pending_operation_storage(deps.storage)
+        .may_load(&id.to_be_bytes())?
+        .ok_or(ContractError::InvalidRequest {
+            reason: "no pending operation found",
+        })?;
+    if pending.approvals.contains(&info.sender) {
+        return Err(ContractError::InvalidRequest {
+            reason: "operation already approved by sender",
+        });
+    }
+    pending.approvals.push(info.sender);
+
+    execute_if_approved(deps, env, pending)
+}
+
+// runs the operation as the gp once enough distinct members have approved it, approvals
+// from anyone since removed from the committee no longer count
+fn execute_if_approved(
+    mut deps: DepsMut<ProvenanceQuery>,
+    env: Env,
+    mut pending: PendingOperation,
+) -> ContractResponse {
+    let state = config_read(deps.storage).load()?;
+    pending
+        .approvals
+        .retain(|approval| state.gp_committee.contains(approval));
+    let key = pending.id.to_be_bytes();
+    record_change(
+        deps.storage,
+        ChangedEntity::PendingOperation,
+        &pending.id.to_string(),
+    )?;
+
+    if pending.approvals.len() < state.approval_threshold as usize {
+        let approvals = pending.approvals.len();
+        pending_operation_storage(deps.storage).save(&key, &pending)?;
+        return Ok(Response::new().add_attribute("approvals", approvals.to_string()));
+    }
+
+    check_marker_setup(deps.storage, &pending.operation)?;
+    pending_operation_storage(deps.storage).remove(&key);
+    let response = dispatch(
+        deps.branch(),
+        env,
+        MessageInfo {
+            sender: state.gp,
+            funds: vec![],
+        },
+        pending.operation,
+    )?;
+
+    Ok(response.add_attribute("approved", pending.id.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::execute;
+    use crate::contract::tests::default_deps;
+    use crate::mock::load_markers;
+    use crate::mock::{msg_at_index, send_args};
+    use crate::msg::{AssetExchange, IssueAssetExchange, MarkerSetup};
+    use crate::state::tests::set_accepted;
+    use crate::state::{asset_exchange_storage_read, marker_setup, save_asset_exchanges};
+    use crate::state::{pending_operation_storage_read, subscription_lp_storage, State};
+    use cosmwasm_std::coins;
+    use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
+
+    fn committee(state: &mut State) {
+        state.gp_committee = vec![Addr::unchecked("member_1"), Addr::unchecked("member_2")];
+        state.approval_threshold = 2;
+        state.approval_capital_threshold = Some(5_000);
+    }
+
+    fn withdrawal(amount: u64) -> HandleMsg {
+        HandleMsg::IssueWithdrawal {
+            to: Addr::unchecked("omni"),
+            amount,
+            memo: None,
+            denom: None,
+        }
+    }
+
+    #[test]
+    fn approve_high_value_withdrawal() {
+        let mut deps = default_deps(Some(committee));
+        deps.querier
+            .base
+            .update_balance(MOCK_CONTRACT_ADDR, coins(20_000, "stable_coin"));
+
+        // verify withdrawals under the threshold execute immediately
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            withdrawal(5_000),
+        )
+        .unwrap();
+        assert_eq!(1, res.messages.len());
+
+        // verify a high value withdrawal is held for approval
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            withdrawal(10_000),
+        )
+        .unwrap();
+        assert_eq!(0, res.messages.len());
+        let pending = pending_operation_storage_read(&deps.storage)
+            .load(&1u64.to_be_bytes())
+            .unwrap();
+        assert_eq!(withdrawal(10_000), pending.operation);
+        assert!(pending.approvals.is_empty());
+
+        let approve = HandleMsg::ApproveOperation { id: 1 };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("member_1", &[]),
+            approve.clone(),
+        )
+        .unwrap();
+        assert_eq!(0, res.messages.len());
+
+        // verify a member can't approve twice and outsiders can't approve
+        assert!(execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("member_1", &[]),
+            approve.clone(),
+        )
+        .is_err());
+        assert!(execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            approve.clone(),
+        )
+        .is_err());
+
+        // verify the withdrawal is sent once the threshold is met
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("member_2", &[]),
+            approve,
+        )
+        .unwrap();
+        let (to_address, coins) = send_args(msg_at_index(&res, 0));
+        assert_eq!("omni", to_address);
+        assert_eq!(10_000, coins.first().unwrap().amount.u128());
+        assert!(pending_operation_storage_read(&deps.storage)
+            .may_load(&1u64.to_be_bytes())
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn approvals_count_toward_threshold() {
+        let mut deps = default_deps(Some(|state: &mut State| {
+            committee(state);
+            state.gp_committee.push(Addr::unchecked("member_3"));
+            state.approval_threshold = 3;
+        }));
+        deps.querier
+            .base
+            .update_balance(MOCK_CONTRACT_ADDR, coins(20_000, "stable_coin"));
+
+        // verify a proposing member counts as the first approval
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("member_1", &[]),
+            withdrawal(10_000),
+        )
+        .unwrap();
+        assert_eq!(0, res.messages.len());
+        assert_eq!(
+            Some("1"),
+            res.attributes
+                .iter()
+                .find(|attr| attr.key == "approvals")
+                .map(|attr| attr.value.as_str())
+        );
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("member_2", &[]),
+            HandleMsg::ApproveOperation { id: 1 },
+        )
+        .unwrap();
+        assert_eq!(0, res.messages.len());
+        assert_eq!("approvals", res.attributes[0].key);
+        assert_eq!("2", res.attributes[0].value);
+
+        // verify the operation runs on the last approval needed
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("member_3", &[]),
+            HandleMsg::ApproveOperation { id: 1 },
+        )
+        .unwrap();
+        assert_eq!(1, res.messages.len());
+        assert!(res
+            .attributes
+            .iter()
+            .any(|attr| attr.key == "approved" && attr.value == "1"));
+    }
+
+    #[test]
+    fn duplicate_approval_is_not_counted() {
+        let mut deps = default_deps(Some(committee));
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("member_1", &[]),
+            withdrawal(10_000),
+        )
+        .unwrap();
+
+        assert_eq!(
+            ContractError::InvalidRequest {
+                reason: "operation already approved by sender",
+            },
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("member_1", &[]),
+                HandleMsg::ApproveOperation { id: 1 },
+            )
+            .unwrap_err()
+        );
+
+        // verify the rejected approval left the operation pending with one approval
+        let pending = pending_operation_storage_read(&deps.storage)
+            .load(&1u64.to_be_bytes())
+            .unwrap();
+        assert_eq!(vec![Addr::unchecked("member_1")], pending.approvals);
+    }
+
+    #[test]
+    fn removed_member_approval_is_not_counted() {
+        let mut deps = default_deps(Some(committee));
+        deps.querier
+            .base
+            .update_balance(MOCK_CONTRACT_ADDR, coins(20_000, "stable_coin"));
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("member_1", &[]),
+            withdrawal(10_000),
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("marketpalace", &[]),
+            HandleMsg::UpdateGpCommittee {
+                gp_committee: vec![Addr::unchecked("member_2"), Addr::unchecked("member_3")],
+                approval_threshold: 2,
+                approval_capital_threshold: Some(5_000),
+            },
+        )
+        .unwrap();
+
+        // verify the removed member's approval is dropped rather than meeting the threshold
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("member_2", &[]),
+            HandleMsg::ApproveOperation { id: 1 },
+        )
+        .unwrap();
+        assert_eq!(0, res.messages.len());
+        let pending = pending_operation_storage_read(&deps.storage)
+            .load(&1u64.to_be_bytes())
+            .unwrap();
+        assert_eq!(vec![Addr::unchecked("member_2")], pending.approvals);
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("member_3", &[]),
+            HandleMsg::ApproveOperation { id: 1 },
+        )
+        .unwrap();
+        assert_eq!(1, res.messages.len());
+    }
+
+    #[test]
+    fn approved_operation_waits_for_marker_setup() {
+        let mut deps = default_deps(Some(committee));
+        deps.querier
+            .base
+            .update_balance(MOCK_CONTRACT_ADDR, coins(20_000, "stable_coin"));
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("member_1", &[]),
+            withdrawal(10_000),
+        )
+        .unwrap();
+
+        // verify the approved operation still can't move assets past an unfinished marker
+        marker_setup(&mut deps.storage)
+            .save(&vec![MarkerSetup::new(String::from("investment_coin"))])
+            .unwrap();
+        assert_eq!(
+            ContractError::MarkerSetupIncomplete {
+                denom: String::from("investment_coin"),
+            },
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("member_2", &[]),
+                HandleMsg::ApproveOperation { id: 1 },
+            )
+            .unwrap_err()
+        );
+        assert!(pending_operation_storage_read(&deps.storage)
+            .may_load(&1u64.to_be_bytes())
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn issuance_under_capital_threshold_passes_through() {
+        let mut deps = default_deps(Some(committee));
+        load_markers(&mut deps.querier);
+        set_accepted(&mut deps.storage, vec!["sub_1"]);
+        save_asset_exchanges(
+            &mut deps.storage,
+            &Addr::unchecked("sub_1"),
+            &[AssetExchange {
+                commitment_in_shares: Some(5_000),
+                ..AssetExchange::default()
+            }],
+        )
+        .unwrap();
+        let capital_call = |capital: i64| AssetExchange {
+            investment: Some(-capital),
+            commitment_in_shares: Some(capital),
+            capital: Some(capital),
+            ..AssetExchange::default()
+        };
+
+        // verify capital summed across the batch at the threshold executes immediately
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::IssueAssetExchanges {
+                notify_subscriptions: false,
+                verify_capital_denom: false,
+                asset_exchanges: vec![IssueAssetExchange {
+                    subscription: Addr::unchecked("sub_1"),
+                    exchanges: vec![capital_call(-2_000), capital_call(-3_000)],
+                }],
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            3,
+            asset_exchange_storage_read(&deps.storage)
+                .load(b"sub_1")
+                .unwrap()
+                .len()
+        );
+        assert!(pending_operation_storage_read(&deps.storage)
+            .may_load(&1u64.to_be_bytes())
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn overflowing_batch_requires_approval() {
+        let mut deps = default_deps(Some(committee));
+        let exchange = AssetExchange {
+            capital: Some(i64::MAX),
+            ..AssetExchange::default()
+        };
+        let issue = HandleMsg::IssueAssetExchanges {
+            notify_subscriptions: false,
+            verify_capital_denom: false,
+            asset_exchanges: vec![IssueAssetExchange {
+                subscription: Addr::unchecked("sub_1"),
+                exchanges: vec![exchange.clone(), exchange.clone(), exchange],
+            }],
+        };
+
+        // verify capital that overflows the sum saturates instead of slipping under the threshold
+        assert_eq!(Some(u64::MAX), operation_capital(&issue));
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            issue.clone(),
+        )
+        .unwrap();
+        assert_eq!(0, res.messages.len());
+        assert_eq!(
+            issue,
+            pending_operation_storage_read(&deps.storage)
+                .load(&1u64.to_be_bytes())
+                .unwrap()
+                .operation
+        );
+    }
+
+    #[test]
+    fn capital_threshold_bypass() {
+        // verify without a capital threshold nothing waits for approval
+        let mut deps = default_deps(Some(|state: &mut State| {
+            committee(state);
+            state.approval_capital_threshold = None;
+        }));
+        deps.querier
+            .base
+            .update_balance(MOCK_CONTRACT_ADDR, coins(20_000, "stable_coin"));
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            withdrawal(10_000),
+        )
+        .unwrap();
+        assert_eq!(1, res.messages.len());
+
+        // verify a zero approval threshold also bypasses the committee
+        let mut deps = default_deps(Some(|state: &mut State| {
+            committee(state);
+            state.approval_threshold = 0;
+        }));
+        deps.querier
+            .base
+            .update_balance(MOCK_CONTRACT_ADDR, coins(20_000, "stable_coin"));
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            withdrawal(10_000),
+        )
+        .unwrap();
+        assert_eq!(1, res.messages.len());
+        assert!(pending_operation_storage_read(&deps.storage)
+            .may_load(&1u64.to_be_bytes())
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn update_gp_committee() {
+        let mut deps = default_deps(None);
+        let update = |approval_threshold| HandleMsg::UpdateGpCommittee {
+            gp_committee: vec![Addr::unchecked("member_1")],
+            approval_threshold,
+            approval_capital_threshold: Some(1_000),
+        };
+
+        // verify the gp can't change its own controls
+        assert!(execute(deps.as_mut(), mock_env(), mock_info("gp", &[]), update(1)).is_err());
+
+        // verify the threshold can't exceed the committee
+        assert!(execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("marketpalace", &[]),
+            update(2)
+        )
+        .is_err());
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("marketpalace", &[]),
+            update(1),
+        )
+        .unwrap();
+        let state = config_read(&deps.storage).load().unwrap();
+        assert!(state.requires_approval(1_001));
+        assert!(!state.requires_approval(1_000));
+    }
+
+    #[test]
+    fn extend_fund_term_with_admin() {
+        let mut deps = default_deps(None);
+        let extend = |term_end| HandleMsg::ExtendFundTerm { term_end };
+
+        // verify the gp sets the initial term alone
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            extend(1_000),
+        )
+        .unwrap();
+        assert_eq!(
+            Some(1_000),
+            config_read(&deps.storage).load().unwrap().term_end
+        );
+
+        // verify an extension waits for the admin to agree to the same term
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            extend(2_000),
+        )
+        .unwrap();
+        assert_eq!(
+            Some(1_000),
+            config_read(&deps.storage).load().unwrap().term_end
+        );
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("marketpalace", &[]),
+            extend(2_000),
+        )
+        .unwrap();
+        assert_eq!(
+            Some(2_000),
+            config_read(&deps.storage).load().unwrap().term_end
+        );
+
+        // verify the term can't be shortened
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("marketpalace", &[]),
+            extend(1_500),
+        );
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn extend_fund_term_with_consent() {
+        let mut deps = default_deps(Some(|state| {
+            state.term_end = Some(1_000);
+        }));
+        load_markers(&mut deps.querier);
+        set_accepted(&mut deps.storage, vec!["sub_1"]);
+        subscription_lp_storage(&mut deps.storage)
+            .save(b"sub_1", &Addr::unchecked("lp"))
+            .unwrap();
+        deps.querier
+            .base
+            .update_balance(Addr::unchecked("sub_1"), coins(420, "commitment_coin"));
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::OpenConsentProposal {
+                amendment_hash: String::from("extension"),
+                voting_seconds: 60,
+                threshold_bps: 5_000,
+                change: Some(Box::new(HandleMsg::ExtendFundTerm { term_end: 2_000 })),
+            },
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("lp", &[]),
+            HandleMsg::VoteConsent {
+                id: 1,
+                subscription: Addr::unchecked("sub_1"),
+                approve: true,
+            },
+        )
+        .unwrap();
+
+        // verify lp consent extends the term without the admin
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(60);
+        execute(
+            deps.as_mut(),
+            env,
+            mock_info("anyone", &[]),
+            HandleMsg::CloseConsentProposal { id: 1 },
+        )
+        .unwrap();
+        assert_eq!(
+            Some(2_000),
+            config_read(&deps.storage).load().unwrap().term_end
+        );
+    }
+}
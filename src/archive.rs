@@ -0,0 +1,199 @@
+use cosmwasm_std::{to_binary, Addr, DepsMut, Env, MessageInfo, Order, Response, StdResult};
+use provwasm_std::ProvenanceQuery;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::contract::ContractResponse;
+use crate::error::ContractError;
+use crate::msg::{ArchiveCheckpoint, Change, Receipt};
+use crate::state::{
+    change_log, change_log_checkpoint, change_revision_read, config_read,
+    receipt_checkpoint_storage, receipt_storage, receipt_storage_read, MAX_ARCHIVE_BATCH,
+};
+
+pub fn try_archive_history(
+    deps: DepsMut<ProvenanceQuery>,
+    env: Env,
+    info: MessageInfo,
+    subscriptions: Vec<Addr>,
+) -> ContractResponse {
+    let state = config_read(deps.storage).load()?;
+
+    if info.sender != state.recovery_admin {
+        return Err(ContractError::Unauthorized {
+            required: "admin",
+            action: "archive history",
+        });
+    }
+
+    let mut response = Response::new();
+
+    if let Some(retention) = state.change_log_retention {
+        let revision = change_revision_read(deps.storage)
+            .may_load()?
+            .unwrap_or_default();
+        let oldest_kept = revision.saturating_sub(retention).saturating_add(1);
+        let archived: Vec<(Vec<u8>, Change)> = change_log(deps.storage)
+            .range(None, Some(&oldest_kept.to_be_bytes()), Order::Ascending)
+            .take(MAX_ARCHIVE_BATCH)
+            .collect::<StdResult<_>>()?;
+
+        if let Some((_, last)) = archived.last() {
+            let mut checkpoint = change_log_checkpoint(deps.storage)
+                .may_load()?
+                .unwrap_or_default();
+            let changes: Vec<&Change> = archived.iter().map(|(_, change)| change).collect();
+            advance_checkpoint(&mut checkpoint, &changes, last.revision)?;
+            for (key, _) in &archived {
+                change_log(deps.storage).remove(key);
+            }
+            change_log_checkpoint(deps.storage).save(&checkpoint)?;
+            response = response.add_attribute("change_log_checkpoint", &checkpoint.hash);
+        }
+    }
+
+    if let Some(retention) = state.receipt_retention_seconds {
+        let cutoff = env.block.time.seconds().saturating_sub(retention);
+        for subscription in subscriptions {
+            let archived: Vec<(Vec<u8>, Receipt)> =
+                receipt_storage_read(deps.storage, &subscription)
+                    .range(None, None, Order::Ascending)
+                    .take(MAX_ARCHIVE_BATCH)
+                    .take_while(|item| {
+                        item.as_ref()
+                            .map_or(true, |(_, receipt)| receipt.time < cutoff)
+                    })
+                    .collect::<StdResult<_>>()?;
+
+            if let Some((_, last)) = archived.last() {
+                let mut checkpoint = receipt_checkpoint_storage(deps.storage)
+                    .may_load(subscription.as_bytes())?
+                    .unwrap_or_default();
+                let receipts: Vec<&Receipt> = archived.iter().map(|(_, receipt)| receipt).collect();
+                advance_checkpoint(&mut checkpoint, &receipts, last.sequence)?;
+                for (key, _) in &archived {
+                    receipt_storage(deps.storage, &subscription).remove(key);
+                }
+                receipt_checkpoint_storage(deps.storage)
+                    .save(subscription.as_bytes(), &checkpoint)?;
+                response = response.add_attribute(
+                    format!("receipt_checkpoint_{}", subscription),
+                    &checkpoint.hash,
+                );
+            }
+        }
+    }
+
+    Ok(response)
+}
+
+// chain the archived entries onto the previous checkpoint hash
+fn advance_checkpoint<T: Serialize>(
+    checkpoint: &mut ArchiveCheckpoint,
+    entries: &[T],
+    through: u64,
+) -> StdResult<()> {
+    let mut hasher = Sha256::new();
+    hasher.update(checkpoint.hash.as_bytes());
+    for entry in entries {
+        hasher.update(to_binary(entry)?.as_slice());
+    }
+    checkpoint.hash = format!("{:x}", hasher.finalize());
+    checkpoint.archived += entries.len() as u64;
+    checkpoint.through = through;
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::contract::execute;
+    use crate::contract::tests::default_deps;
+    use crate::msg::{ChangedEntity, HandleMsg};
+    use crate::state::{change_log_checkpoint_read, change_log_read, record_change};
+    use cosmwasm_std::testing::{mock_env, mock_info};
+
+    #[test]
+    fn archive_history() {
+        let mut deps = default_deps(Some(|state| {
+            state.change_log_retention = Some(1);
+            state.receipt_retention_seconds = Some(3_600);
+        }));
+        let subscription = Addr::unchecked("sub_1");
+        for key in ["sub_1", "sub_2", "sub_3"] {
+            record_change(&mut deps.storage, ChangedEntity::Subscription, key).unwrap();
+        }
+        let now = mock_env().block.time.seconds();
+        for (sequence, time) in [(1u64, now - 7_200), (2, now)] {
+            receipt_storage(&mut deps.storage, &subscription)
+                .save(
+                    &sequence.to_be_bytes(),
+                    &Receipt {
+                        sequence,
+                        exchange_id: None,
+                        height: 12_345,
+                        time,
+                        tx_index: None,
+                        investment: 0,
+                        commitment_in_shares: 0,
+                        capital: 100,
+                        destination: subscription.clone(),
+                    },
+                )
+                .unwrap();
+        }
+
+        // only the recovery admin can archive
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::ArchiveHistory {
+                subscriptions: vec![subscription.clone()],
+            },
+        );
+        assert!(res.is_err());
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("marketpalace", &[]),
+            HandleMsg::ArchiveHistory {
+                subscriptions: vec![subscription.clone()],
+            },
+        )
+        .unwrap();
+
+        // verify only the retained revision is left and checkpoint is emitted
+        let changes = change_log_read(&deps.storage)
+            .range(None, None, Order::Ascending)
+            .map(|item| item.unwrap().1.revision)
+            .collect::<Vec<u64>>();
+        assert_eq!(vec![3], changes);
+        let checkpoint = change_log_checkpoint_read(&deps.storage).load().unwrap();
+        assert_eq!(2, checkpoint.archived);
+        assert_eq!(2, checkpoint.through);
+        assert_eq!(
+            checkpoint.hash,
+            res.attributes
+                .iter()
+                .find(|attr| attr.key == "change_log_checkpoint")
+                .unwrap()
+                .value
+        );
+
+        // verify receipts older than the retention period are archived
+        let receipts = receipt_storage_read(&deps.storage, &subscription)
+            .range(None, None, Order::Ascending)
+            .map(|item| item.unwrap().1.sequence)
+            .collect::<Vec<u64>>();
+        assert_eq!(vec![2], receipts);
+        assert_eq!(
+            1,
+            receipt_checkpoint_storage(&mut deps.storage)
+                .load(subscription.as_bytes())
+                .unwrap()
+                .through
+        );
+    }
+}
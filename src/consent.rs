@@ -0,0 +1,318 @@
+use cosmwasm_std::Uint128;
+use cosmwasm_std::{Addr, DepsMut, Env, MessageInfo, Order, Response, StdResult, Storage};
+use provwasm_std::ProvenanceQuery;
+
+use crate::approval::operation_capital;
+use crate::contract::{dispatch, ContractResponse};
+use crate::error::ContractError;
+use crate::msg::{ChangedEntity, ConsentProposal, ConsentResult, ConsentVote, HandleMsg};
+use crate::ownership::{position, total_shares};
+use crate::state::subscription_lp_storage_read;
+use crate::state::{config_read, consent_sequence, record_change};
+use crate::state::{consent_proposal_storage, consent_proposal_storage_read};
+use crate::state::{consent_vote_storage, has_subscription_status, SubscriptionStatus};
+use crate::subscribe::lp_for_sub;
+
+pub fn try_open_consent_proposal(
+    deps: DepsMut<ProvenanceQuery>,
+    env: Env,
+    info: MessageInfo,
+    amendment_hash: String,
+    voting_seconds: u64,
+    threshold_bps: u16,
+    change: Option<Box<HandleMsg>>,
+) -> ContractResponse {
+    let state = config_read(deps.storage).load()?;
+
+    if info.sender != state.gp {
+        return Err(ContractError::Unauthorized {
+            required: "gp",
+            action: "open consent proposals",
+        });
+    }
+    if threshold_bps == 0 || threshold_bps > 10_000 {
+        return Err(ContractError::InvalidRequest {
+            reason: "consent threshold must be between 1 and 10000 bps",
+        });
+    }
+    // capital movements stay behind committee approval
+    if change
+        .as_ref()
+        .is_some_and(|change| operation_capital(change).is_some())
+    {
+        return Err(ContractError::InvalidRequest {
+            reason: "consent can't gate operations that move capital",
+        });
+    }
+
+    let id = consent_sequence(deps.storage)
+        .may_load()?
+        .unwrap_or_default()
+        + 1;
+    consent_sequence(deps.storage).save(&id)?;
+
+    consent_proposal_storage(deps.storage).save(
+        &id.to_be_bytes(),
+        &ConsentProposal {
+            id,
+            amendment_hash,
+            closes_at: env.block.time.seconds().saturating_add(voting_seconds),
+            threshold_bps,
+            change: change.map(|change| *change),
+            shares_for: Uint128::zero(),
+            shares_against: Uint128::zero(),
+            result: None,
+        },
+    )?;
+    record_change(
+        deps.storage,
+        ChangedEntity::ConsentProposal,
+        &id.to_string(),
+    )?;
+
+    Ok(Response::new().add_attribute("consent_proposal", id.to_string()))
+}
+
+// an accepted sub's lp votes with the sub's commitment and investment at the time of the vote
+pub fn try_vote_consent(
+    deps: DepsMut<ProvenanceQuery>,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+    subscription: Addr,
+    approve: bool,
+) -> ContractResponse {
+    let state = config_read(deps.storage).load()?;
+    let mut proposal = load_proposal(deps.storage, id)?;
+
+    if env.block.time.seconds() >= proposal.closes_at {
+        return Err(ContractError::InvalidRequest {
+            reason: "consent voting has closed",
+        });
+    }
+    if !has_subscription_status(deps.storage, &subscription, SubscriptionStatus::Accepted)? {
+        return Err(ContractError::SubscriptionNotFound {
+            subscription,
+            expected: "accepted",
+        });
+    }
+    // subs proposed before lps were recorded are asked directly
+    let lp = match subscription_lp_storage_read(deps.storage).may_load(subscription.as_bytes())? {
+        Some(lp) => lp,
+        None => lp_for_sub(deps.as_ref(), &subscription)?,
+    };
+    if info.sender != lp {
+        return Err(ContractError::Unauthorized {
+            required: "subscription lp",
+            action: "vote on consent",
+        });
+    }
+    if consent_vote_storage(deps.storage, id)
+        .may_load(subscription.as_bytes())?
+        .is_some()
+    {
+        return Err(ContractError::InvalidRequest {
+            reason: "subscription has already voted",
+        });
+    }
+
+    let shares = Uint128::new(position(deps.as_ref(), &state, &subscription)?);
+    if shares.is_zero() {
+        return Err(ContractError::InvalidRequest {
+            reason: "subscription holds no shares to vote",
+        });
+    }
+
+    consent_vote_storage(deps.storage, id)
+        .save(subscription.as_bytes(), &ConsentVote { approve, shares })?;
+    if approve {
+        proposal.shares_for += shares;
+    } else {
+        proposal.shares_against += shares;
+    }
+    consent_proposal_storage(deps.storage).save(&id.to_be_bytes(), &proposal)?;
+    record_change(
+        deps.storage,
+        ChangedEntity::ConsentProposal,
+        &id.to_string(),
+    )?;
+
+    Ok(Response::new().add_attribute("shares", shares))
+}
+
+// consent is measured against every outstanding share, not just those that voted
+pub fn try_close_consent_proposal(
+    mut deps: DepsMut<ProvenanceQuery>,
+    env: Env,
+    id: u64,
+) -> ContractResponse {
+    let state = config_read(deps.storage).load()?;
+    let mut proposal = load_proposal(deps.storage, id)?;
+
+    if proposal.result.is_some() {
+        return Err(ContractError::InvalidRequest {
+            reason: "consent proposal already closed",
+        });
+    }
+    if env.block.time.seconds() < proposal.closes_at {
+        return Err(ContractError::InvalidRequest {
+            reason: "consent voting is still open",
+        });
+    }
+
+    let total = total_shares(deps.as_ref(), &state)?;
+    let passed = proposal.shares_for.u128() * 10_000 >= u128::from(proposal.threshold_bps) * total;
+    proposal.result = Some(if passed {
+        ConsentResult::Passed
+    } else {
+        ConsentResult::Rejected
+    });
+    consent_proposal_storage(deps.storage).save(&id.to_be_bytes(), &proposal)?;
+    record_change(
+        deps.storage,
+        ChangedEntity::ConsentProposal,
+        &id.to_string(),
+    )?;
+
+    let response = match proposal.change {
+        Some(change) if passed => dispatch(
+            deps.branch(),
+            env,
+            MessageInfo {
+                sender: state.gp,
+                funds: vec![],
+            },
+            change,
+        )?,
+        _ => Response::new(),
+    };
+
+    Ok(response.add_attribute("consent", format!("{passed}")))
+}
+
+// whether a closed proposal gave consent to exactly this change
+pub fn has_consent(storage: &dyn Storage, change: &HandleMsg) -> StdResult<bool> {
+    for item in consent_proposal_storage_read(storage).range(None, None, Order::Ascending) {
+        let (_, proposal) = item?;
+        if proposal.result == Some(ConsentResult::Passed)
+            && proposal.change.as_ref() == Some(change)
+        {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn load_proposal(storage: &dyn Storage, id: u64) -> Result<ConsentProposal, ContractError> {
+    consent_proposal_storage_read(storage)
+        .may_load(&id.to_be_bytes())?
+        .ok_or(ContractError::InvalidRequest {
+            reason: "no consent proposal found",
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::execute;
+    use crate::contract::tests::default_deps;
+    use crate::mock::load_markers;
+    use crate::state::subscription_lp_storage;
+    use crate::state::tests::set_accepted;
+    use cosmwasm_std::coins;
+    use cosmwasm_std::testing::{mock_env, mock_info};
+
+    #[test]
+    fn consent_gates_config_change() {
+        let mut deps = default_deps(None);
+        load_markers(&mut deps.querier);
+        set_accepted(&mut deps.storage, vec!["sub_1", "sub_2"]);
+        subscription_lp_storage(&mut deps.storage)
+            .save(b"sub_1", &Addr::unchecked("lp_1"))
+            .unwrap();
+        deps.querier
+            .base
+            .update_balance(Addr::unchecked("sub_1"), coins(300, "commitment_coin"));
+        let open = |change| HandleMsg::OpenConsentProposal {
+            amendment_hash: String::from("amendment"),
+            voting_seconds: 3_600,
+            threshold_bps: 3_000,
+            change: Some(Box::new(change)),
+        };
+
+        // verify capital movements can't be gated by consent
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            open(HandleMsg::IssueWithdrawal {
+                to: Addr::unchecked("omni"),
+                amount: 1_000,
+                memo: None,
+                denom: None,
+            }),
+        );
+        assert!(res.is_err());
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            open(HandleMsg::UpdateMaxOwnership {
+                max_ownership_bps: Some(5_000),
+            }),
+        )
+        .unwrap();
+
+        // verify only the sub's lp may vote, and only once
+        let vote = HandleMsg::VoteConsent {
+            id: 1,
+            subscription: Addr::unchecked("sub_1"),
+            approve: true,
+        };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("lp_2", &[]),
+            vote.clone(),
+        );
+        assert!(res.is_err());
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("lp_1", &[]),
+            vote.clone(),
+        )
+        .unwrap();
+        assert_eq!("300", res.attributes[0].value);
+        let res = execute(deps.as_mut(), mock_env(), mock_info("lp_1", &[]), vote);
+        assert!(res.is_err());
+
+        // verify the proposal can't close while voting is open
+        let close = HandleMsg::CloseConsentProposal { id: 1 };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("anyone", &[]),
+            close.clone(),
+        );
+        assert!(res.is_err());
+
+        // verify 300 of 840 shares passes a 30% threshold and applies the change
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(3_600);
+        let res = execute(deps.as_mut(), env, mock_info("anyone", &[]), close).unwrap();
+        assert_eq!("true", res.attributes[0].value);
+        assert_eq!(
+            Some(ConsentResult::Passed),
+            consent_proposal_storage_read(&deps.storage)
+                .load(&1u64.to_be_bytes())
+                .unwrap()
+                .result
+        );
+        assert_eq!(
+            Some(5_000),
+            config_read(&deps.storage).load().unwrap().max_ownership_bps
+        );
+    }
+}
@@ -1,69 +1,389 @@
 use cosmwasm_std::to_binary;
 use cosmwasm_std::WasmMsg;
 use cosmwasm_std::{
-    coins, entry_point, Addr, Attribute, BankMsg, DepsMut, Env, Event, MessageInfo, Reply,
-    Response, SubMsgResult,
+    coin, Addr, Attribute, DepsMut, Env, MessageInfo, Order, Reply, Response, StdResult, Storage,
+    SubMsg, SubMsgResult,
 };
-use provwasm_std::{transfer_marker_coins, ProvenanceMsg};
-use provwasm_std::{ProvenanceQuerier, ProvenanceQuery};
-use serde::Serialize;
-use std::vec::IntoIter;
+use provwasm_std::ProvenanceMsg;
+use provwasm_std::ProvenanceQuery;
+use sha2::{Digest, Sha256};
 
-use crate::error::contract_error;
+use crate::approval::{
+    operation_capital, try_approve_operation, try_extend_fund_term, try_propose_operation,
+    try_update_gp_committee, try_update_management_fee,
+};
+use crate::archive::try_archive_history;
+use crate::consent::{try_close_consent_proposal, try_open_consent_proposal, try_vote_consent};
 use crate::error::ContractError;
-use crate::exchange_asset::try_cancel_asset_exchanges;
+use crate::exchange_asset::try_cancel_batch;
 use crate::exchange_asset::try_complete_asset_exchange;
+use crate::exchange_asset::try_complete_asset_exchange_for;
+use crate::exchange_asset::try_expire_capital_calls;
 use crate::exchange_asset::try_issue_asset_exchanges;
-use crate::msg::{HandleMsg, SubscriptionMigrateMsg};
+use crate::exchange_asset::try_pay_capital_call;
+use crate::exchange_asset::{try_authorize_gp_settlement, try_revoke_gp_settlement};
+use crate::exchange_asset::{try_cancel_asset_exchange, try_cancel_asset_exchanges};
+use crate::exchange_asset::{try_process_queue, try_queue_asset_exchange, try_settle_queued};
+use crate::instantiate::marker_setup_messages;
+use crate::msg::ExchangeProvenance;
+use crate::msg::{ChangedEntity, HandleMsg, SubscriptionMigrateMsg};
+use crate::msg::{MarkerSetup, MARKER_SETUP_STEPS};
+use crate::ownership::check_ownership;
+use crate::performance_fee::try_crystallize_performance_fees;
+use crate::reply::{instantiated_contract_address, reply_namespace, ReplyNamespace};
+use crate::reply::{
+    ELIGIBLE_SUBSCRIPTION_REPLY_ID, MARKER_SETUP_REPLY_ID_OFFSET,
+    MIGRATE_SUBSCRIPTION_REPLY_ID_OFFSET, NOTIFY_SUBSCRIPTION_REPLY_ID_OFFSET,
+    PENDING_SUBSCRIPTION_REPLY_ID, SETTLE_QUEUED_REPLY_ID_OFFSET,
+};
+use crate::settlement::{memo_events, send_capital, send_coin};
 use crate::state::config;
-use crate::state::eligible_subscriptions;
-use crate::state::pending_subscriptions;
+use crate::state::config_read;
+use crate::state::lp_subscription_storage;
+use crate::state::migration_batch;
+use crate::state::migration_batch_read;
+use crate::state::migration_failure_storage;
+use crate::state::migration_failure_storage_read;
+use crate::state::outstanding_obligations_read;
+use crate::state::proposing_lp;
+use crate::state::record_change;
+use crate::state::record_nav;
+use crate::state::subscription_lp_storage;
+use crate::state::{authorized_funds_read, escrowed_capital_read};
+use crate::state::{auto_accepted_capital, proposing_auto_accept};
+use crate::state::{is_valid_denom, PendingTreasury, TREASURY_TIMELOCK_SECONDS};
+use crate::state::{marker_setup, marker_setup_read};
+use crate::state::{notification_batch_read, notifications_disabled_storage};
+use crate::state::{proposing_agent, subscription_agent_storage};
+use crate::state::{proposing_deposit, save_escrow};
+use crate::state::{set_subscription_status, SubscriptionStatus};
+use crate::state::{settlement_batch_read, settlement_queue};
+use crate::state::{CapitalAttribute, NetAssetValue};
+use crate::subscribe::save_initial_commitment;
 use crate::subscribe::try_accept_subscriptions;
 use crate::subscribe::try_close_subscriptions;
 use crate::subscribe::try_propose_subscription;
+use crate::subscribe::try_refresh_eligibility;
+use crate::subscribe::try_transfer_commitment;
 use crate::subscribe::try_upgrade_eligible_subscriptions;
 
 pub type ContractResponse = Result<Response<ProvenanceMsg>, ContractError>;
 
-#[entry_point]
-pub fn reply(deps: DepsMut<ProvenanceQuery>, _env: Env, msg: Reply) -> ContractResponse {
+#[cfg_attr(not(feature = "library"), cosmwasm_std::entry_point)]
+pub fn reply(deps: DepsMut<ProvenanceQuery>, env: Env, msg: Reply) -> ContractResponse {
+    match reply_namespace(msg.id) {
+        Some(ReplyNamespace::Subscription) => reply_subscription_instantiated(deps, env, msg),
+        Some(ReplyNamespace::Migration) => reply_subscription_migrated(deps, msg),
+        Some(ReplyNamespace::Notification) => reply_subscription_notified(deps, msg),
+        Some(ReplyNamespace::Queue) => reply_queued_settlement(deps, msg),
+        Some(ReplyNamespace::MarkerSetup) => reply_marker_setup(deps, msg),
+        None => Err(ContractError::UnknownReplyId { id: msg.id }),
+    }
+}
+
+fn reply_subscription_migrated(deps: DepsMut<ProvenanceQuery>, msg: Reply) -> ContractResponse {
+    let index = (msg.id - MIGRATE_SUBSCRIPTION_REPLY_ID_OFFSET) as usize;
+    let subscription = migration_batch_read(deps.storage)
+        .load()?
+        .get(index)
+        .cloned()
+        .ok_or(ContractError::ReplyItemNotFound {
+            item: "subscription",
+            id: msg.id,
+        })?;
+
+    match msg.result {
+        SubMsgResult::Err(error) => {
+            migration_failure_storage(deps.storage).save(subscription.as_bytes(), &error)?;
+            record_change(
+                deps.storage,
+                ChangedEntity::MigrationFailure,
+                subscription.as_str(),
+            )?;
+            Ok(Response::new().add_attribute("migration_failed", subscription))
+        }
+        SubMsgResult::Ok(_) => Ok(Response::default()),
+    }
+}
+
+fn reply_subscription_notified(deps: DepsMut<ProvenanceQuery>, msg: Reply) -> ContractResponse {
+    let index = (msg.id - NOTIFY_SUBSCRIPTION_REPLY_ID_OFFSET) as usize;
+    let subscription = notification_batch_read(deps.storage)
+        .load()?
+        .get(index)
+        .cloned()
+        .ok_or(ContractError::ReplyItemNotFound {
+            item: "subscription",
+            id: msg.id,
+        })?;
+
+    // a sub that rejects the notification shouldn't block issuance
+    match msg.result {
+        SubMsgResult::Err(_) => {
+            Ok(Response::new().add_attribute("notification_failed", subscription))
+        }
+        SubMsgResult::Ok(_) => Ok(Response::default()),
+    }
+}
+
+fn reply_queued_settlement(deps: DepsMut<ProvenanceQuery>, msg: Reply) -> ContractResponse {
+    let index = (msg.id - SETTLE_QUEUED_REPLY_ID_OFFSET) as usize;
+    let sequence = settlement_batch_read(deps.storage)
+        .load()?
+        .get(index)
+        .cloned()
+        .ok_or(ContractError::ReplyItemNotFound {
+            item: "queued settlement",
+            id: msg.id,
+        })?;
+
+    // the failed settlement was rolled back, so drop it to keep the queue moving
+    match msg.result {
+        SubMsgResult::Err(error) => {
+            settlement_queue(deps.storage).remove(&sequence.to_be_bytes());
+            record_change(
+                deps.storage,
+                ChangedEntity::SettlementQueue,
+                &sequence.to_string(),
+            )?;
+            Ok(Response::new()
+                .add_attribute("queued_settlement_failed", sequence.to_string())
+                .add_attribute("error", error))
+        }
+        SubMsgResult::Ok(_) => Ok(Response::default()),
+    }
+}
+
+fn reply_marker_setup(deps: DepsMut<ProvenanceQuery>, msg: Reply) -> ContractResponse {
+    let offset = (msg.id - MARKER_SETUP_REPLY_ID_OFFSET) as usize;
+    let step = MARKER_SETUP_STEPS[offset % MARKER_SETUP_STEPS.len()];
+    let mut setups = marker_setup_read(deps.storage)
+        .may_load()?
+        .unwrap_or_default();
+    let setup = setups.get_mut(offset / MARKER_SETUP_STEPS.len()).ok_or(
+        ContractError::ReplyItemNotFound {
+            item: "marker setup",
+            id: msg.id,
+        },
+    )?;
+
+    // the failed step was rolled back, so it's left for a retry
+    let response = match msg.result {
+        SubMsgResult::Err(error) => {
+            let response = Response::new()
+                .add_attribute("marker_setup_failed", &setup.denom)
+                .add_attribute("error", &error);
+            setup.failure.get_or_insert(error);
+            response
+        }
+        SubMsgResult::Ok(_) => {
+            if !setup.completed.contains(&step) {
+                setup.completed.push(step);
+            }
+            Response::default()
+        }
+    };
+    let denom = setup.denom.clone();
+    marker_setup(deps.storage).save(&setups)?;
+    record_change(deps.storage, ChangedEntity::MarkerSetup, &denom)?;
+
+    Ok(response)
+}
+
+fn try_migrate_subscriptions(
+    deps: DepsMut<ProvenanceQuery>,
+    subscriptions: Vec<Addr>,
+) -> ContractResponse {
+    let state = config(deps.storage).load()?;
+    let migration_msg = to_binary(&SubscriptionMigrateMsg {
+        required_capital_attribute: state.required_capital_attribute.clone(),
+        capital_denom: Some(state.capital_denom.clone()),
+    })?;
+
+    let mut messages = vec![];
+    for (index, sub) in subscriptions.iter().enumerate() {
+        if migration_failure_storage_read(deps.storage)
+            .may_load(sub.as_bytes())?
+            .is_some()
+        {
+            migration_failure_storage(deps.storage).remove(sub.as_bytes());
+            record_change(deps.storage, ChangedEntity::MigrationFailure, sub.as_str())?;
+        }
+        messages.push(SubMsg::reply_on_error(
+            WasmMsg::Migrate {
+                contract_addr: sub.to_string(),
+                new_code_id: state.subscription_code_id,
+                msg: migration_msg.clone(),
+            },
+            MIGRATE_SUBSCRIPTION_REPLY_ID_OFFSET + index as u64,
+        ));
+    }
+    migration_batch(deps.storage).save(&subscriptions)?;
+
+    Ok(Response::new().add_submessages(messages))
+}
+
+fn reply_subscription_instantiated(
+    deps: DepsMut<ProvenanceQuery>,
+    env: Env,
+    msg: Reply,
+) -> ContractResponse {
+    if msg.id != PENDING_SUBSCRIPTION_REPLY_ID && msg.id != ELIGIBLE_SUBSCRIPTION_REPLY_ID {
+        return Err(ContractError::UnknownReplyId { id: msg.id });
+    }
+
+    let lp = proposing_lp(deps.storage).may_load()?;
+    proposing_lp(deps.storage).remove();
+    let agent = proposing_agent(deps.storage).may_load()?;
+    proposing_agent(deps.storage).remove();
+    let deposit = proposing_deposit(deps.storage).may_load()?;
+    proposing_deposit(deps.storage).remove();
+    let auto_accept = proposing_auto_accept(deps.storage).may_load()?;
+    proposing_auto_accept(deps.storage).remove();
+
+    let mut response = Response::new();
+
     // look for a contract address from instantiating subscription contract
-    if let SubMsgResult::Ok(response) = msg.result {
-        if let Some(contract_address) = contract_address(&response.events) {
-            let eligible = msg.id == 1;
-            let mut storage = if eligible {
-                eligible_subscriptions(deps.storage)
+    if let SubMsgResult::Ok(instantiated) = msg.result {
+        let code_id = config_read(deps.storage).load()?.subscription_code_id;
+        if let Some(contract_address) = instantiated_contract_address(&instantiated, code_id) {
+            // a sub that would own too much is left eligible for the gp to review
+            let auto_accept = match auto_accept {
+                Some(commitment) if msg.id == ELIGIBLE_SUBSCRIPTION_REPLY_ID => {
+                    let state = config_read(deps.storage).load()?;
+                    let shares = u128::from(state.capital_to_shares(commitment));
+                    check_ownership(deps.as_ref(), &state, &[(&contract_address, shares)])
+                        .ok()
+                        .map(|_| (state, commitment))
+                }
+                _ => None,
+            };
+            let status = if auto_accept.is_some() {
+                SubscriptionStatus::Accepted
+            } else if msg.id == ELIGIBLE_SUBSCRIPTION_REPLY_ID {
+                SubscriptionStatus::Eligible
             } else {
-                pending_subscriptions(deps.storage)
+                SubscriptionStatus::Pending
             };
-            let mut subscriptions = storage.may_load()?.unwrap_or_default();
-            subscriptions.insert(contract_address);
-            storage.save(&subscriptions)?;
+            set_subscription_status(deps.storage, &contract_address, Some(status))?;
+            record_change(
+                deps.storage,
+                ChangedEntity::Subscription,
+                contract_address.as_str(),
+            )?;
+
+            if let Some(lp) = lp {
+                subscription_lp_storage(deps.storage).save(contract_address.as_bytes(), &lp)?;
+                lp_subscription_storage(deps.storage, &lp)
+                    .save(contract_address.as_bytes(), &contract_address)?;
+            }
+            if let Some(agent) = agent {
+                subscription_agent_storage(deps.storage)
+                    .save(contract_address.as_bytes(), &agent)?;
+            }
+            if let Some(deposit) = deposit {
+                save_escrow(deps.storage, &contract_address, deposit)?;
+                record_change(
+                    deps.storage,
+                    ChangedEntity::Escrow,
+                    contract_address.as_str(),
+                )?;
+            }
+
+            if let Some((state, commitment)) = auto_accept {
+                save_initial_commitment(
+                    deps.storage,
+                    &state,
+                    &contract_address,
+                    commitment,
+                    ExchangeProvenance {
+                        issued_by: env.contract.address,
+                        issued_at: env.block.time.seconds(),
+                        batch: None,
+                    },
+                )?;
+                let auto_accepted = auto_accepted_capital(deps.storage)
+                    .may_load()?
+                    .unwrap_or_default();
+                auto_accepted_capital(deps.storage).save(&(auto_accepted + commitment))?;
+                response = response.add_attribute("auto_accepted", contract_address);
+            }
         } else {
-            return contract_error("no contract address found");
+            return Err(ContractError::InstantiationFailed {});
         }
     } else {
-        return contract_error("subscription contract instantiation failed");
+        return Err(ContractError::InstantiationFailed {});
+    }
+
+    Ok(response)
+}
+
+#[cfg_attr(not(feature = "library"), cosmwasm_std::entry_point)]
+pub fn execute(
+    deps: DepsMut<ProvenanceQuery>,
+    env: Env,
+    info: MessageInfo,
+    msg: HandleMsg,
+) -> ContractResponse {
+    match msg {
+        HandleMsg::Recover { .. }
+        | HandleMsg::SetPause { .. }
+        | HandleMsg::RetryMarkerSetup {}
+        | HandleMsg::CancelTreasuryUpdate {} => {}
+        _ => {
+            if config_read(deps.storage).load()?.paused {
+                return Err(ContractError::Paused {});
+            }
+        }
     }
 
-    Ok(Response::default())
+    check_marker_setup(deps.storage, &msg)?;
+
+    // high value capital movements wait for committee approval
+    if let Some(capital) = operation_capital(&msg) {
+        if config_read(deps.storage).load()?.requires_approval(capital) {
+            return try_propose_operation(deps, env, info, msg);
+        }
+    }
+
+    dispatch(deps, env, info, msg)
 }
 
-fn contract_address(events: &[Event]) -> Option<Addr> {
-    events.first().and_then(|event| {
-        event
-            .attributes
-            .iter()
-            .find(|attr| attr.key == "_contract_address")
-            .map(|attr| Addr::unchecked(attr.value.clone()))
-    })
+// nothing can be committed, invested or paid until every marker is active
+pub fn check_marker_setup(storage: &dyn Storage, msg: &HandleMsg) -> Result<(), ContractError> {
+    if moves_assets(msg) {
+        if let Some(setup) = marker_setup_read(storage)
+            .may_load()?
+            .unwrap_or_default()
+            .into_iter()
+            .find(|setup| !setup.is_complete())
+        {
+            return Err(ContractError::MarkerSetupIncomplete { denom: setup.denom });
+        }
+    }
+    Ok(())
 }
 
-#[derive(Serialize)]
-struct EmptyArgs {}
+fn moves_assets(msg: &HandleMsg) -> bool {
+    matches!(
+        msg,
+        HandleMsg::ProposeSubscription { .. }
+            | HandleMsg::AcceptSubscriptions { .. }
+            | HandleMsg::CloseSubscriptions { .. }
+            | HandleMsg::IssueAssetExchanges { .. }
+            | HandleMsg::CompleteAssetExchange { .. }
+            | HandleMsg::QueueAssetExchange { .. }
+            | HandleMsg::ProcessQueue { .. }
+            | HandleMsg::SettleQueued { .. }
+            | HandleMsg::AuthorizeGpSettlement { .. }
+            | HandleMsg::CompleteAssetExchangeFor { .. }
+            | HandleMsg::PayCapitalCall { .. }
+            | HandleMsg::TransferCommitment { .. }
+            | HandleMsg::IssueWithdrawal { .. }
+    )
+}
 
-#[entry_point]
-pub fn execute(
+pub fn dispatch(
     deps: DepsMut<ProvenanceQuery>,
     env: Env,
     info: MessageInfo,
@@ -74,11 +394,109 @@ pub fn execute(
             let mut state = config(deps.storage).load()?;
 
             if info.sender != state.recovery_admin {
-                return contract_error("only admin can recover raise");
+                return Err(ContractError::Unauthorized {
+                    required: "admin",
+                    action: "recover raise",
+                });
             }
 
             state.gp = gp;
             config(deps.storage).save(&state)?;
+            record_change(deps.storage, ChangedEntity::State, "config")?;
+
+            Ok(Response::default())
+        }
+        HandleMsg::SetPause { paused } => {
+            let mut state = config(deps.storage).load()?;
+
+            if info.sender != state.recovery_admin {
+                return Err(ContractError::Unauthorized {
+                    required: "admin",
+                    action: "pause raise",
+                });
+            }
+
+            state.paused = paused;
+            config(deps.storage).save(&state)?;
+            record_change(deps.storage, ChangedEntity::State, "config")?;
+
+            Ok(Response::new().add_attribute("paused", paused.to_string()))
+        }
+        HandleMsg::SetAcceptingProposals {
+            accepting_proposals,
+        } => {
+            let mut state = config(deps.storage).load()?;
+
+            if info.sender != state.gp {
+                return Err(ContractError::Unauthorized {
+                    required: "gp",
+                    action: "open or close proposals",
+                });
+            }
+
+            state.accepting_proposals = accepting_proposals;
+            config(deps.storage).save(&state)?;
+            record_change(deps.storage, ChangedEntity::State, "config")?;
+
+            Ok(Response::new()
+                .add_attribute("accepting_proposals", accepting_proposals.to_string()))
+        }
+        HandleMsg::RetryMarkerSetup {} => {
+            let state = config_read(deps.storage).load()?;
+
+            if info.sender != state.recovery_admin {
+                return Err(ContractError::Unauthorized {
+                    required: "admin",
+                    action: "retry marker setup",
+                });
+            }
+
+            let mut setups = marker_setup_read(deps.storage)
+                .may_load()?
+                .unwrap_or_default();
+            if setups.iter().all(MarkerSetup::is_complete) {
+                return Err(ContractError::InvalidRequest {
+                    reason: "marker setup is already complete",
+                });
+            }
+            for setup in setups.iter_mut().filter(|setup| !setup.is_complete()) {
+                setup.failure = None;
+                record_change(deps.storage, ChangedEntity::MarkerSetup, &setup.denom)?;
+            }
+            marker_setup(deps.storage).save(&setups)?;
+
+            Ok(Response::new()
+                .add_submessages(marker_setup_messages(&setups, &env.contract.address)?))
+        }
+        HandleMsg::AddDelegate { delegate } => {
+            let mut state = config(deps.storage).load()?;
+
+            if info.sender != state.gp {
+                return Err(ContractError::Unauthorized {
+                    required: "gp",
+                    action: "add delegates",
+                });
+            }
+
+            state.delegates.insert(delegate);
+            config(deps.storage).save(&state)?;
+            record_change(deps.storage, ChangedEntity::State, "config")?;
+
+            Ok(Response::default())
+        }
+        HandleMsg::RemoveDelegate { delegate } => {
+            let mut state = config(deps.storage).load()?;
+
+            if info.sender != state.gp {
+                return Err(ContractError::Unauthorized {
+                    required: "gp",
+                    action: "remove delegates",
+                });
+            }
+
+            state.delegates.remove(&delegate);
+            config(deps.storage).save(&state)?;
+            record_change(deps.storage, ChangedEntity::State, "config")?;
 
             Ok(Response::default())
         }
@@ -88,309 +506,1721 @@ pub fn execute(
             let mut state = config(deps.storage).load()?;
 
             if info.sender != state.gp {
-                return contract_error("only gp can update required attestations");
+                return Err(ContractError::Unauthorized {
+                    required: "gp",
+                    action: "update required attestations",
+                });
             }
 
             state.required_attestations = required_attestations;
+            state.validate()?;
 
             config(deps.storage).save(&state)?;
+            record_change(deps.storage, ChangedEntity::State, "config")?;
 
             Ok(Response::default())
         }
-        HandleMsg::MigrateSubscriptions { subscriptions } => {
-            let state = config(deps.storage).load()?;
-            let migration_msg = SubscriptionMigrateMsg {
-                required_capital_attribute: state.required_capital_attribute.clone(),
-                capital_denom: Some(state.capital_denom.clone()),
-            };
-            Ok(
-                Response::new().add_messages(subscriptions.iter().map(|sub| WasmMsg::Migrate {
-                    contract_addr: sub.to_string(),
-                    new_code_id: state.subscription_code_id,
-                    msg: to_binary(&migration_msg).unwrap(),
-                })),
-            )
-        }
-        HandleMsg::ProposeSubscription { initial_commitment } => {
-            try_propose_subscription(deps, env, info, initial_commitment)
-        }
-        HandleMsg::CloseSubscriptions { subscriptions } => {
-            try_close_subscriptions(deps, info, subscriptions)
-        }
-        HandleMsg::UpdateEligibleSubscriptions { subscriptions } => {
-            try_upgrade_eligible_subscriptions(deps, info, subscriptions)
-        }
-        HandleMsg::AcceptSubscriptions { subscriptions } => {
-            try_accept_subscriptions(deps, info, subscriptions)
-        }
-        HandleMsg::IssueAssetExchanges { asset_exchanges } => {
-            try_issue_asset_exchanges(deps, info, asset_exchanges)
-        }
-        HandleMsg::CancelAssetExchanges { cancellations } => {
-            try_cancel_asset_exchanges(deps, info, cancellations)
-        }
-        HandleMsg::CompleteAssetExchange {
-            exchanges,
-            to,
-            memo,
-        } => try_complete_asset_exchange(deps, env, info, exchanges, to, memo),
-        HandleMsg::IssueWithdrawal { to, amount, memo } => {
-            let state = config(deps.storage).load()?;
+        HandleMsg::UpdateComplianceContract {
+            compliance_contract,
+        } => {
+            let mut state = config(deps.storage).load()?;
 
             if info.sender != state.gp {
-                return contract_error("only gp can redeem capital");
+                return Err(ContractError::Unauthorized {
+                    required: "gp",
+                    action: "update compliance contract",
+                });
             }
 
-            let attributes = match memo {
-                Some(memo) => {
-                    vec![Attribute {
-                        key: String::from("memo"),
-                        value: memo,
-                    }]
-                }
-                None => vec![],
-            };
+            state.compliance_contract = compliance_contract;
 
-            let response = match state.required_capital_attribute {
-                None => {
-                    let bank_send = BankMsg::Send {
-                        to_address: to.to_string(),
-                        amount: coins(amount as u128, &state.capital_denom),
-                    };
-                    Response::new()
-                        .add_message(bank_send)
-                        .add_attributes(attributes)
-                }
-                Some(required_capital_attribute) => {
-                    if !query_attributes(deps, &to)
-                        .any(|attr| attr.name == required_capital_attribute)
-                    {
-                        return contract_error(
-                            format!(
-                                "{} does not have required attribute of {}",
-                                &to, &required_capital_attribute
-                            )
-                            .as_str(),
-                        );
-                    }
-
-                    let marker_transfer = transfer_marker_coins(
-                        amount as u128,
-                        &state.capital_denom,
-                        to,
-                        env.contract.address,
-                    )?;
-                    Response::new()
-                        .add_message(marker_transfer)
-                        .add_attributes(attributes)
-                }
-            };
+            config(deps.storage).save(&state)?;
+            record_change(deps.storage, ChangedEntity::State, "config")?;
 
-            Ok(response)
+            Ok(Response::default())
         }
-    }
-}
+        HandleMsg::UpdateMetadata { metadata } => {
+            let mut state = config(deps.storage).load()?;
 
-fn query_attributes(
-    deps: DepsMut<ProvenanceQuery>,
-    address: &Addr,
-) -> IntoIter<provwasm_std::Attribute> {
-    ProvenanceQuerier::new(&deps.querier)
-        .get_attributes(address.clone(), None as Option<String>)
-        .unwrap()
-        .attributes
-        .into_iter()
-}
+            if info.sender != state.gp {
+                return Err(ContractError::Unauthorized {
+                    required: "gp",
+                    action: "update metadata",
+                });
+            }
 
-#[cfg(test)]
-pub mod tests {
-    use cosmwasm_std::testing::{mock_env, mock_info, MockApi, MockStorage, MOCK_CONTRACT_ADDR};
-    use cosmwasm_std::{coin, SubMsgResponse};
-    use cosmwasm_std::{Addr, OwnedDeps};
-    use provwasm_mocks::{mock_dependencies, ProvenanceMockQuerier};
-    use provwasm_std::MarkerMsgParams;
+            metadata.validate()?;
+            state.metadata = metadata;
 
-    use crate::mock::send_args;
-    use crate::mock::{load_markers, marker_transfer_msg, msg_at_index};
-    use crate::state::config_read;
-    use crate::state::eligible_subscriptions_read;
-    use crate::state::pending_subscriptions_read;
-    use crate::state::State;
+            config(deps.storage).save(&state)?;
+            record_change(deps.storage, ChangedEntity::State, "config")?;
 
-    use super::*;
+            Ok(Response::default())
+        }
+        HandleMsg::UpdateMinInitialCommitment {
+            min_initial_commitment,
+        } => {
+            let mut state = config(deps.storage).load()?;
 
-    impl State {
-        pub fn test_capital_coin() -> State {
-            State {
-                subscription_code_id: 100,
-                recovery_admin: Addr::unchecked("marketpalace"),
-                gp: Addr::unchecked("gp"),
-                required_attestations: vec![vec![String::from("506c")].into_iter().collect()],
-                commitment_denom: String::from("commitment_coin"),
-                investment_denom: String::from("investment_coin"),
-                capital_denom: String::from("capital_coin"),
-                capital_per_share: 100,
-                required_capital_attribute: None,
+            if info.sender != state.gp {
+                return Err(ContractError::Unauthorized {
+                    required: "gp",
+                    action: "update minimum initial commitment",
+                });
             }
+
+            state.min_initial_commitment = min_initial_commitment;
+
+            config(deps.storage).save(&state)?;
+            record_change(deps.storage, ChangedEntity::State, "config")?;
+
+            Ok(Response::default())
         }
+        HandleMsg::PostNav { per_share } => {
+            let mut state = config(deps.storage).load()?;
 
-        pub fn test_restricted_capital_coin() -> State {
-            State {
-                subscription_code_id: 100,
-                recovery_admin: Addr::unchecked("marketpalace"),
-                gp: Addr::unchecked("gp"),
-                required_attestations: vec![vec![String::from("506c")].into_iter().collect()],
-                commitment_denom: String::from("commitment_coin"),
-                investment_denom: String::from("investment_coin"),
-                capital_denom: String::from("restricted_capital_coin"),
-                capital_per_share: 100,
-                required_capital_attribute: Some(String::from("capital.test")),
+            if info.sender != state.gp {
+                return Err(ContractError::Unauthorized {
+                    required: "gp",
+                    action: "post nav",
+                });
             }
-        }
-    }
 
-    pub fn default_deps(
-        update_state: Option<fn(&mut State)>,
-    ) -> OwnedDeps<MockStorage, MockApi, ProvenanceMockQuerier, ProvenanceQuery> {
-        let mut deps = mock_dependencies(&[]);
+            let nav = NetAssetValue {
+                per_share,
+                posted_at: env.block.time.seconds(),
+            };
+            record_nav(deps.storage, &nav)?;
+            state.nav = Some(nav);
 
-        let mut state = State::test_default();
-        if let Some(update) = update_state {
-            update(&mut state);
+            config(deps.storage).save(&state)?;
+            record_change(deps.storage, ChangedEntity::State, "config")?;
+
+            Ok(Response::new().add_attribute("nav", per_share.to_string()))
         }
-        config(&mut deps.storage).save(&state).unwrap();
+        HandleMsg::UpdatePerformanceFee { performance_fee } => {
+            let mut state = config(deps.storage).load()?;
 
-        deps
-    }
+            if info.sender != state.gp {
+                return Err(ContractError::Unauthorized {
+                    required: "gp",
+                    action: "update performance fee",
+                });
+            }
+            if performance_fee
+                .as_ref()
+                .is_some_and(|performance_fee| performance_fee.fee_bps > 10_000)
+            {
+                return Err(ContractError::InvalidRequest {
+                    reason: "performance fee can't exceed 10000 bps",
+                });
+            }
 
-    pub fn capital_coin_deps(
-        update_state: Option<fn(&mut State)>,
-    ) -> OwnedDeps<MockStorage, MockApi, ProvenanceMockQuerier, ProvenanceQuery> {
-        let mut deps = mock_dependencies(&[]);
+            state.performance_fee = performance_fee;
 
-        let mut state = State::test_capital_coin();
-        if let Some(update) = update_state {
-            update(&mut state);
+            config(deps.storage).save(&state)?;
+            record_change(deps.storage, ChangedEntity::State, "config")?;
+
+            Ok(Response::default())
         }
-        config(&mut deps.storage).save(&state).unwrap();
+        HandleMsg::CrystallizePerformanceFees {} => {
+            try_crystallize_performance_fees(deps, env, info)
+        }
+        HandleMsg::UpdateAutoAccept { auto_accept } => {
+            let mut state = config(deps.storage).load()?;
 
-        deps
-    }
+            if info.sender != state.gp {
+                return Err(ContractError::Unauthorized {
+                    required: "gp",
+                    action: "update auto accept",
+                });
+            }
+            if auto_accept.as_ref().is_some_and(|auto_accept| {
+                state.not_evenly_divisble(auto_accept.max_commitment)
+                    || auto_accept.max_commitment > auto_accept.total_cap
+            }) {
+                return Err(ContractError::InvalidRequest {
+                    reason: "auto accept max commitment must be whole shares within the total cap",
+                });
+            }
 
-    pub fn restricted_capital_coin_deps(
-        update_state: Option<fn(&mut State)>,
-    ) -> OwnedDeps<MockStorage, MockApi, ProvenanceMockQuerier, ProvenanceQuery> {
-        let mut deps = mock_dependencies(&[]);
+            state.auto_accept = auto_accept;
 
-        let mut state = State::test_restricted_capital_coin();
-        if let Some(update) = update_state {
-            update(&mut state);
+            config(deps.storage).save(&state)?;
+            record_change(deps.storage, ChangedEntity::State, "config")?;
+
+            Ok(Response::default())
         }
-        config(&mut deps.storage).save(&state).unwrap();
+        HandleMsg::UpdateMaxOwnership { max_ownership_bps } => {
+            let mut state = config(deps.storage).load()?;
 
-        deps
-    }
+            if info.sender != state.gp {
+                return Err(ContractError::Unauthorized {
+                    required: "gp",
+                    action: "update max ownership",
+                });
+            }
+            if max_ownership_bps.is_some_and(|max| max > 10_000) {
+                return Err(ContractError::InvalidRequest {
+                    reason: "max ownership can't exceed 10000 bps",
+                });
+            }
 
-    #[test]
-    fn reply_pending() {
-        let mut deps = default_deps(None);
+            state.max_ownership_bps = max_ownership_bps;
 
-        reply(
-            deps.as_mut(),
-            mock_env(),
-            Reply {
-                id: 0,
-                result: cosmwasm_std::SubMsgResult::Ok(SubMsgResponse {
-                    events: vec![
-                        Event::new("contract address").add_attribute("_contract_address", "sub_1")
-                    ],
-                    data: None,
+            config(deps.storage).save(&state)?;
+            record_change(deps.storage, ChangedEntity::State, "config")?;
+
+            Ok(Response::default())
+        }
+        HandleMsg::UpdateTreasury { treasury } => {
+            let mut state = config(deps.storage).load()?;
+
+            if info.sender != state.gp {
+                return Err(ContractError::Unauthorized {
+                    required: "gp",
+                    action: "update treasury",
+                });
+            }
+
+            // registering the first treasury only narrows what the gp can do
+            if state.treasury.is_none() {
+                state.treasury = treasury;
+                state.pending_treasury = None;
+                config(deps.storage).save(&state)?;
+                record_change(deps.storage, ChangedEntity::State, "config")?;
+                return Ok(Response::default());
+            }
+
+            let effective_at = env.block.time.seconds() + TREASURY_TIMELOCK_SECONDS;
+            state.pending_treasury = Some(PendingTreasury {
+                treasury,
+                effective_at,
+            });
+            config(deps.storage).save(&state)?;
+            record_change(deps.storage, ChangedEntity::State, "config")?;
+
+            Ok(Response::new().add_attribute("treasury_effective_at", effective_at.to_string()))
+        }
+        HandleMsg::ApplyTreasuryUpdate {} => {
+            let mut state = config(deps.storage).load()?;
+
+            if info.sender != state.gp {
+                return Err(ContractError::Unauthorized {
+                    required: "gp",
+                    action: "apply treasury update",
+                });
+            }
+
+            let pending = state
+                .pending_treasury
+                .take()
+                .ok_or(ContractError::InvalidRequest {
+                    reason: "no pending treasury update",
+                })?;
+            if env.block.time.seconds() < pending.effective_at {
+                return Err(ContractError::Timelocked {
+                    effective_at: pending.effective_at,
+                });
+            }
+
+            state.treasury = pending.treasury;
+            config(deps.storage).save(&state)?;
+            record_change(deps.storage, ChangedEntity::State, "config")?;
+
+            Ok(Response::default())
+        }
+        HandleMsg::CancelTreasuryUpdate {} => {
+            let mut state = config(deps.storage).load()?;
+
+            // the admin can stop a compromised gp from redirecting withdrawals
+            if info.sender != state.gp && info.sender != state.recovery_admin {
+                return Err(ContractError::Unauthorized {
+                    required: "gp or admin",
+                    action: "cancel treasury update",
+                });
+            }
+
+            state.pending_treasury = None;
+            config(deps.storage).save(&state)?;
+            record_change(deps.storage, ChangedEntity::State, "config")?;
+
+            Ok(Response::default())
+        }
+        HandleMsg::UpdateApprovedDestinations {
+            approved_destinations,
+        } => {
+            let mut state = config(deps.storage).load()?;
+
+            if info.sender != state.gp && info.sender != state.recovery_admin {
+                return Err(ContractError::Unauthorized {
+                    required: "gp or admin",
+                    action: "update approved destinations",
+                });
+            }
+
+            state.approved_destinations = approved_destinations;
+
+            config(deps.storage).save(&state)?;
+            record_change(deps.storage, ChangedEntity::State, "config")?;
+
+            Ok(Response::default())
+        }
+        HandleMsg::UpdateCapitalExchangeRates {
+            capital_exchange_rates,
+        } => {
+            let mut state = config(deps.storage).load()?;
+
+            if info.sender != state.gp {
+                return Err(ContractError::Unauthorized {
+                    required: "gp",
+                    action: "update capital exchange rates",
+                });
+            }
+
+            for rate in &capital_exchange_rates {
+                if !is_valid_denom(&rate.denom) {
+                    return Err(ContractError::InvalidDenom {
+                        denom: rate.denom.clone(),
+                    });
+                }
+                if rate.denom == state.capital_denom {
+                    return Err(ContractError::InvalidRequest {
+                        reason: "capital denom can't have an exchange rate",
+                    });
+                }
+                if rate.numerator == 0 || rate.denominator == 0 {
+                    return Err(ContractError::InvalidRequest {
+                        reason: "exchange rate terms must be greater than zero",
+                    });
+                }
+            }
+
+            state.capital_exchange_rates = capital_exchange_rates;
+
+            config(deps.storage).save(&state)?;
+            record_change(deps.storage, ChangedEntity::State, "config")?;
+
+            Ok(Response::default())
+        }
+        HandleMsg::UpdateObligationLimits {
+            max_outstanding_exchanges,
+            max_outstanding_capital,
+        } => {
+            let mut state = config(deps.storage).load()?;
+
+            if info.sender != state.recovery_admin {
+                return Err(ContractError::Unauthorized {
+                    required: "admin",
+                    action: "update obligation limits",
+                });
+            }
+
+            state.max_outstanding_exchanges = max_outstanding_exchanges;
+            state.max_outstanding_capital = max_outstanding_capital;
+
+            config(deps.storage).save(&state)?;
+            record_change(deps.storage, ChangedEntity::State, "config")?;
+
+            Ok(Response::default())
+        }
+        HandleMsg::UpdateBatchCancelThreshold {
+            batch_cancel_cosign_threshold,
+        } => {
+            let mut state = config(deps.storage).load()?;
+
+            if info.sender != state.recovery_admin {
+                return Err(ContractError::Unauthorized {
+                    required: "admin",
+                    action: "update batch cancel threshold",
+                });
+            }
+
+            state.batch_cancel_cosign_threshold = batch_cancel_cosign_threshold;
+
+            config(deps.storage).save(&state)?;
+            record_change(deps.storage, ChangedEntity::State, "config")?;
+
+            Ok(Response::default())
+        }
+        HandleMsg::UpdateTombstoneCodeId { tombstone_code_id } => {
+            let mut state = config(deps.storage).load()?;
+
+            if info.sender != state.recovery_admin {
+                return Err(ContractError::Unauthorized {
+                    required: "admin",
+                    action: "update tombstone code id",
+                });
+            }
+
+            state.tombstone_code_id = tombstone_code_id;
+
+            config(deps.storage).save(&state)?;
+            record_change(deps.storage, ChangedEntity::State, "config")?;
+
+            Ok(Response::default())
+        }
+        HandleMsg::UpdateProposalLimits {
+            proposal_cooldown_blocks,
+            max_pending_per_lp,
+        } => {
+            let mut state = config(deps.storage).load()?;
+
+            if info.sender != state.recovery_admin {
+                return Err(ContractError::Unauthorized {
+                    required: "admin",
+                    action: "update proposal limits",
+                });
+            }
+
+            state.proposal_cooldown_blocks = proposal_cooldown_blocks;
+            state.max_pending_per_lp = max_pending_per_lp;
+
+            config(deps.storage).save(&state)?;
+            record_change(deps.storage, ChangedEntity::State, "config")?;
+
+            Ok(Response::default())
+        }
+        HandleMsg::UpdateMemoTransferEvents {
+            memo_transfer_events,
+        } => {
+            let mut state = config(deps.storage).load()?;
+
+            if info.sender != state.gp {
+                return Err(ContractError::Unauthorized {
+                    required: "gp",
+                    action: "update memo transfer events",
+                });
+            }
+
+            state.memo_transfer_events = memo_transfer_events;
+
+            config(deps.storage).save(&state)?;
+            record_change(deps.storage, ChangedEntity::State, "config")?;
+
+            Ok(Response::default())
+        }
+        HandleMsg::UpdateDueGracePeriod { due_grace_seconds } => {
+            let mut state = config(deps.storage).load()?;
+
+            if info.sender != state.gp {
+                return Err(ContractError::Unauthorized {
+                    required: "gp",
+                    action: "update due grace period",
+                });
+            }
+
+            state.due_grace_seconds = due_grace_seconds;
+
+            config(deps.storage).save(&state)?;
+            record_change(deps.storage, ChangedEntity::State, "config")?;
+
+            Ok(Response::default())
+        }
+        HandleMsg::UpdateSettlementHours { settlement_hours } => {
+            let mut state = config(deps.storage).load()?;
+
+            if info.sender != state.gp {
+                return Err(ContractError::Unauthorized {
+                    required: "gp",
+                    action: "update settlement hours",
+                });
+            }
+
+            if settlement_hours
+                .as_ref()
+                .is_some_and(|hours| !hours.is_valid())
+            {
+                return Err(ContractError::InvalidSettlementHours {});
+            }
+
+            state.settlement_hours = settlement_hours;
+            config(deps.storage).save(&state)?;
+            record_change(deps.storage, ChangedEntity::State, "config")?;
+
+            Ok(Response::default())
+        }
+        HandleMsg::UpdateGpCommittee {
+            gp_committee,
+            approval_threshold,
+            approval_capital_threshold,
+        } => try_update_gp_committee(
+            deps,
+            info,
+            gp_committee,
+            approval_threshold,
+            approval_capital_threshold,
+        ),
+        HandleMsg::ApproveOperation { id } => try_approve_operation(deps, env, info, id),
+        HandleMsg::OpenConsentProposal {
+            amendment_hash,
+            voting_seconds,
+            threshold_bps,
+            change,
+        } => try_open_consent_proposal(
+            deps,
+            env,
+            info,
+            amendment_hash,
+            voting_seconds,
+            threshold_bps,
+            change,
+        ),
+        HandleMsg::VoteConsent {
+            id,
+            subscription,
+            approve,
+        } => try_vote_consent(deps, env, info, id, subscription, approve),
+        HandleMsg::CloseConsentProposal { id } => try_close_consent_proposal(deps, env, id),
+        HandleMsg::UpdateManagementFee { management_fee } => {
+            try_update_management_fee(deps, info, management_fee)
+        }
+        HandleMsg::ExtendFundTerm { term_end } => try_extend_fund_term(deps, info, term_end),
+        HandleMsg::UpdateReceiptRetention { max_receipts } => {
+            let mut state = config(deps.storage).load()?;
+
+            if info.sender != state.gp {
+                return Err(ContractError::Unauthorized {
+                    required: "gp",
+                    action: "update receipt retention",
+                });
+            }
+
+            state.max_receipts = max_receipts;
+            config(deps.storage).save(&state)?;
+            record_change(deps.storage, ChangedEntity::State, "config")?;
+
+            Ok(Response::default())
+        }
+        HandleMsg::UpdateHistoryRetention {
+            receipt_retention_seconds,
+            change_log_retention,
+        } => {
+            let mut state = config(deps.storage).load()?;
+
+            if info.sender != state.recovery_admin {
+                return Err(ContractError::Unauthorized {
+                    required: "admin",
+                    action: "update history retention",
+                });
+            }
+
+            state.receipt_retention_seconds = receipt_retention_seconds;
+            state.change_log_retention = change_log_retention;
+            config(deps.storage).save(&state)?;
+            record_change(deps.storage, ChangedEntity::State, "config")?;
+
+            Ok(Response::default())
+        }
+        HandleMsg::ArchiveHistory { subscriptions } => {
+            try_archive_history(deps, env, info, subscriptions)
+        }
+        HandleMsg::UpdateRequiredCapitalAttribute {
+            denom,
+            required_capital_attribute,
+        } => {
+            let mut state = config(deps.storage).load()?;
+
+            if info.sender != state.gp && info.sender != state.recovery_admin {
+                return Err(ContractError::Unauthorized {
+                    required: "gp or admin",
+                    action: "update required capital attribute",
+                });
+            }
+
+            if !state.is_like_capital_denom(&denom) {
+                return Err(ContractError::InvalidDenom { denom });
+            }
+
+            // outstanding exchanges may still need to move restricted capital, and the
+            // running totals don't say in which denom, so any outstanding capital blocks removal
+            if required_capital_attribute.is_none()
+                && state.required_attribute_for(&denom).is_some()
+                && outstanding_obligations_read(deps.storage)
+                    .may_load()?
+                    .unwrap_or_default()
+                    .capital
+                    > 0
+            {
+                return Err(ContractError::InvalidRequest {
+                    reason: "outstanding capital exchanges need the capital attribute",
+                });
+            }
+
+            if denom == state.capital_denom {
+                state.required_capital_attribute = required_capital_attribute;
+            } else {
+                state
+                    .capital_attributes
+                    .retain(|required| required.denom != denom);
+                if let Some(attribute) = required_capital_attribute {
+                    state
+                        .capital_attributes
+                        .push(CapitalAttribute { denom, attribute });
+                }
+            }
+            config(deps.storage).save(&state)?;
+            record_change(deps.storage, ChangedEntity::State, "config")?;
+
+            Ok(Response::new().add_attribute(
+                "terms_revision",
+                format!("{:x}", Sha256::digest(to_binary(&state)?.as_slice())),
+            ))
+        }
+        HandleMsg::SetSubscriptionNotifications {
+            subscription,
+            enabled,
+        } => {
+            let state = config_read(deps.storage).load()?;
+
+            if info.sender != state.gp {
+                return Err(ContractError::Unauthorized {
+                    required: "gp",
+                    action: "set subscription notifications",
+                });
+            }
+
+            if enabled {
+                notifications_disabled_storage(deps.storage).remove(subscription.as_bytes());
+            } else {
+                notifications_disabled_storage(deps.storage)
+                    .save(subscription.as_bytes(), &true)?;
+            }
+            record_change(
+                deps.storage,
+                ChangedEntity::Subscription,
+                subscription.as_str(),
+            )?;
+
+            Ok(Response::default())
+        }
+        HandleMsg::MigrateSubscriptions { subscriptions } => {
+            try_migrate_subscriptions(deps, subscriptions.into_iter().collect())
+        }
+        HandleMsg::RetryFailedMigrations {} => {
+            let state = config_read(deps.storage).load()?;
+
+            if info.sender != state.gp && info.sender != state.recovery_admin {
+                return Err(ContractError::Unauthorized {
+                    required: "gp or admin",
+                    action: "retry failed migrations",
+                });
+            }
+
+            let failed = migration_failure_storage_read(deps.storage)
+                .range(None, None, Order::Ascending)
+                .map(|item| item.map(|(key, _)| Addr::unchecked(String::from_utf8_lossy(&key))))
+                .collect::<StdResult<Vec<Addr>>>()?;
+
+            try_migrate_subscriptions(deps, failed)
+        }
+        HandleMsg::ProposeSubscription {
+            initial_commitment,
+            owner,
+            forward_funds,
+        } => try_propose_subscription(deps, env, info, initial_commitment, owner, forward_funds),
+        HandleMsg::CloseSubscriptions {
+            subscriptions,
+            treasury,
+            retirement,
+        } => try_close_subscriptions(deps, env, info, subscriptions, treasury, retirement),
+        HandleMsg::RefreshEligibility { subscription } => {
+            try_refresh_eligibility(deps, subscription)
+        }
+        HandleMsg::UpdateEligibleSubscriptions { subscriptions } => {
+            try_upgrade_eligible_subscriptions(deps, info, subscriptions)
+        }
+        HandleMsg::AcceptSubscriptions { subscriptions } => {
+            try_accept_subscriptions(deps, env, info, subscriptions)
+        }
+        HandleMsg::TransferCommitment {
+            from_subscription,
+            to_subscription,
+            shares,
+            investment,
+        } => try_transfer_commitment(
+            deps,
+            env,
+            info,
+            from_subscription,
+            to_subscription,
+            shares,
+            investment,
+        ),
+        HandleMsg::IssueAssetExchanges {
+            asset_exchanges,
+            notify_subscriptions,
+            verify_capital_denom,
+        } => try_issue_asset_exchanges(
+            deps,
+            env,
+            info,
+            asset_exchanges,
+            notify_subscriptions,
+            verify_capital_denom,
+        ),
+        HandleMsg::CancelAssetExchanges { cancellations } => {
+            try_cancel_asset_exchanges(deps, info, cancellations)
+        }
+        HandleMsg::CancelAssetExchange {
+            subscription,
+            exchange,
+        } => try_cancel_asset_exchange(deps, info, subscription, exchange),
+        HandleMsg::CancelBatch { batch_id } => try_cancel_batch(deps, info, batch_id),
+        HandleMsg::CompleteAssetExchange {
+            exchanges,
+            to,
+            memo,
+        } => try_complete_asset_exchange(deps, env, info, exchanges, to, memo),
+        HandleMsg::QueueAssetExchange {
+            exchanges,
+            to,
+            memo,
+        } => try_queue_asset_exchange(deps, info, exchanges, to, memo),
+        HandleMsg::ProcessQueue { limit } => try_process_queue(deps, env, limit),
+        HandleMsg::SettleQueued { sequence } => try_settle_queued(deps, env, info, sequence),
+        HandleMsg::AuthorizeGpSettlement {
+            exchanges_hash,
+            expires,
+        } => try_authorize_gp_settlement(deps, env, info, exchanges_hash, expires),
+        HandleMsg::RevokeGpSettlement {} => try_revoke_gp_settlement(deps, env, info),
+        HandleMsg::CompleteAssetExchangeFor {
+            subscription,
+            exchanges,
+            to,
+        } => try_complete_asset_exchange_for(deps, env, info, subscription, exchanges, to),
+        HandleMsg::PayCapitalCall { exchange } => try_pay_capital_call(deps, env, info, exchange),
+        HandleMsg::ExpireCapitalCalls {} => try_expire_capital_calls(deps, env, info),
+        HandleMsg::IssueWithdrawal {
+            to,
+            amount,
+            memo,
+            denom,
+        } => {
+            let state = config(deps.storage).load()?;
+
+            if info.sender != state.gp {
+                return Err(ContractError::Unauthorized {
+                    required: "gp",
+                    action: "redeem capital",
+                });
+            }
+
+            if let Some(treasury) = &state.treasury {
+                if &to != treasury {
+                    return Err(ContractError::NotTreasury {
+                        treasury: treasury.clone(),
+                    });
+                }
+            }
+            state.check_destination(&to)?;
+
+            // supply accounting relies on commitment and investment coins staying put
+            let withdrawn = denom.unwrap_or_else(|| state.capital_denom.clone());
+            if !state.is_like_capital_denom(&withdrawn) {
+                return Err(ContractError::UnsupportedWithdrawalDenom { denom: withdrawn });
+            }
+
+            // capital escrowed for subs is held for them, so it can't be withdrawn
+            let escrowed = if withdrawn == state.capital_denom {
+                escrowed_capital_read(deps.storage)
+                    .may_load()?
+                    .unwrap_or_default()
+            } else {
+                0
+            };
+            // and so are funds a sub attached to a gp settlement authorization
+            let authorized = authorized_funds_read(deps.storage)
+                .may_load()?
+                .unwrap_or_default()
+                .into_iter()
+                .find(|held| held.denom == withdrawn)
+                .map_or(0, |held| held.amount.u128());
+            let available = deps
+                .querier
+                .query_balance(&env.contract.address, &withdrawn)?
+                .amount
+                .u128()
+                .saturating_sub(escrowed.into())
+                .saturating_sub(authorized);
+            if u128::from(amount) > available {
+                return Err(ContractError::WithdrawalExceedsAvailable {
+                    denom: withdrawn,
+                    available,
+                });
+            }
+
+            let send = if withdrawn == state.capital_denom {
+                send_capital(
+                    deps.as_ref(),
+                    &state,
+                    amount as u128,
+                    to,
+                    env.contract.address,
+                )?
+            } else {
+                send_coin(
+                    deps.as_ref(),
+                    &state,
+                    coin(amount.into(), withdrawn),
+                    to,
+                    env.contract.address,
+                )?
+            };
+            let events = memo_events(&state, std::slice::from_ref(&send), &memo);
+
+            let attributes = match memo {
+                Some(memo) => {
+                    vec![Attribute {
+                        key: String::from("memo"),
+                        value: memo,
+                    }]
+                }
+                None => vec![],
+            };
+
+            Ok(Response::new()
+                .add_message(send)
+                .add_attributes(attributes)
+                .add_events(events))
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use cosmwasm_std::testing::{mock_env, mock_info, MockApi, MockStorage, MOCK_CONTRACT_ADDR};
+    use cosmwasm_std::{coin, coins, Event, ReplyOn, SubMsgResponse};
+    use cosmwasm_std::{Addr, OwnedDeps};
+    use provwasm_mocks::{mock_dependencies, ProvenanceMockQuerier};
+    use provwasm_std::MarkerMsgParams;
+
+    use crate::mock::send_args;
+    use crate::mock::{load_markers, marker_transfer_msg, msg_at_index};
+    use crate::msg::AssetExchange;
+    use crate::msg::{QueryMsg, QueuedSettlement, RaiseState};
+    use crate::query::query;
+    use crate::reply::tests::instantiate_response_data;
+    use crate::state::asset_exchange_storage;
+    use crate::state::escrow_storage_read;
+    use crate::state::has_subscription_status;
+    use crate::state::notification_batch;
+    use crate::state::settlement_batch;
+    use crate::state::subscriptions_with_status;
+    use crate::state::tests::set_accepted;
+    use crate::state::SubscriptionStatus;
+    use crate::state::MAX_METADATA_FIELD_LENGTH;
+    use crate::state::{remove_asset_exchanges, save_asset_exchanges};
+    use crate::state::{
+        AutoAcceptConfig, CapitalExchangeRate, FundMetadata, SettlementHours, State,
+    };
+    use cosmwasm_std::from_binary;
+    use std::collections::BTreeSet;
+
+    use super::*;
+
+    impl State {
+        pub fn test_capital_coin() -> State {
+            State {
+                capital_denom: String::from("capital_coin"),
+                ..State::test_default()
+            }
+        }
+
+        pub fn test_restricted_capital_coin() -> State {
+            State {
+                capital_denom: String::from("restricted_capital_coin"),
+                required_capital_attribute: Some(String::from("capital.test")),
+                ..State::test_default()
+            }
+        }
+    }
+
+    pub fn default_deps(
+        update_state: Option<fn(&mut State)>,
+    ) -> OwnedDeps<MockStorage, MockApi, ProvenanceMockQuerier, ProvenanceQuery> {
+        let mut deps = mock_dependencies(&[]);
+
+        let mut state = State::test_default();
+        if let Some(update) = update_state {
+            update(&mut state);
+        }
+        config(&mut deps.storage).save(&state).unwrap();
+
+        deps
+    }
+
+    pub fn capital_coin_deps(
+        update_state: Option<fn(&mut State)>,
+    ) -> OwnedDeps<MockStorage, MockApi, ProvenanceMockQuerier, ProvenanceQuery> {
+        let mut deps = mock_dependencies(&[]);
+
+        let mut state = State::test_capital_coin();
+        if let Some(update) = update_state {
+            update(&mut state);
+        }
+        config(&mut deps.storage).save(&state).unwrap();
+
+        deps
+    }
+
+    pub fn restricted_capital_coin_deps(
+        update_state: Option<fn(&mut State)>,
+    ) -> OwnedDeps<MockStorage, MockApi, ProvenanceMockQuerier, ProvenanceQuery> {
+        let mut deps = mock_dependencies(&[]);
+
+        let mut state = State::test_restricted_capital_coin();
+        if let Some(update) = update_state {
+            update(&mut state);
+        }
+        config(&mut deps.storage).save(&state).unwrap();
+
+        deps
+    }
+
+    #[test]
+    fn reply_pending() {
+        let mut deps = default_deps(None);
+
+        reply(
+            deps.as_mut(),
+            mock_env(),
+            Reply {
+                id: PENDING_SUBSCRIPTION_REPLY_ID,
+                result: cosmwasm_std::SubMsgResult::Ok(SubMsgResponse {
+                    events: vec![
+                        Event::new("instantiate").add_attribute("_contract_address", "sub_1")
+                    ],
+                    data: None,
                 }),
             },
         )
         .unwrap();
 
-        // verify pending sub saved
+        // verify pending sub saved
+        assert_eq!(
+            "sub_1",
+            subscriptions_with_status(&deps.storage, SubscriptionStatus::Pending)
+                .unwrap()
+                .first()
+                .unwrap()
+                .as_str()
+        );
+    }
+
+    #[test]
+    fn reply_eligible() {
+        let mut deps = default_deps(None);
+
+        reply(
+            deps.as_mut(),
+            mock_env(),
+            Reply {
+                id: ELIGIBLE_SUBSCRIPTION_REPLY_ID,
+                result: cosmwasm_std::SubMsgResult::Ok(SubMsgResponse {
+                    events: vec![
+                        Event::new("instantiate").add_attribute("_contract_address", "sub_1")
+                    ],
+                    data: None,
+                }),
+            },
+        )
+        .unwrap();
+
+        // verify pending sub saved
+        assert_eq!(
+            "sub_1",
+            subscriptions_with_status(&deps.storage, SubscriptionStatus::Eligible)
+                .unwrap()
+                .first()
+                .unwrap()
+                .as_str()
+        );
+    }
+
+    #[test]
+    fn reply_auto_accepted() {
+        let mut deps = default_deps(Some(|state| {
+            state.auto_accept = Some(AutoAcceptConfig {
+                max_commitment: 1_000,
+                total_cap: 5_000,
+            });
+        }));
+        auto_accepted_capital(&mut deps.storage).save(&800).unwrap();
+        proposing_auto_accept(&mut deps.storage)
+            .save(&1_000)
+            .unwrap();
+
+        let res = reply(
+            deps.as_mut(),
+            mock_env(),
+            Reply {
+                id: ELIGIBLE_SUBSCRIPTION_REPLY_ID,
+                result: cosmwasm_std::SubMsgResult::Ok(SubMsgResponse {
+                    events: vec![
+                        Event::new("instantiate").add_attribute("_contract_address", "sub_1")
+                    ],
+                    data: None,
+                }),
+            },
+        )
+        .unwrap();
+        assert_eq!("sub_1", res.attributes[0].value);
+
+        // verify sub skips gp acceptance with its initial commitment to be issued
+        assert!(has_subscription_status(
+            &deps.storage,
+            &Addr::unchecked("sub_1"),
+            SubscriptionStatus::Accepted
+        )
+        .unwrap());
+        let exchanges = asset_exchange_storage(&mut deps.storage)
+            .load(b"sub_1")
+            .unwrap();
+        assert_eq!(Some(10), exchanges[0].commitment_in_shares);
+        assert_eq!(
+            Addr::unchecked(MOCK_CONTRACT_ADDR),
+            exchanges[0].provenance.as_ref().unwrap().issued_by
+        );
+        assert_eq!(
+            1_800,
+            auto_accepted_capital(&mut deps.storage).load().unwrap()
+        );
+        assert!(proposing_auto_accept(&mut deps.storage)
+            .may_load()
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn reply_records_lp() {
+        let mut deps = default_deps(None);
+        proposing_lp(&mut deps.storage)
+            .save(&Addr::unchecked("lp"))
+            .unwrap();
+        proposing_agent(&mut deps.storage)
+            .save(&Addr::unchecked("agent"))
+            .unwrap();
+        proposing_deposit(&mut deps.storage).save(&500).unwrap();
+
+        reply(
+            deps.as_mut(),
+            mock_env(),
+            Reply {
+                id: PENDING_SUBSCRIPTION_REPLY_ID,
+                result: cosmwasm_std::SubMsgResult::Ok(SubMsgResponse {
+                    events: vec![],
+                    data: Some(instantiate_response_data("sub_1", None)),
+                }),
+            },
+        )
+        .unwrap();
+
+        // verify sub is linked to the proposing lp
+        assert_eq!(
+            Addr::unchecked("lp"),
+            subscription_lp_storage(&mut deps.storage)
+                .load(Addr::unchecked("sub_1").as_bytes())
+                .unwrap()
+        );
+        assert!(proposing_lp(&mut deps.storage)
+            .may_load()
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            Addr::unchecked("agent"),
+            subscription_agent_storage(&mut deps.storage)
+                .load(Addr::unchecked("sub_1").as_bytes())
+                .unwrap()
+        );
+        assert!(
+            subscriptions_with_status(&deps.storage, SubscriptionStatus::Pending)
+                .unwrap()
+                .contains(&Addr::unchecked("sub_1"))
+        );
+
+        // verify capital attached to the proposal is escrowed for the sub
+        assert_eq!(
+            500,
+            escrow_storage_read(&deps.storage)
+                .load(Addr::unchecked("sub_1").as_bytes())
+                .unwrap()
+        );
+
+        // verify the lp's subs can be found from the lp until the sub is closed
+        let subscriptions_for_lp = |deps: &OwnedDeps<_, _, _, _>| -> Vec<Addr> {
+            from_binary(
+                &query(
+                    deps.as_ref(),
+                    mock_env(),
+                    QueryMsg::GetSubscriptionForLp {
+                        lp: Addr::unchecked("lp"),
+                    },
+                )
+                .unwrap(),
+            )
+            .unwrap()
+        };
+        assert_eq!(vec![Addr::unchecked("sub_1")], subscriptions_for_lp(&deps));
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::CloseSubscriptions {
+                subscriptions: vec![Addr::unchecked("sub_1")].into_iter().collect(),
+                treasury: None,
+                retirement: None,
+            },
+        )
+        .unwrap();
+        assert!(subscriptions_for_lp(&deps).is_empty());
+        let lp: Option<Addr> = from_binary(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::GetLpForSubscription {
+                    subscription: Addr::unchecked("sub_1"),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(None, lp);
+    }
+
+    #[test]
+    fn lp_index_tracks_each_subscription() {
+        let mut deps = default_deps(None);
+        for subscription in ["sub_1", "sub_2"] {
+            proposing_lp(&mut deps.storage)
+                .save(&Addr::unchecked("lp"))
+                .unwrap();
+            reply(
+                deps.as_mut(),
+                mock_env(),
+                Reply {
+                    id: PENDING_SUBSCRIPTION_REPLY_ID,
+                    result: cosmwasm_std::SubMsgResult::Ok(SubMsgResponse {
+                        events: vec![],
+                        data: Some(instantiate_response_data(subscription, None)),
+                    }),
+                },
+            )
+            .unwrap();
+        }
+        let subscriptions_for_lp = |deps: &OwnedDeps<_, _, _, _>, lp: &str| -> Vec<Addr> {
+            from_binary(
+                &query(
+                    deps.as_ref(),
+                    mock_env(),
+                    QueryMsg::GetSubscriptionForLp {
+                        lp: Addr::unchecked(lp),
+                    },
+                )
+                .unwrap(),
+            )
+            .unwrap()
+        };
+        let lp_for_subscription = |deps: &OwnedDeps<_, _, _, _>, subscription: &str| {
+            from_binary::<Option<Addr>>(
+                &query(
+                    deps.as_ref(),
+                    mock_env(),
+                    QueryMsg::GetLpForSubscription {
+                        subscription: Addr::unchecked(subscription),
+                    },
+                )
+                .unwrap(),
+            )
+            .unwrap()
+        };
+
+        assert_eq!(
+            vec![Addr::unchecked("sub_1"), Addr::unchecked("sub_2")],
+            subscriptions_for_lp(&deps, "lp")
+        );
+        assert_eq!(
+            Some(Addr::unchecked("lp")),
+            lp_for_subscription(&deps, "sub_2")
+        );
+
+        // verify unknown lps and subs aren't found
+        assert!(subscriptions_for_lp(&deps, "other_lp").is_empty());
+        assert_eq!(None, lp_for_subscription(&deps, "sub_3"));
+
+        // verify a rejected close leaves the index alone
+        let close = HandleMsg::CloseSubscriptions {
+            subscriptions: vec![Addr::unchecked("sub_1")].into_iter().collect(),
+            treasury: None,
+            retirement: None,
+        };
+        assert!(execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bad_actor", &[]),
+            close.clone()
+        )
+        .is_err());
+        assert_eq!(2, subscriptions_for_lp(&deps, "lp").len());
+
+        // verify closing one sub keeps the lp's other sub indexed
+        execute(deps.as_mut(), mock_env(), mock_info("gp", &[]), close).unwrap();
+        assert_eq!(
+            vec![Addr::unchecked("sub_2")],
+            subscriptions_for_lp(&deps, "lp")
+        );
+        assert_eq!(None, lp_for_subscription(&deps, "sub_1"));
+    }
+
+    #[test]
+    fn reply_unknown_id() {
+        let mut deps = default_deps(None);
+
+        let res = reply(
+            deps.as_mut(),
+            mock_env(),
+            Reply {
+                id: 99,
+                result: cosmwasm_std::SubMsgResult::Ok(SubMsgResponse {
+                    events: vec![],
+                    data: Some(instantiate_response_data("sub_1", None)),
+                }),
+            },
+        );
+
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn reply_notification_failed() {
+        let mut deps = default_deps(None);
+        notification_batch(&mut deps.storage)
+            .save(&vec![Addr::unchecked("sub_1"), Addr::unchecked("sub_2")])
+            .unwrap();
+
+        let res = reply(
+            deps.as_mut(),
+            mock_env(),
+            Reply {
+                id: NOTIFY_SUBSCRIPTION_REPLY_ID_OFFSET + 1,
+                result: cosmwasm_std::SubMsgResult::Err(String::from("unknown variant")),
+            },
+        )
+        .unwrap();
+
+        // verify the failure is reported without failing issuance
+        assert_eq!(
+            "sub_2",
+            res.attributes
+                .iter()
+                .find(|attr| attr.key == "notification_failed")
+                .unwrap()
+                .value
+        );
+    }
+
+    #[test]
+    fn reply_queued_settlement_failed() {
+        let mut deps = default_deps(None);
+        settlement_queue(&mut deps.storage)
+            .save(
+                &1u64.to_be_bytes(),
+                &QueuedSettlement {
+                    subscription: Addr::unchecked("sub_1"),
+                    exchanges: vec![],
+                    to: None,
+                    memo: None,
+                },
+            )
+            .unwrap();
+        settlement_batch(&mut deps.storage).save(&vec![1]).unwrap();
+
+        let res = reply(
+            deps.as_mut(),
+            mock_env(),
+            Reply {
+                id: SETTLE_QUEUED_REPLY_ID_OFFSET,
+                result: cosmwasm_std::SubMsgResult::Err(String::from("exchange past due")),
+            },
+        )
+        .unwrap();
+
+        // verify the failed settlement is dropped from the queue
         assert_eq!(
-            "sub_1",
-            pending_subscriptions_read(&deps.storage)
-                .load()
-                .unwrap()
+            "1",
+            res.attributes
                 .iter()
-                .next()
+                .find(|attr| attr.key == "queued_settlement_failed")
                 .unwrap()
-                .as_str()
+                .value
         );
+        assert!(settlement_queue(&mut deps.storage)
+            .may_load(&1u64.to_be_bytes())
+            .unwrap()
+            .is_none());
     }
 
     #[test]
-    fn reply_eligible() {
+    fn recover() {
+        let mut deps = default_deps(None);
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("marketpalace", &[]),
+            HandleMsg::Recover {
+                gp: Addr::unchecked("gp_2"),
+            },
+        )
+        .unwrap();
+
+        // verify that gp has been updated
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!("gp_2", state.gp);
+    }
+
+    #[test]
+    fn migrate_subscriptions_records_failures() {
         let mut deps = default_deps(None);
 
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::MigrateSubscriptions {
+                subscriptions: vec![Addr::unchecked("sub_1")].into_iter().collect(),
+            },
+        )
+        .unwrap();
+
+        // verify migration replies on error
+        assert_eq!(1, res.messages.len());
+        let sub_msg = res.messages.first().unwrap();
+        assert_eq!(MIGRATE_SUBSCRIPTION_REPLY_ID_OFFSET, sub_msg.id);
+        assert_eq!(ReplyOn::Error, sub_msg.reply_on);
+
         reply(
             deps.as_mut(),
             mock_env(),
             Reply {
-                id: 1,
-                result: cosmwasm_std::SubMsgResult::Ok(SubMsgResponse {
-                    events: vec![
-                        Event::new("contract address").add_attribute("_contract_address", "sub_1")
-                    ],
-                    data: None,
-                }),
+                id: MIGRATE_SUBSCRIPTION_REPLY_ID_OFFSET,
+                result: SubMsgResult::Err(String::from("incompatible version")),
             },
         )
         .unwrap();
 
-        // verify pending sub saved
+        // verify failure is recorded
         assert_eq!(
-            "sub_1",
-            eligible_subscriptions_read(&deps.storage)
-                .load()
-                .unwrap()
-                .iter()
-                .next()
+            "incompatible version",
+            migration_failure_storage_read(&deps.storage)
+                .load(b"sub_1")
                 .unwrap()
-                .as_str()
         );
+
+        // verify only the gp or admin can retry failed migrations
+        assert_eq!(
+            ContractError::Unauthorized {
+                required: "gp or admin",
+                action: "retry failed migrations",
+            },
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("lp", &[]),
+                HandleMsg::RetryFailedMigrations {},
+            )
+            .unwrap_err()
+        );
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::RetryFailedMigrations {},
+        )
+        .unwrap();
+
+        // verify failed sub is migrated again and its failure cleared
+        assert_eq!(1, res.messages.len());
+        assert!(migration_failure_storage_read(&deps.storage)
+            .may_load(b"sub_1")
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn set_pause() {
+        let mut deps = default_deps(None);
+
+        // only the recovery admin can pause
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::SetPause { paused: true },
+        );
+        assert!(res.is_err());
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("marketpalace", &[]),
+            HandleMsg::SetPause { paused: true },
+        )
+        .unwrap();
+
+        // verify lp facing operations are rejected while paused
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("lp", &[]),
+            HandleMsg::ProposeSubscription {
+                initial_commitment: None,
+                owner: None,
+                forward_funds: false,
+            },
+        );
+        assert_eq!(ContractError::Paused {}, res.unwrap_err());
+
+        // verify recovery still works while paused
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("marketpalace", &[]),
+            HandleMsg::Recover {
+                gp: Addr::unchecked("gp_2"),
+            },
+        )
+        .unwrap();
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("marketpalace", &[]),
+            HandleMsg::SetPause { paused: false },
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("lp", &[]),
+            HandleMsg::ProposeSubscription {
+                initial_commitment: None,
+                owner: None,
+                forward_funds: false,
+            },
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn update_required_capital_attribute() {
+        let mut deps = capital_coin_deps(Some(|state| {
+            state.capital_exchange_rates = vec![CapitalExchangeRate {
+                denom: String::from("other_coin"),
+                numerator: 1,
+                denominator: 1,
+            }];
+        }));
+        deps.querier
+            .with_attributes("holder", &[("other.test", "", "")]);
+        deps.querier
+            .base
+            .update_balance(MOCK_CONTRACT_ADDR, coins(100, "other_coin"));
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("marketpalace", &[]),
+            HandleMsg::UpdateRequiredCapitalAttribute {
+                denom: String::from("capital_coin"),
+                required_capital_attribute: Some(String::from("capital.test")),
+            },
+        )
+        .unwrap();
+
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(
+            Some(String::from("capital.test")),
+            state.required_capital_attribute
+        );
+
+        // a like capital denom keeps its own attribute
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::UpdateRequiredCapitalAttribute {
+                denom: String::from("other_coin"),
+                required_capital_attribute: Some(String::from("other.test")),
+            },
+        )
+        .unwrap();
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(
+            vec![CapitalAttribute {
+                denom: String::from("other_coin"),
+                attribute: String::from("other.test"),
+            }],
+            state.capital_attributes
+        );
+        assert_eq!(
+            Some(&String::from("capital.test")),
+            state.required_attribute_for("capital_coin")
+        );
+
+        // which restricts where it can be withdrawn to
+        let withdraw = |deps: &mut OwnedDeps<_, _, _, _>, to: &str| {
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("gp", &[]),
+                HandleMsg::IssueWithdrawal {
+                    to: Addr::unchecked(to),
+                    amount: 100,
+                    memo: None,
+                    denom: Some(String::from("other_coin")),
+                },
+            )
+        };
+        assert_eq!(
+            ContractError::MissingCapitalAttribute {
+                address: Addr::unchecked("stranger"),
+                attribute: String::from("other.test"),
+            },
+            withdraw(&mut deps, "stranger").unwrap_err()
+        );
+        let res = withdraw(&mut deps, "holder").unwrap();
+        assert_eq!(
+            &MarkerMsgParams::TransferMarkerCoins {
+                coin: coin(100, "other_coin"),
+                to: Addr::unchecked("holder"),
+                from: Addr::unchecked(MOCK_CONTRACT_ADDR),
+            },
+            marker_transfer_msg(msg_at_index(&res, 0)),
+        );
+
+        // only capital and like capital denoms can require an attribute
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::UpdateRequiredCapitalAttribute {
+                denom: String::from("unknown_coin"),
+                required_capital_attribute: Some(String::from("capital.test")),
+            },
+        );
+        assert_eq!(
+            ContractError::InvalidDenom {
+                denom: String::from("unknown_coin"),
+            },
+            res.unwrap_err()
+        );
+
+        // verify bad actor can't update
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bad_actor", &[]),
+            HandleMsg::UpdateRequiredCapitalAttribute {
+                denom: String::from("capital_coin"),
+                required_capital_attribute: None,
+            },
+        );
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn remove_required_capital_attribute_with_outstanding_exchange() {
+        let mut deps = restricted_capital_coin_deps(None);
+        set_accepted(&mut deps.storage, vec!["sub_1"]);
+        save_asset_exchanges(
+            &mut deps.storage,
+            &Addr::unchecked("sub_1"),
+            &[AssetExchange {
+                capital: Some(1_000),
+                ..AssetExchange::default()
+            }],
+        )
+        .unwrap();
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::UpdateRequiredCapitalAttribute {
+                denom: String::from("restricted_capital_coin"),
+                required_capital_attribute: None,
+            },
+        );
+        assert_eq!(
+            ContractError::InvalidRequest {
+                reason: "outstanding capital exchanges need the capital attribute",
+            },
+            res.unwrap_err()
+        );
+
+        remove_asset_exchanges(&mut deps.storage, &Addr::unchecked("sub_1")).unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::UpdateRequiredCapitalAttribute {
+                denom: String::from("restricted_capital_coin"),
+                required_capital_attribute: None,
+            },
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn add_delegate_bad_actor() {
+        let mut deps = default_deps(None);
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bad_actor", &[]),
+            HandleMsg::AddDelegate {
+                delegate: Addr::unchecked("bad_actor"),
+            },
+        );
+        assert!(res.is_err());
+        assert!(config_read(&deps.storage)
+            .load()
+            .unwrap()
+            .delegates
+            .is_empty());
+    }
+
+    #[test]
+    fn update_required_attestations() {
+        let mut deps = default_deps(None);
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::UpdateRequiredAttestations {
+                required_attestations: vec![],
+            },
+        )
+        .unwrap();
+
+        // verify that gp has been updated
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(0, state.required_attestations.len());
+
+        // verify an empty attestation set is rejected and nothing is saved
+        assert_eq!(
+            ContractError::EmptyAttestationSet { index: 1 },
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("gp", &[]),
+                HandleMsg::UpdateRequiredAttestations {
+                    required_attestations: vec![
+                        BTreeSet::from([String::from("506c")]),
+                        BTreeSet::new(),
+                    ],
+                },
+            )
+            .unwrap_err()
+        );
+        assert!(config_read(&deps.storage)
+            .load()
+            .unwrap()
+            .required_attestations
+            .is_empty());
+    }
+
+    #[test]
+    fn update_due_grace_period() {
+        let mut deps = default_deps(None);
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::UpdateDueGracePeriod {
+                due_grace_seconds: 3_600,
+            },
+        )
+        .unwrap();
+
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(3_600, state.due_grace_seconds);
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bad_actor", &[]),
+            HandleMsg::UpdateDueGracePeriod {
+                due_grace_seconds: 0,
+            },
+        );
+        assert!(res.is_err());
     }
 
     #[test]
-    fn recover() {
+    fn update_settlement_hours() {
         let mut deps = default_deps(None);
+        let hours = SettlementHours {
+            weekdays: BTreeSet::from([0, 1, 2, 3, 4]),
+            start_seconds: 9 * 3_600,
+            end_seconds: 17 * 3_600,
+        };
 
         execute(
             deps.as_mut(),
             mock_env(),
-            mock_info("marketpalace", &vec![]),
-            HandleMsg::Recover {
-                gp: Addr::unchecked("gp_2"),
+            mock_info("gp", &[]),
+            HandleMsg::UpdateSettlementHours {
+                settlement_hours: Some(hours.clone()),
             },
         )
         .unwrap();
-
-        // verify that gp has been updated
         let state = config_read(&deps.storage).load().unwrap();
-        assert_eq!("gp_2", state.gp);
+        assert_eq!(Some(hours.clone()), state.settlement_hours);
+
+        // verify an empty window is rejected
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::UpdateSettlementHours {
+                settlement_hours: Some(SettlementHours {
+                    start_seconds: 17 * 3_600,
+                    ..hours
+                }),
+            },
+        );
+        assert!(res.is_err());
     }
 
     #[test]
-    fn update_required_attestations() {
+    fn update_receipt_retention() {
         let mut deps = default_deps(None);
 
         execute(
             deps.as_mut(),
             mock_env(),
-            mock_info("gp", &vec![]),
-            HandleMsg::UpdateRequiredAttestations {
-                required_attestations: vec![],
+            mock_info("gp", &[]),
+            HandleMsg::UpdateReceiptRetention {
+                max_receipts: Some(50),
             },
         )
         .unwrap();
 
-        // verify that gp has been updated
         let state = config_read(&deps.storage).load().unwrap();
-        assert_eq!(0, state.required_attestations.len());
+        assert_eq!(Some(50), state.max_receipts);
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bad_actor", &[]),
+            HandleMsg::UpdateReceiptRetention { max_receipts: None },
+        );
+        assert!(res.is_err());
     }
 
     #[test]
@@ -400,7 +2230,7 @@ pub mod tests {
         let res = execute(
             deps.as_mut(),
             mock_env(),
-            mock_info("bad_actor", &vec![]),
+            mock_info("bad_actor", &[]),
             HandleMsg::Recover {
                 gp: Addr::unchecked("bad_actor"),
             },
@@ -416,6 +2246,9 @@ pub mod tests {
     fn issue_withdrawal() {
         let mut deps = capital_coin_deps(None);
         load_markers(&mut deps.querier);
+        deps.querier
+            .base
+            .update_balance(MOCK_CONTRACT_ADDR, coins(10_000, "capital_coin"));
 
         let res = execute(
             deps.as_mut(),
@@ -425,6 +2258,7 @@ pub mod tests {
                 to: Addr::unchecked("omni"),
                 amount: 10_000,
                 memo: None,
+                denom: None,
             },
         )
         .unwrap();
@@ -436,12 +2270,121 @@ pub mod tests {
         assert_eq!(10_000, coins.first().unwrap().amount.u128());
     }
 
+    #[test]
+    fn issue_withdrawal_above_available() {
+        let mut deps = capital_coin_deps(None);
+        load_markers(&mut deps.querier);
+        deps.querier
+            .base
+            .update_balance(MOCK_CONTRACT_ADDR, coins(10_000, "capital_coin"));
+        save_escrow(&mut deps.storage, &Addr::unchecked("sub_1"), 4_000).unwrap();
+        let mut withdraw = |amount| {
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("gp", &[]),
+                HandleMsg::IssueWithdrawal {
+                    to: Addr::unchecked("omni"),
+                    amount,
+                    memo: None,
+                    denom: None,
+                },
+            )
+        };
+
+        // verify more than the contract holds can't be withdrawn
+        assert_eq!(
+            ContractError::WithdrawalExceedsAvailable {
+                denom: String::from("capital_coin"),
+                available: 6_000,
+            },
+            withdraw(10_001).unwrap_err()
+        );
+
+        // verify capital escrowed for subs is left in place
+        assert_eq!(
+            ContractError::WithdrawalExceedsAvailable {
+                denom: String::from("capital_coin"),
+                available: 6_000,
+            },
+            withdraw(6_001).unwrap_err()
+        );
+        let res = withdraw(6_000).unwrap();
+        let (_, coins) = send_args(msg_at_index(&res, 0));
+        assert_eq!(6_000, coins.first().unwrap().amount.u128());
+    }
+
+    #[test]
+    fn issue_withdrawal_with_authorized_settlement() {
+        let mut deps = capital_coin_deps(None);
+        load_markers(&mut deps.querier);
+        set_accepted(&mut deps.storage, vec!["sub_1"]);
+        deps.querier
+            .base
+            .update_balance(MOCK_CONTRACT_ADDR, coins(10_000, "capital_coin"));
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &coins(3_000, "capital_coin")),
+            HandleMsg::AuthorizeGpSettlement {
+                exchanges_hash: String::from("hash"),
+                expires: mock_env().block.time.seconds() + 60,
+            },
+        )
+        .unwrap();
+        let withdrawal = |amount| HandleMsg::IssueWithdrawal {
+            to: Addr::unchecked("omni"),
+            amount,
+            memo: None,
+            denom: None,
+        };
+
+        // verify funds held for an outstanding authorization are left in place
+        assert_eq!(
+            ContractError::WithdrawalExceedsAvailable {
+                denom: String::from("capital_coin"),
+                available: 7_000,
+            },
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("gp", &[]),
+                withdrawal(7_001)
+            )
+            .unwrap_err()
+        );
+
+        // verify revoking the authorization releases the funds it held
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &[]),
+            HandleMsg::RevokeGpSettlement {},
+        )
+        .unwrap();
+        deps.querier
+            .base
+            .update_balance(MOCK_CONTRACT_ADDR, coins(7_000, "capital_coin"));
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            withdrawal(7_000),
+        )
+        .unwrap();
+        let (_, coins) = send_args(msg_at_index(&res, 0));
+        assert_eq!(7_000, coins.first().unwrap().amount.u128());
+    }
+
     #[test]
     fn issue_restricted_coin_withdrawal() {
         let mut deps = restricted_capital_coin_deps(None);
         deps.querier
             .with_attributes("omni", &[("capital.test", "", "")]);
         load_markers(&mut deps.querier);
+        deps.querier
+            .base
+            .update_balance(MOCK_CONTRACT_ADDR, coins(10_000, "restricted_capital_coin"));
 
         let res = execute(
             deps.as_mut(),
@@ -451,6 +2394,7 @@ pub mod tests {
                 to: Addr::unchecked("omni"),
                 amount: 10_000,
                 memo: None,
+                denom: None,
             },
         )
         .unwrap();
@@ -467,6 +2411,25 @@ pub mod tests {
         );
     }
 
+    #[test]
+    fn issue_restricted_coin_withdrawal_missing_attribute() {
+        let mut deps = restricted_capital_coin_deps(None);
+        load_markers(&mut deps.querier);
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::IssueWithdrawal {
+                to: Addr::unchecked("omni"),
+                amount: 10_000,
+                memo: None,
+                denom: None,
+            },
+        );
+        assert!(res.is_err());
+    }
+
     #[test]
     fn issue_withdrawal_bad_actor() {
         let mut deps = default_deps(None);
@@ -479,8 +2442,312 @@ pub mod tests {
                 to: Addr::unchecked("omni"),
                 amount: 10_000,
                 memo: None,
+                denom: None,
+            },
+        );
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn update_metadata() {
+        let mut deps = default_deps(None);
+        let metadata = FundMetadata {
+            name: Some(String::from("Fund I")),
+            terms_uri: Some(String::from("https://example.com/terms")),
+            jurisdiction: Some(String::from("Delaware")),
+            closing_date: Some(1_700_000_000),
+            ..FundMetadata::default()
+        };
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bad_actor", &[]),
+            HandleMsg::UpdateMetadata {
+                metadata: metadata.clone(),
+            },
+        );
+        assert_eq!(
+            ContractError::Unauthorized {
+                required: "gp",
+                action: "update metadata",
+            },
+            res.unwrap_err()
+        );
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::UpdateMetadata {
+                metadata: metadata.clone(),
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            metadata,
+            config_read(&deps.storage).load().unwrap().metadata
+        );
+
+        // verify oversized fields are rejected
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::UpdateMetadata {
+                metadata: FundMetadata {
+                    jurisdiction: Some("x".repeat(MAX_METADATA_FIELD_LENGTH + 1)),
+                    ..metadata.clone()
+                },
+            },
+        );
+        assert_eq!(
+            ContractError::MetadataTooLong {
+                field: "jurisdiction",
+                max: MAX_METADATA_FIELD_LENGTH
+            },
+            res.unwrap_err()
+        );
+        assert_eq!(
+            metadata,
+            config_read(&deps.storage).load().unwrap().metadata
+        );
+
+        // verify fields right at the limit are accepted and reported in state
+        let metadata = FundMetadata {
+            logo_uri: Some("x".repeat(MAX_METADATA_FIELD_LENGTH)),
+            ..metadata
+        };
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::UpdateMetadata {
+                metadata: metadata.clone(),
+            },
+        )
+        .unwrap();
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetState {}).unwrap();
+        assert_eq!(
+            metadata,
+            from_binary::<RaiseState>(&res).unwrap().general.metadata
+        );
+    }
+
+    #[test]
+    fn issue_withdrawal_in_like_capital_denom() {
+        let mut deps = capital_coin_deps(Some(|state| {
+            state.capital_exchange_rates = vec![CapitalExchangeRate {
+                denom: String::from("other_coin"),
+                numerator: 1,
+                denominator: 1,
+            }];
+        }));
+        load_markers(&mut deps.querier);
+        deps.querier
+            .base
+            .update_balance(MOCK_CONTRACT_ADDR, coins(1_000, "other_coin"));
+        let mut withdraw = |denom: &str| {
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("gp", &[]),
+                HandleMsg::IssueWithdrawal {
+                    to: Addr::unchecked("omni"),
+                    amount: 1_000,
+                    memo: None,
+                    denom: Some(String::from(denom)),
+                },
+            )
+        };
+
+        // verify the fund's own commitment and investment coins can't be withdrawn
+        for denom in ["commitment_coin", "investment_coin"] {
+            assert_eq!(
+                ContractError::UnsupportedWithdrawalDenom {
+                    denom: String::from(denom)
+                },
+                withdraw(denom).unwrap_err()
+            );
+        }
+
+        let res = withdraw("other_coin").unwrap();
+        let (to_address, coins) = send_args(msg_at_index(&res, 0));
+        assert_eq!("omni", to_address);
+        assert_eq!(&coin(1_000, "other_coin"), coins.first().unwrap());
+    }
+
+    #[test]
+    fn issue_withdrawal_outside_treasury() {
+        let mut deps = capital_coin_deps(Some(|state| {
+            state.treasury = Some(Addr::unchecked("treasury"));
+        }));
+        load_markers(&mut deps.querier);
+        deps.querier
+            .base
+            .update_balance(MOCK_CONTRACT_ADDR, coins(10_000, "capital_coin"));
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::IssueWithdrawal {
+                to: Addr::unchecked("omni"),
+                amount: 10_000,
+                memo: None,
+                denom: None,
+            },
+        );
+        assert_eq!(
+            ContractError::NotTreasury {
+                treasury: Addr::unchecked("treasury")
+            },
+            res.unwrap_err()
+        );
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::IssueWithdrawal {
+                to: Addr::unchecked("treasury"),
+                amount: 10_000,
+                memo: None,
+                denom: None,
+            },
+        )
+        .unwrap();
+        let (to_address, _) = send_args(msg_at_index(&res, 0));
+        assert_eq!("treasury", to_address);
+    }
+
+    #[test]
+    fn issue_withdrawal_to_unapproved_destination() {
+        let mut deps = capital_coin_deps(None);
+        load_markers(&mut deps.querier);
+        deps.querier
+            .base
+            .update_balance(MOCK_CONTRACT_ADDR, coins(10_000, "capital_coin"));
+        let update = HandleMsg::UpdateApprovedDestinations {
+            approved_destinations: Some(BTreeSet::from([Addr::unchecked("approved")])),
+        };
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bad_actor", &[]),
+            update.clone(),
+        );
+        assert!(res.is_err());
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("marketpalace", &[]),
+            update,
+        )
+        .unwrap();
+
+        let withdraw = |deps: &mut OwnedDeps<_, _, _, _>, to: &str| {
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("gp", &[]),
+                HandleMsg::IssueWithdrawal {
+                    to: Addr::unchecked(to),
+                    amount: 10_000,
+                    memo: None,
+                    denom: None,
+                },
+            )
+        };
+        assert_eq!(
+            ContractError::DestinationNotApproved {
+                destination: Addr::unchecked("omni")
+            },
+            withdraw(&mut deps, "omni").unwrap_err()
+        );
+        let res = withdraw(&mut deps, "approved").unwrap();
+        let (to_address, _) = send_args(msg_at_index(&res, 0));
+        assert_eq!("approved", to_address);
+    }
+
+    #[test]
+    fn update_treasury_timelocked() {
+        let mut deps = default_deps(None);
+
+        // the first treasury applies immediately
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::UpdateTreasury {
+                treasury: Some(Addr::unchecked("treasury")),
+            },
+        )
+        .unwrap();
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(Some(Addr::unchecked("treasury")), state.treasury);
+
+        // later changes wait for the timelock
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::UpdateTreasury {
+                treasury: Some(Addr::unchecked("other")),
             },
+        )
+        .unwrap();
+        let effective_at = mock_env().block.time.seconds() + TREASURY_TIMELOCK_SECONDS;
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::ApplyTreasuryUpdate {},
+        );
+        assert_eq!(ContractError::Timelocked { effective_at }, res.unwrap_err());
+
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(TREASURY_TIMELOCK_SECONDS);
+        execute(
+            deps.as_mut(),
+            env,
+            mock_info("gp", &[]),
+            HandleMsg::ApplyTreasuryUpdate {},
+        )
+        .unwrap();
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(Some(Addr::unchecked("other")), state.treasury);
+        assert_eq!(None, state.pending_treasury);
+    }
+
+    #[test]
+    fn cancel_treasury_update() {
+        let mut deps = default_deps(Some(|state| {
+            state.treasury = Some(Addr::unchecked("treasury"));
+            state.pending_treasury = Some(PendingTreasury {
+                treasury: None,
+                effective_at: 0,
+            });
+        }));
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bad_actor", &[]),
+            HandleMsg::CancelTreasuryUpdate {},
         );
         assert!(res.is_err());
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("marketpalace", &[]),
+            HandleMsg::CancelTreasuryUpdate {},
+        )
+        .unwrap();
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(Some(Addr::unchecked("treasury")), state.treasury);
+        assert_eq!(None, state.pending_treasury);
     }
 }
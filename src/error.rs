@@ -1,24 +1,181 @@
 use std::num::TryFromIntError;
 
-use cosmwasm_std::{Response, StdError};
-use provwasm_std::ProvenanceMsg;
+use cosmwasm_std::{Addr, StdError};
 use thiserror::Error;
 
-#[derive(Error, Debug)]
+#[derive(Error, Debug, PartialEq)]
 pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
-    #[error("Unauthorized")]
-    Unauthorized {},
-    // Add any other custom errors you like here.
-    // Look at https://docs.rs/thiserror/1.0.21/thiserror/ for details.
-}
+    #[error("only {required} can {action}")]
+    Unauthorized {
+        required: &'static str,
+        action: &'static str,
+    },
 
-impl From<&str> for ContractError {
-    fn from(msg: &str) -> Self {
-        ContractError::Std(StdError::generic_err(msg))
-    }
+    #[error("contract paused")]
+    Paused {},
+
+    #[error("marker setup for {denom} is incomplete")]
+    MarkerSetupIncomplete { denom: String },
+
+    #[error("raise is not accepting proposals")]
+    ProposalsClosed {},
+
+    #[error("next proposal allowed at height {available_at}")]
+    ProposalCooldown { available_at: u64 },
+
+    #[error("{proposer} already has the maximum of {max} pending proposals")]
+    PendingLimitExceeded { proposer: Addr, max: u64 },
+
+    #[error("subscription {subscription} must be {expected}")]
+    SubscriptionNotFound {
+        subscription: Addr,
+        expected: &'static str,
+    },
+
+    #[error("no asset exchange found for subscription {subscription}")]
+    ExchangeNotFound { subscription: Addr },
+
+    #[error("duplicate exchange id {exchange_id}")]
+    DuplicateExchangeId { exchange_id: String },
+
+    #[error("exchange not yet available")]
+    NotYetAvailable {},
+
+    #[error("exchange past due")]
+    PastDue {},
+
+    #[error("capital call past due requires late fee of {late_fee}")]
+    LateFeeRequired { late_fee: u128 },
+
+    #[error("{amount} must be evenly divisible by capital per share of {capital_per_share}")]
+    IndivisibleAmount { amount: u64, capital_per_share: u64 },
+
+    #[error("invalid payment of {paid} for capital call of {owed}")]
+    InvalidPayment { paid: u64, owed: u64 },
+
+    #[error("subscription {subscription} cannot settle capital denom {denom}")]
+    UnsupportedCapitalDenom { subscription: Addr, denom: String },
+
+    #[error("converting {from} to {to} overflows")]
+    ConversionOverflow { from: String, to: String },
+
+    #[error("distributions can't pay out in {denom}")]
+    UnsupportedDistributionDenom { denom: String },
+
+    #[error("withdrawals can't be made in {denom}")]
+    UnsupportedWithdrawalDenom { denom: String },
+
+    #[error("only {available} {denom} is available to withdraw")]
+    WithdrawalExceedsAvailable { denom: String, available: u128 },
+
+    #[error("capital per share must be greater than zero")]
+    ZeroCapitalPerShare {},
+
+    #[error("invalid denom {denom}")]
+    InvalidDenom { denom: String },
+
+    #[error("metadata {field} exceeds {max} bytes")]
+    MetadataTooLong { field: &'static str, max: usize },
+
+    #[error("denom {denom} is used more than once")]
+    DuplicateDenom { denom: String },
+
+    #[error("required attestation set {index} is empty")]
+    EmptyAttestationSet { index: usize },
+
+    #[error("initial commitment of {initial_commitment} is below the minimum of {min_initial_commitment}")]
+    CommitmentBelowMinimum {
+        initial_commitment: u64,
+        min_initial_commitment: u64,
+    },
+
+    #[error("unknown share class {class}")]
+    UnknownShareClass { class: String },
+
+    #[error("{address} does not have required attestation of {attestation}")]
+    MissingAttestation { address: Addr, attestation: String },
+
+    #[error("subscription owner must have one of acceptable attestations: {acceptable:?}")]
+    MissingAcceptableAttestation { acceptable: Vec<String> },
+
+    #[error("{address} is not eligible according to compliance contract {compliance_contract}")]
+    NotEligible {
+        address: Addr,
+        compliance_contract: Addr,
+    },
+
+    #[error("{address} does not have required attribute of {attribute}")]
+    MissingCapitalAttribute { address: Addr, attribute: String },
+
+    #[error("subscription {subscription} still has remaining {denom}")]
+    RemainingBalance { subscription: Addr, denom: String },
+
+    #[error("subscription {subscription} holds less than {required} {denom}")]
+    InsufficientBalance {
+        subscription: Addr,
+        denom: String,
+        required: u64,
+    },
+
+    #[error("withdrawals may only be sent to treasury {treasury}")]
+    NotTreasury { treasury: Addr },
+
+    #[error("capital can't be sent to unapproved destination {destination}")]
+    DestinationNotApproved { destination: Addr },
+
+    #[error("timelocked until {effective_at}")]
+    Timelocked { effective_at: u64 },
+
+    #[error("outstanding {obligation} would exceed the maximum of {max}")]
+    ObligationLimitExceeded { obligation: &'static str, max: u64 },
+
+    #[error("outside settlement hours")]
+    OutsideSettlementHours {},
+
+    #[error("invalid settlement hours")]
+    InvalidSettlementHours {},
+
+    #[error("unknown reply id {id}")]
+    UnknownReplyId { id: u64 },
+
+    #[error("no {item} found for reply {id}")]
+    ReplyItemNotFound { item: &'static str, id: u64 },
+
+    #[error("subscription contract instantiation failed")]
+    InstantiationFailed {},
+
+    #[error(
+        "{subscription} would own {ownership_bps} bps, above the maximum of {max_ownership_bps}"
+    )]
+    OwnershipLimitExceeded {
+        subscription: Addr,
+        ownership_bps: u64,
+        max_ownership_bps: u16,
+    },
+
+    #[error("fund term ended at {term_end}")]
+    FundTermEnded { term_end: u64 },
+
+    #[error("no nav has been posted")]
+    NavNotPosted {},
+
+    #[error("subscription {subscription} has not authorized gp settlement")]
+    SettlementNotAuthorized { subscription: Addr },
+
+    #[error("gp settlement authorization expired at {expires}")]
+    SettlementAuthorizationExpired { expires: u64 },
+
+    #[error("exchanges do not match the authorized hash")]
+    SettlementAuthorizationMismatch {},
+
+    #[error("queued settlement {sequence} not found")]
+    QueuedSettlementNotFound { sequence: u64 },
+
+    #[error("{reason}")]
+    InvalidRequest { reason: &'static str },
 }
 
 impl From<TryFromIntError> for ContractError {
@@ -26,7 +183,3 @@ impl From<TryFromIntError> for ContractError {
         ContractError::Std(StdError::generic_err(err.to_string()))
     }
 }
-
-pub fn contract_error(err: &str) -> Result<Response<ProvenanceMsg>, ContractError> {
-    Err(ContractError::Std(StdError::generic_err(err)))
-}
@@ -1,49 +1,371 @@
-use std::cmp::Ordering;
-use std::vec::IntoIter;
+use std::convert::{TryFrom, TryInto};
 
-use cosmwasm_std::{coins, Addr, BankMsg, DepsMut, Env, MessageInfo, Response};
-use provwasm_std::{
-    burn_marker_supply, mint_marker_supply, transfer_marker_coins, withdraw_coins,
-    ProvenanceQuerier, ProvenanceQuery,
+use cosmwasm_std::{
+    coin, to_binary, Addr, BankMsg, Coin, CosmosMsg, Deps, DepsMut, Env, MessageInfo, Order,
+    Response, StdError, StdResult, Storage, SubMsg, WasmMsg,
 };
+use provwasm_std::{ProvenanceMsg, ProvenanceQuery};
+use sha2::{Digest, Sha256};
 
 use crate::{
     contract::ContractResponse,
-    error::contract_error,
-    msg::{AssetExchange, ExchangeDate, IssueAssetExchange},
-    state::{accepted_subscriptions_read, asset_exchange_storage, config_read},
+    error::ContractError,
+    msg::{
+        AssetExchange, ChangedEntity, CompletedAssetExchange, ExchangeProvenance,
+        ExchangeReference, HandleMsg, IssueAssetExchange, QueuedSettlement, Receipt,
+        SettlementAuthorization, SubscriptionAssetExchanges,
+    },
+    ownership::check_ownership,
+    reply::{NOTIFY_SUBSCRIPTION_REPLY_ID_OFFSET, SETTLE_QUEUED_REPLY_ID_OFFSET},
+    settlement::{
+        cache_marker_addresses, check_capital_attribute, memo_events, send_coin, Settlement,
+    },
+    state::{
+        asset_exchange_storage_read, batch_cancellation_storage, completed_exchange_storage,
+        config_read, escrow_storage_read, has_subscription_status, issuance_batch_storage,
+        issuance_batch_storage_read, issuance_sequence, notification_batch,
+        notifications_disabled_storage_read, outstanding_obligations_read, prepayment_storage,
+        receipt_sequence_storage, receipt_storage, receipt_storage_read, record_change,
+        remove_settlement_authorization, save_asset_exchanges, save_escrow,
+        save_settlement_authorization, settlement_authorization_storage_read, settlement_batch,
+        settlement_queue, settlement_queue_read, settlement_queue_sequence,
+        subscription_lp_storage_read, subscription_stats_storage, subscriptions_with_status, State,
+        SubscriptionStatus, MAX_BATCH_CANCELLATIONS, MAX_COMPLETED_EXCHANGES, MAX_QUEUE_BATCH,
+    },
+    sub_msg::{SubExecuteMsg, SubQueryMsg, SubState},
+    subscribe::{attributes, lp_for_sub},
 };
 
 pub fn try_issue_asset_exchanges(
     deps: DepsMut<ProvenanceQuery>,
+    env: Env,
     info: MessageInfo,
     asset_exchanges: Vec<IssueAssetExchange>,
+    notify_subscriptions: bool,
+    verify_capital_denom: bool,
 ) -> ContractResponse {
     let state = config_read(deps.storage).load()?;
-    let accepted = accepted_subscriptions_read(deps.storage)
-        .may_load()?
-        .unwrap_or_default();
-    let mut storage = asset_exchange_storage(deps.storage);
 
-    if info.sender != state.gp {
-        return contract_error("only gp can issue redemptions");
+    if !state.is_gp_or_delegate(&info.sender) {
+        return Err(ContractError::Unauthorized {
+            required: "gp or delegate",
+            action: "issue redemptions",
+        });
     }
 
+    let batch = issuance_sequence(deps.storage)
+        .may_load()?
+        .unwrap_or_default()
+        + 1;
+    issuance_sequence(deps.storage).save(&batch)?;
+
+    let mut notifications = vec![];
+    let mut notified = vec![];
+    let mut issued = vec![];
     for mut issuance in asset_exchanges {
-        if !accepted.contains(&issuance.subscription) {
-            return contract_error("subscription not accepted");
+        if !has_subscription_status(
+            deps.storage,
+            &issuance.subscription,
+            SubscriptionStatus::Accepted,
+        )? {
+            return Err(ContractError::SubscriptionNotFound {
+                subscription: issuance.subscription,
+                expected: "accepted",
+            });
+        }
+
+        if let Some(term_end) = state.term_end {
+            if env.block.time.seconds() >= term_end
+                && issuance
+                    .exchanges
+                    .iter()
+                    .any(|exchange| exchange.is_capital_call())
+            {
+                return Err(ContractError::FundTermEnded { term_end });
+            }
+        }
+
+        // catch raise and sub denom drift before an lp tries to settle
+        if verify_capital_denom
+            && issuance
+                .exchanges
+                .iter()
+                .any(|exchange| exchange.capital.unwrap_or_default() != 0)
+        {
+            let sub_state: SubState = deps
+                .querier
+                .query_wasm_smart(issuance.subscription.clone(), &SubQueryMsg::GetState {})?;
+            if !sub_state.can_settle(&state.capital_denom) {
+                return Err(ContractError::UnsupportedCapitalDenom {
+                    subscription: issuance.subscription,
+                    denom: state.capital_denom,
+                });
+            }
+        }
+
+        // credit any capital prepaid by the sub against newly issued capital calls
+        let mut prepayment = prepayment_storage(deps.storage)
+            .may_load(issuance.subscription.as_bytes())?
+            .unwrap_or_default();
+        if prepayment > 0 {
+            for exchange in issuance.exchanges.iter_mut() {
+                if let Some(capital) = exchange.capital.filter(|capital| *capital < 0) {
+                    // a partial credit stops at a whole share so the rest can still be paid
+                    let owed = capital.unsigned_abs();
+                    let mut credit = prepayment.min(owed);
+                    if credit < owed {
+                        credit -= credit % state.capital_per_share;
+                    }
+                    exchange.capital = Some(capital + i64::try_from(credit)?);
+                    prepayment -= credit;
+                }
+            }
+            prepayment_storage(deps.storage).save(issuance.subscription.as_bytes(), &prepayment)?;
+            record_change(
+                deps.storage,
+                ChangedEntity::Prepayment,
+                issuance.subscription.as_str(),
+            )?;
         }
 
-        let mut existing = storage
+        let mut existing = asset_exchange_storage_read(deps.storage)
             .may_load(issuance.subscription.as_bytes())?
             .unwrap_or_default();
 
-        existing.append(&mut issuance.exchanges);
+        for exchange in issuance.exchanges.iter_mut() {
+            state.investment_denom_for(&exchange.class)?;
+            if exchange.price.is_some()
+                && (exchange.capital.is_some() || exchange.investment.unwrap_or_default() >= 0)
+            {
+                return Err(ContractError::InvalidRequest {
+                    reason: "only redemptions without fixed capital can be priced",
+                });
+            }
+            if let Some(denom) = &exchange.denom {
+                if !exchange.is_distribution() || !state.is_like_capital_denom(denom) {
+                    return Err(ContractError::UnsupportedDistributionDenom {
+                        denom: denom.clone(),
+                    });
+                }
+            }
+            exchange.provenance = Some(ExchangeProvenance {
+                issued_by: info.sender.clone(),
+                issued_at: env.block.time.seconds(),
+                batch: Some(batch),
+            });
+            if let Some(exchange_id) = &exchange.exchange_id {
+                if existing
+                    .iter()
+                    .any(|e| e.exchange_id.as_ref() == Some(exchange_id))
+                {
+                    return Err(ContractError::DuplicateExchangeId {
+                        exchange_id: exchange_id.clone(),
+                    });
+                }
+            }
+            existing.push(exchange.clone());
+        }
+
+        save_asset_exchanges(deps.storage, &issuance.subscription, &existing)?;
+        record_change(
+            deps.storage,
+            ChangedEntity::AssetExchange,
+            issuance.subscription.as_str(),
+        )?;
+        if !issued.contains(&issuance.subscription) {
+            issued.push(issuance.subscription.clone());
+        }
+
+        // let the sub contract track the exchange without polling the raise
+        if notify_subscriptions
+            && !notifications_disabled_storage_read(deps.storage)
+                .may_load(issuance.subscription.as_bytes())?
+                .unwrap_or_default()
+        {
+            for exchange in &issuance.exchanges {
+                if let Some(notification) = sub_notification(exchange) {
+                    notifications.push(SubMsg::reply_on_error(
+                        WasmMsg::Execute {
+                            contract_addr: issuance.subscription.to_string(),
+                            msg: to_binary(&notification)?,
+                            funds: vec![],
+                        },
+                        NOTIFY_SUBSCRIPTION_REPLY_ID_OFFSET + notified.len() as u64,
+                    ));
+                    notified.push(issuance.subscription.clone());
+                }
+            }
+        }
+    }
 
-        storage.save(issuance.subscription.as_bytes(), &existing)?;
+    if !notified.is_empty() {
+        notification_batch(deps.storage).save(&notified)?;
     }
+    issuance_batch_storage(deps.storage).save(&batch.to_be_bytes(), &issued)?;
 
-    Ok(Response::default())
+    check_obligation_limits(deps.storage, &state)?;
+
+    Ok(Response::new()
+        .add_attribute("batch", batch.to_string())
+        .add_submessages(notifications))
+}
+
+// outstanding exchanges issued by a batch, grouped by sub
+pub fn batch_exchanges(
+    storage: &dyn Storage,
+    batch: u64,
+) -> StdResult<Vec<SubscriptionAssetExchanges>> {
+    let subscriptions = issuance_batch_storage_read(storage)
+        .may_load(&batch.to_be_bytes())?
+        .unwrap_or_default();
+
+    let mut batch_exchanges = vec![];
+    for subscription in subscriptions {
+        let exchanges: Vec<AssetExchange> = asset_exchange_storage_read(storage)
+            .may_load(subscription.as_bytes())?
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|exchange| in_batch(exchange, batch))
+            .collect();
+        if !exchanges.is_empty() {
+            batch_exchanges.push(SubscriptionAssetExchanges {
+                subscription,
+                exchanges,
+            });
+        }
+    }
+
+    Ok(batch_exchanges)
+}
+
+fn in_batch(exchange: &AssetExchange, batch: u64) -> bool {
+    exchange
+        .provenance
+        .as_ref()
+        .is_some_and(|provenance| provenance.batch == Some(batch))
+}
+
+// exchanges from the batch that have already completed are left in place, and
+// large batches are worked through a chunk of subs per call
+pub fn try_cancel_batch(
+    deps: DepsMut<ProvenanceQuery>,
+    info: MessageInfo,
+    batch: u64,
+) -> ContractResponse {
+    let state = config_read(deps.storage).load()?;
+
+    if info.sender != state.gp && info.sender != state.recovery_admin {
+        return Err(ContractError::Unauthorized {
+            required: "gp or admin",
+            action: "cancel batches",
+        });
+    }
+
+    let mut subscriptions = issuance_batch_storage_read(deps.storage)
+        .may_load(&batch.to_be_bytes())?
+        .unwrap_or_default();
+    if subscriptions.is_empty() {
+        return Err(ContractError::InvalidRequest {
+            reason: "no outstanding exchanges in batch",
+        });
+    }
+
+    if state
+        .batch_cancel_cosign_threshold
+        .is_some_and(|threshold| subscriptions.len() as u64 > threshold)
+    {
+        let mut approvals = batch_cancellation_storage(deps.storage)
+            .may_load(&batch.to_be_bytes())?
+            .unwrap_or_default();
+        if !approvals.contains(&info.sender) {
+            approvals.push(info.sender);
+            batch_cancellation_storage(deps.storage).save(&batch.to_be_bytes(), &approvals)?;
+        }
+        if !(approvals.contains(&state.gp) && approvals.contains(&state.recovery_admin)) {
+            return Ok(Response::new().add_attribute("batch_cancellation", batch.to_string()));
+        }
+    } else if info.sender != state.gp {
+        return Err(ContractError::Unauthorized {
+            required: "gp",
+            action: "cancel batches",
+        });
+    }
+
+    let remaining = subscriptions.split_off(subscriptions.len().min(MAX_BATCH_CANCELLATIONS));
+    let mut cancelled = 0;
+    for subscription in subscriptions {
+        let mut existing = asset_exchange_storage_read(deps.storage)
+            .may_load(subscription.as_bytes())?
+            .unwrap_or_default();
+        let outstanding = existing.len();
+        existing.retain(|exchange| !in_batch(exchange, batch));
+        if existing.len() == outstanding {
+            continue;
+        }
+        cancelled += outstanding - existing.len();
+        save_asset_exchanges(deps.storage, &subscription, &existing)?;
+        record_change(
+            deps.storage,
+            ChangedEntity::AssetExchange,
+            subscription.as_str(),
+        )?;
+    }
+
+    if remaining.is_empty() {
+        issuance_batch_storage(deps.storage).remove(&batch.to_be_bytes());
+        batch_cancellation_storage(deps.storage).remove(&batch.to_be_bytes());
+    } else {
+        issuance_batch_storage(deps.storage).save(&batch.to_be_bytes(), &remaining)?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("cancelled", cancelled.to_string())
+        .add_attribute("remaining", remaining.len().to_string()))
+}
+
+fn check_obligation_limits(storage: &dyn Storage, state: &State) -> Result<(), ContractError> {
+    let outstanding = outstanding_obligations_read(storage)
+        .may_load()?
+        .unwrap_or_default();
+
+    if let Some(max) = state
+        .max_outstanding_exchanges
+        .filter(|max| outstanding.exchanges > *max)
+    {
+        return Err(ContractError::ObligationLimitExceeded {
+            obligation: "exchanges",
+            max,
+        });
+    }
+    if let Some(max) = state
+        .max_outstanding_capital
+        .filter(|max| outstanding.capital > *max)
+    {
+        return Err(ContractError::ObligationLimitExceeded {
+            obligation: "capital",
+            max,
+        });
+    }
+
+    Ok(())
+}
+
+fn sub_notification(exchange: &AssetExchange) -> Option<SubExecuteMsg> {
+    if exchange.is_capital_call() {
+        Some(SubExecuteMsg::IssueCapitalCall {
+            capital_call: exchange.clone(),
+        })
+    } else if exchange.is_distribution() {
+        Some(SubExecuteMsg::IssueDistribution {
+            distribution: exchange.clone(),
+        })
+    } else if exchange.is_redemption() {
+        Some(SubExecuteMsg::IssueRedemption {
+            redemption: exchange.clone(),
+        })
+    } else {
+        None
+    }
 }
 
 pub fn try_cancel_asset_exchanges(
@@ -52,179 +374,193 @@ pub fn try_cancel_asset_exchanges(
     cancellations: Vec<IssueAssetExchange>,
 ) -> ContractResponse {
     let state = config_read(deps.storage).load()?;
-    let mut storage = asset_exchange_storage(deps.storage);
 
-    if info.sender != state.gp {
-        return contract_error("only gp can cancel redemptions");
+    if !state.is_gp_or_delegate(&info.sender) {
+        return Err(ContractError::Unauthorized {
+            required: "gp or delegate",
+            action: "cancel redemptions",
+        });
     }
 
     for cancel in &cancellations {
-        let mut existing = storage
+        let mut existing = asset_exchange_storage_read(deps.storage)
             .may_load(cancel.subscription.as_bytes())?
-            .ok_or("no asset exchange found for subscription")?;
+            .ok_or_else(|| ContractError::ExchangeNotFound {
+                subscription: cancel.subscription.clone(),
+            })?;
 
         for exchange in &cancel.exchanges {
-            let index = existing
-                .iter()
-                .position(|e| exchange == e)
-                .ok_or("no asset exchange found for subcription")?;
+            let index = existing.iter().position(|e| exchange == e).ok_or_else(|| {
+                ContractError::ExchangeNotFound {
+                    subscription: cancel.subscription.clone(),
+                }
+            })?;
 
             existing.remove(index);
         }
 
-        storage.save(cancel.subscription.as_bytes(), &existing)?;
+        save_asset_exchanges(deps.storage, &cancel.subscription, &existing)?;
+    }
+
+    for cancel in &cancellations {
+        record_change(
+            deps.storage,
+            ChangedEntity::AssetExchange,
+            cancel.subscription.as_str(),
+        )?;
     }
 
     Ok(Response::default())
 }
 
-pub fn try_complete_asset_exchange(
+pub fn try_cancel_asset_exchange(
     deps: DepsMut<ProvenanceQuery>,
-    env: Env,
     info: MessageInfo,
-    exchanges: Vec<AssetExchange>,
-    to: Option<Addr>,
-    memo: Option<String>,
+    subscription: Addr,
+    reference: ExchangeReference,
 ) -> ContractResponse {
     let state = config_read(deps.storage).load()?;
-    let mut storage = asset_exchange_storage(deps.storage);
 
-    let mut existing = storage
-        .may_load(info.sender.as_bytes())?
-        .ok_or("no asset exchange found for subscription")?;
+    if !state.is_gp_or_delegate(&info.sender) {
+        return Err(ContractError::Unauthorized {
+            required: "gp or delegate",
+            action: "cancel redemptions",
+        });
+    }
 
-    for exchange in &exchanges {
-        let index = existing
+    let not_found = || ContractError::ExchangeNotFound {
+        subscription: subscription.clone(),
+    };
+    let mut existing = asset_exchange_storage_read(deps.storage)
+        .may_load(subscription.as_bytes())?
+        .ok_or_else(not_found)?;
+    let index = match &reference {
+        ExchangeReference::Index(index) => usize::try_from(*index)
+            .ok()
+            .filter(|index| *index < existing.len()),
+        ExchangeReference::ExchangeId(exchange_id) => existing
             .iter()
-            .position(|e| exchange == e)
-            .ok_or("no asset exchange found for subcription")?;
-        existing.remove(index);
+            .position(|e| e.exchange_id.as_ref() == Some(exchange_id)),
+    }
+    .ok_or_else(not_found)?;
+    existing.remove(index);
 
-        if let Some(date) = &exchange.date {
-            match date {
-                ExchangeDate::Due(epoch_seconds) => {
-                    if epoch_seconds < &env.block.time.seconds() {
-                        return contract_error("exchange past due");
-                    }
-                }
-                ExchangeDate::Available(epoch_seconds) => {
-                    if epoch_seconds > &env.block.time.seconds() {
-                        return contract_error("exchange not yet available");
-                    }
-                }
+    save_asset_exchanges(deps.storage, &subscription, &existing)?;
+    record_change(
+        deps.storage,
+        ChangedEntity::AssetExchange,
+        subscription.as_str(),
+    )?;
+
+    Ok(Response::default())
+}
+
+pub fn try_expire_capital_calls(
+    deps: DepsMut<ProvenanceQuery>,
+    env: Env,
+    info: MessageInfo,
+) -> ContractResponse {
+    let state = config_read(deps.storage).load()?;
+    let accepted = subscriptions_with_status(deps.storage, SubscriptionStatus::Accepted)?;
+
+    if !state.is_gp_or_delegate(&info.sender) {
+        return Err(ContractError::Unauthorized {
+            required: "gp or delegate",
+            action: "expire capital calls",
+        });
+    }
+
+    let mut expired = 0;
+    let mut changed = vec![];
+    for subscription in accepted {
+        if let Some(mut existing) =
+            asset_exchange_storage_read(deps.storage).may_load(subscription.as_bytes())?
+        {
+            let outstanding = existing.len();
+            existing.retain(|exchange| {
+                !(exchange.is_capital_call()
+                    && exchange.is_past_due(env.block.time.seconds(), state.due_grace_seconds))
+            });
+
+            if existing.len() != outstanding {
+                expired += outstanding - existing.len();
+                save_asset_exchanges(deps.storage, &subscription, &existing)?;
+                changed.push(subscription);
             }
         }
     }
-    storage.save(info.sender.as_bytes(), &existing)?;
 
-    let response = Response::new();
+    for subscription in changed {
+        record_change(
+            deps.storage,
+            ChangedEntity::AssetExchange,
+            subscription.as_str(),
+        )?;
+    }
 
-    let total_investment: i64 = exchanges.iter().filter_map(|e| e.investment).sum();
-    let abs_investment = total_investment.unsigned_abs();
-    let response = match total_investment.cmp(&0) {
-        Ordering::Less => {
-            let investment_marker = ProvenanceQuerier::new(&deps.querier)
-                .get_marker_by_denom(state.investment_denom.clone())?;
-            let deposit_investment = BankMsg::Send {
-                to_address: investment_marker.address.into_string(),
-                amount: coins(abs_investment.into(), state.investment_denom.clone()),
-            };
-            let burn_investment =
-                burn_marker_supply(abs_investment.into(), state.investment_denom.clone())?;
-
-            response
-                .add_message(deposit_investment)
-                .add_message(burn_investment)
-        }
-        Ordering::Greater => {
-            let mint_investment =
-                mint_marker_supply(abs_investment.into(), state.investment_denom.clone())?;
-            let withdraw_investment = withdraw_coins(
-                state.investment_denom.clone(),
-                abs_investment.into(),
-                state.investment_denom.clone(),
-                info.sender.clone(),
-            )?;
+    Ok(Response::new().add_attribute("expired", format!("{expired}")))
+}
 
-            response
-                .add_message(mint_investment)
-                .add_message(withdraw_investment)
-        }
-        _ => response,
-    };
+pub fn try_complete_asset_exchange(
+    mut deps: DepsMut<ProvenanceQuery>,
+    env: Env,
+    info: MessageInfo,
+    exchanges: Vec<AssetExchange>,
+    to: Option<Addr>,
+    memo: Option<String>,
+) -> ContractResponse {
+    let mut state = config_read(deps.storage).load()?;
+    cache_marker_addresses(deps.branch(), &mut state)?;
+    let plan = plan_completion(deps.as_ref(), &env, &state, &info, &exchanges, to.clone())?;
 
-    let total_commitment: i64 = exchanges
-        .iter()
-        .filter_map(|e| e.commitment_in_shares)
-        .sum();
-    let abs_commitment = total_commitment.unsigned_abs();
-    let response = match total_commitment.cmp(&0) {
-        Ordering::Less => {
-            let deposit_commitment = BankMsg::Send {
-                to_address: ProvenanceQuerier::new(&deps.querier)
-                    .get_marker_by_denom(state.commitment_denom.clone())?
-                    .address
-                    .into_string(),
-                amount: coins(abs_commitment.into(), state.commitment_denom.clone()),
-            };
-            let burn_commitment =
-                burn_marker_supply(abs_commitment.into(), state.commitment_denom)?;
-
-            response
-                .add_message(deposit_commitment)
-                .add_message(burn_commitment)
-        }
-        Ordering::Greater => {
-            let mint_commitment =
-                mint_marker_supply(abs_commitment.into(), state.commitment_denom.clone())?;
-            let withdraw_commitment = withdraw_coins(
-                state.commitment_denom.clone(),
-                abs_commitment.into(),
-                state.commitment_denom,
-                info.sender.clone(),
-            )?;
+    // settlement is sent as plain messages rather than submessages, so a failed mint,
+    // burn or transfer reverts the whole transaction, including the removal below
+    save_asset_exchanges(deps.storage, &info.sender, &plan.remaining)?;
+    record_change(
+        deps.storage,
+        ChangedEntity::AssetExchange,
+        info.sender.as_str(),
+    )?;
+    record_completed_exchanges(
+        deps.storage,
+        &env,
+        &info.sender,
+        &plan.completed,
+        &to,
+        plan.released_commitment,
+        &plan.contributed,
+    )?;
 
-            response
-                .add_message(mint_commitment)
-                .add_message(withdraw_commitment)
-        }
-        _ => response,
-    };
+    release_escrow(deps.storage, &info.sender, plan.released_escrow)?;
+    if plan.excess_capital > 0 {
+        let mut prepayment_storage = prepayment_storage(deps.storage);
+        let prepayment = prepayment_storage
+            .may_load(info.sender.as_bytes())?
+            .unwrap_or_default();
+        prepayment_storage.save(info.sender.as_bytes(), &(prepayment + plan.excess_capital))?;
+        record_change(
+            deps.storage,
+            ChangedEntity::Prepayment,
+            info.sender.as_str(),
+        )?;
+    }
 
-    let total_capital: i64 = exchanges.iter().filter_map(|e| e.capital).sum();
-    let abs_capital = total_capital.unsigned_abs();
-    let response = if total_capital > 0 {
-        match state.required_capital_attribute {
-            None => {
-                let send_capital = BankMsg::Send {
-                    to_address: to.unwrap_or(info.sender).into_string(),
-                    amount: coins(abs_capital.into(), state.capital_denom),
-                };
-                response.add_message(send_capital)
-            }
-            Some(required_capital_attribute) => {
-                let to_addr = to.unwrap_or(info.sender);
-                if !query_attributes(deps, &to_addr)
-                    .any(|attr| attr.name == required_capital_attribute)
-                {
-                    return contract_error(
-                        format!(
-                            "{} does not have required attribute of {}",
-                            &to_addr, &required_capital_attribute
-                        )
-                        .as_str(),
-                    );
-                }
+    let response = Response::new()
+        .add_events(memo_events(&state, &plan.messages, &memo))
+        .add_messages(plan.messages);
 
-                let marker_transfer = transfer_marker_coins(
-                    abs_capital.into(),
-                    &state.capital_denom,
-                    to_addr,
-                    env.contract.address,
-                )?;
-                response.add_message(marker_transfer)
-            }
-        }
+    let response = if plan.late_fee > 0 {
+        response.add_attribute("late_fee", plan.late_fee.to_string())
+    } else {
+        response
+    };
+    let response = if plan.management_fees.is_empty() {
+        response
+    } else {
+        response.add_attribute("management_fee", fee_attribute(&plan.management_fees))
+    };
+    let response = if plan.released_commitment > 0 {
+        response.add_attribute("released_commitment", plan.released_commitment.to_string())
     } else {
         response
     };
@@ -235,468 +571,4004 @@ pub fn try_complete_asset_exchange(
     })
 }
 
-fn query_attributes(
-    deps: DepsMut<ProvenanceQuery>,
-    address: &Addr,
-) -> IntoIter<provwasm_std::Attribute> {
-    ProvenanceQuerier::new(&deps.querier)
-        .get_attributes(address.clone(), None as Option<String>)
-        .unwrap()
-        .attributes
-        .into_iter()
+fn fee_attribute(fees: &[Coin]) -> String {
+    fees.iter()
+        .map(|fee| fee.to_string())
+        .collect::<Vec<String>>()
+        .join(",")
 }
 
-#[cfg(test)]
-pub mod tests {
-    use super::*;
-    use crate::contract::execute;
-    use crate::contract::tests::{capital_coin_deps, default_deps, restricted_capital_coin_deps};
-    use crate::mock::load_markers;
-    use crate::mock::msg_at_index;
-    use crate::mock::send_args;
-    use crate::mock::{burn_args, marker_transfer_msg};
-    use crate::msg::HandleMsg;
-    use crate::msg::IssueAssetExchange;
-    use crate::state::tests::asset_exchange_storage_read;
-    use crate::state::tests::set_accepted;
-    use cosmwasm_std::from_binary;
-    use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
-    use cosmwasm_std::to_binary;
-    use cosmwasm_std::Addr;
-    use cosmwasm_std::Timestamp;
-    use provwasm_std::MarkerMsgParams;
+// the outcome of completing exchanges, worked out without touching storage
+pub struct CompletionPlan {
+    pub remaining: Vec<AssetExchange>,
+    pub completed: Vec<AssetExchange>,
+    pub released_escrow: u64,
+    pub excess_capital: u64,
+    pub late_fee: u64,
+    pub released_commitment: i64,
+    pub contributed: Vec<Coin>,
+    pub management_fees: Vec<Coin>,
+    pub messages: Vec<CosmosMsg<ProvenanceMsg>>,
+}
 
-    #[test]
-    fn size() {
-        let exchange = AssetExchange {
-            investment: Some(-1_000),
-            commitment_in_shares: None,
-            capital: Some(1_000),
-            date: Some(ExchangeDate::Available(0)),
-        };
-        let as_bytes = to_binary(&exchange).unwrap();
-        println!("{}", std::str::from_utf8(as_bytes.as_slice()).unwrap());
-        assert_eq!(41, as_bytes.len());
+pub fn plan_completion(
+    deps: Deps<ProvenanceQuery>,
+    env: &Env,
+    state: &State,
+    info: &MessageInfo,
+    exchanges: &[AssetExchange],
+    to: Option<Addr>,
+) -> Result<CompletionPlan, ContractError> {
+    check_settlement_hours(state, env)?;
+    if let Some(to) = &to {
+        state.check_destination(to)?;
+    }
 
-        println!("{:?}", from_binary::<AssetExchange>(&as_bytes).unwrap());
+    let mut remaining = asset_exchange_storage_read(deps.storage)
+        .may_load(info.sender.as_bytes())?
+        .ok_or_else(|| ContractError::ExchangeNotFound {
+            subscription: info.sender.clone(),
+        })?;
+
+    // the stored exchanges carry their provenance into the completed records
+    let mut completed = vec![];
+    for exchange in exchanges {
+        let index = remaining
+            .iter()
+            .position(|e| exchange == e)
+            .ok_or_else(|| ContractError::ExchangeNotFound {
+                subscription: info.sender.clone(),
+            })?;
+        completed.push(state.priced(&remaining.remove(index))?);
+        check_exchange_dates(state, env, exchange)?;
     }
 
-    #[test]
-    fn issue_asset_exchange_for_capital_call() {
-        let mut deps = default_deps(None);
-        set_accepted(&mut deps.storage, vec!["sub_1"]);
-        {
-            asset_exchange_storage(&mut deps.storage)
-                .save(
-                    Addr::unchecked("sub_1").as_bytes(),
-                    &vec![AssetExchange {
-                        investment: None,
-                        commitment_in_shares: Some(1_000),
-                        capital: None,
-                        date: None,
-                    }],
-                )
-                .unwrap();
-        }
+    // priced exchanges settle at the nav posted when they complete
+    let exchanges = exchanges
+        .iter()
+        .map(|exchange| state.priced(exchange))
+        .collect::<Result<Vec<_>, _>>()?;
 
-        execute(
-            deps.as_mut(),
-            mock_env(),
-            mock_info("gp", &vec![]),
-            HandleMsg::IssueAssetExchanges {
-                asset_exchanges: vec![IssueAssetExchange {
-                    subscription: Addr::unchecked("sub_1"),
-                    exchanges: vec![AssetExchange {
-                        investment: Some(1_000),
-                        commitment_in_shares: Some(-1_000),
-                        capital: Some(-1_000),
-                        date: None,
-                    }],
-                }],
-            },
-        )
-        .unwrap();
+    check_required_attestations(deps, &info.sender, &exchanges)?;
 
-        // verify asset exchange added
-        assert_eq!(
-            2,
-            asset_exchange_storage_read(&deps.storage)
-                .load(Addr::unchecked("sub_1").as_bytes())
-                .unwrap()
-                .len()
-        )
-    }
+    let total_commitment: i64 = exchanges
+        .iter()
+        .filter_map(|e| e.commitment_in_shares)
+        .sum();
+    let total_capital: i64 = exchanges
+        .iter()
+        .filter(|e| payout_denom(state, e).is_none())
+        .filter_map(|e| e.capital)
+        .sum();
 
-    #[test]
-    fn issue_asset_exchange_bad_actor() {
-        let res = execute(
-            default_deps(None).as_mut(),
-            mock_env(),
-            mock_info("bad_actor", &vec![]),
-            HandleMsg::IssueAssetExchanges {
-                asset_exchanges: vec![],
-            },
-        );
+    let late_fee: u64 = completed
+        .iter()
+        .map(|e| e.late_fee(env.block.time.seconds(), state.due_grace_seconds))
+        .sum();
 
-        assert!(res.is_err());
+    // any capital attached beyond what is owed is credited to the next capital call
+    let mut owed_capital = if total_capital < 0 {
+        total_capital.unsigned_abs() + late_fee
+    } else {
+        0
+    };
+
+    // capital escrowed at acceptance covers capital calls before attached funds
+    let mut released_escrow = 0;
+    if owed_capital > 0 {
+        released_escrow = escrowed_capital(deps.storage, &info.sender)?.min(owed_capital);
+        owed_capital -= released_escrow;
+    }
+    let attached_capital = state.capital_value(&info.funds)?;
+    if late_fee > 0 && attached_capital < u128::from(owed_capital) {
+        return Err(ContractError::LateFeeRequired {
+            late_fee: late_fee.into(),
+        });
     }
+    let excess_capital = u64::try_from(attached_capital)?.saturating_sub(owed_capital);
 
-    #[test]
-    fn issue_asset_exchange_not_accepted() {
+    let mut contributed = state.capital_funds(&info.funds);
+    if released_escrow > 0 {
+        contributed.push(coin(released_escrow.into(), &state.capital_denom));
+    }
+
+    // restricted capital markers require both parties of a transfer to be attributed,
+    // so the sub is checked for each denom it pays in or is paid out in
+    if total_capital != 0 {
+        let paid_out = exchanges
+            .iter()
+            .filter(|exchange| exchange.capital.unwrap_or_default() > 0)
+            .map(|exchange| payout_denom(state, exchange).unwrap_or(&state.capital_denom));
+        for denom in contributed
+            .iter()
+            .map(|coin| coin.denom.as_str())
+            .chain(paid_out)
+        {
+            check_capital_attribute(deps, state, &info.sender, denom)?;
+        }
+    }
+
+    let released_commitment =
+        released_commitment(deps, state, info, &completed, &remaining, total_commitment)?;
+
+    // capital calls only convert commitment into investment, so just growth is checked
+    let total_investment: i64 = exchanges.iter().filter_map(|e| e.investment).sum();
+    let increase = total_investment + total_commitment - released_commitment;
+    if increase > 0 {
+        check_ownership(deps, state, &[(&info.sender, increase as u128)])?;
+    }
+
+    let mut settlement = Settlement::new(info.sender.clone());
+    for exchange in &exchanges {
+        settlement = settlement.leg(
+            state.investment_denom_for(&exchange.class)?,
+            exchange.investment.unwrap_or_default(),
+        );
+    }
+    settlement = settlement.leg(
+        &state.commitment_denom,
+        total_commitment - released_commitment,
+    );
+    // the management fee comes out of capital paid in or distributed
+    let to = to.unwrap_or_else(|| info.sender.clone());
+    let mut management_fees = vec![];
+    for exchange in &exchanges {
+        if let Some(denom) = payout_denom(state, exchange) {
+            // capital is stated in the capital denom and paid at equal value in this one
+            let capital = exchange.capital.unwrap_or_default().unsigned_abs();
+            let fee = state.management_fee_on(capital);
+            let payout = state.normalize((capital - fee).into(), &state.capital_denom, denom)?;
+            settlement = settlement.send_like_capital(to.clone(), coin(payout, denom));
+            if fee > 0 {
+                let fee = state.normalize(fee.into(), &state.capital_denom, denom)?;
+                management_fees.push(coin(fee, denom));
+            }
+        }
+    }
+    let fee = state.management_fee_on(total_capital.unsigned_abs());
+    if total_capital > 0 {
+        settlement = settlement.send_capital(to, (total_capital.unsigned_abs() - fee).into());
+    }
+    if fee > 0 {
+        management_fees.push(coin(fee.into(), &state.capital_denom));
+    }
+    if let Some(management_fee) = &state.management_fee {
+        for fee in &management_fees {
+            settlement = if fee.denom == state.capital_denom {
+                settlement.send_capital(management_fee.collector.clone(), fee.amount.u128())
+            } else {
+                settlement.send_like_capital(management_fee.collector.clone(), fee.clone())
+            };
+        }
+    }
+    let messages = settlement.messages(deps, state, &env.contract.address)?;
+
+    Ok(CompletionPlan {
+        remaining,
+        completed,
+        released_escrow,
+        excess_capital,
+        late_fee,
+        released_commitment,
+        contributed,
+        management_fees,
+        messages,
+    })
+}
+
+// settlement is allowed from an exchange's available date until it is past due,
+// though a capital call with a late fee can still be paid late
+fn check_exchange_dates(
+    state: &State,
+    env: &Env,
+    exchange: &AssetExchange,
+) -> Result<(), ContractError> {
+    let now = env.block.time.seconds();
+    if exchange.is_past_due(now, state.due_grace_seconds) && !exchange.accepts_late_payment() {
+        return Err(ContractError::PastDue {});
+    }
+    if exchange.is_not_yet_available(now) {
+        return Err(ContractError::NotYetAvailable {});
+    }
+
+    Ok(())
+}
+
+fn check_required_attestations(
+    deps: Deps<ProvenanceQuery>,
+    subscription: &Addr,
+    exchanges: &[AssetExchange],
+) -> Result<(), ContractError> {
+    let required_attestations: Vec<&String> = exchanges
+        .iter()
+        .filter_map(|e| e.required_attestation.as_ref())
+        .collect();
+    if required_attestations.is_empty() {
+        return Ok(());
+    }
+
+    let lp = lp_for_sub(deps, subscription)?;
+    let lp_attributes = attributes(deps, &lp);
+    for required_attestation in required_attestations {
+        if !lp_attributes.contains(required_attestation) {
+            return Err(ContractError::MissingAttestation {
+                address: lp,
+                attestation: required_attestation.clone(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+fn escrowed_capital(storage: &dyn Storage, subscription: &Addr) -> StdResult<u64> {
+    Ok(escrow_storage_read(storage)
+        .may_load(subscription.as_bytes())?
+        .unwrap_or_default())
+}
+
+fn release_escrow(storage: &mut dyn Storage, subscription: &Addr, released: u64) -> StdResult<()> {
+    if released > 0 {
+        let escrowed = escrowed_capital(storage, subscription)?;
+        save_escrow(storage, subscription, escrowed - released)?;
+        record_change(storage, ChangedEntity::Escrow, subscription.as_str())?;
+    }
+
+    Ok(())
+}
+
+// distributions in a like denom are paid apart from the capital denom total
+fn payout_denom<'a>(state: &State, exchange: &'a AssetExchange) -> Option<&'a str> {
+    exchange
+        .denom
+        .as_deref()
+        .filter(|denom| *denom != state.capital_denom)
+}
+
+// commitment attached beyond what the exchanges consume is burned, but only when
+// a redemption leaves the sub with no investment and no capital calls to fund
+fn released_commitment(
+    deps: Deps<ProvenanceQuery>,
+    state: &State,
+    info: &MessageInfo,
+    completed: &[AssetExchange],
+    remaining: &[AssetExchange],
+    total_commitment: i64,
+) -> Result<i64, ContractError> {
+    let attached: u128 = info
+        .funds
+        .iter()
+        .filter(|coin| coin.denom == state.commitment_denom)
+        .map(|coin| coin.amount.u128())
+        .sum();
+    let consumed = u128::from(total_commitment.min(0).unsigned_abs());
+    let released = attached.saturating_sub(consumed);
+    if released == 0 {
+        return Ok(0);
+    }
+
+    let investment_denoms = state.investment_denoms();
+    let fully_redeemed = completed.iter().any(|exchange| exchange.is_redemption())
+        && !remaining.iter().any(|exchange| exchange.is_capital_call())
+        && deps
+            .querier
+            .query_all_balances(&info.sender)?
+            .iter()
+            .all(|coin| !investment_denoms.contains(&&coin.denom) || coin.amount.is_zero());
+    if !fully_redeemed {
+        return Err(ContractError::InvalidRequest {
+            reason: "only a full redemption can release commitment",
+        });
+    }
+
+    Ok(i64::try_from(released)?)
+}
+
+pub fn try_pay_capital_call(
+    mut deps: DepsMut<ProvenanceQuery>,
+    env: Env,
+    info: MessageInfo,
+    exchange: AssetExchange,
+) -> ContractResponse {
+    let mut state = config_read(deps.storage).load()?;
+    check_settlement_hours(&state, &env)?;
+
+    if !exchange.is_capital_call() {
+        return Err(ContractError::InvalidRequest {
+            reason: "exchange is not a capital call",
+        });
+    }
+
+    let mut existing = asset_exchange_storage_read(deps.storage)
+        .may_load(info.sender.as_bytes())?
+        .ok_or_else(|| ContractError::ExchangeNotFound {
+            subscription: info.sender.clone(),
+        })?;
+    let index = existing
+        .iter()
+        .position(|e| &exchange == e)
+        .ok_or_else(|| ContractError::ExchangeNotFound {
+            subscription: info.sender.clone(),
+        })?;
+
+    check_exchange_dates(&state, &env, &exchange)?;
+    check_required_attestations(deps.as_ref(), &info.sender, std::slice::from_ref(&exchange))?;
+
+    // a tranche pays capital and deposits the commitment it converts, nothing else
+    if let Some(unsupported) = info.funds.iter().find(|coin| {
+        !state.is_like_capital_denom(&coin.denom) && coin.denom != state.commitment_denom
+    }) {
+        return Err(ContractError::UnsupportedCapitalDenom {
+            subscription: info.sender.clone(),
+            denom: unsupported.denom.clone(),
+        });
+    }
+
+    // the late fee on the outstanding balance is collected with the first late tranche,
+    // and escrowed capital makes up whatever the attached funds leave owing
+    let owed = exchange.capital.unwrap_or_default().unsigned_abs();
+    let late_fee = existing[index].late_fee(env.block.time.seconds(), state.due_grace_seconds);
+    let attached = u64::try_from(state.capital_value(&info.funds)?)?;
+    let released_escrow = escrowed_capital(deps.storage, &info.sender)?
+        .min((owed + late_fee).saturating_sub(attached));
+    let payment = attached + released_escrow;
+    if late_fee > 0 && payment <= late_fee {
+        return Err(ContractError::LateFeeRequired {
+            late_fee: late_fee.into(),
+        });
+    }
+    let paid = payment.saturating_sub(late_fee);
+    if paid == 0 || paid > owed {
+        return Err(ContractError::InvalidPayment { paid, owed });
+    }
+    if state.not_evenly_divisble(paid) {
+        return Err(ContractError::IndivisibleAmount {
+            amount: paid,
+            capital_per_share: state.capital_per_share,
+        });
+    }
+
+    // the investment and commitment legs settle in proportion to the capital paid
+    let portion = |amount: Option<i64>| -> Result<Option<i64>, ContractError> {
+        match amount {
+            None => Ok(None),
+            Some(amount) => {
+                let scaled = i128::from(amount) * i128::from(paid);
+                if scaled % i128::from(owed) != 0 {
+                    return Err(ContractError::InvalidPayment { paid, owed });
+                }
+                Ok(Some(i64::try_from(scaled / i128::from(owed))?))
+            }
+        }
+    };
+    let paid_exchange = AssetExchange {
+        investment: portion(exchange.investment)?,
+        commitment_in_shares: portion(exchange.commitment_in_shares)?,
+        capital: Some(-i64::try_from(paid)?),
+        ..exchange.clone()
+    };
+
+    if paid == owed {
+        existing.remove(index);
+    } else {
+        let remaining = &mut existing[index];
+        remaining.investment = exchange
+            .investment
+            .zip(paid_exchange.investment)
+            .map(|(total, paid)| total - paid);
+        remaining.commitment_in_shares = exchange
+            .commitment_in_shares
+            .zip(paid_exchange.commitment_in_shares)
+            .map(|(total, paid)| total - paid);
+        remaining.capital = Some(-i64::try_from(owed - paid)?);
+        if late_fee > 0 {
+            remaining.late_fee_paid = true;
+        }
+    }
+    save_asset_exchanges(deps.storage, &info.sender, &existing)?;
+    record_change(
+        deps.storage,
+        ChangedEntity::AssetExchange,
+        info.sender.as_str(),
+    )?;
+    release_escrow(deps.storage, &info.sender, released_escrow)?;
+
+    let mut contributed = state.capital_funds(&info.funds);
+    if released_escrow > 0 {
+        contributed.push(coin(released_escrow.into(), &state.capital_denom));
+    }
+    record_completed_exchanges(
+        deps.storage,
+        &env,
+        &info.sender,
+        std::slice::from_ref(&paid_exchange),
+        &None,
+        0,
+        &contributed,
+    )?;
+
+    for coin in &contributed {
+        check_capital_attribute(deps.as_ref(), &state, &info.sender, &coin.denom)?;
+    }
+
+    cache_marker_addresses(deps.branch(), &mut state)?;
+    let mut settlement = Settlement::new(info.sender.clone())
+        .leg(
+            state.investment_denom_for(&paid_exchange.class)?,
+            paid_exchange.investment.unwrap_or_default(),
+        )
+        .leg(
+            &state.commitment_denom,
+            paid_exchange.commitment_in_shares.unwrap_or_default(),
+        );
+    let fee = state.management_fee_on(paid);
+    if let Some(management_fee) = state.management_fee.as_ref().filter(|_| fee > 0) {
+        settlement = settlement.send_capital(management_fee.collector.clone(), fee.into());
+    }
+
+    let response = Response::new()
+        .add_messages(settlement.messages(deps.as_ref(), &state, &env.contract.address)?)
+        .add_attribute("paid", paid.to_string())
+        .add_attribute("remaining", (owed - paid).to_string());
+    let response = if late_fee > 0 {
+        response.add_attribute("late_fee", late_fee.to_string())
+    } else {
+        response
+    };
+
+    Ok(if fee > 0 {
+        response.add_attribute(
+            "management_fee",
+            fee_attribute(&[coin(fee.into(), &state.capital_denom)]),
+        )
+    } else {
+        response
+    })
+}
+
+pub fn try_queue_asset_exchange(
+    deps: DepsMut<ProvenanceQuery>,
+    info: MessageInfo,
+    exchanges: Vec<AssetExchange>,
+    to: Option<Addr>,
+    memo: Option<String>,
+) -> ContractResponse {
+    if exchanges.is_empty() {
+        return Err(ContractError::InvalidRequest {
+            reason: "no exchanges to queue",
+        });
+    }
+
+    // queued settlements run without attached funds
+    if !info.funds.is_empty() {
+        return Err(ContractError::InvalidRequest {
+            reason: "funds cannot be attached to a queued settlement",
+        });
+    }
+    if exchanges
+        .iter()
+        .any(|exchange| exchange.capital.unwrap_or_default() < 0)
+    {
+        return Err(ContractError::InvalidRequest {
+            reason: "capital calls cannot be queued",
+        });
+    }
+    // rejected up front rather than when the queue is processed
+    if let Some(to) = &to {
+        config_read(deps.storage).load()?.check_destination(to)?;
+    }
+
+    let mut existing = asset_exchange_storage_read(deps.storage)
+        .may_load(info.sender.as_bytes())?
+        .unwrap_or_default();
+    for exchange in &exchanges {
+        let index = existing.iter().position(|e| exchange == e).ok_or_else(|| {
+            ContractError::ExchangeNotFound {
+                subscription: info.sender.clone(),
+            }
+        })?;
+        existing.remove(index);
+    }
+
+    let sequence = settlement_queue_sequence(deps.storage)
+        .may_load()?
+        .unwrap_or_default()
+        + 1;
+    settlement_queue_sequence(deps.storage).save(&sequence)?;
+    settlement_queue(deps.storage).save(
+        &sequence.to_be_bytes(),
+        &QueuedSettlement {
+            subscription: info.sender,
+            exchanges,
+            to,
+            memo,
+        },
+    )?;
+    record_change(
+        deps.storage,
+        ChangedEntity::SettlementQueue,
+        &sequence.to_string(),
+    )?;
+
+    Ok(Response::new().add_attribute("queued", sequence.to_string()))
+}
+
+pub fn try_process_queue(deps: DepsMut<ProvenanceQuery>, env: Env, limit: u32) -> ContractResponse {
+    let state = config_read(deps.storage).load()?;
+    if check_settlement_hours(&state, &env).is_err() {
+        return Ok(Response::default());
+    }
+
+    // settlements that aren't available yet are passed over, so they can't hold up the queue
+    let now = env.block.time.seconds();
+    let queued: Vec<(Vec<u8>, QueuedSettlement)> = settlement_queue_read(deps.storage)
+        .range(None, None, Order::Ascending)
+        .filter(|item| match item {
+            Ok((_, settlement)) => !settlement
+                .exchanges
+                .iter()
+                .any(|exchange| exchange.is_not_yet_available(now)),
+            Err(_) => true,
+        })
+        .take(limit.min(MAX_QUEUE_BATCH) as usize)
+        .collect::<StdResult<_>>()?;
+
+    // each settlement runs as its own submessage so one failure can't block the rest
+    let mut batch = vec![];
+    let mut messages = vec![];
+    for (key, _) in queued {
+        let sequence = u64::from_be_bytes(
+            key.as_slice()
+                .try_into()
+                .map_err(|_| StdError::generic_err("invalid queued settlement key"))?,
+        );
+        messages.push(SubMsg::reply_on_error(
+            WasmMsg::Execute {
+                contract_addr: env.contract.address.to_string(),
+                msg: to_binary(&HandleMsg::SettleQueued { sequence })?,
+                funds: vec![],
+            },
+            SETTLE_QUEUED_REPLY_ID_OFFSET + batch.len() as u64,
+        ));
+        batch.push(sequence);
+    }
+
+    if !batch.is_empty() {
+        settlement_batch(deps.storage).save(&batch)?;
+    }
+
+    Ok(Response::new().add_submessages(messages))
+}
+
+pub fn try_settle_queued(
+    deps: DepsMut<ProvenanceQuery>,
+    env: Env,
+    info: MessageInfo,
+    sequence: u64,
+) -> ContractResponse {
+    if info.sender != env.contract.address {
+        return Err(ContractError::Unauthorized {
+            required: "the raise",
+            action: "settle queued exchanges",
+        });
+    }
+
+    let settlement = settlement_queue(deps.storage)
+        .may_load(&sequence.to_be_bytes())?
+        .ok_or(ContractError::QueuedSettlementNotFound { sequence })?;
+    settlement_queue(deps.storage).remove(&sequence.to_be_bytes());
+    record_change(
+        deps.storage,
+        ChangedEntity::SettlementQueue,
+        &sequence.to_string(),
+    )?;
+
+    try_complete_asset_exchange(
+        deps,
+        env,
+        MessageInfo {
+            sender: settlement.subscription,
+            funds: vec![],
+        },
+        settlement.exchanges,
+        settlement.to,
+        settlement.memo,
+    )
+}
+
+// the hash a sub authorizes, over the exchanges exactly as the gp will submit them
+pub fn exchanges_hash(exchanges: &[AssetExchange]) -> StdResult<String> {
+    Ok(format!(
+        "{:x}",
+        Sha256::digest(to_binary(exchanges)?.as_slice())
+    ))
+}
+
+pub fn try_authorize_gp_settlement(
+    mut deps: DepsMut<ProvenanceQuery>,
+    env: Env,
+    info: MessageInfo,
+    exchanges_hash: String,
+    expires: u64,
+) -> ContractResponse {
+    if !has_subscription_status(deps.storage, &info.sender, SubscriptionStatus::Accepted)? {
+        return Err(ContractError::SubscriptionNotFound {
+            subscription: info.sender,
+            expected: "accepted",
+        });
+    }
+    if expires <= env.block.time.seconds() {
+        return Err(ContractError::SettlementAuthorizationExpired { expires });
+    }
+
+    // funds held for a replaced authorization go back to the sub
+    let refund = revoke_gp_settlement(deps.branch(), &env.contract.address, &info.sender)?;
+    save_settlement_authorization(
+        deps.storage,
+        &info.sender,
+        &SettlementAuthorization {
+            exchanges_hash,
+            expires,
+            funds: info.funds,
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_messages(refund)
+        .add_attribute("authorized_until", expires.to_string()))
+}
+
+pub fn try_revoke_gp_settlement(
+    deps: DepsMut<ProvenanceQuery>,
+    env: Env,
+    info: MessageInfo,
+) -> ContractResponse {
+    let refund = revoke_gp_settlement(deps, &env.contract.address, &info.sender)?;
+    Ok(Response::new().add_messages(refund))
+}
+
+fn revoke_gp_settlement(
+    deps: DepsMut<ProvenanceQuery>,
+    contract: &Addr,
+    subscription: &Addr,
+) -> Result<Vec<CosmosMsg<ProvenanceMsg>>, ContractError> {
+    let authorization = match settlement_authorization_storage_read(deps.storage)
+        .may_load(subscription.as_bytes())?
+    {
+        Some(authorization) => authorization,
+        None => return Ok(vec![]),
+    };
+    remove_settlement_authorization(deps.storage, subscription)?;
+    record_change(
+        deps.storage,
+        ChangedEntity::SettlementAuthorization,
+        subscription.as_str(),
+    )?;
+
+    // restricted capital can only be moved back through its marker
+    let state = config_read(deps.storage).load()?;
+    let (capital, other): (Vec<Coin>, Vec<Coin>) = authorization
+        .funds
+        .into_iter()
+        .partition(|coin| state.is_like_capital_denom(&coin.denom));
+    let mut refund = vec![];
+    for coin in capital {
+        refund.push(send_coin(
+            deps.as_ref(),
+            &state,
+            coin,
+            subscription.clone(),
+            contract.clone(),
+        )?);
+    }
+    if !other.is_empty() {
+        refund.push(
+            BankMsg::Send {
+                to_address: subscription.to_string(),
+                amount: other,
+            }
+            .into(),
+        );
+    }
+
+    Ok(refund)
+}
+
+pub fn try_complete_asset_exchange_for(
+    deps: DepsMut<ProvenanceQuery>,
+    env: Env,
+    info: MessageInfo,
+    subscription: Addr,
+    exchanges: Vec<AssetExchange>,
+    to: Option<Addr>,
+) -> ContractResponse {
+    let state = config_read(deps.storage).load()?;
+    if info.sender != state.gp {
+        return Err(ContractError::Unauthorized {
+            required: "gp",
+            action: "complete exchanges for a subscription",
+        });
+    }
+    // only the funds the sub attached to its authorization are settled
+    if !info.funds.is_empty() {
+        return Err(ContractError::InvalidRequest {
+            reason: "funds cannot be attached to a settlement for a subscription",
+        });
+    }
+
+    let authorization = settlement_authorization_storage_read(deps.storage)
+        .may_load(subscription.as_bytes())?
+        .ok_or_else(|| ContractError::SettlementNotAuthorized {
+            subscription: subscription.clone(),
+        })?;
+    if authorization.expires <= env.block.time.seconds() {
+        return Err(ContractError::SettlementAuthorizationExpired {
+            expires: authorization.expires,
+        });
+    }
+    if authorization.exchanges_hash != exchanges_hash(&exchanges)? {
+        return Err(ContractError::SettlementAuthorizationMismatch {});
+    }
+    // payouts stay with the sub or go to the lp registered for it
+    if let Some(to) = &to {
+        let lp = subscription_lp_storage_read(deps.storage).may_load(subscription.as_bytes())?;
+        if lp.as_ref() != Some(to) {
+            return Err(ContractError::DestinationNotApproved {
+                destination: to.clone(),
+            });
+        }
+    }
+
+    remove_settlement_authorization(deps.storage, &subscription)?;
+    record_change(
+        deps.storage,
+        ChangedEntity::SettlementAuthorization,
+        subscription.as_str(),
+    )?;
+
+    try_complete_asset_exchange(
+        deps,
+        env,
+        MessageInfo {
+            sender: subscription,
+            funds: authorization.funds,
+        },
+        exchanges,
+        to,
+        None,
+    )
+}
+
+fn check_settlement_hours(state: &State, env: &Env) -> Result<(), ContractError> {
+    match &state.settlement_hours {
+        Some(hours) if !hours.allows(env.block.time.seconds()) => {
+            Err(ContractError::OutsideSettlementHours {})
+        }
+        _ => Ok(()),
+    }
+}
+
+fn record_completed_exchanges(
+    storage: &mut dyn Storage,
+    env: &Env,
+    subscription: &Addr,
+    exchanges: &[AssetExchange],
+    to: &Option<Addr>,
+    released_commitment: i64,
+    contributed: &[Coin],
+) -> StdResult<()> {
+    record_subscription_stats(
+        storage,
+        subscription,
+        exchanges,
+        released_commitment,
+        contributed,
+    )?;
+
+    let mut completed_storage = completed_exchange_storage(storage);
+    let mut completed = completed_storage
+        .may_load(subscription.as_bytes())?
+        .unwrap_or_default();
+    completed.extend(exchanges.iter().map(|exchange| CompletedAssetExchange {
+        exchange: exchange.clone(),
+        to: to.clone(),
+        height: env.block.height,
+        time: env.block.time.seconds(),
+    }));
+    if completed.len() > MAX_COMPLETED_EXCHANGES {
+        completed.drain(..completed.len() - MAX_COMPLETED_EXCHANGES);
+    }
+    completed_storage.save(subscription.as_bytes(), &completed)?;
+    record_change(
+        storage,
+        ChangedEntity::CompletedExchange,
+        subscription.as_str(),
+    )?;
+
+    record_receipts(storage, env, subscription, exchanges, to)
+}
+
+pub fn record_subscription_stats(
+    storage: &mut dyn Storage,
+    subscription: &Addr,
+    exchanges: &[AssetExchange],
+    released_commitment: i64,
+    contributed: &[Coin],
+) -> StdResult<()> {
+    let mut stats_storage = subscription_stats_storage(storage);
+    let mut stats = stats_storage
+        .may_load(subscription.as_bytes())?
+        .unwrap_or_default();
+    let mut commitment = i64::try_from(stats.commitment).unwrap_or(i64::MAX) - released_commitment;
+    for exchange in exchanges {
+        let capital = exchange.capital.unwrap_or_default();
+        if capital < 0 {
+            stats.capital_contributed += capital.unsigned_abs();
+        } else {
+            stats.capital_distributed += capital.unsigned_abs();
+        }
+        stats.shares_redeemed += exchange
+            .investment
+            .unwrap_or_default()
+            .min(0)
+            .unsigned_abs();
+        commitment += exchange.commitment_in_shares.unwrap_or_default();
+    }
+    stats.commitment = commitment.max(0).unsigned_abs();
+    for paid in contributed {
+        match stats
+            .capital_contributed_by_denom
+            .iter_mut()
+            .find(|total| total.denom == paid.denom)
+        {
+            Some(total) => total.amount += paid.amount,
+            None => stats.capital_contributed_by_denom.push(paid.clone()),
+        }
+    }
+    stats
+        .capital_contributed_by_denom
+        .sort_by(|a, b| a.denom.cmp(&b.denom));
+    stats_storage.save(subscription.as_bytes(), &stats)?;
+
+    record_change(
+        storage,
+        ChangedEntity::SubscriptionStats,
+        subscription.as_str(),
+    )
+}
+
+pub fn record_receipts(
+    storage: &mut dyn Storage,
+    env: &Env,
+    subscription: &Addr,
+    exchanges: &[AssetExchange],
+    to: &Option<Addr>,
+) -> StdResult<()> {
+    let max_receipts = config_read(storage).load()?.max_receipts;
+    let mut sequence = receipt_sequence_storage(storage)
+        .may_load(subscription.as_bytes())?
+        .unwrap_or_default();
+
+    for exchange in exchanges {
+        sequence += 1;
+        receipt_storage(storage, subscription).save(
+            &sequence.to_be_bytes(),
+            &Receipt {
+                sequence,
+                exchange_id: exchange.exchange_id.clone(),
+                height: env.block.height,
+                time: env.block.time.seconds(),
+                tx_index: env.transaction.as_ref().map(|tx| tx.index),
+                investment: exchange.investment.unwrap_or_default(),
+                commitment_in_shares: exchange.commitment_in_shares.unwrap_or_default(),
+                capital: exchange.capital.unwrap_or_default(),
+                destination: to.clone().unwrap_or_else(|| subscription.clone()),
+            },
+        )?;
+    }
+    receipt_sequence_storage(storage).save(subscription.as_bytes(), &sequence)?;
+
+    // drop the oldest receipts once the configured retention is exceeded
+    if let Some(max_receipts) = max_receipts {
+        let oldest_kept = sequence.saturating_sub(max_receipts).saturating_add(1);
+        let pruned = receipt_storage_read(storage, subscription)
+            .range(None, Some(&oldest_kept.to_be_bytes()), Order::Ascending)
+            .map(|item| item.map(|(key, _)| key))
+            .collect::<StdResult<Vec<Vec<u8>>>>()?;
+        for key in pruned {
+            receipt_storage(storage, subscription).remove(&key);
+        }
+    }
+
+    record_change(storage, ChangedEntity::Receipt, subscription.as_str())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::contract::execute;
+    use crate::contract::tests::{capital_coin_deps, default_deps, restricted_capital_coin_deps};
+    use crate::mock::execute_args;
+    use crate::mock::load_markers;
+    use crate::mock::msg_at_index;
+    use crate::mock::send_args;
+    use crate::mock::{burn_args, marker_transfer_msg, mint_args};
+    use crate::mock::{wasm_smart_mock_dependencies, MockWasmSmartHandler};
+    use crate::msg::IssueAssetExchange;
+    use crate::msg::{ExchangeDate, ExchangePrice, ExchangeReference};
+    use crate::msg::{HandleMsg, QueryMsg};
+    use crate::query::query;
+    use crate::state::tests::asset_exchange_storage_read;
+    use crate::state::tests::set_accepted;
+    use crate::state::{
+        asset_exchange_storage, authorized_funds_read, config, escrowed_capital_read,
+        remove_asset_exchanges, subscription_lp_storage, CapitalAttribute, CapitalExchangeRate,
+        ManagementFee, NetAssetValue, OutstandingObligations, SettlementHours, ShareClass, State,
+    };
+    use crate::subscribe::tests::mock_sub_state;
+    use cosmwasm_std::from_binary;
+    use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
+    use cosmwasm_std::to_binary;
+    use cosmwasm_std::ReplyOn;
+    use cosmwasm_std::Timestamp;
+    use cosmwasm_std::{coin, coins, OwnedDeps};
+    use cosmwasm_std::{Addr, Attribute};
+    use cosmwasm_std::{ContractResult, QuerierResult, SystemResult};
+    use provwasm_std::MarkerMsgParams;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn size() {
+        let exchange = AssetExchange {
+            investment: Some(-1_000),
+            capital: Some(1_000),
+            date: Some(ExchangeDate::Available(0)),
+            ..AssetExchange::default()
+        };
+        let as_bytes = to_binary(&exchange).unwrap();
+        println!("{}", std::str::from_utf8(as_bytes.as_slice()).unwrap());
+        assert_eq!(41, as_bytes.len());
+
+        println!("{:?}", from_binary::<AssetExchange>(&as_bytes).unwrap());
+    }
+
+    #[test]
+    fn issue_asset_exchange_for_capital_call() {
+        let mut deps = default_deps(None);
+        set_accepted(&mut deps.storage, vec!["sub_1"]);
+        {
+            asset_exchange_storage(&mut deps.storage)
+                .save(
+                    Addr::unchecked("sub_1").as_bytes(),
+                    &vec![AssetExchange {
+                        commitment_in_shares: Some(1_000),
+                        ..AssetExchange::default()
+                    }],
+                )
+                .unwrap();
+        }
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::IssueAssetExchanges {
+                notify_subscriptions: false,
+                verify_capital_denom: false,
+                asset_exchanges: vec![IssueAssetExchange {
+                    subscription: Addr::unchecked("sub_1"),
+                    exchanges: vec![AssetExchange {
+                        investment: Some(1_000),
+                        commitment_in_shares: Some(-1_000),
+                        capital: Some(-1_000),
+                        ..AssetExchange::default()
+                    }],
+                }],
+            },
+        )
+        .unwrap();
+
+        // verify asset exchange added
+        let exchanges = asset_exchange_storage_read(&deps.storage)
+            .load(Addr::unchecked("sub_1").as_bytes())
+            .unwrap();
+        assert_eq!(2, exchanges.len());
+
+        // verify the issuer and issue time are recorded without affecting matching
+        let capital_call = exchanges.iter().find(|e| e.is_capital_call()).unwrap();
+        assert_eq!(
+            Some(ExchangeProvenance {
+                issued_by: Addr::unchecked("gp"),
+                issued_at: mock_env().block.time.seconds(),
+                batch: Some(1),
+            }),
+            capital_call.provenance
+        );
+        assert_eq!(
+            &AssetExchange {
+                provenance: None,
+                price: None,
+                denom: None,
+                ..capital_call.clone()
+            },
+            capital_call
+        );
+    }
+
+    #[test]
+    fn issue_asset_exchange_over_obligation_limits() {
+        let mut deps = default_deps(Some(|state| {
+            state.max_outstanding_exchanges = Some(2);
+            state.max_outstanding_capital = Some(1_500);
+        }));
+        set_accepted(&mut deps.storage, vec!["sub_1", "sub_2"]);
+        let capital_call = AssetExchange {
+            investment: Some(1_000),
+            commitment_in_shares: Some(-1_000),
+            capital: Some(-1_000),
+            ..AssetExchange::default()
+        };
+        save_asset_exchanges(
+            &mut deps.storage,
+            &Addr::unchecked("sub_1"),
+            std::slice::from_ref(&capital_call),
+        )
+        .unwrap();
+        let issue = |capital: i64| HandleMsg::IssueAssetExchanges {
+            notify_subscriptions: false,
+            verify_capital_denom: false,
+            asset_exchanges: vec![IssueAssetExchange {
+                subscription: Addr::unchecked("sub_2"),
+                exchanges: vec![AssetExchange {
+                    capital: Some(capital),
+                    ..capital_call.clone()
+                }],
+            }],
+        };
+
+        // aggregate capital across subs is capped
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            issue(-1_000),
+        );
+        assert_eq!(
+            ContractError::ObligationLimitExceeded {
+                obligation: "capital",
+                max: 1_500
+            },
+            res.unwrap_err()
+        );
+
+        // so is the number of outstanding exchanges
+        remove_asset_exchanges(&mut deps.storage, &Addr::unchecked("sub_2")).unwrap();
+        execute(deps.as_mut(), mock_env(), mock_info("gp", &[]), issue(-500)).unwrap();
+        assert_eq!(
+            OutstandingObligations {
+                exchanges: 2,
+                capital: 1_500,
+            },
+            outstanding_obligations_read(&deps.storage).load().unwrap()
+        );
+        let res = execute(deps.as_mut(), mock_env(), mock_info("gp", &[]), issue(0));
+        assert_eq!(
+            ContractError::ObligationLimitExceeded {
+                obligation: "exchanges",
+                max: 2
+            },
+            res.unwrap_err()
+        );
+
+        // cancelling an exchange frees its share of the limits
+        remove_asset_exchanges(&mut deps.storage, &Addr::unchecked("sub_2")).unwrap();
+        save_asset_exchanges(
+            &mut deps.storage,
+            &Addr::unchecked("sub_2"),
+            &[AssetExchange {
+                capital: Some(-500),
+                ..capital_call.clone()
+            }],
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::CancelAssetExchange {
+                subscription: Addr::unchecked("sub_1"),
+                exchange: ExchangeReference::Index(0),
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            OutstandingObligations {
+                exchanges: 1,
+                capital: 500,
+            },
+            outstanding_obligations_read(&deps.storage).load().unwrap()
+        );
+        execute(deps.as_mut(), mock_env(), mock_info("gp", &[]), issue(0)).unwrap();
+    }
+
+    #[test]
+    fn issue_asset_exchange_after_fund_term() {
+        let mut deps = default_deps(Some(|state| {
+            state.term_end = Some(mock_env().block.time.seconds());
+        }));
+        set_accepted(&mut deps.storage, vec!["sub_1"]);
+        let issue = |investment: i64, capital: i64| HandleMsg::IssueAssetExchanges {
+            notify_subscriptions: false,
+            verify_capital_denom: false,
+            asset_exchanges: vec![IssueAssetExchange {
+                subscription: Addr::unchecked("sub_1"),
+                exchanges: vec![AssetExchange {
+                    investment: Some(investment),
+                    capital: Some(capital),
+                    ..AssetExchange::default()
+                }],
+            }],
+        };
+
+        // verify no new capital can be called once the term ends
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            issue(1_000, -1_000),
+        );
+        assert_eq!(
+            ContractError::FundTermEnded {
+                term_end: mock_env().block.time.seconds()
+            },
+            res.unwrap_err()
+        );
+
+        // verify redemptions can still be issued
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            issue(-1_000, 1_000),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn issue_asset_exchange_notifies_subscription() {
+        let mut deps = default_deps(None);
+        set_accepted(&mut deps.storage, vec!["sub_1"]);
+        let capital_call = AssetExchange {
+            investment: Some(1_000),
+            commitment_in_shares: Some(-1_000),
+            capital: Some(-1_000),
+            ..AssetExchange::default()
+        };
+        let distribution = AssetExchange {
+            capital: Some(1_000),
+            ..AssetExchange::default()
+        };
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::IssueAssetExchanges {
+                notify_subscriptions: true,
+                verify_capital_denom: false,
+                asset_exchanges: vec![IssueAssetExchange {
+                    subscription: Addr::unchecked("sub_1"),
+                    exchanges: vec![capital_call.clone(), distribution.clone()],
+                }],
+            },
+        )
+        .unwrap();
+
+        // verify each exchange is sent to the sub contract
+        assert_eq!(2, res.messages.len());
+        assert_eq!(ReplyOn::Error, res.messages[0].reply_on);
+        let (contract_addr, msg, funds) = execute_args::<SubExecuteMsg>(msg_at_index(&res, 0));
+        assert_eq!("sub_1", contract_addr);
+        assert_eq!(SubExecuteMsg::IssueCapitalCall { capital_call }, msg);
+        assert_eq!(0, funds.len());
+        let (_, msg, _) = execute_args::<SubExecuteMsg>(msg_at_index(&res, 1));
+        assert_eq!(
+            SubExecuteMsg::IssueDistribution {
+                distribution: distribution.clone()
+            },
+            msg
+        );
+
+        // verify no notifications are sent once disabled for the sub
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::SetSubscriptionNotifications {
+                subscription: Addr::unchecked("sub_1"),
+                enabled: false,
+            },
+        )
+        .unwrap();
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::IssueAssetExchanges {
+                notify_subscriptions: true,
+                verify_capital_denom: false,
+                asset_exchanges: vec![IssueAssetExchange {
+                    subscription: Addr::unchecked("sub_1"),
+                    exchanges: vec![distribution],
+                }],
+            },
+        )
+        .unwrap();
+        assert_eq!(0, res.messages.len());
+    }
+
+    #[test]
+    fn issue_asset_exchange_verifies_capital_denom() {
+        fn sub_state(like_capital_denoms: Vec<String>) -> QuerierResult {
+            SystemResult::Ok(ContractResult::Ok(
+                to_binary(&SubState {
+                    admin: Addr::unchecked("marketpalace"),
+                    lp: Addr::unchecked("lp"),
+                    raise: Addr::unchecked("raise_1"),
+                    commitment_denom: String::from("raise_1.commitment"),
+                    investment_denom: String::from("raise_1.investment"),
+                    capital_denom: String::from("legacy_coin"),
+                    capital_per_share: 1,
+                    required_capital_attribute: None,
+                    like_capital_denoms,
+                })
+                .unwrap(),
+            ))
+        }
+        let issue = |handler: MockWasmSmartHandler| {
+            let mut deps = wasm_smart_mock_dependencies(&[], handler);
+            config(&mut deps.storage)
+                .save(&State::test_default())
+                .unwrap();
+            set_accepted(&mut deps.storage, vec!["sub_1"]);
+
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("gp", &[]),
+                HandleMsg::IssueAssetExchanges {
+                    notify_subscriptions: false,
+                    verify_capital_denom: true,
+                    asset_exchanges: vec![IssueAssetExchange {
+                        subscription: Addr::unchecked("sub_1"),
+                        exchanges: vec![AssetExchange {
+                            capital: Some(1_000),
+                            ..AssetExchange::default()
+                        }],
+                    }],
+                },
+            )
+        };
+
+        // verify a sub that can't settle the raise capital denom is rejected
+        assert_eq!(
+            ContractError::UnsupportedCapitalDenom {
+                subscription: Addr::unchecked("sub_1"),
+                denom: String::from("stable_coin"),
+            },
+            issue(|_, _| sub_state(vec![])).unwrap_err()
+        );
+
+        // verify a like capital denom reported by the sub is accepted
+        assert!(issue(|_, _| sub_state(vec![String::from("stable_coin")])).is_ok());
+    }
+
+    #[test]
+    fn issue_asset_exchange_rejects_duplicate_id() {
+        let mut deps = default_deps(None);
+        set_accepted(&mut deps.storage, vec!["sub_1"]);
+        let exchange = |id: &str| AssetExchange {
+            investment: Some(1_000),
+            commitment_in_shares: Some(-1_000),
+            capital: Some(-1_000),
+            exchange_id: Some(String::from(id)),
+            ..AssetExchange::default()
+        };
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::IssueAssetExchanges {
+                notify_subscriptions: false,
+                verify_capital_denom: false,
+                asset_exchanges: vec![IssueAssetExchange {
+                    subscription: Addr::unchecked("sub_1"),
+                    exchanges: vec![exchange("call_2"), exchange("call_1")],
+                }],
+            },
+        )
+        .unwrap();
+
+        // verify stored exchanges keep the order they were issued in
+        assert_eq!(
+            vec![exchange("call_2"), exchange("call_1")],
+            asset_exchange_storage_read(&deps.storage)
+                .load(Addr::unchecked("sub_1").as_bytes())
+                .unwrap()
+        );
+
+        // verify issuing the same batch again fails
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::IssueAssetExchanges {
+                notify_subscriptions: false,
+                verify_capital_denom: false,
+                asset_exchanges: vec![IssueAssetExchange {
+                    subscription: Addr::unchecked("sub_1"),
+                    exchanges: vec![exchange("call_1")],
+                }],
+            },
+        );
+        assert_eq!(
+            ContractError::DuplicateExchangeId {
+                exchange_id: String::from("call_1"),
+            },
+            res.unwrap_err()
+        );
+
+        // verify a later issuance doesn't move existing exchanges out from under an index
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::IssueAssetExchanges {
+                notify_subscriptions: false,
+                verify_capital_denom: false,
+                asset_exchanges: vec![IssueAssetExchange {
+                    subscription: Addr::unchecked("sub_1"),
+                    exchanges: vec![exchange("call_0")],
+                }],
+            },
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::CancelAssetExchange {
+                subscription: Addr::unchecked("sub_1"),
+                exchange: ExchangeReference::Index(0),
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            vec![exchange("call_1"), exchange("call_0")],
+            asset_exchange_storage_read(&deps.storage)
+                .load(Addr::unchecked("sub_1").as_bytes())
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn issue_asset_exchange_bad_actor() {
+        let res = execute(
+            default_deps(None).as_mut(),
+            mock_env(),
+            mock_info("bad_actor", &[]),
+            HandleMsg::IssueAssetExchanges {
+                notify_subscriptions: false,
+                verify_capital_denom: false,
+                asset_exchanges: vec![],
+            },
+        );
+
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn issue_asset_exchange_as_delegate() {
+        let mut deps = default_deps(None);
+        set_accepted(&mut deps.storage, vec!["sub_1"]);
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::AddDelegate {
+                delegate: Addr::unchecked("operator"),
+            },
+        )
+        .unwrap();
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("operator", &[]),
+            HandleMsg::IssueAssetExchanges {
+                notify_subscriptions: false,
+                verify_capital_denom: false,
+                asset_exchanges: vec![IssueAssetExchange {
+                    subscription: Addr::unchecked("sub_1"),
+                    exchanges: vec![AssetExchange {
+                        investment: Some(1_000),
+                        commitment_in_shares: Some(-1_000),
+                        capital: Some(-1_000),
+                        ..AssetExchange::default()
+                    }],
+                }],
+            },
+        )
+        .unwrap();
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::RemoveDelegate {
+                delegate: Addr::unchecked("operator"),
+            },
+        )
+        .unwrap();
+
+        // verify removed delegate can no longer issue
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("operator", &[]),
+            HandleMsg::IssueAssetExchanges {
+                notify_subscriptions: false,
+                verify_capital_denom: false,
+                asset_exchanges: vec![],
+            },
+        );
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn issue_asset_exchange_not_accepted() {
+        let res = execute(
+            default_deps(None).as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::IssueAssetExchanges {
+                notify_subscriptions: false,
+                verify_capital_denom: false,
+                asset_exchanges: vec![IssueAssetExchange {
+                    subscription: Addr::unchecked("sub_1"),
+                    exchanges: vec![AssetExchange {
+                        investment: Some(1_000),
+                        commitment_in_shares: Some(-1_000),
+                        capital: Some(-1_000),
+                        ..AssetExchange::default()
+                    }],
+                }],
+            },
+        );
+
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn cancel_asset_exchange() {
+        let mut deps = default_deps(None);
+        {
+            asset_exchange_storage(&mut deps.storage)
+                .save(
+                    Addr::unchecked("sub_1").as_bytes(),
+                    &vec![AssetExchange {
+                        investment: Some(1_000),
+                        commitment_in_shares: Some(-1_000),
+                        capital: Some(-1_000),
+                        ..AssetExchange::default()
+                    }],
+                )
+                .unwrap();
+        }
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::CancelAssetExchanges {
+                cancellations: vec![IssueAssetExchange {
+                    subscription: Addr::unchecked("sub_1"),
+                    exchanges: vec![AssetExchange {
+                        investment: Some(1_000),
+                        commitment_in_shares: Some(-1_000),
+                        capital: Some(-1_000),
+                        ..AssetExchange::default()
+                    }],
+                }],
+            },
+        )
+        .unwrap();
+
+        // verify exchange is removed
+        assert_eq!(
+            0,
+            asset_exchange_storage_read(&deps.storage)
+                .load(Addr::unchecked("sub_1").as_bytes())
+                .unwrap()
+                .len()
+        )
+    }
+
+    #[test]
+    fn cancel_asset_exchange_by_reference() {
+        let mut deps = default_deps(None);
+        let exchange = |exchange_id: &str| AssetExchange {
+            investment: Some(1_000),
+            commitment_in_shares: Some(-1_000),
+            capital: Some(-1_000),
+            date: Some(ExchangeDate::Due(100)),
+            exchange_id: Some(String::from(exchange_id)),
+            ..AssetExchange::default()
+        };
+        asset_exchange_storage(&mut deps.storage)
+            .save(
+                b"sub_1",
+                &vec![exchange("call_1"), exchange("call_2"), exchange("call_3")],
+            )
+            .unwrap();
+        let mut cancel = |reference: ExchangeReference| {
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("gp", &[]),
+                HandleMsg::CancelAssetExchange {
+                    subscription: Addr::unchecked("sub_1"),
+                    exchange: reference,
+                },
+            )
+        };
+
+        cancel(ExchangeReference::ExchangeId(String::from("call_2"))).unwrap();
+        cancel(ExchangeReference::Index(0)).unwrap();
+        assert_eq!(
+            ContractError::ExchangeNotFound {
+                subscription: Addr::unchecked("sub_1")
+            },
+            cancel(ExchangeReference::Index(1)).unwrap_err()
+        );
+        assert!(cancel(ExchangeReference::ExchangeId(String::from("call_1"))).is_err());
+
+        // verify only the last exchange remains
+        assert_eq!(
+            vec![exchange("call_3")],
+            asset_exchange_storage_read(&deps.storage)
+                .load(b"sub_1")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn cancel_batch() {
+        let mut deps = default_deps(None);
+        set_accepted(&mut deps.storage, vec!["sub_1", "sub_2"]);
+        let exchange = |exchange_id: &str| AssetExchange {
+            investment: Some(1_000),
+            commitment_in_shares: Some(-1_000),
+            capital: Some(-1_000),
+            date: Some(ExchangeDate::Due(100)),
+            exchange_id: Some(String::from(exchange_id)),
+            ..AssetExchange::default()
+        };
+        let mut issue = |issuances: Vec<(&str, &str)>| {
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("gp", &[]),
+                HandleMsg::IssueAssetExchanges {
+                    notify_subscriptions: false,
+                    verify_capital_denom: false,
+                    asset_exchanges: issuances
+                        .into_iter()
+                        .map(|(subscription, exchange_id)| IssueAssetExchange {
+                            subscription: Addr::unchecked(subscription),
+                            exchanges: vec![exchange(exchange_id)],
+                        })
+                        .collect(),
+                },
+            )
+            .unwrap()
+        };
+        issue(vec![("sub_1", "call_1")]);
+        let res = issue(vec![("sub_1", "call_2"), ("sub_2", "call_2")]);
+        assert_eq!("2", res.attributes[0].value);
+
+        // verify the batch can be looked up across subs
+        let batch: Vec<SubscriptionAssetExchanges> = from_binary(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::GetBatch { batch_id: 2 },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            vec![
+                SubscriptionAssetExchanges {
+                    subscription: Addr::unchecked("sub_1"),
+                    exchanges: vec![exchange("call_2")],
+                },
+                SubscriptionAssetExchanges {
+                    subscription: Addr::unchecked("sub_2"),
+                    exchanges: vec![exchange("call_2")],
+                },
+            ],
+            batch
+        );
+
+        // verify only the gp or a delegate can cancel a batch
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bad_actor", &[]),
+            HandleMsg::CancelBatch { batch_id: 2 },
+        );
+        assert!(res.is_err());
+
+        // verify cancelling leaves exchanges from other batches in place
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::CancelBatch { batch_id: 2 },
+        )
+        .unwrap();
+        assert_eq!("2", res.attributes[0].value);
+        assert_eq!(
+            vec![exchange("call_1")],
+            asset_exchange_storage_read(&deps.storage)
+                .load(b"sub_1")
+                .unwrap()
+        );
+        assert!(asset_exchange_storage_read(&deps.storage)
+            .load(b"sub_2")
+            .unwrap()
+            .is_empty());
+
+        // verify a cancelled batch can't be cancelled again
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::CancelBatch { batch_id: 2 },
+        );
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn cancel_large_batch_with_cosign_in_chunks() {
+        let mut deps = default_deps(None);
+        let subscriptions: Vec<String> = (0..MAX_BATCH_CANCELLATIONS + 1)
+            .map(|i| format!("sub_{i}"))
+            .collect();
+        set_accepted(
+            &mut deps.storage,
+            subscriptions.iter().map(String::as_str).collect(),
+        );
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("marketpalace", &[]),
+            HandleMsg::UpdateBatchCancelThreshold {
+                batch_cancel_cosign_threshold: Some(1),
+            },
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::IssueAssetExchanges {
+                notify_subscriptions: false,
+                verify_capital_denom: false,
+                asset_exchanges: subscriptions
+                    .iter()
+                    .map(|subscription| IssueAssetExchange {
+                        subscription: Addr::unchecked(subscription),
+                        exchanges: vec![AssetExchange {
+                            investment: Some(1_000),
+                            commitment_in_shares: Some(-1_000),
+                            capital: Some(-1_000),
+                            ..AssetExchange::default()
+                        }],
+                    })
+                    .collect(),
+            },
+        )
+        .unwrap();
+        let mut cancel = |sender: &str| {
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(sender, &[]),
+                HandleMsg::CancelBatch { batch_id: 1 },
+            )
+            .unwrap()
+        };
+
+        // verify the gp alone can't cancel a batch above the threshold
+        let res = cancel("gp");
+        assert_eq!("batch_cancellation", res.attributes[0].key);
+
+        // verify the admin's co-sign cancels the first chunk
+        let res = cancel("marketpalace");
+        assert_eq!(
+            vec![
+                Attribute::new("cancelled", MAX_BATCH_CANCELLATIONS.to_string()),
+                Attribute::new("remaining", "1")
+            ],
+            res.attributes
+        );
+
+        // verify the approval carries over to the rest of the batch
+        let res = cancel("gp");
+        assert_eq!(
+            vec![
+                Attribute::new("cancelled", "1"),
+                Attribute::new("remaining", "0")
+            ],
+            res.attributes
+        );
+        assert!(query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetBatch { batch_id: 1 }
+        )
+        .map(|res| from_binary::<Vec<SubscriptionAssetExchanges>>(&res).unwrap())
+        .unwrap()
+        .is_empty());
+    }
+
+    #[test]
+    fn cancel_asset_exchange_bad_actor() {
+        let res = execute(
+            default_deps(None).as_mut(),
+            mock_env(),
+            mock_info("bad_actor", &[]),
+            HandleMsg::CancelAssetExchanges {
+                cancellations: vec![],
+            },
+        );
+
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn cancel_asset_exchange_not_found() {
+        let res = execute(
+            default_deps(None).as_mut(),
+            mock_env(),
+            mock_info("gp", &coins(10_000, "stable_coin")),
+            HandleMsg::CancelAssetExchanges {
+                cancellations: vec![IssueAssetExchange {
+                    subscription: Addr::unchecked("sub_1"),
+                    exchanges: vec![AssetExchange {
+                        investment: Some(1_000),
+                        commitment_in_shares: Some(-1_000),
+                        capital: Some(-1_000),
+                        ..AssetExchange::default()
+                    }],
+                }],
+            },
+        );
+
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn complete_asset_exchange() {
+        let mut deps = capital_coin_deps(None);
+        load_markers(&mut deps.querier);
+        {
+            asset_exchange_storage(&mut deps.storage)
+                .save(
+                    Addr::unchecked("sub_1").as_bytes(),
+                    &vec![
+                        AssetExchange {
+                            investment: Some(-1_000),
+                            capital: Some(1_000),
+                            ..AssetExchange::default()
+                        },
+                        AssetExchange {
+                            investment: Some(-1_000),
+                            capital: Some(1_000),
+                            ..AssetExchange::default()
+                        },
+                    ],
+                )
+                .unwrap();
+        }
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &coins(1_000, "investment_coin")),
+            HandleMsg::CompleteAssetExchange {
+                exchanges: vec![
+                    AssetExchange {
+                        investment: Some(-1_000),
+                        capital: Some(1_000),
+                        ..AssetExchange::default()
+                    },
+                    AssetExchange {
+                        investment: Some(-1_000),
+                        capital: Some(1_000),
+                        ..AssetExchange::default()
+                    },
+                ],
+                to: Some(Addr::unchecked("destination")),
+                memo: Some(String::from("note")),
+            },
+        )
+        .unwrap();
+
+        assert_eq!(3, res.messages.len());
+
+        // verify memo
+        assert_eq!(1, res.attributes.len());
+        let attribute = res.attributes.first().unwrap();
+        assert_eq!("memo", attribute.key);
+        assert_eq!("note", attribute.value);
+
+        // verify deposit investment
+        let (to_address, coins) = send_args(msg_at_index(&res, 0));
+        let coin = coins.first().unwrap();
+        assert_eq!("tp18vd8fpwxzck93qlwghaj6arh4p7c5n89x8kskz", to_address);
+        assert_eq!("investment_coin", coin.denom);
+        assert_eq!(2_000, coin.amount.u128());
+
+        // verify burn investment
+        let coin = burn_args(msg_at_index(&res, 1));
+        assert_eq!("investment_coin", coin.denom);
+        assert_eq!(2_000, coin.amount.u128());
+
+        // verify send message
+        let (to_address, coins) = send_args(msg_at_index(&res, 2));
+        let coin = coins.first().unwrap();
+        assert_eq!("destination", to_address);
+        assert_eq!("capital_coin", coin.denom);
+        assert_eq!(2_000, coin.amount.u128());
+
+        // verify exchange is removed
+        assert_eq!(
+            0,
+            asset_exchange_storage_read(&deps.storage)
+                .load(Addr::unchecked("sub_1").as_bytes())
+                .unwrap()
+                .len()
+        );
+
+        // verify exchanges are recorded as completed
+        let completed = completed_exchange_storage(&mut deps.storage)
+            .load(Addr::unchecked("sub_1").as_bytes())
+            .unwrap();
+        assert_eq!(2, completed.len());
+        let record = completed.first().unwrap();
+        assert_eq!(Some(1_000), record.exchange.capital);
+        assert_eq!(Some(Addr::unchecked("destination")), record.to);
+        assert_eq!(mock_env().block.height, record.height);
+        assert_eq!(mock_env().block.time.seconds(), record.time);
+    }
+
+    #[test]
+    fn complete_asset_exchange_to_unapproved_destination() {
+        let mut deps = capital_coin_deps(Some(|state| {
+            state.approved_destinations = Some(BTreeSet::from([Addr::unchecked("approved")]));
+        }));
+        load_markers(&mut deps.querier);
+        let redemption = AssetExchange {
+            investment: Some(-1_000),
+            capital: Some(1_000),
+            ..AssetExchange::default()
+        };
+        asset_exchange_storage(&mut deps.storage)
+            .save(b"sub_1", &vec![redemption.clone()])
+            .unwrap();
+        let complete = |deps: &mut OwnedDeps<_, _, _, _>, to: Option<Addr>| {
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("sub_1", &coins(1_000, "investment_coin")),
+                HandleMsg::CompleteAssetExchange {
+                    exchanges: vec![redemption.clone()],
+                    to,
+                    memo: None,
+                },
+            )
+        };
+
+        assert_eq!(
+            ContractError::DestinationNotApproved {
+                destination: Addr::unchecked("destination")
+            },
+            complete(&mut deps, Some(Addr::unchecked("destination"))).unwrap_err()
+        );
+
+        // verify settling to the sub itself isn't restricted
+        complete(&mut deps, None).unwrap();
+    }
+
+    #[test]
+    fn complete_asset_exchange_with_restricted_marker() {
+        let mut deps = restricted_capital_coin_deps(None);
+        deps.querier
+            .with_attributes("sub_1", &[("capital.test", "", "")]);
+        load_markers(&mut deps.querier);
+        {
+            asset_exchange_storage(&mut deps.storage)
+                .save(
+                    Addr::unchecked("sub_1").as_bytes(),
+                    &vec![
+                        AssetExchange {
+                            investment: Some(-1_000),
+                            capital: Some(1_000),
+                            ..AssetExchange::default()
+                        },
+                        AssetExchange {
+                            investment: Some(-1_000),
+                            capital: Some(1_000),
+                            ..AssetExchange::default()
+                        },
+                    ],
+                )
+                .unwrap();
+        }
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &coins(1_000, "investment_coin")),
+            HandleMsg::CompleteAssetExchange {
+                exchanges: vec![
+                    AssetExchange {
+                        investment: Some(-1_000),
+                        capital: Some(1_000),
+                        ..AssetExchange::default()
+                    },
+                    AssetExchange {
+                        investment: Some(-1_000),
+                        capital: Some(1_000),
+                        ..AssetExchange::default()
+                    },
+                ],
+                to: None,
+                memo: Some(String::from("note")),
+            },
+        )
+        .unwrap();
+
+        assert_eq!(3, res.messages.len());
+
+        // verify memo
+        assert_eq!(1, res.attributes.len());
+        let attribute = res.attributes.first().unwrap();
+        assert_eq!("memo", attribute.key);
+        assert_eq!("note", attribute.value);
+
+        // verify deposit investment
+        let (to_address, coins) = send_args(msg_at_index(&res, 0));
+        let coin = coins.first().unwrap();
+        assert_eq!("tp18vd8fpwxzck93qlwghaj6arh4p7c5n89x8kskz", to_address);
+        assert_eq!("investment_coin", coin.denom);
+        assert_eq!(2_000, coin.amount.u128());
+
+        // verify burn investment
+        let coin = burn_args(msg_at_index(&res, 1));
+        assert_eq!("investment_coin", coin.denom);
+        assert_eq!(2_000, coin.amount.u128());
+
+        // verify transfer message
+        assert_eq!(
+            &MarkerMsgParams::TransferMarkerCoins {
+                coin: cosmwasm_std::coin(2_000, "restricted_capital_coin"),
+                to: Addr::unchecked("sub_1"),
+                from: Addr::unchecked(MOCK_CONTRACT_ADDR),
+            },
+            marker_transfer_msg(msg_at_index(&res, 2)),
+        );
+
+        // verify exchange is removed
+        assert_eq!(
+            0,
+            asset_exchange_storage_read(&deps.storage)
+                .load(Addr::unchecked("sub_1").as_bytes())
+                .unwrap()
+                .len()
+        )
+    }
+
+    #[test]
+    fn complete_asset_exchange_with_restricted_marker_unattributed_sender() {
+        let mut deps = restricted_capital_coin_deps(None);
+        deps.querier
+            .with_attributes("destination", &[("capital.test", "", "")]);
+        load_markers(&mut deps.querier);
+        let exchange = AssetExchange {
+            investment: Some(-1_000),
+            capital: Some(1_000),
+            ..AssetExchange::default()
+        };
+        asset_exchange_storage(&mut deps.storage)
+            .save(Addr::unchecked("sub_1").as_bytes(), &vec![exchange.clone()])
+            .unwrap();
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &coins(1_000, "investment_coin")),
+            HandleMsg::CompleteAssetExchange {
+                exchanges: vec![exchange],
+                to: Some(Addr::unchecked("destination")),
+                memo: None,
+            },
+        );
+
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn complete_asset_exchange_without_asset() {
+        let mut deps = default_deps(None);
+        {
+            asset_exchange_storage(&mut deps.storage)
+                .save(
+                    Addr::unchecked("sub_1").as_bytes(),
+                    &vec![AssetExchange {
+                        investment: Some(-1_000),
+                        capital: Some(1_000),
+                        ..AssetExchange::default()
+                    }],
+                )
+                .unwrap();
+        }
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &[]),
+            HandleMsg::CompleteAssetExchange {
+                exchanges: vec![AssetExchange {
+                    investment: Some(-1_000),
+                    capital: Some(1_000),
+                    ..AssetExchange::default()
+                }],
+                to: Some(Addr::unchecked("destination")),
+                memo: Some(String::from("note")),
+            },
+        );
+
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn complete_distribution_in_like_capital_denom() {
+        let mut deps = capital_coin_deps(Some(|state| {
+            state.capital_exchange_rates = vec![CapitalExchangeRate {
+                denom: String::from("other_coin"),
+                numerator: 1,
+                denominator: 1,
+            }];
+        }));
+        load_markers(&mut deps.querier);
+        set_accepted(&mut deps.storage, vec!["sub_1"]);
+        let exchange = |capital: i64, denom: Option<&str>| AssetExchange {
+            capital: Some(capital),
+            denom: denom.map(String::from),
+            ..AssetExchange::default()
+        };
+        let mut issue = |exchanges: Vec<AssetExchange>| {
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("gp", &[]),
+                HandleMsg::IssueAssetExchanges {
+                    notify_subscriptions: false,
+                    verify_capital_denom: false,
+                    asset_exchanges: vec![IssueAssetExchange {
+                        subscription: Addr::unchecked("sub_1"),
+                        exchanges,
+                    }],
+                },
+            )
+        };
+
+        // verify only distributions in a like capital denom are accepted
+        assert_eq!(
+            ContractError::UnsupportedDistributionDenom {
+                denom: String::from("unknown_coin")
+            },
+            issue(vec![exchange(500, Some("unknown_coin"))]).unwrap_err()
+        );
+        assert!(issue(vec![exchange(-500, Some("other_coin"))]).is_err());
+        issue(vec![exchange(500, Some("other_coin")), exchange(300, None)]).unwrap();
+
+        // verify each distribution is paid in its own denom
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &[]),
+            HandleMsg::CompleteAssetExchange {
+                exchanges: vec![exchange(500, Some("other_coin")), exchange(300, None)],
+                to: None,
+                memo: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(2, res.messages.len());
+        let (to_address, coins) = send_args(msg_at_index(&res, 0));
+        assert_eq!("sub_1", to_address);
+        assert_eq!(&coin(300, "capital_coin"), coins.first().unwrap());
+        let (_, coins) = send_args(msg_at_index(&res, 1));
+        assert_eq!(&coin(500, "other_coin"), coins.first().unwrap());
+    }
+
+    #[test]
+    fn complete_full_redemption_releases_commitment() {
+        let mut deps = capital_coin_deps(None);
+        load_markers(&mut deps.querier);
+        let redemption = AssetExchange {
+            investment: Some(-1_000),
+            capital: Some(1_000),
+            ..AssetExchange::default()
+        };
+        let save = |deps: &mut OwnedDeps<_, _, _, _>| {
+            asset_exchange_storage(&mut deps.storage)
+                .save(
+                    Addr::unchecked("sub_1").as_bytes(),
+                    &vec![redemption.clone()],
+                )
+                .unwrap();
+        };
+        let complete = HandleMsg::CompleteAssetExchange {
+            exchanges: vec![redemption.clone()],
+            to: None,
+            memo: None,
+        };
+        let funds = vec![coin(1_000, "investment_coin"), coin(500, "commitment_coin")];
+
+        // verify commitment isn't released while the sub still holds investment
+        save(&mut deps);
+        deps.querier
+            .base
+            .update_balance(Addr::unchecked("sub_1"), coins(100, "investment_coin"));
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &funds),
+            complete.clone(),
+        );
+        assert!(res.is_err());
+
+        save(&mut deps);
+        deps.querier
+            .base
+            .update_balance(Addr::unchecked("sub_1"), vec![]);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &funds),
+            complete,
+        )
+        .unwrap();
+
+        // verify the attached commitment is burned alongside the investment
+        assert_eq!(5, res.messages.len());
+        let coin = burn_args(msg_at_index(&res, 3));
+        assert_eq!("commitment_coin", coin.denom);
+        assert_eq!(500, coin.amount.u128());
+        assert_eq!(
+            "500",
+            res.attributes
+                .iter()
+                .find(|attr| attr.key == "released_commitment")
+                .unwrap()
+                .value
+        );
+    }
+
+    #[test]
+    fn complete_nav_redemption() {
+        let mut deps = capital_coin_deps(None);
+        load_markers(&mut deps.querier);
+        set_accepted(&mut deps.storage, vec!["sub_1"]);
+        let redemption = |capital: Option<i64>| AssetExchange {
+            investment: Some(-1_000),
+            capital,
+            price: Some(ExchangePrice::Nav),
+            ..AssetExchange::default()
+        };
+        let issue = |deps: &mut OwnedDeps<_, _, _, _>, exchange: AssetExchange| {
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("gp", &[]),
+                HandleMsg::IssueAssetExchanges {
+                    notify_subscriptions: false,
+                    verify_capital_denom: false,
+                    asset_exchanges: vec![IssueAssetExchange {
+                        subscription: Addr::unchecked("sub_1"),
+                        exchanges: vec![exchange],
+                    }],
+                },
+            )
+        };
+        let complete = |deps: &mut OwnedDeps<_, _, _, _>| {
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("sub_1", &coins(1_000, "investment_coin")),
+                HandleMsg::CompleteAssetExchange {
+                    exchanges: vec![redemption(None)],
+                    to: None,
+                    memo: None,
+                },
+            )
+        };
+
+        // verify a priced exchange can't also fix its capital
+        assert!(issue(&mut deps, redemption(Some(1_000))).is_err());
+        issue(&mut deps, redemption(None)).unwrap();
+
+        assert_eq!(
+            ContractError::NavNotPosted {},
+            complete(&mut deps).unwrap_err()
+        );
+
+        let post = |deps: &mut OwnedDeps<_, _, _, _>, sender: &str| {
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(sender, &[]),
+                HandleMsg::PostNav { per_share: 3 },
+            )
+        };
+        assert!(post(&mut deps, "bad_actor").is_err());
+        post(&mut deps, "gp").unwrap();
+        assert_eq!(
+            Some(NetAssetValue {
+                per_share: 3,
+                posted_at: mock_env().block.time.seconds(),
+            }),
+            config_read(&deps.storage).load().unwrap().nav
+        );
+
+        // verify capital is paid at the posted nav
+        let res = complete(&mut deps).unwrap();
+        let (to_address, coins) = send_args(msg_at_index(&res, 2));
+        let coin = coins.first().unwrap();
+        assert_eq!("sub_1", to_address);
+        assert_eq!("capital_coin", coin.denom);
+        assert_eq!(3_000, coin.amount.u128());
+    }
+
+    #[test]
+    fn complete_asset_exchange_over_ownership_limit() {
+        let mut deps = capital_coin_deps(Some(|state| {
+            state.max_ownership_bps = Some(2_000);
+        }));
+        load_markers(&mut deps.querier);
+        let commitment = AssetExchange {
+            commitment_in_shares: Some(500),
+            ..AssetExchange::default()
+        };
+        let capital_call = AssetExchange {
+            investment: Some(500),
+            commitment_in_shares: Some(-500),
+            capital: Some(-500),
+            ..commitment.clone()
+        };
+        asset_exchange_storage(&mut deps.storage)
+            .save(b"sub_1", &vec![commitment.clone(), capital_call.clone()])
+            .unwrap();
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &[]),
+            HandleMsg::CompleteAssetExchange {
+                exchanges: vec![commitment],
+                to: None,
+                memo: None,
+            },
+        );
+        assert_eq!(
+            ContractError::OwnershipLimitExceeded {
+                subscription: Addr::unchecked("sub_1"),
+                ownership_bps: 3_731,
+                max_ownership_bps: 2_000,
+            },
+            res.unwrap_err()
+        );
+
+        // verify converting commitment into investment isn't an increase
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(
+                "sub_1",
+                &[coin(500, "capital_coin"), coin(500, "commitment_coin")],
+            ),
+            HandleMsg::CompleteAssetExchange {
+                exchanges: vec![capital_call],
+                to: None,
+                memo: None,
+            },
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn claim_asset_exchange_not_available_yet() {
+        let mut deps = default_deps(None);
+        load_markers(&mut deps.querier);
+        {
+            asset_exchange_storage(&mut deps.storage)
+                .save(
+                    Addr::unchecked("sub_1").as_bytes(),
+                    &vec![AssetExchange {
+                        investment: Some(-1_000),
+                        capital: Some(1_000),
+                        date: Some(ExchangeDate::Available(1675209600)), // Feb 01 2023 UTC
+                        ..AssetExchange::default()
+                    }],
+                )
+                .unwrap();
+        }
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(1672531200); // Jan 01 2023 UTC
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &coins(1_000, "investment_coin")),
+            HandleMsg::CompleteAssetExchange {
+                exchanges: vec![AssetExchange {
+                    investment: Some(-1_000),
+                    capital: Some(1_000),
+                    ..AssetExchange::default()
+                }],
+                to: Some(Addr::unchecked("destination")),
+                memo: Some(String::from("note")),
+            },
+        );
+
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn complete_asset_exchange_prunes_history() {
+        let mut deps = capital_coin_deps(None);
+        load_markers(&mut deps.querier);
+        let exchange = AssetExchange {
+            capital: Some(1),
+            ..AssetExchange::default()
+        };
+        {
+            asset_exchange_storage(&mut deps.storage)
+                .save(
+                    Addr::unchecked("sub_1").as_bytes(),
+                    &vec![exchange.clone(); MAX_COMPLETED_EXCHANGES + 1],
+                )
+                .unwrap();
+        }
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &[]),
+            HandleMsg::CompleteAssetExchange {
+                exchanges: vec![exchange; MAX_COMPLETED_EXCHANGES + 1],
+                to: None,
+                memo: None,
+            },
+        )
+        .unwrap();
+
+        // verify only the most recent exchanges are retained
+        assert_eq!(
+            MAX_COMPLETED_EXCHANGES,
+            completed_exchange_storage(&mut deps.storage)
+                .load(Addr::unchecked("sub_1").as_bytes())
+                .unwrap()
+                .len()
+        );
+    }
+
+    #[test]
+    fn complete_asset_exchange_writes_receipts() {
+        let mut deps = capital_coin_deps(Some(|state| state.max_receipts = Some(2)));
+        load_markers(&mut deps.querier);
+        let exchanges: Vec<AssetExchange> = ["a", "b", "c"]
+            .iter()
+            .map(|id| AssetExchange {
+                capital: Some(1),
+                exchange_id: Some(String::from(*id)),
+                ..AssetExchange::default()
+            })
+            .collect();
+        {
+            asset_exchange_storage(&mut deps.storage)
+                .save(Addr::unchecked("sub_1").as_bytes(), &exchanges)
+                .unwrap();
+        }
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &[]),
+            HandleMsg::CompleteAssetExchange {
+                exchanges,
+                to: None,
+                memo: None,
+            },
+        )
+        .unwrap();
+
+        // verify the oldest receipt was pruned past the retention limit
+        let receipts = receipt_storage_read(&deps.storage, &Addr::unchecked("sub_1"))
+            .range(None, None, Order::Ascending)
+            .map(|item| item.unwrap().1)
+            .collect::<Vec<Receipt>>();
+        assert_eq!(2, receipts.len());
+        assert_eq!(2, receipts[0].sequence);
+        assert_eq!(Some(String::from("b")), receipts[0].exchange_id);
+        assert_eq!(1, receipts[0].capital);
+        assert_eq!(Addr::unchecked("sub_1"), receipts[0].destination);
+        assert_eq!(mock_env().block.height, receipts[0].height);
+        assert_eq!(Some(String::from("c")), receipts[1].exchange_id);
+    }
+
+    #[test]
+    fn complete_asset_exchange_with_required_attestation() {
+        let mut deps = mock_sub_state();
+        deps.querier.base.with_attributes("lp", &[("kyc", "", "")]);
+        load_markers(&mut deps.querier.base);
+        config(&mut deps.storage)
+            .save(&State::test_capital_coin())
+            .unwrap();
+        let exchange = AssetExchange {
+            investment: Some(-1_000),
+            capital: Some(1_000),
+            required_attestation: Some(String::from("kyc")),
+            ..AssetExchange::default()
+        };
+        asset_exchange_storage(&mut deps.storage)
+            .save(Addr::unchecked("sub_1").as_bytes(), &vec![exchange.clone()])
+            .unwrap();
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &coins(1_000, "investment_coin")),
+            HandleMsg::CompleteAssetExchange {
+                exchanges: vec![exchange],
+                to: None,
+                memo: None,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(3, res.messages.len());
+    }
+
+    #[test]
+    fn complete_asset_exchange_missing_required_attestation() {
+        let mut deps = mock_sub_state();
+        deps.querier.base.with_attributes("lp", &[("506c", "", "")]);
+        load_markers(&mut deps.querier.base);
+        config(&mut deps.storage)
+            .save(&State::test_capital_coin())
+            .unwrap();
+        let exchange = AssetExchange {
+            investment: Some(-1_000),
+            capital: Some(1_000),
+            required_attestation: Some(String::from("kyc")),
+            ..AssetExchange::default()
+        };
+        asset_exchange_storage(&mut deps.storage)
+            .save(Addr::unchecked("sub_1").as_bytes(), &vec![exchange.clone()])
+            .unwrap();
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &coins(1_000, "investment_coin")),
+            HandleMsg::CompleteAssetExchange {
+                exchanges: vec![exchange],
+                to: None,
+                memo: None,
+            },
+        );
+
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn complete_asset_exchange_within_window() {
+        let mut deps = capital_coin_deps(None);
+        load_markers(&mut deps.querier);
+        let now = mock_env().block.time.seconds();
+        let exchange = AssetExchange {
+            investment: Some(1_000),
+            commitment_in_shares: Some(-1_000),
+            capital: Some(-1_000),
+            date: Some(ExchangeDate::Window(now - 10, now + 10)),
+            ..AssetExchange::default()
+        };
+        asset_exchange_storage(&mut deps.storage)
+            .save(Addr::unchecked("sub_1").as_bytes(), &vec![exchange.clone()])
+            .unwrap();
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &coins(1_000, "capital_coin")),
+            HandleMsg::CompleteAssetExchange {
+                exchanges: vec![exchange],
+                to: None,
+                memo: None,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(4, res.messages.len());
+    }
+
+    #[test]
+    fn complete_asset_exchange_outside_window() {
+        let mut deps = capital_coin_deps(None);
+        load_markers(&mut deps.querier);
+        let now = mock_env().block.time.seconds();
+        let early = AssetExchange {
+            investment: Some(1_000),
+            commitment_in_shares: Some(-1_000),
+            capital: Some(-1_000),
+            date: Some(ExchangeDate::Window(now + 10, now + 20)),
+            ..AssetExchange::default()
+        };
+        let late = AssetExchange {
+            date: Some(ExchangeDate::Window(now - 20, now - 10)),
+            ..early.clone()
+        };
+        asset_exchange_storage(&mut deps.storage)
+            .save(
+                Addr::unchecked("sub_1").as_bytes(),
+                &vec![early.clone(), late.clone()],
+            )
+            .unwrap();
+
+        for exchange in [early, late] {
+            let res = execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("sub_1", &coins(1_000, "capital_coin")),
+                HandleMsg::CompleteAssetExchange {
+                    exchanges: vec![exchange],
+                    to: None,
+                    memo: None,
+                },
+            );
+
+            assert!(res.is_err());
+        }
+    }
+
+    #[test]
+    fn process_queue_skips_unavailable_settlements() {
+        let mut deps = capital_coin_deps(None);
+        load_markers(&mut deps.querier);
+        let now = mock_env().block.time.seconds();
+        let future = AssetExchange {
+            capital: Some(1_000),
+            date: Some(ExchangeDate::Available(now + 60)),
+            ..AssetExchange::default()
+        };
+        let available = AssetExchange {
+            date: None,
+            ..future.clone()
+        };
+        asset_exchange_storage(&mut deps.storage)
+            .save(
+                Addr::unchecked("sub_1").as_bytes(),
+                &vec![future.clone(), available.clone()],
+            )
+            .unwrap();
+        for exchange in [future, available] {
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("sub_1", &[]),
+                HandleMsg::QueueAssetExchange {
+                    exchanges: vec![exchange],
+                    to: None,
+                    memo: None,
+                },
+            )
+            .unwrap();
+        }
+
+        // the future settlement at the front doesn't use up the limit
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("anyone", &[]),
+            HandleMsg::ProcessQueue { limit: 1 },
+        )
+        .unwrap();
+        assert_eq!(1, res.messages.len());
+        let (_, msg, _) = execute_args::<HandleMsg>(msg_at_index(&res, 0));
+        assert_eq!(HandleMsg::SettleQueued { sequence: 2 }, msg);
+    }
+
+    #[test]
+    fn queue_and_process_settlement() {
+        let mut deps = capital_coin_deps(None);
+        load_markers(&mut deps.querier);
+        let now = mock_env().block.time.seconds();
+        let distribution = AssetExchange {
+            capital: Some(1_000),
+            date: Some(ExchangeDate::Available(now + 60)),
+            ..AssetExchange::default()
+        };
+        let capital_call = AssetExchange {
+            investment: Some(1_000),
+            commitment_in_shares: Some(-1_000),
+            capital: Some(-1_000),
+            date: None,
+            ..distribution.clone()
+        };
+        asset_exchange_storage(&mut deps.storage)
+            .save(
+                Addr::unchecked("sub_1").as_bytes(),
+                &vec![distribution.clone(), capital_call.clone()],
+            )
+            .unwrap();
+
+        // verify capital calls can't be queued since they need attached funds
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &[]),
+            HandleMsg::QueueAssetExchange {
+                exchanges: vec![capital_call],
+                to: None,
+                memo: None,
+            },
+        );
+        assert!(res.is_err());
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &[]),
+            HandleMsg::QueueAssetExchange {
+                exchanges: vec![distribution],
+                to: None,
+                memo: None,
+            },
+        )
+        .unwrap();
+
+        // verify nothing is settled before the exchange is available
+        let process = HandleMsg::ProcessQueue { limit: 10 };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("anyone", &[]),
+            process.clone(),
+        )
+        .unwrap();
+        assert_eq!(0, res.messages.len());
+
+        let mut later = mock_env();
+        later.block.time = Timestamp::from_seconds(now + 60);
+        let res = execute(
+            deps.as_mut(),
+            later.clone(),
+            mock_info("anyone", &[]),
+            process,
+        )
+        .unwrap();
+        assert_eq!(1, res.messages.len());
+        assert_eq!(ReplyOn::Error, res.messages[0].reply_on);
+        let (contract_addr, msg, _) = execute_args::<HandleMsg>(msg_at_index(&res, 0));
+        assert_eq!(MOCK_CONTRACT_ADDR, contract_addr);
+        assert_eq!(HandleMsg::SettleQueued { sequence: 1 }, msg);
+
+        // verify only the raise itself can settle a queued exchange
+        let res = execute(
+            deps.as_mut(),
+            later.clone(),
+            mock_info("anyone", &[]),
+            msg.clone(),
+        );
+        assert!(res.is_err());
+
+        let res = execute(
+            deps.as_mut(),
+            later,
+            mock_info(MOCK_CONTRACT_ADDR, &[]),
+            msg,
+        )
+        .unwrap();
+        let (to_address, coins) = send_args(msg_at_index(&res, 0));
+        assert_eq!("sub_1", to_address);
+        assert_eq!(1_000, coins.first().unwrap().amount.u128());
+        assert_eq!(
+            1,
+            asset_exchange_storage_read(&deps.storage)
+                .load(Addr::unchecked("sub_1").as_bytes())
+                .unwrap()
+                .len()
+        );
+    }
+
+    #[test]
+    fn complete_asset_exchange_for_share_class() {
+        let mut deps = capital_coin_deps(Some(|state| {
+            state.share_classes = vec![ShareClass {
+                name: String::from("side_pocket"),
+                investment_denom: String::from("side_pocket_coin"),
+            }]
+        }));
+        load_markers(&mut deps.querier);
+        set_accepted(&mut deps.storage, vec!["sub_1"]);
+        let exchange = AssetExchange {
+            investment: Some(1_000),
+            class: Some(String::from("side_pocket")),
+            ..AssetExchange::default()
+        };
+
+        // verify issuance in an unknown class is rejected
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::IssueAssetExchanges {
+                notify_subscriptions: false,
+                verify_capital_denom: false,
+                asset_exchanges: vec![IssueAssetExchange {
+                    subscription: Addr::unchecked("sub_1"),
+                    exchanges: vec![AssetExchange {
+                        class: Some(String::from("unknown")),
+                        provenance: None,
+                        price: None,
+                        denom: None,
+                        ..exchange.clone()
+                    }],
+                }],
+            },
+        );
+        assert_eq!(
+            ContractError::UnknownShareClass {
+                class: String::from("unknown"),
+            },
+            res.unwrap_err()
+        );
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::IssueAssetExchanges {
+                notify_subscriptions: false,
+                verify_capital_denom: false,
+                asset_exchanges: vec![IssueAssetExchange {
+                    subscription: Addr::unchecked("sub_1"),
+                    exchanges: vec![exchange.clone()],
+                }],
+            },
+        )
+        .unwrap();
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &[]),
+            HandleMsg::CompleteAssetExchange {
+                exchanges: vec![exchange],
+                to: None,
+                memo: None,
+            },
+        )
+        .unwrap();
+
+        // verify shares are minted in the class denom
+        assert_eq!(2, res.messages.len());
+        let coin = mint_args(msg_at_index(&res, 0));
+        assert_eq!("side_pocket_coin", coin.denom);
+        assert_eq!(1_000, coin.amount.u128());
+    }
+
+    #[test]
+    fn complete_asset_exchange_within_settlement_hours() {
+        let mut deps = capital_coin_deps(Some(|state| {
+            state.settlement_hours = Some(SettlementHours {
+                weekdays: BTreeSet::from([2]),
+                start_seconds: 9 * 3_600,
+                end_seconds: 17 * 3_600,
+            })
+        }));
+        load_markers(&mut deps.querier);
+        let exchange = AssetExchange {
+            capital: Some(1_000),
+            ..AssetExchange::default()
+        };
+        asset_exchange_storage(&mut deps.storage)
+            .save(Addr::unchecked("sub_1").as_bytes(), &vec![exchange.clone()])
+            .unwrap();
+        let complete = HandleMsg::CompleteAssetExchange {
+            exchanges: vec![exchange],
+            to: None,
+            memo: None,
+        };
+
+        // mock env time is a wednesday at 02:23 utc
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &[]),
+            complete.clone(),
+        );
+        assert_eq!(ContractError::OutsideSettlementHours {}, res.unwrap_err());
+
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(1571821200); // Wed Oct 23 2019 09:00 UTC
+        execute(deps.as_mut(), env, mock_info("sub_1", &[]), complete).unwrap();
+    }
+
+    #[test]
+    fn complete_capital_call_with_late_fee() {
+        let mut deps = capital_coin_deps(Some(|state| state.due_grace_seconds = 3_600));
+        load_markers(&mut deps.querier);
+        let now = mock_env().block.time.seconds();
+        let within_grace = AssetExchange {
+            investment: Some(1_000),
+            commitment_in_shares: Some(-1_000),
+            capital: Some(-1_000),
+            date: Some(ExchangeDate::Due(now - 60)),
+            late_fee_bps: Some(150),
+            ..AssetExchange::default()
+        };
+        let capital_call = AssetExchange {
+            date: Some(ExchangeDate::Due(now - 3_601)),
+            ..within_grace.clone()
+        };
+        asset_exchange_storage(&mut deps.storage)
+            .save(
+                Addr::unchecked("sub_1").as_bytes(),
+                &vec![within_grace.clone()],
+            )
+            .unwrap();
+
+        // no fee accrues within the grace period
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &coins(1_000, "capital_coin")),
+            HandleMsg::CompleteAssetExchange {
+                exchanges: vec![within_grace],
+                to: None,
+                memo: None,
+            },
+        )
+        .unwrap();
+        assert!(!res.attributes.iter().any(|attr| attr.key == "late_fee"));
+
+        asset_exchange_storage(&mut deps.storage)
+            .save(
+                Addr::unchecked("sub_1").as_bytes(),
+                &vec![capital_call.clone()],
+            )
+            .unwrap();
+
+        // verify the late fee is required on top of the called capital past the grace period
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &coins(1_000, "capital_coin")),
+            HandleMsg::CompleteAssetExchange {
+                exchanges: vec![capital_call.clone()],
+                to: None,
+                memo: None,
+            },
+        );
+        assert_eq!(
+            ContractError::LateFeeRequired { late_fee: 15 },
+            res.unwrap_err()
+        );
+
+        asset_exchange_storage(&mut deps.storage)
+            .save(
+                Addr::unchecked("sub_1").as_bytes(),
+                &vec![capital_call.clone()],
+            )
+            .unwrap();
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &coins(1_015, "capital_coin")),
+            HandleMsg::CompleteAssetExchange {
+                exchanges: vec![capital_call],
+                to: None,
+                memo: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            "15",
+            res.attributes
+                .iter()
+                .find(|attr| attr.key == "late_fee")
+                .unwrap()
+                .value
+        );
+    }
+
+    #[test]
+    fn complete_asset_exchange_within_grace_period() {
+        let mut deps = capital_coin_deps(Some(|state| state.due_grace_seconds = 60));
+        load_markers(&mut deps.querier);
+        let now = mock_env().block.time.seconds();
+        let within_grace = AssetExchange {
+            investment: Some(1_000),
+            commitment_in_shares: Some(-1_000),
+            capital: Some(-1_000),
+            date: Some(ExchangeDate::Due(now - 60)),
+            ..AssetExchange::default()
+        };
+        let beyond_grace = AssetExchange {
+            date: Some(ExchangeDate::Due(now - 61)),
+            ..within_grace.clone()
+        };
+        asset_exchange_storage(&mut deps.storage)
+            .save(
+                Addr::unchecked("sub_1").as_bytes(),
+                &vec![within_grace.clone(), beyond_grace.clone()],
+            )
+            .unwrap();
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &coins(1_000, "capital_coin")),
+            HandleMsg::CompleteAssetExchange {
+                exchanges: vec![within_grace],
+                to: None,
+                memo: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(4, res.messages.len());
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &coins(1_000, "capital_coin")),
+            HandleMsg::CompleteAssetExchange {
+                exchanges: vec![beyond_grace],
+                to: None,
+                memo: None,
+            },
+        );
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn expire_capital_calls() {
+        let mut deps = default_deps(None);
+        set_accepted(&mut deps.storage, vec!["sub_1"]);
+        let now = mock_env().block.time.seconds();
+        let overdue_call = AssetExchange {
+            investment: Some(1_000),
+            commitment_in_shares: Some(-1_000),
+            capital: Some(-1_000),
+            date: Some(ExchangeDate::Due(now - 1)),
+            ..AssetExchange::default()
+        };
+        let open_call = AssetExchange {
+            date: Some(ExchangeDate::Window(now - 10, now + 10)),
+            ..overdue_call.clone()
+        };
+        let overdue_distribution = AssetExchange {
+            capital: Some(1_000),
+            date: Some(ExchangeDate::Due(now - 1)),
+            ..AssetExchange::default()
+        };
+        asset_exchange_storage(&mut deps.storage)
+            .save(
+                Addr::unchecked("sub_1").as_bytes(),
+                &vec![
+                    overdue_call,
+                    open_call.clone(),
+                    overdue_distribution.clone(),
+                ],
+            )
+            .unwrap();
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::ExpireCapitalCalls {},
+        )
+        .unwrap();
+
+        // verify only the overdue capital call is removed
+        assert_eq!("1", res.attributes.first().unwrap().value);
+        assert_eq!(
+            vec![open_call, overdue_distribution],
+            asset_exchange_storage_read(&deps.storage)
+                .load(Addr::unchecked("sub_1").as_bytes())
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn expire_capital_calls_bad_actor() {
         let res = execute(
             default_deps(None).as_mut(),
             mock_env(),
-            mock_info("gp", &vec![]),
-            HandleMsg::IssueAssetExchanges {
-                asset_exchanges: vec![IssueAssetExchange {
-                    subscription: Addr::unchecked("sub_1"),
-                    exchanges: vec![AssetExchange {
-                        investment: Some(1_000),
-                        commitment_in_shares: Some(-1_000),
-                        capital: Some(-1_000),
-                        date: None,
-                    }],
-                }],
+            mock_info("bad_actor", &[]),
+            HandleMsg::ExpireCapitalCalls {},
+        );
+
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn complete_asset_exchange_credits_overpayment() {
+        let mut deps = capital_coin_deps(None);
+        load_markers(&mut deps.querier);
+        let exchange = AssetExchange {
+            investment: Some(1_000),
+            commitment_in_shares: Some(-1_000),
+            capital: Some(-1_000),
+            ..AssetExchange::default()
+        };
+        asset_exchange_storage(&mut deps.storage)
+            .save(Addr::unchecked("sub_1").as_bytes(), &vec![exchange.clone()])
+            .unwrap();
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &coins(1_500, "capital_coin")),
+            HandleMsg::CompleteAssetExchange {
+                exchanges: vec![exchange],
+                to: None,
+                memo: None,
+            },
+        )
+        .unwrap();
+
+        // verify excess capital is recorded as a prepayment
+        assert_eq!(
+            500,
+            prepayment_storage(&mut deps.storage)
+                .load(Addr::unchecked("sub_1").as_bytes())
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn complete_asset_exchange_skims_management_fee() {
+        let mut deps = capital_coin_deps(None);
+        load_markers(&mut deps.querier);
+        let management_fee = HandleMsg::UpdateManagementFee {
+            management_fee: Some(ManagementFee {
+                fee_bps: 200,
+                collector: Addr::unchecked("collector"),
+            }),
+        };
+
+        // verify the fee waits on the admin's approval of the gp's change
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            management_fee.clone(),
+        )
+        .unwrap();
+        assert_eq!(
+            None,
+            config_read(&deps.storage).load().unwrap().management_fee
+        );
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("marketpalace", &[]),
+            management_fee,
+        )
+        .unwrap();
+
+        let exchange = |investment: i64, capital: i64| AssetExchange {
+            investment: Some(investment),
+            commitment_in_shares: Some(-investment),
+            capital: Some(capital),
+            ..AssetExchange::default()
+        };
+        asset_exchange_storage(&mut deps.storage)
+            .save(b"sub_1", &vec![exchange(1_000, -1_000)])
+            .unwrap();
+
+        // verify the collector's share of a capital call is sent on
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &coins(1_000, "capital_coin")),
+            HandleMsg::CompleteAssetExchange {
+                exchanges: vec![exchange(1_000, -1_000)],
+                to: None,
+                memo: None,
+            },
+        )
+        .unwrap();
+        let (to_address, coins) = send_args(msg_at_index(&res, 4));
+        assert_eq!("collector", to_address);
+        assert_eq!(&coin(20, "capital_coin"), coins.first().unwrap());
+        assert_eq!(
+            Some(&Attribute::new("management_fee", "20capital_coin")),
+            res.attributes.first()
+        );
+
+        // verify a distribution is paid net of the fee
+        let distribution = AssetExchange {
+            investment: None,
+            commitment_in_shares: None,
+            ..exchange(0, 500)
+        };
+        asset_exchange_storage(&mut deps.storage)
+            .save(b"sub_1", &vec![distribution.clone()])
+            .unwrap();
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &[]),
+            HandleMsg::CompleteAssetExchange {
+                exchanges: vec![distribution],
+                to: None,
+                memo: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(2, res.messages.len());
+        let (to_address, coins) = send_args(msg_at_index(&res, 0));
+        assert_eq!("sub_1", to_address);
+        assert_eq!(&coin(490, "capital_coin"), coins.first().unwrap());
+        let (to_address, coins) = send_args(msg_at_index(&res, 1));
+        assert_eq!("collector", to_address);
+        assert_eq!(&coin(10, "capital_coin"), coins.first().unwrap());
+    }
+
+    #[test]
+    fn complete_asset_exchange_draws_from_escrow() {
+        let mut deps = capital_coin_deps(Some(|state| state.escrow_capital = true));
+        load_markers(&mut deps.querier);
+        let exchange = AssetExchange {
+            investment: Some(1_000),
+            commitment_in_shares: Some(-1_000),
+            capital: Some(-1_000),
+            ..AssetExchange::default()
+        };
+        asset_exchange_storage(&mut deps.storage)
+            .save(Addr::unchecked("sub_1").as_bytes(), &vec![exchange.clone()])
+            .unwrap();
+        save_escrow(&mut deps.storage, &Addr::unchecked("sub_1"), 1_500).unwrap();
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &[]),
+            HandleMsg::CompleteAssetExchange {
+                exchanges: vec![exchange],
+                to: None,
+                memo: None,
             },
+        )
+        .unwrap();
+
+        // verify the call is paid out of escrow
+        assert_eq!(
+            500,
+            escrow_storage_read(&deps.storage)
+                .load(Addr::unchecked("sub_1").as_bytes())
+                .unwrap()
         );
+        assert_eq!(500, escrowed_capital_read(&deps.storage).load().unwrap());
+        assert!(prepayment_storage(&mut deps.storage)
+            .may_load(Addr::unchecked("sub_1").as_bytes())
+            .unwrap()
+            .is_none());
+    }
 
-        assert!(res.is_err());
+    #[test]
+    fn pay_capital_call_in_like_capital_denom() {
+        let mut deps = capital_coin_deps(Some(|state| {
+            state.capital_exchange_rates = vec![CapitalExchangeRate {
+                denom: String::from("other_coin"),
+                numerator: 2,
+                denominator: 1,
+            }];
+        }));
+        load_markers(&mut deps.querier);
+        let exchange = AssetExchange {
+            investment: Some(1_000),
+            commitment_in_shares: Some(-1_000),
+            capital: Some(-1_000),
+            ..AssetExchange::default()
+        };
+        asset_exchange_storage(&mut deps.storage)
+            .save(Addr::unchecked("sub_1").as_bytes(), &vec![exchange.clone()])
+            .unwrap();
+
+        // verify the like denom is credited at its rate
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &coins(200, "other_coin")),
+            HandleMsg::PayCapitalCall { exchange },
+        )
+        .unwrap();
+        assert_eq!(
+            vec![
+                Attribute::new("paid", "400"),
+                Attribute::new("remaining", "600")
+            ],
+            res.attributes
+        );
+
+        // verify the contribution is tallied in the denom actually paid
+        assert_eq!(
+            vec![coin(200, "other_coin")],
+            subscription_stats_storage(&mut deps.storage)
+                .load(b"sub_1")
+                .unwrap()
+                .capital_contributed_by_denom
+        );
     }
 
     #[test]
-    fn cancel_asset_exchange() {
-        let mut deps = default_deps(None);
-        {
+    fn capital_call_checks_attribute_of_denom_paid() {
+        let exchange = AssetExchange {
+            investment: Some(1_000),
+            commitment_in_shares: Some(-1_000),
+            capital: Some(-1_000),
+            ..AssetExchange::default()
+        };
+        let setup = || {
+            let mut deps = capital_coin_deps(Some(|state| {
+                state.capital_exchange_rates = vec![CapitalExchangeRate {
+                    denom: String::from("other_coin"),
+                    numerator: 2,
+                    denominator: 1,
+                }];
+                state.capital_attributes = vec![CapitalAttribute {
+                    denom: String::from("other_coin"),
+                    attribute: String::from("other.test"),
+                }];
+            }));
+            load_markers(&mut deps.querier);
             asset_exchange_storage(&mut deps.storage)
-                .save(
-                    Addr::unchecked("sub_1").as_bytes(),
-                    &vec![AssetExchange {
-                        investment: Some(1_000),
-                        commitment_in_shares: Some(-1_000),
-                        capital: Some(-1_000),
-                        date: None,
-                    }],
-                )
+                .save(Addr::unchecked("sub_1").as_bytes(), &vec![exchange.clone()])
                 .unwrap();
-        }
+            deps
+        };
+        let missing = ContractError::MissingCapitalAttribute {
+            address: Addr::unchecked("sub_1"),
+            attribute: String::from("other.test"),
+        };
+
+        // verify the sub needs the attribute of the like denom, not the capital denom's
+        assert_eq!(
+            missing,
+            execute(
+                setup().as_mut(),
+                mock_env(),
+                mock_info("sub_1", &coins(200, "other_coin")),
+                HandleMsg::PayCapitalCall {
+                    exchange: exchange.clone()
+                },
+            )
+            .unwrap_err()
+        );
+        let mut deps = setup();
+        assert_eq!(
+            missing,
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(
+                    "sub_1",
+                    &[coin(500, "other_coin"), coin(1_000, "commitment_coin")]
+                ),
+                HandleMsg::CompleteAssetExchange {
+                    exchanges: vec![exchange.clone()],
+                    to: None,
+                    memo: None,
+                },
+            )
+            .unwrap_err()
+        );
 
+        deps.querier
+            .with_attributes("sub_1", &[("other.test", "", "")]);
         execute(
             deps.as_mut(),
             mock_env(),
-            mock_info("gp", &vec![]),
-            HandleMsg::CancelAssetExchanges {
-                cancellations: vec![IssueAssetExchange {
-                    subscription: Addr::unchecked("sub_1"),
-                    exchanges: vec![AssetExchange {
-                        investment: Some(1_000),
-                        commitment_in_shares: Some(-1_000),
-                        capital: Some(-1_000),
-                        date: None,
-                    }],
-                }],
+            mock_info(
+                "sub_1",
+                &[coin(500, "other_coin"), coin(1_000, "commitment_coin")],
+            ),
+            HandleMsg::CompleteAssetExchange {
+                exchanges: vec![exchange],
+                to: None,
+                memo: None,
+            },
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn capital_contributed_tallied_by_denom() {
+        let mut deps = capital_coin_deps(Some(|state| {
+            state.capital_exchange_rates = vec![CapitalExchangeRate {
+                denom: String::from("other_coin"),
+                numerator: 2,
+                denominator: 1,
+            }];
+        }));
+        load_markers(&mut deps.querier);
+        let exchange = |amount: i64| AssetExchange {
+            investment: Some(amount),
+            commitment_in_shares: Some(-amount),
+            capital: Some(-amount),
+            ..AssetExchange::default()
+        };
+        asset_exchange_storage(&mut deps.storage)
+            .save(Addr::unchecked("sub_1").as_bytes(), &vec![exchange(1_000)])
+            .unwrap();
+        let tally = |deps: &mut OwnedDeps<_, _, _, _>| {
+            subscription_stats_storage(&mut deps.storage)
+                .load(b"sub_1")
+                .unwrap()
+                .capital_contributed_by_denom
+        };
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &coins(200, "other_coin")),
+            HandleMsg::PayCapitalCall {
+                exchange: exchange(1_000),
             },
         )
         .unwrap();
 
-        // verify exchange is removed
+        // verify a payment in an unknown denom is rejected and isn't tallied
         assert_eq!(
-            0,
+            ContractError::UnsupportedCapitalDenom {
+                subscription: Addr::unchecked("sub_1"),
+                denom: String::from("unknown_coin"),
+            },
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("sub_1", &coins(600, "unknown_coin")),
+                HandleMsg::PayCapitalCall {
+                    exchange: exchange(600),
+                },
+            )
+            .unwrap_err()
+        );
+        assert_eq!(vec![coin(200, "other_coin")], tally(&mut deps));
+
+        // verify each denom keeps its own running total
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &coins(600, "capital_coin")),
+            HandleMsg::PayCapitalCall {
+                exchange: exchange(600),
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            vec![coin(600, "capital_coin"), coin(200, "other_coin")],
+            tally(&mut deps)
+        );
+        assert_eq!(
+            1_000,
+            subscription_stats_storage(&mut deps.storage)
+                .load(b"sub_1")
+                .unwrap()
+                .capital_contributed
+        );
+    }
+
+    #[test]
+    fn pay_capital_call_in_tranches() {
+        let mut deps = capital_coin_deps(None);
+        load_markers(&mut deps.querier);
+        let exchange = AssetExchange {
+            investment: Some(1_000),
+            commitment_in_shares: Some(-1_000),
+            capital: Some(-1_000),
+            ..AssetExchange::default()
+        };
+        asset_exchange_storage(&mut deps.storage)
+            .save(Addr::unchecked("sub_1").as_bytes(), &vec![exchange.clone()])
+            .unwrap();
+
+        // verify payments must be evenly divisible by capital per share
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &coins(450, "capital_coin")),
+            HandleMsg::PayCapitalCall {
+                exchange: exchange.clone(),
+            },
+        );
+        assert!(res.is_err());
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &coins(400, "capital_coin")),
+            HandleMsg::PayCapitalCall { exchange },
+        )
+        .unwrap();
+
+        // verify mint/withdraw investment and deposit/burn commitment for the tranche
+        assert_eq!(4, res.messages.len());
+        assert_eq!(400, mint_args(msg_at_index(&res, 0)).amount.u128());
+        assert_eq!(400, burn_args(msg_at_index(&res, 3)).amount.u128());
+
+        // verify the outstanding call is reduced
+        let remaining = AssetExchange {
+            investment: Some(600),
+            commitment_in_shares: Some(-600),
+            capital: Some(-600),
+            ..AssetExchange::default()
+        };
+        assert_eq!(
+            vec![remaining.clone()],
             asset_exchange_storage_read(&deps.storage)
                 .load(Addr::unchecked("sub_1").as_bytes())
                 .unwrap()
-                .len()
+        );
+
+        // verify overpaying the remainder fails
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &coins(700, "capital_coin")),
+            HandleMsg::PayCapitalCall {
+                exchange: remaining.clone(),
+            },
+        );
+        assert!(res.is_err());
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &coins(600, "capital_coin")),
+            HandleMsg::PayCapitalCall {
+                exchange: remaining,
+            },
         )
+        .unwrap();
+
+        // verify the call is removed once fully paid
+        assert!(asset_exchange_storage_read(&deps.storage)
+            .load(Addr::unchecked("sub_1").as_bytes())
+            .unwrap()
+            .is_empty());
     }
 
     #[test]
-    fn cancel_asset_exchange_bad_actor() {
+    fn pay_capital_call_missing_required_attestation() {
+        let mut deps = mock_sub_state();
+        deps.querier.base.with_attributes("lp", &[("506c", "", "")]);
+        load_markers(&mut deps.querier.base);
+        config(&mut deps.storage)
+            .save(&State::test_capital_coin())
+            .unwrap();
+        let exchange = AssetExchange {
+            investment: Some(1_000),
+            commitment_in_shares: Some(-1_000),
+            capital: Some(-1_000),
+            required_attestation: Some(String::from("kyc")),
+            ..AssetExchange::default()
+        };
+        asset_exchange_storage(&mut deps.storage)
+            .save(Addr::unchecked("sub_1").as_bytes(), &vec![exchange.clone()])
+            .unwrap();
+
         let res = execute(
-            default_deps(None).as_mut(),
+            deps.as_mut(),
             mock_env(),
-            mock_info("bad_actor", &vec![]),
-            HandleMsg::CancelAssetExchanges {
-                cancellations: vec![],
+            mock_info("sub_1", &coins(400, "capital_coin")),
+            HandleMsg::PayCapitalCall { exchange },
+        );
+        assert_eq!(
+            ContractError::MissingAttestation {
+                address: Addr::unchecked("lp"),
+                attestation: String::from("kyc"),
+            },
+            res.unwrap_err()
+        );
+    }
+
+    #[test]
+    fn pay_capital_call_with_late_fee() {
+        let mut deps = capital_coin_deps(Some(|state| state.due_grace_seconds = 60));
+        load_markers(&mut deps.querier);
+        let now = mock_env().block.time.seconds();
+        let exchange = AssetExchange {
+            investment: Some(1_000),
+            commitment_in_shares: Some(-1_000),
+            capital: Some(-1_000),
+            date: Some(ExchangeDate::Due(now - 61)),
+            late_fee_bps: Some(150),
+            ..AssetExchange::default()
+        };
+        asset_exchange_storage(&mut deps.storage)
+            .save(Addr::unchecked("sub_1").as_bytes(), &vec![exchange.clone()])
+            .unwrap();
+
+        // verify the fee on the whole outstanding call comes off the first tranche
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &coins(10, "capital_coin")),
+            HandleMsg::PayCapitalCall {
+                exchange: exchange.clone(),
+            },
+        );
+        assert_eq!(
+            ContractError::LateFeeRequired { late_fee: 15 },
+            res.unwrap_err()
+        );
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &coins(415, "capital_coin")),
+            HandleMsg::PayCapitalCall {
+                exchange: exchange.clone(),
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            vec![
+                Attribute::new("paid", "400"),
+                Attribute::new("remaining", "600"),
+                Attribute::new("late_fee", "15"),
+            ],
+            res.attributes
+        );
+
+        // verify the remainder can still be paid late without the fee already collected
+        let remaining = asset_exchange_storage_read(&deps.storage)
+            .load(Addr::unchecked("sub_1").as_bytes())
+            .unwrap();
+        assert_eq!(Some(-600), remaining[0].capital);
+        assert_eq!(Some(150), remaining[0].late_fee_bps);
+        assert!(remaining[0].late_fee_paid);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &coins(600, "capital_coin")),
+            HandleMsg::PayCapitalCall {
+                exchange: AssetExchange {
+                    investment: Some(600),
+                    commitment_in_shares: Some(-600),
+                    capital: Some(-600),
+                    ..exchange
+                },
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            vec![
+                Attribute::new("paid", "600"),
+                Attribute::new("remaining", "0"),
+            ],
+            res.attributes
+        );
+        assert!(asset_exchange_storage_read(&deps.storage)
+            .load(Addr::unchecked("sub_1").as_bytes())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn pay_capital_call_in_unsupported_denom() {
+        let mut deps = capital_coin_deps(None);
+        load_markers(&mut deps.querier);
+        let exchange = AssetExchange {
+            investment: Some(1_000),
+            commitment_in_shares: Some(-1_000),
+            capital: Some(-1_000),
+            ..AssetExchange::default()
+        };
+        asset_exchange_storage(&mut deps.storage)
+            .save(Addr::unchecked("sub_1").as_bytes(), &vec![exchange.clone()])
+            .unwrap();
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(
+                "sub_1",
+                &[coin(400, "capital_coin"), coin(100, "other_coin")],
+            ),
+            HandleMsg::PayCapitalCall { exchange },
+        );
+        assert_eq!(
+            ContractError::UnsupportedCapitalDenom {
+                subscription: Addr::unchecked("sub_1"),
+                denom: String::from("other_coin"),
             },
+            res.unwrap_err()
+        );
+    }
+
+    #[test]
+    fn pay_capital_call_from_escrow() {
+        let mut deps = capital_coin_deps(Some(|state| state.escrow_capital = true));
+        load_markers(&mut deps.querier);
+        let exchange = AssetExchange {
+            investment: Some(1_000),
+            commitment_in_shares: Some(-1_000),
+            capital: Some(-1_000),
+            ..AssetExchange::default()
+        };
+        asset_exchange_storage(&mut deps.storage)
+            .save(Addr::unchecked("sub_1").as_bytes(), &vec![exchange.clone()])
+            .unwrap();
+        save_escrow(&mut deps.storage, &Addr::unchecked("sub_1"), 300).unwrap();
+
+        // verify escrow covers part of the tranche before attached funds
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &coins(100, "capital_coin")),
+            HandleMsg::PayCapitalCall { exchange },
+        )
+        .unwrap();
+        assert_eq!(Attribute::new("paid", "400"), res.attributes[0]);
+        assert_eq!(
+            0,
+            escrow_storage_read(&deps.storage)
+                .load(Addr::unchecked("sub_1").as_bytes())
+                .unwrap()
+        );
+
+        // verify released escrow is no longer held back from withdrawals
+        assert_eq!(0, escrowed_capital_read(&deps.storage).load().unwrap());
+    }
+
+    #[test]
+    fn pay_capital_call_from_escrow_and_attached_funds() {
+        let mut deps = capital_coin_deps(Some(|state| state.escrow_capital = true));
+        load_markers(&mut deps.querier);
+        let exchange = AssetExchange {
+            investment: Some(1_000),
+            commitment_in_shares: Some(-1_000),
+            capital: Some(-1_000),
+            ..AssetExchange::default()
+        };
+        asset_exchange_storage(&mut deps.storage)
+            .save(Addr::unchecked("sub_1").as_bytes(), &vec![exchange.clone()])
+            .unwrap();
+        save_escrow(&mut deps.storage, &Addr::unchecked("sub_1"), 800).unwrap();
+
+        // verify only the balance left after attached funds is taken from escrow
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &coins(400, "capital_coin")),
+            HandleMsg::PayCapitalCall { exchange },
+        )
+        .unwrap();
+        assert_eq!(Attribute::new("paid", "1000"), res.attributes[0]);
+        assert_eq!(
+            200,
+            escrow_storage_read(&deps.storage)
+                .load(Addr::unchecked("sub_1").as_bytes())
+                .unwrap()
         );
+        assert_eq!(200, escrowed_capital_read(&deps.storage).load().unwrap());
+    }
+
+    #[test]
+    fn issue_asset_exchange_applies_prepayment() {
+        let mut deps = default_deps(None);
+        set_accepted(&mut deps.storage, vec!["sub_1"]);
+        prepayment_storage(&mut deps.storage)
+            .save(Addr::unchecked("sub_1").as_bytes(), &1_500)
+            .unwrap();
+
+        let call = AssetExchange {
+            investment: Some(1_000),
+            commitment_in_shares: Some(-1_000),
+            capital: Some(-1_000),
+            ..AssetExchange::default()
+        };
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::IssueAssetExchanges {
+                notify_subscriptions: false,
+                verify_capital_denom: false,
+                asset_exchanges: vec![IssueAssetExchange {
+                    subscription: Addr::unchecked("sub_1"),
+                    exchanges: vec![call.clone(), call],
+                }],
+            },
+        )
+        .unwrap();
 
-        assert!(res.is_err());
+        // verify prepayment covers the first call fully and the second partially
+        let issued = asset_exchange_storage_read(&deps.storage)
+            .load(Addr::unchecked("sub_1").as_bytes())
+            .unwrap();
+        assert_eq!(Some(0), issued[0].capital);
+        assert_eq!(Some(-500), issued[1].capital);
+        assert_eq!(
+            0,
+            prepayment_storage(&mut deps.storage)
+                .load(Addr::unchecked("sub_1").as_bytes())
+                .unwrap()
+        );
     }
 
     #[test]
-    fn cancel_asset_exchange_not_found() {
-        let res = execute(
-            default_deps(None).as_mut(),
+    fn issue_asset_exchange_rounds_partial_prepayment() {
+        let mut deps = default_deps(None);
+        set_accepted(&mut deps.storage, vec!["sub_1"]);
+        prepayment_storage(&mut deps.storage)
+            .save(Addr::unchecked("sub_1").as_bytes(), &550)
+            .unwrap();
+
+        execute(
+            deps.as_mut(),
             mock_env(),
-            mock_info("gp", &coins(10_000, "stable_coin")),
-            HandleMsg::CancelAssetExchanges {
-                cancellations: vec![IssueAssetExchange {
+            mock_info("gp", &[]),
+            HandleMsg::IssueAssetExchanges {
+                notify_subscriptions: false,
+                verify_capital_denom: false,
+                asset_exchanges: vec![IssueAssetExchange {
                     subscription: Addr::unchecked("sub_1"),
                     exchanges: vec![AssetExchange {
                         investment: Some(1_000),
                         commitment_in_shares: Some(-1_000),
                         capital: Some(-1_000),
-                        date: None,
+                        ..AssetExchange::default()
                     }],
                 }],
             },
-        );
+        )
+        .unwrap();
 
-        assert!(res.is_err());
+        // verify the credit stops at a whole share and the rest stays prepaid
+        let issued = asset_exchange_storage_read(&deps.storage)
+            .load(Addr::unchecked("sub_1").as_bytes())
+            .unwrap();
+        assert_eq!(Some(-500), issued[0].capital);
+        assert_eq!(
+            50,
+            prepayment_storage(&mut deps.storage)
+                .load(Addr::unchecked("sub_1").as_bytes())
+                .unwrap()
+        );
     }
 
     #[test]
-    fn complete_asset_exchange() {
+    fn complete_asset_exchange_for_authorized_subscription() {
         let mut deps = capital_coin_deps(None);
         load_markers(&mut deps.querier);
-        {
-            asset_exchange_storage(&mut deps.storage)
-                .save(
-                    Addr::unchecked("sub_1").as_bytes(),
-                    &vec![
-                        AssetExchange {
-                            investment: Some(-1_000),
-                            commitment_in_shares: None,
-                            capital: Some(1_000),
-                            date: None,
-                        },
-                        AssetExchange {
-                            investment: Some(-1_000),
-                            commitment_in_shares: None,
-                            capital: Some(1_000),
-                            date: None,
-                        },
-                    ],
-                )
-                .unwrap();
-        }
+        set_accepted(&mut deps.storage, vec!["sub_1"]);
+        subscription_lp_storage(&mut deps.storage)
+            .save(b"sub_1", &Addr::unchecked("lp"))
+            .unwrap();
+        let exchange = AssetExchange {
+            investment: Some(-1_000),
+            capital: Some(1_000),
+            ..AssetExchange::default()
+        };
+        asset_exchange_storage(&mut deps.storage)
+            .save(b"sub_1", &vec![exchange.clone()])
+            .unwrap();
 
-        let res = execute(
+        execute(
             deps.as_mut(),
             mock_env(),
             mock_info("sub_1", &coins(1_000, "investment_coin")),
-            HandleMsg::CompleteAssetExchange {
-                exchanges: vec![
-                    AssetExchange {
-                        investment: Some(-1_000),
-                        commitment_in_shares: None,
-                        capital: Some(1_000),
-                        date: None,
-                    },
-                    AssetExchange {
-                        investment: Some(-1_000),
-                        commitment_in_shares: None,
-                        capital: Some(1_000),
-                        date: None,
-                    },
-                ],
-                to: Some(Addr::unchecked("destination")),
-                memo: Some(String::from("note")),
+            HandleMsg::AuthorizeGpSettlement {
+                exchanges_hash: exchanges_hash(std::slice::from_ref(&exchange)).unwrap(),
+                expires: mock_env().block.time.seconds() + 60,
             },
         )
         .unwrap();
 
-        assert_eq!(3, res.messages.len());
-
-        // verify memo
-        assert_eq!(1, res.attributes.len());
-        let attribute = res.attributes.get(0).unwrap();
-        assert_eq!("memo", attribute.key);
-        assert_eq!("note", attribute.value);
-
-        // verify deposit investment
-        let (to_address, coins) = send_args(msg_at_index(&res, 0));
-        let coin = coins.first().unwrap();
-        assert_eq!("tp18vd8fpwxzck93qlwghaj6arh4p7c5n89x8kskz", to_address);
-        assert_eq!("investment_coin", coin.denom);
-        assert_eq!(2_000, coin.amount.u128());
+        // only the gp may settle, and only what was authorized
+        let complete_for =
+            |exchanges: Vec<AssetExchange>, to: Option<&str>| HandleMsg::CompleteAssetExchangeFor {
+                subscription: Addr::unchecked("sub_1"),
+                exchanges,
+                to: to.map(Addr::unchecked),
+            };
+        assert_eq!(
+            ContractError::Unauthorized {
+                required: "gp",
+                action: "complete exchanges for a subscription",
+            },
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("lp", &[]),
+                complete_for(vec![exchange.clone()], None),
+            )
+            .unwrap_err()
+        );
+        assert_eq!(
+            ContractError::SettlementAuthorizationMismatch {},
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("gp", &[]),
+                complete_for(vec![], None),
+            )
+            .unwrap_err()
+        );
+        assert_eq!(
+            ContractError::DestinationNotApproved {
+                destination: Addr::unchecked("gp"),
+            },
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("gp", &[]),
+                complete_for(vec![exchange.clone()], Some("gp")),
+            )
+            .unwrap_err()
+        );
 
-        // verify burn investment
-        let coin = burn_args(msg_at_index(&res, 1));
-        assert_eq!("investment_coin", coin.denom);
-        assert_eq!(2_000, coin.amount.u128());
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            complete_for(vec![exchange.clone()], Some("lp")),
+        )
+        .unwrap();
 
-        // verify send message
-        let (to_address, coins) = send_args(msg_at_index(&res, 2));
-        let coin = coins.first().unwrap();
-        assert_eq!("destination", to_address);
-        assert_eq!("capital_coin", coin.denom);
-        assert_eq!(2_000, coin.amount.u128());
+        // verify the authorized funds settle as if the sub attached them
+        let (_, amount) = send_args(msg_at_index(&res, 0));
+        assert_eq!(&coins(1_000, "investment_coin"), amount);
+        let (to_address, amount) = send_args(msg_at_index(&res, 2));
+        assert_eq!("lp", to_address);
+        assert_eq!(&coins(1_000, "capital_coin"), amount);
+        assert!(asset_exchange_storage_read(&deps.storage)
+            .load(b"sub_1")
+            .unwrap()
+            .is_empty());
+        assert!(authorized_funds_read(&deps.storage)
+            .load()
+            .unwrap()
+            .is_empty());
 
-        // verify exchange is removed
+        // verify the authorization is used up
         assert_eq!(
-            0,
-            asset_exchange_storage_read(&deps.storage)
-                .load(Addr::unchecked("sub_1").as_bytes())
-                .unwrap()
-                .len()
-        )
+            ContractError::SettlementNotAuthorized {
+                subscription: Addr::unchecked("sub_1"),
+            },
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("gp", &[]),
+                complete_for(vec![exchange], None),
+            )
+            .unwrap_err()
+        );
     }
 
     #[test]
-    fn complete_asset_exchange_with_restricted_marker() {
-        let mut deps = restricted_capital_coin_deps(None);
-        deps.querier
-            .with_attributes("destination", &[("capital.test", "", "")]);
-        load_markers(&mut deps.querier);
-        {
-            asset_exchange_storage(&mut deps.storage)
-                .save(
-                    Addr::unchecked("sub_1").as_bytes(),
-                    &vec![
-                        AssetExchange {
-                            investment: Some(-1_000),
-                            commitment_in_shares: None,
-                            capital: Some(1_000),
-                            date: None,
-                        },
-                        AssetExchange {
-                            investment: Some(-1_000),
-                            commitment_in_shares: None,
-                            capital: Some(1_000),
-                            date: None,
-                        },
-                    ],
-                )
-                .unwrap();
-        }
-
-        let res = execute(
+    fn expired_gp_settlement_authorization() {
+        let mut deps = capital_coin_deps(None);
+        set_accepted(&mut deps.storage, vec!["sub_1"]);
+        let expires = mock_env().block.time.seconds() + 60;
+        execute(
             deps.as_mut(),
             mock_env(),
             mock_info("sub_1", &coins(1_000, "investment_coin")),
-            HandleMsg::CompleteAssetExchange {
-                exchanges: vec![
-                    AssetExchange {
-                        investment: Some(-1_000),
-                        commitment_in_shares: None,
-                        capital: Some(1_000),
-                        date: None,
-                    },
-                    AssetExchange {
-                        investment: Some(-1_000),
-                        commitment_in_shares: None,
-                        capital: Some(1_000),
-                        date: None,
-                    },
-                ],
-                to: Some(Addr::unchecked("destination")),
-                memo: Some(String::from("note")),
+            HandleMsg::AuthorizeGpSettlement {
+                exchanges_hash: exchanges_hash(&[]).unwrap(),
+                expires,
             },
         )
         .unwrap();
 
-        assert_eq!(3, res.messages.len());
-
-        // verify memo
-        assert_eq!(1, res.attributes.len());
-        let attribute = res.attributes.get(0).unwrap();
-        assert_eq!("memo", attribute.key);
-        assert_eq!("note", attribute.value);
-
-        // verify deposit investment
-        let (to_address, coins) = send_args(msg_at_index(&res, 0));
-        let coin = coins.first().unwrap();
-        assert_eq!("tp18vd8fpwxzck93qlwghaj6arh4p7c5n89x8kskz", to_address);
-        assert_eq!("investment_coin", coin.denom);
-        assert_eq!(2_000, coin.amount.u128());
-
-        // verify burn investment
-        let coin = burn_args(msg_at_index(&res, 1));
-        assert_eq!("investment_coin", coin.denom);
-        assert_eq!(2_000, coin.amount.u128());
-
-        // verify transfer message
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(expires);
         assert_eq!(
-            &MarkerMsgParams::TransferMarkerCoins {
-                coin: cosmwasm_std::coin(2_000, "restricted_capital_coin"),
-                to: Addr::unchecked("destination"),
-                from: Addr::unchecked(MOCK_CONTRACT_ADDR),
-            },
-            marker_transfer_msg(msg_at_index(&res, 2)),
+            ContractError::SettlementAuthorizationExpired { expires },
+            execute(
+                deps.as_mut(),
+                env,
+                mock_info("gp", &[]),
+                HandleMsg::CompleteAssetExchangeFor {
+                    subscription: Addr::unchecked("sub_1"),
+                    exchanges: vec![],
+                    to: None,
+                },
+            )
+            .unwrap_err()
         );
 
-        // verify exchange is removed
-        assert_eq!(
-            0,
-            asset_exchange_storage_read(&deps.storage)
-                .load(Addr::unchecked("sub_1").as_bytes())
-                .unwrap()
-                .len()
+        // revoking returns the held funds
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &[]),
+            HandleMsg::RevokeGpSettlement {},
         )
+        .unwrap();
+        let (to_address, amount) = send_args(msg_at_index(&res, 0));
+        assert_eq!("sub_1", to_address);
+        assert_eq!(&coins(1_000, "investment_coin"), amount);
     }
 
     #[test]
-    fn complete_asset_exchange_without_asset() {
-        let mut deps = default_deps(None);
-        {
-            asset_exchange_storage(&mut deps.storage)
-                .save(
-                    Addr::unchecked("sub_1").as_bytes(),
-                    &vec![AssetExchange {
-                        investment: Some(-1_000),
-                        commitment_in_shares: None,
-                        capital: Some(1_000),
-                        date: None,
-                    }],
-                )
-                .unwrap();
-        }
-
-        let res = execute(
+    fn revoke_gp_settlement_with_restricted_capital() {
+        let mut deps = restricted_capital_coin_deps(None);
+        deps.querier
+            .with_attributes("sub_1", &[("capital.test", "", "")]);
+        set_accepted(&mut deps.storage, vec!["sub_1"]);
+        execute(
             deps.as_mut(),
             mock_env(),
-            mock_info("sub_1", &vec![]),
-            HandleMsg::CompleteAssetExchange {
-                exchanges: vec![AssetExchange {
-                    investment: Some(-1_000),
-                    commitment_in_shares: None,
-                    capital: Some(1_000),
-                    date: None,
-                }],
-                to: Some(Addr::unchecked("destination")),
-                memo: Some(String::from("note")),
+            mock_info(
+                "sub_1",
+                &[
+                    coin(1_000, "restricted_capital_coin"),
+                    coin(100, "commitment_coin"),
+                ],
+            ),
+            HandleMsg::AuthorizeGpSettlement {
+                exchanges_hash: exchanges_hash(&[]).unwrap(),
+                expires: mock_env().block.time.seconds() + 60,
             },
-        );
-
-        assert!(res.is_err());
-    }
-
-    #[test]
-    fn claim_asset_exchange_not_available_yet() {
-        let mut deps = default_deps(None);
-        load_markers(&mut deps.querier);
-        {
-            asset_exchange_storage(&mut deps.storage)
-                .save(
-                    Addr::unchecked("sub_1").as_bytes(),
-                    &vec![AssetExchange {
-                        investment: Some(-1_000),
-                        commitment_in_shares: None,
-                        capital: Some(1_000),
-                        date: Some(ExchangeDate::Available(1675209600)), // Feb 01 2023 UTC
-                    }],
-                )
-                .unwrap();
-        }
-        let mut env = mock_env();
-        env.block.time = Timestamp::from_seconds(1672531200); // Jan 01 2023 UTC
+        )
+        .unwrap();
 
+        // restricted capital is returned through its marker, everything else by bank send
         let res = execute(
             deps.as_mut(),
             mock_env(),
-            mock_info("sub_1", &coins(1_000, "investment_coin")),
-            HandleMsg::CompleteAssetExchange {
-                exchanges: vec![AssetExchange {
-                    investment: Some(-1_000),
-                    commitment_in_shares: None,
-                    capital: Some(1_000),
-                    date: None,
-                }],
-                to: Some(Addr::unchecked("destination")),
-                memo: Some(String::from("note")),
+            mock_info("sub_1", &[]),
+            HandleMsg::RevokeGpSettlement {},
+        )
+        .unwrap();
+        assert_eq!(2, res.messages.len());
+        assert_eq!(
+            &MarkerMsgParams::TransferMarkerCoins {
+                coin: coin(1_000, "restricted_capital_coin"),
+                to: Addr::unchecked("sub_1"),
+                from: Addr::unchecked(MOCK_CONTRACT_ADDR),
             },
+            marker_transfer_msg(msg_at_index(&res, 0)),
         );
-
-        assert!(res.is_err());
+        let (to_address, amount) = send_args(msg_at_index(&res, 1));
+        assert_eq!("sub_1", to_address);
+        assert_eq!(&coins(100, "commitment_coin"), amount);
     }
 }
@@ -1,20 +1,37 @@
 use crate::contract::ContractResponse;
-use crate::msg::InstantiateMsg;
+use crate::error::ContractError;
+use crate::msg::{
+    AcceptSubscription, AssetExchange, ChangedEntity, ExchangeProvenance, InstantiateMsg,
+    MarkerSetup, MarkerSetupStep, MARKER_SETUP_STEPS,
+};
+use crate::reply::MARKER_SETUP_REPLY_ID_OFFSET;
 use crate::state::config;
+use crate::state::instantiate_terms;
+use crate::state::marker_setup;
+use crate::state::record_change;
+use crate::state::record_subscription_code_id;
+use crate::state::InstantiateTerms;
+use crate::state::ShareClass;
 use crate::state::State;
+use crate::state::{save_asset_exchanges, set_subscription_status, SubscriptionStatus};
 use crate::version::CONTRACT_NAME;
 use crate::version::CONTRACT_VERSION;
-use cosmwasm_std::{entry_point, CosmosMsg, DepsMut, Env, MessageInfo, Response, StdResult};
+use cosmwasm_std::{
+    to_binary, Addr, DepsMut, Env, MessageInfo, Response, StdResult, Storage, SubMsg,
+};
 use cw2::set_contract_version;
 use provwasm_std::ProvenanceQuery;
 use provwasm_std::{
     activate_marker, create_marker, finalize_marker, grant_marker_access, MarkerAccess, MarkerType,
     ProvenanceMsg,
 };
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+use std::convert::TryInto;
 
 // Note, you can use StdResult in some functions where you do not
 // make use of the custom errors
-#[entry_point]
+#[cfg_attr(not(feature = "library"), cosmwasm_std::entry_point)]
 pub fn instantiate(
     deps: DepsMut<ProvenanceQuery>,
     env: Env,
@@ -23,6 +40,13 @@ pub fn instantiate(
 ) -> ContractResponse {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
+    // keep the original terms so later config changes can be audited against them
+    let terms = to_binary(&msg)?;
+    instantiate_terms(deps.storage).save(&InstantiateTerms {
+        hash: format!("{:x}", Sha256::digest(terms.as_slice())),
+        msg: terms,
+    })?;
+
     let state = State {
         subscription_code_id: msg.subscription_code_id,
         recovery_admin: msg.recovery_admin,
@@ -32,46 +56,211 @@ pub fn instantiate(
         investment_denom: format!("{}.investment", env.contract.address),
         capital_denom: msg.capital_denom,
         capital_per_share: msg.capital_per_share,
-        required_capital_attribute: msg.required_capital_attribute,
+        required_capital_attribute: msg.required_capital_attribute.clone(),
+        due_grace_seconds: msg.due_grace_seconds.unwrap_or_default(),
+        escrow_capital: msg.escrow_capital,
+        permissionless_eligibility: msg.permissionless_eligibility,
+        paused: false,
+        delegates: BTreeSet::new(),
+        // marker addresses are cached once the markers exist
+        commitment_marker: None,
+        investment_marker: None,
+        max_receipts: None,
+        receipt_retention_seconds: None,
+        change_log_retention: None,
+        settlement_hours: None,
+        gp_committee: vec![],
+        approval_threshold: 0,
+        approval_capital_threshold: None,
+        compliance_contract: None,
+        treasury: None,
+        pending_treasury: None,
+        max_outstanding_exchanges: None,
+        max_outstanding_capital: None,
+        capital_exchange_rates: vec![],
+        capital_attributes: vec![],
+        metadata: msg.metadata.clone(),
+        min_initial_commitment: msg.min_initial_commitment,
+        share_classes: msg
+            .share_classes
+            .iter()
+            .map(|class| ShareClass {
+                name: class.clone(),
+                investment_denom: format!("{}.investment.{}", env.contract.address, class),
+            })
+            .collect(),
+        nav: None,
+        max_ownership_bps: None,
+        approved_destinations: None,
+        auto_accept: None,
+        performance_fee: None,
+        term_end: None,
+        batch_cancel_cosign_threshold: None,
+        management_fee: None,
+        memo_transfer_events: false,
+        denom_decimals: msg.denom_decimals.clone(),
+        tombstone_code_id: None,
+        accepting_proposals: true,
+        proposal_cooldown_blocks: None,
+        max_pending_per_lp: None,
     };
+    state.validate()?;
 
     config(deps.storage).save(&state)?;
+    record_change(deps.storage, ChangedEntity::State, "config")?;
+    record_subscription_code_id(
+        deps.storage,
+        state.subscription_code_id,
+        Some(env.block.height),
+    )?;
 
-    let create_and_activate_marker = |denom: String| -> StdResult<Vec<CosmosMsg<ProvenanceMsg>>> {
-        Ok(vec![
-            create_marker(0, denom.clone(), MarkerType::Coin)?,
-            grant_marker_access(
-                denom.clone(),
-                env.contract.address.clone(),
-                vec![
-                    MarkerAccess::Admin,
-                    MarkerAccess::Mint,
-                    MarkerAccess::Burn,
-                    MarkerAccess::Withdraw,
-                ],
-            )?,
-            finalize_marker(denom.clone())?,
-            activate_marker(denom)?,
-        ])
-    };
+    import_accepted_subscriptions(
+        deps.storage,
+        &env,
+        &state,
+        msg.initial_accepted_subscriptions,
+    )?;
+
+    let mut setups = vec![
+        MarkerSetup::new(state.commitment_denom.clone()),
+        MarkerSetup::new(state.investment_denom.clone()),
+    ];
+    for share_class in &state.share_classes {
+        setups.push(MarkerSetup::new(share_class.investment_denom.clone()));
+    }
+    marker_setup(deps.storage).save(&setups)?;
+    for setup in &setups {
+        record_change(deps.storage, ChangedEntity::MarkerSetup, &setup.denom)?;
+    }
+
+    Ok(Response::default().add_submessages(marker_setup_messages(&setups, &env.contract.address)?))
+}
+
+// every step a marker hasn't completed, each replied to so a failure is recorded
+// for a retry instead of leaving the raise with a broken denom
+pub fn marker_setup_messages(
+    setups: &[MarkerSetup],
+    contract: &Addr,
+) -> StdResult<Vec<SubMsg<ProvenanceMsg>>> {
+    let mut messages = vec![];
+    for (index, setup) in setups.iter().enumerate() {
+        for (position, step) in MARKER_SETUP_STEPS.iter().enumerate() {
+            if setup.completed.contains(step) {
+                continue;
+            }
+            let msg = match step {
+                MarkerSetupStep::Create => create_marker(0, setup.denom.clone(), MarkerType::Coin)?,
+                MarkerSetupStep::Grant => grant_marker_access(
+                    setup.denom.clone(),
+                    contract.clone(),
+                    vec![
+                        MarkerAccess::Admin,
+                        MarkerAccess::Mint,
+                        MarkerAccess::Burn,
+                        MarkerAccess::Withdraw,
+                    ],
+                )?,
+                MarkerSetupStep::Finalize => finalize_marker(setup.denom.clone())?,
+                MarkerSetupStep::Activate => activate_marker(setup.denom.clone())?,
+            };
+            let id = index * MARKER_SETUP_STEPS.len() + position;
+            messages.push(SubMsg::reply_always(
+                msg,
+                MARKER_SETUP_REPLY_ID_OFFSET + id as u64,
+            ));
+        }
+    }
 
-    Ok(Response::default()
-        .add_messages(create_and_activate_marker(state.commitment_denom.clone())?)
-        .add_messages(create_and_activate_marker(state.investment_denom)?))
+    Ok(messages)
+}
+
+// seeds subscriptions that were accepted, and funded, before this raise existed
+fn import_accepted_subscriptions(
+    storage: &mut dyn Storage,
+    env: &Env,
+    state: &State,
+    accepts: Vec<AcceptSubscription>,
+) -> Result<(), ContractError> {
+    if accepts.is_empty() {
+        return Ok(());
+    }
+
+    let mut accepted = BTreeSet::new();
+    for accept in accepts {
+        if state.not_evenly_divisble(accept.commitment_in_capital) {
+            return Err(ContractError::IndivisibleAmount {
+                amount: accept.commitment_in_capital,
+                capital_per_share: state.capital_per_share,
+            });
+        }
+        if !accepted.insert(accept.subscription.clone()) {
+            return Err(ContractError::InvalidRequest {
+                reason: "initial accepted subscriptions must be unique",
+            });
+        }
+
+        save_asset_exchanges(
+            storage,
+            &accept.subscription,
+            &[AssetExchange {
+                investment: None,
+                commitment_in_shares: Some(
+                    state
+                        .capital_to_shares(accept.commitment_in_capital)
+                        .try_into()?,
+                ),
+                capital: None,
+                date: None,
+                required_attestation: None,
+                exchange_id: None,
+                late_fee_bps: None,
+                class: None,
+                provenance: Some(ExchangeProvenance {
+                    issued_by: state.gp.clone(),
+                    issued_at: env.block.time.seconds(),
+                    batch: None,
+                }),
+                price: None,
+                denom: None,
+                late_fee_paid: false,
+            }],
+        )?;
+        set_subscription_status(
+            storage,
+            &accept.subscription,
+            Some(SubscriptionStatus::Accepted),
+        )?;
+        record_change(
+            storage,
+            ChangedEntity::Subscription,
+            accept.subscription.as_str(),
+        )?;
+        record_change(
+            storage,
+            ChangedEntity::AssetExchange,
+            accept.subscription.as_str(),
+        )?;
+    }
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::contract::{execute, reply};
     use crate::mock::marker_msg;
     use crate::mock::msg_at_index;
-    use crate::msg::QueryMsg;
     use crate::msg::RaiseState;
+    use crate::msg::{HandleMsg, QueryMsg};
     use crate::query::query;
+    use crate::state::{DenomDecimals, FundMetadata, InstantiateTerms, MAX_METADATA_FIELD_LENGTH};
     use cosmwasm_std::coin;
     use cosmwasm_std::testing::MOCK_CONTRACT_ADDR;
     use cosmwasm_std::testing::{mock_env, mock_info};
-    use cosmwasm_std::{from_binary, Addr};
+    use cosmwasm_std::{
+        from_binary, Addr, OwnedDeps, Reply, ReplyOn, SubMsgResponse, SubMsgResult,
+    };
     use provwasm_mocks::mock_dependencies;
     use provwasm_std::MarkerMsgParams;
 
@@ -92,6 +281,14 @@ mod tests {
                 capital_denom: String::from("stable_coin"),
                 capital_per_share: 100,
                 required_capital_attribute: None,
+                due_grace_seconds: None,
+                escrow_capital: false,
+                permissionless_eligibility: false,
+                share_classes: vec![],
+                initial_accepted_subscriptions: vec![],
+                metadata: FundMetadata::default(),
+                min_initial_commitment: None,
+                denom_decimals: vec![],
             },
         )
         .unwrap();
@@ -199,4 +396,373 @@ mod tests {
         assert_eq!("stable_coin", state.general.capital_denom);
         assert_eq!(100, state.general.capital_per_share);
     }
+
+    #[test]
+    fn marker_setup_tracked_and_retried() {
+        let mut deps = mock_dependencies(&[]);
+        let res = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            InstantiateMsg {
+                subscription_code_id: 0,
+                recovery_admin: Addr::unchecked("marketpalace"),
+                required_attestations: vec![],
+                capital_denom: String::from("stable_coin"),
+                capital_per_share: 100,
+                required_capital_attribute: None,
+                due_grace_seconds: None,
+                escrow_capital: false,
+                permissionless_eligibility: false,
+                share_classes: vec![],
+                initial_accepted_subscriptions: vec![],
+                metadata: FundMetadata::default(),
+                min_initial_commitment: None,
+                denom_decimals: vec![],
+            },
+        )
+        .unwrap();
+
+        // every setup step replies under its own id
+        assert!(res
+            .messages
+            .iter()
+            .all(|msg| msg.reply_on == ReplyOn::Always));
+        let ids: Vec<u64> = res.messages.iter().map(|msg| msg.id).collect();
+        assert_eq!(
+            (0..8)
+                .map(|step| MARKER_SETUP_REPLY_ID_OFFSET + step)
+                .collect::<Vec<u64>>(),
+            ids
+        );
+
+        // the investment marker is created, but granting access fails and so does the rest
+        let mut respond = |id: u64, result: SubMsgResult| {
+            reply(deps.as_mut(), mock_env(), Reply { id, result }).unwrap()
+        };
+        let ok = || {
+            SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: None,
+            })
+        };
+        for id in ids.iter().take(5) {
+            respond(*id, ok());
+        }
+        let res = respond(ids[5], SubMsgResult::Err(String::from("grant failed")));
+        assert_eq!(
+            format!("{}.investment", MOCK_CONTRACT_ADDR),
+            res.attributes[0].value
+        );
+        respond(ids[6], SubMsgResult::Err(String::from("finalize failed")));
+        respond(ids[7], SubMsgResult::Err(String::from("activate failed")));
+
+        let setup = |deps: &OwnedDeps<_, _, _, _>| -> Vec<MarkerSetup> {
+            from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::GetMarkerSetup {}).unwrap())
+                .unwrap()
+        };
+        let setups = setup(&deps);
+        assert!(setups[0].is_complete());
+        assert_eq!(vec![MarkerSetupStep::Create], setups[1].completed);
+        assert_eq!(Some(String::from("grant failed")), setups[1].failure);
+
+        // no commitment can be proposed against the half built investment marker
+        let propose = |deps: &mut OwnedDeps<_, _, _, _>| {
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("lp", &[]),
+                HandleMsg::ProposeSubscription {
+                    initial_commitment: Some(10_000),
+                    owner: None,
+                    forward_funds: false,
+                },
+            )
+        };
+        assert_eq!(
+            ContractError::MarkerSetupIncomplete {
+                denom: format!("{}.investment", MOCK_CONTRACT_ADDR),
+            },
+            propose(&mut deps).unwrap_err()
+        );
+
+        // only the admin can retry, and only the steps left over are sent again
+        let retry = |deps: &mut OwnedDeps<_, _, _, _>, sender: &str| {
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(sender, &[]),
+                HandleMsg::RetryMarkerSetup {},
+            )
+        };
+        assert_eq!(
+            ContractError::Unauthorized {
+                required: "admin",
+                action: "retry marker setup",
+            },
+            retry(&mut deps, "gp").unwrap_err()
+        );
+        let res = retry(&mut deps, "marketpalace").unwrap();
+        assert_eq!(
+            ids[5..].to_vec(),
+            res.messages.iter().map(|msg| msg.id).collect::<Vec<u64>>()
+        );
+        assert_eq!(
+            &MarkerMsgParams::GrantMarkerAccess {
+                denom: format!("{}.investment", MOCK_CONTRACT_ADDR),
+                address: Addr::unchecked(MOCK_CONTRACT_ADDR),
+                permissions: vec![
+                    MarkerAccess::Admin,
+                    MarkerAccess::Mint,
+                    MarkerAccess::Burn,
+                    MarkerAccess::Withdraw,
+                ],
+            },
+            marker_msg(msg_at_index(&res, 0)),
+        );
+        assert_eq!(None, setup(&deps)[1].failure);
+
+        for id in &ids[5..] {
+            reply(
+                deps.as_mut(),
+                mock_env(),
+                Reply {
+                    id: *id,
+                    result: ok(),
+                },
+            )
+            .unwrap();
+        }
+        assert!(setup(&deps).iter().all(MarkerSetup::is_complete));
+        assert!(propose(&mut deps).is_ok());
+        assert_eq!(
+            ContractError::InvalidRequest {
+                reason: "marker setup is already complete",
+            },
+            retry(&mut deps, "marketpalace").unwrap_err()
+        );
+    }
+
+    #[test]
+    fn initialization_with_share_classes() {
+        let mut deps = mock_dependencies(&[]);
+
+        let res = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            InstantiateMsg {
+                subscription_code_id: 0,
+                recovery_admin: Addr::unchecked("marketpalace"),
+                required_attestations: vec![],
+                capital_denom: String::from("stable_coin"),
+                capital_per_share: 100,
+                required_capital_attribute: None,
+                due_grace_seconds: None,
+                escrow_capital: false,
+                permissionless_eligibility: false,
+                share_classes: vec![String::from("side_pocket")],
+                initial_accepted_subscriptions: vec![],
+                metadata: FundMetadata::default(),
+                min_initial_commitment: None,
+                denom_decimals: vec![],
+            },
+        )
+        .unwrap();
+
+        // verify a third marker is configured for the side pocket class
+        assert_eq!(12, res.messages.len());
+        let side_pocket_denom = format!("{}.investment.side_pocket", MOCK_CONTRACT_ADDR);
+        assert_eq!(
+            &MarkerMsgParams::CreateMarker {
+                coin: coin(0, side_pocket_denom.clone()),
+                marker_type: MarkerType::Coin
+            },
+            marker_msg(msg_at_index(&res, 8)),
+        );
+        assert_eq!(
+            vec![ShareClass {
+                name: String::from("side_pocket"),
+                investment_denom: side_pocket_denom,
+            }],
+            config(&mut deps.storage).load().unwrap().share_classes
+        );
+    }
+
+    #[test]
+    fn initialization_with_accepted_subscriptions() {
+        let mut deps = mock_dependencies(&[]);
+
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            InstantiateMsg {
+                subscription_code_id: 0,
+                recovery_admin: Addr::unchecked("marketpalace"),
+                required_attestations: vec![],
+                capital_denom: String::from("stable_coin"),
+                capital_per_share: 100,
+                required_capital_attribute: None,
+                due_grace_seconds: None,
+                escrow_capital: false,
+                permissionless_eligibility: false,
+                share_classes: vec![],
+                initial_accepted_subscriptions: vec![AcceptSubscription {
+                    subscription: Addr::unchecked("sub_1"),
+                    commitment_in_capital: 10_000,
+                }],
+                metadata: FundMetadata {
+                    name: Some(String::from("Fund I")),
+                    ..FundMetadata::default()
+                },
+                min_initial_commitment: None,
+                denom_decimals: vec![],
+            },
+        )
+        .unwrap();
+
+        // verify the sub is accepted with its initial commitment exchange
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetState {}).unwrap();
+        let state: RaiseState = from_binary(&res).unwrap();
+        assert_eq!(vec![Addr::unchecked("sub_1")], state.accepted_subscriptions);
+        assert_eq!(Some(String::from("Fund I")), state.general.metadata.name);
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetAssetExchangesForSubscription {
+                subscription: Addr::unchecked("sub_1"),
+            },
+        )
+        .unwrap();
+        let exchanges: Option<Vec<AssetExchange>> = from_binary(&res).unwrap();
+        assert_eq!(
+            Some(100),
+            exchanges.unwrap().first().unwrap().commitment_in_shares
+        );
+    }
+
+    #[test]
+    fn instantiate_terms_recorded() {
+        let mut deps = mock_dependencies(&[]);
+        let msg = InstantiateMsg {
+            subscription_code_id: 0,
+            recovery_admin: Addr::unchecked("marketpalace"),
+            required_attestations: vec![],
+            capital_denom: String::from("stable_coin"),
+            capital_per_share: 100,
+            required_capital_attribute: None,
+            due_grace_seconds: None,
+            escrow_capital: false,
+            permissionless_eligibility: false,
+            share_classes: vec![],
+            initial_accepted_subscriptions: vec![],
+            metadata: FundMetadata::default(),
+            min_initial_commitment: None,
+            denom_decimals: vec![],
+        };
+
+        instantiate(deps.as_mut(), mock_env(), mock_info("gp", &[]), msg.clone()).unwrap();
+
+        // verify the original terms can be recovered and match their hash
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetInstantiateTerms {}).unwrap();
+        let terms: Option<InstantiateTerms> = from_binary(&res).unwrap();
+        let terms = terms.unwrap();
+        assert_eq!(msg, from_binary::<InstantiateMsg>(&terms.msg).unwrap());
+        assert_eq!(
+            format!("{:x}", Sha256::digest(terms.msg.as_slice())),
+            terms.hash
+        );
+        assert_eq!(64, terms.hash.len());
+    }
+
+    #[test]
+    fn instantiate_rejects_invalid_terms() {
+        let msg = InstantiateMsg {
+            subscription_code_id: 0,
+            recovery_admin: Addr::unchecked("marketpalace"),
+            required_attestations: vec![],
+            capital_denom: String::from("stable_coin"),
+            capital_per_share: 100,
+            required_capital_attribute: None,
+            due_grace_seconds: None,
+            escrow_capital: false,
+            permissionless_eligibility: false,
+            share_classes: vec![],
+            initial_accepted_subscriptions: vec![],
+            metadata: FundMetadata::default(),
+            min_initial_commitment: None,
+            denom_decimals: vec![],
+        };
+        let instantiate_err = |msg: InstantiateMsg| {
+            instantiate(
+                mock_dependencies(&[]).as_mut(),
+                mock_env(),
+                mock_info("gp", &[]),
+                msg,
+            )
+            .unwrap_err()
+        };
+
+        assert_eq!(
+            ContractError::ZeroCapitalPerShare {},
+            instantiate_err(InstantiateMsg {
+                capital_per_share: 0,
+                ..msg.clone()
+            })
+        );
+        assert_eq!(
+            ContractError::InvalidDenom {
+                denom: String::from("1 bad coin")
+            },
+            instantiate_err(InstantiateMsg {
+                capital_denom: String::from("1 bad coin"),
+                ..msg.clone()
+            })
+        );
+        assert_eq!(
+            ContractError::InvalidRequest {
+                reason: "denom decimals can't exceed 18"
+            },
+            instantiate_err(InstantiateMsg {
+                denom_decimals: vec![DenomDecimals {
+                    denom: String::from("wei_coin"),
+                    decimals: 19,
+                }],
+                ..msg.clone()
+            })
+        );
+        assert_eq!(
+            ContractError::EmptyAttestationSet { index: 1 },
+            instantiate_err(InstantiateMsg {
+                required_attestations: vec![
+                    BTreeSet::from([String::from("506c")]),
+                    BTreeSet::new()
+                ],
+                ..msg.clone()
+            })
+        );
+        assert_eq!(
+            ContractError::DuplicateDenom {
+                denom: format!("{}.investment.side_pocket", MOCK_CONTRACT_ADDR)
+            },
+            instantiate_err(InstantiateMsg {
+                share_classes: vec![String::from("side_pocket"), String::from("side_pocket")],
+                ..msg.clone()
+            })
+        );
+        assert_eq!(
+            ContractError::MetadataTooLong {
+                field: "name",
+                max: MAX_METADATA_FIELD_LENGTH
+            },
+            instantiate_err(InstantiateMsg {
+                metadata: FundMetadata {
+                    name: Some("x".repeat(MAX_METADATA_FIELD_LENGTH + 1)),
+                    ..FundMetadata::default()
+                },
+                ..msg
+            })
+        );
+    }
 }
@@ -1,14 +1,47 @@
+pub mod approval;
+pub mod archive;
+pub mod compliance_msg;
+pub mod consent;
 pub mod contract;
 pub mod error;
 pub mod exchange_asset;
 pub mod instantiate;
 pub mod migrate;
 pub mod msg;
+pub mod ownership;
+pub mod performance_fee;
 pub mod query;
+pub mod reply;
+pub mod settlement;
 pub mod state;
 pub mod sub_msg;
 pub mod subscribe;
 pub mod version;
 
+pub use crate::error::ContractError;
+pub use crate::msg::{HandleMsg, InstantiateMsg, MigrateMsg, QueryMsg};
+pub use crate::sub_msg::{SubExecuteMsg, SubInstantiateMsg, SubQueryMsg, SubState};
+
 #[cfg(test)]
 pub mod mock;
+
+#[cfg(test)]
+mod tests {
+    use crate::{ContractError, QueryMsg};
+    use cosmwasm_std::{from_slice, to_vec};
+
+    #[test]
+    fn message_types_exported_from_crate_root() {
+        let msg: QueryMsg = from_slice(br#"{"get_state":{}}"#).unwrap();
+        assert_eq!(QueryMsg::GetState {}, msg);
+        assert_eq!(br#"{"get_state":{}}"#.to_vec(), to_vec(&msg).unwrap());
+        assert_eq!(
+            "only gp can close",
+            ContractError::Unauthorized {
+                required: "gp",
+                action: "close"
+            }
+            .to_string()
+        );
+    }
+}
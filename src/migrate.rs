@@ -1,37 +1,44 @@
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::convert::TryFrom;
 
 use crate::contract::ContractResponse;
-use crate::msg::MigrateMsg;
+use crate::error::ContractError;
+use crate::msg::{AssetExchange, ChangedEntity, MigrateMsg};
+use crate::settlement::cache_marker_addresses;
 use crate::state::config;
-use crate::state::State;
 use crate::state::CONFIG_KEY;
+use crate::state::{asset_exchange_storage, record_change, record_subscription_code_id};
+use crate::state::{save_asset_exchanges, tally_outstanding_obligations};
+use crate::state::{set_subscription_status, FundMetadata, State, SubscriptionStatus};
+use crate::state::{tally_authorized_funds, tally_escrowed_capital};
+use crate::state::{
+    ACCEPTED_SUBSCRIPTIONS_KEY, ELIGIBLE_SUBSCRIPTIONS_KEY, PENDING_SUBSCRIPTIONS_KEY,
+};
 use crate::version::CONTRACT_NAME;
 use crate::version::CONTRACT_VERSION;
-use cosmwasm_std::entry_point;
 use cosmwasm_std::Addr;
 use cosmwasm_std::DepsMut;
 use cosmwasm_std::Env;
 use cosmwasm_std::Response;
-use cosmwasm_storage::singleton_read;
+use cosmwasm_std::Storage;
+use cosmwasm_storage::{singleton, singleton_read};
 use cw2::{get_contract_version, set_contract_version};
 use provwasm_std::ProvenanceQuery;
 use serde::Deserialize;
 use serde::Serialize;
 
-#[derive(Serialize)]
-struct EmptyArgs {}
-
-#[entry_point]
+#[cfg_attr(not(feature = "library"), cosmwasm_std::entry_point)]
 pub fn migrate(
-    deps: DepsMut<ProvenanceQuery>,
-    _: Env,
+    mut deps: DepsMut<ProvenanceQuery>,
+    env: Env,
     migrate_msg: MigrateMsg,
 ) -> ContractResponse {
     let contract_info = get_contract_version(deps.storage)?;
 
     match contract_info.version.as_str() {
         "2.2.0" => {
-            let old_state: State = singleton_read(deps.storage, CONFIG_KEY).load()?;
+            let old_state: StateV2_2_0 = singleton_read(deps.storage, CONFIG_KEY).load()?;
+            record_subscription_code_id(deps.storage, old_state.subscription_code_id, None)?;
             let capital_denom = match migrate_msg.capital_denom {
                 None => old_state.capital_denom,
                 Some(capital_denom) => capital_denom,
@@ -46,12 +53,51 @@ pub fn migrate(
                 capital_denom,
                 capital_per_share: old_state.capital_per_share,
                 required_capital_attribute: migrate_msg.required_capital_attribute,
+                due_grace_seconds: migrate_msg.due_grace_seconds.unwrap_or_default(),
+                escrow_capital: false,
+                permissionless_eligibility: false,
+                paused: false,
+                delegates: BTreeSet::new(),
+                commitment_marker: None,
+                investment_marker: None,
+                max_receipts: None,
+                receipt_retention_seconds: None,
+                change_log_retention: None,
+                settlement_hours: None,
+                gp_committee: vec![],
+                approval_threshold: 0,
+                approval_capital_threshold: None,
+                compliance_contract: None,
+                treasury: None,
+                pending_treasury: None,
+                max_outstanding_exchanges: None,
+                max_outstanding_capital: None,
+                capital_exchange_rates: vec![],
+                capital_attributes: vec![],
+                metadata: FundMetadata::default(),
+                min_initial_commitment: None,
+                share_classes: vec![],
+                nav: None,
+                max_ownership_bps: None,
+                approved_destinations: None,
+                auto_accept: None,
+                performance_fee: None,
+                term_end: None,
+                batch_cancel_cosign_threshold: None,
+                management_fee: None,
+                memo_transfer_events: false,
+                denom_decimals: migrate_msg.denom_decimals.clone(),
+                tombstone_code_id: None,
+                accepting_proposals: true,
+                proposal_cooldown_blocks: None,
+                max_pending_per_lp: None,
             };
 
             config(deps.storage).save(&new_state)?;
         }
         _ => {
             let old_state: StateV2_0_0 = singleton_read(deps.storage, CONFIG_KEY).load()?;
+            record_subscription_code_id(deps.storage, old_state.subscription_code_id, None)?;
             let capital_denom = match migrate_msg.capital_denom {
                 None => old_state.capital_denom,
                 Some(capital_denom) => capital_denom,
@@ -66,23 +112,188 @@ pub fn migrate(
                 capital_denom,
                 capital_per_share: old_state.capital_per_share,
                 required_capital_attribute: migrate_msg.required_capital_attribute,
+                due_grace_seconds: migrate_msg.due_grace_seconds.unwrap_or_default(),
+                escrow_capital: false,
+                permissionless_eligibility: false,
+                paused: false,
+                delegates: BTreeSet::new(),
+                commitment_marker: None,
+                investment_marker: None,
+                max_receipts: None,
+                receipt_retention_seconds: None,
+                change_log_retention: None,
+                settlement_hours: None,
+                gp_committee: vec![],
+                approval_threshold: 0,
+                approval_capital_threshold: None,
+                compliance_contract: None,
+                treasury: None,
+                pending_treasury: None,
+                max_outstanding_exchanges: None,
+                max_outstanding_capital: None,
+                capital_exchange_rates: vec![],
+                capital_attributes: vec![],
+                metadata: FundMetadata::default(),
+                min_initial_commitment: None,
+                share_classes: vec![],
+                nav: None,
+                max_ownership_bps: None,
+                approved_destinations: None,
+                auto_accept: None,
+                performance_fee: None,
+                term_end: None,
+                batch_cancel_cosign_threshold: None,
+                management_fee: None,
+                memo_transfer_events: false,
+                denom_decimals: migrate_msg.denom_decimals.clone(),
+                tombstone_code_id: None,
+                accepting_proposals: true,
+                proposal_cooldown_blocks: None,
+                max_pending_per_lp: None,
             };
 
             config(deps.storage).save(&new_state)?;
+            backfill_legacy_exchanges(deps.storage)?;
         }
     };
 
+    index_legacy_subscription_sets(deps.storage)?;
+    tally_outstanding_obligations(deps.storage)?;
+    tally_escrowed_capital(deps.storage)?;
+    tally_authorized_funds(deps.storage)?;
+
+    let mut state = config(deps.storage).load()?;
+    state.validate()?;
+    record_subscription_code_id(
+        deps.storage,
+        state.subscription_code_id,
+        Some(env.block.height),
+    )?;
+    cache_marker_addresses(deps.branch(), &mut state)?;
+
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
     Ok(Response::default())
 }
 
+pub static OUTSTANDING_REDEMPTIONS_KEY: &[u8] = b"outstanding_redemptions";
+pub static OUTSTANDING_DISTRIBUTIONS_KEY: &[u8] = b"outstanding_distributions";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RedemptionV1 {
+    pub subscription: Addr,
+    pub asset: u64,
+    pub capital: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DistributionV1 {
+    pub subscription: Addr,
+    pub amount: u64,
+}
+
+// converts the pre asset exchange redemption and distribution sets into asset exchanges
+fn backfill_legacy_exchanges(storage: &mut dyn Storage) -> Result<(), ContractError> {
+    let redemptions: HashSet<RedemptionV1> = singleton_read(storage, OUTSTANDING_REDEMPTIONS_KEY)
+        .may_load()?
+        .unwrap_or_default();
+    let distributions: HashSet<DistributionV1> =
+        singleton_read(storage, OUTSTANDING_DISTRIBUTIONS_KEY)
+            .may_load()?
+            .unwrap_or_default();
+
+    let mut exchanges: HashMap<Addr, Vec<AssetExchange>> = HashMap::new();
+    for redemption in redemptions {
+        exchanges
+            .entry(redemption.subscription)
+            .or_default()
+            .push(AssetExchange {
+                investment: Some(-i64::try_from(redemption.asset)?),
+                commitment_in_shares: None,
+                capital: Some(i64::try_from(redemption.capital)?),
+                date: None,
+                required_attestation: None,
+                exchange_id: None,
+                late_fee_bps: None,
+                class: None,
+                provenance: None,
+                price: None,
+                denom: None,
+                late_fee_paid: false,
+            });
+    }
+    for distribution in distributions {
+        exchanges
+            .entry(distribution.subscription)
+            .or_default()
+            .push(AssetExchange {
+                investment: None,
+                commitment_in_shares: None,
+                capital: Some(i64::try_from(distribution.amount)?),
+                date: None,
+                required_attestation: None,
+                exchange_id: None,
+                late_fee_bps: None,
+                class: None,
+                provenance: None,
+                price: None,
+                denom: None,
+                late_fee_paid: false,
+            });
+    }
+
+    for (subscription, legacy) in exchanges {
+        let mut existing = asset_exchange_storage(storage)
+            .may_load(subscription.as_bytes())?
+            .unwrap_or_default();
+        existing.extend(legacy);
+        save_asset_exchanges(storage, &subscription, &existing)?;
+        record_change(storage, ChangedEntity::AssetExchange, subscription.as_str())?;
+    }
+
+    storage.remove(OUTSTANDING_REDEMPTIONS_KEY);
+    storage.remove(OUTSTANDING_DISTRIBUTIONS_KEY);
+
+    Ok(())
+}
+
+// moves subs out of the whole-set singletons into the status index, applying later
+// stages last so a sub left in an earlier set by a partial failure ends up in the furthest
+fn index_legacy_subscription_sets(storage: &mut dyn Storage) -> Result<(), ContractError> {
+    for (key, status) in [
+        (PENDING_SUBSCRIPTIONS_KEY, SubscriptionStatus::Pending),
+        (ELIGIBLE_SUBSCRIPTIONS_KEY, SubscriptionStatus::Eligible),
+        (ACCEPTED_SUBSCRIPTIONS_KEY, SubscriptionStatus::Accepted),
+    ] {
+        let legacy: BTreeSet<Addr> = singleton_read(storage, key).may_load()?.unwrap_or_default();
+        for subscription in legacy {
+            set_subscription_status(storage, &subscription, Some(status))?;
+        }
+        singleton::<BTreeSet<Addr>>(storage, key).remove();
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct StateV2_2_0 {
+    pub subscription_code_id: u64,
+    pub recovery_admin: Addr,
+    pub gp: Addr,
+    pub required_attestations: Vec<BTreeSet<String>>,
+    pub commitment_denom: String,
+    pub investment_denom: String,
+    pub capital_denom: String,
+    pub capital_per_share: u64,
+    pub required_capital_attribute: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct StateV2_0_0 {
     pub subscription_code_id: u64,
     pub recovery_admin: Addr,
     pub gp: Addr,
-    pub acceptable_accreditations: HashSet<String>,
+    pub acceptable_accreditations: BTreeSet<String>,
     pub commitment_denom: String,
     pub investment_denom: String,
     pub capital_denom: String,
@@ -91,15 +302,24 @@ pub struct StateV2_0_0 {
 
 #[cfg(test)]
 mod tests {
-    use crate::migrate::{migrate, StateV2_0_0};
-    use crate::msg::MigrateMsg;
-    use crate::state::{State, CONFIG_KEY};
+    use crate::error::ContractError;
+    use crate::migrate::{migrate, StateV2_0_0, StateV2_2_0};
+    use crate::migrate::{DistributionV1, RedemptionV1};
+    use crate::migrate::{OUTSTANDING_DISTRIBUTIONS_KEY, OUTSTANDING_REDEMPTIONS_KEY};
+    use crate::msg::{MigrateMsg, SubscriptionCodeId};
+    use crate::state::tests::asset_exchange_storage_read;
+    use crate::state::PENDING_SUBSCRIPTIONS_KEY;
+    use crate::state::{outstanding_obligations_read, OutstandingObligations};
+    use crate::state::{subscription_code_ids_read, FundMetadata, State, CONFIG_KEY};
+    use crate::state::{subscriptions_with_status, SubscriptionStatus};
+    use crate::state::{ACCEPTED_SUBSCRIPTIONS_KEY, ELIGIBLE_SUBSCRIPTIONS_KEY};
     use cosmwasm_std::testing::mock_env;
     use cosmwasm_std::Addr;
+    use cosmwasm_std::Storage;
     use cosmwasm_storage::{singleton, singleton_read};
     use cw2::set_contract_version;
     use provwasm_mocks::mock_dependencies;
-    use std::collections::HashSet;
+    use std::collections::{BTreeSet, HashSet};
 
     #[test]
     fn migration() {
@@ -110,7 +330,7 @@ mod tests {
                 subscription_code_id: 1,
                 recovery_admin: Addr::unchecked("marketpalace"),
                 gp: Addr::unchecked("gp"),
-                acceptable_accreditations: HashSet::from(["506c".to_string()]),
+                acceptable_accreditations: BTreeSet::from(["506c".to_string()]),
                 commitment_denom: "commitment".to_string(),
                 investment_denom: "investment".to_string(),
                 capital_denom: String::from("stable_coin"),
@@ -125,6 +345,8 @@ mod tests {
                 subscription_code_id: 2,
                 capital_denom: None,
                 required_capital_attribute: None,
+                due_grace_seconds: None,
+                denom_decimals: vec![],
             },
         )
         .unwrap();
@@ -133,16 +355,180 @@ mod tests {
             State {
                 subscription_code_id: 2,
                 recovery_admin: Addr::unchecked("marketpalace"),
-                required_attestations: vec![HashSet::from(["506c".to_string()])],
+                required_attestations: vec![BTreeSet::from(["506c".to_string()])],
                 gp: Addr::unchecked("gp"),
                 commitment_denom: String::from("commitment"),
                 investment_denom: String::from("investment"),
                 capital_denom: String::from("stable_coin"),
                 capital_per_share: 100,
                 required_capital_attribute: None,
+                due_grace_seconds: 0,
+                escrow_capital: false,
+                permissionless_eligibility: false,
+                paused: false,
+                delegates: BTreeSet::new(),
+                commitment_marker: None,
+                investment_marker: None,
+                max_receipts: None,
+                receipt_retention_seconds: None,
+                change_log_retention: None,
+                settlement_hours: None,
+                gp_committee: vec![],
+                approval_threshold: 0,
+                approval_capital_threshold: None,
+                compliance_contract: None,
+                treasury: None,
+                pending_treasury: None,
+                max_outstanding_exchanges: None,
+                max_outstanding_capital: None,
+                capital_exchange_rates: vec![],
+                capital_attributes: vec![],
+                metadata: FundMetadata::default(),
+                min_initial_commitment: None,
+                share_classes: vec![],
+                nav: None,
+                max_ownership_bps: None,
+                approved_destinations: None,
+                auto_accept: None,
+                performance_fee: None,
+                term_end: None,
+                batch_cancel_cosign_threshold: None,
+                management_fee: None,
+                memo_transfer_events: false,
+                denom_decimals: vec![],
+                tombstone_code_id: None,
+                accepting_proposals: true,
+                proposal_cooldown_blocks: None,
+                max_pending_per_lp: None,
             },
             singleton_read(&deps.storage, CONFIG_KEY).load().unwrap()
         );
+
+        // verify the legacy code id is kept ahead of the new one
+        assert_eq!(
+            vec![
+                SubscriptionCodeId {
+                    code_id: 1,
+                    activated_at: None,
+                },
+                SubscriptionCodeId {
+                    code_id: 2,
+                    activated_at: Some(mock_env().block.height),
+                },
+            ],
+            subscription_code_ids_read(&deps.storage).load().unwrap()
+        );
+    }
+
+    #[test]
+    fn migration_rejects_invalid_capital_denom() {
+        let mut deps = mock_dependencies(&[]);
+        set_contract_version(&mut deps.storage, "TEST", "2.0.0").unwrap();
+        singleton(&mut deps.storage, CONFIG_KEY)
+            .save(&StateV2_0_0 {
+                subscription_code_id: 1,
+                recovery_admin: Addr::unchecked("marketpalace"),
+                gp: Addr::unchecked("gp"),
+                acceptable_accreditations: BTreeSet::from(["506c".to_string()]),
+                commitment_denom: "commitment".to_string(),
+                investment_denom: "investment".to_string(),
+                capital_denom: String::from("stable_coin"),
+                capital_per_share: 100,
+            })
+            .unwrap();
+
+        let res = migrate(
+            deps.as_mut(),
+            mock_env(),
+            MigrateMsg {
+                subscription_code_id: 2,
+                capital_denom: Some(String::from("commitment")),
+                required_capital_attribute: None,
+                due_grace_seconds: None,
+                denom_decimals: vec![],
+            },
+        );
+        assert_eq!(
+            ContractError::DuplicateDenom {
+                denom: String::from("commitment")
+            },
+            res.unwrap_err()
+        );
+
+        // verify the rejected code id never became active
+        assert_eq!(
+            vec![SubscriptionCodeId {
+                code_id: 1,
+                activated_at: None,
+            }],
+            subscription_code_ids_read(&deps.storage).load().unwrap()
+        );
+    }
+
+    #[test]
+    fn migration_backfills_legacy_exchanges() {
+        let mut deps = mock_dependencies(&[]);
+        set_contract_version(&mut deps.storage, "TEST", "2.0.0").unwrap();
+        singleton(&mut deps.storage, CONFIG_KEY)
+            .save(&StateV2_0_0 {
+                subscription_code_id: 1,
+                recovery_admin: Addr::unchecked("marketpalace"),
+                gp: Addr::unchecked("gp"),
+                acceptable_accreditations: BTreeSet::from(["506c".to_string()]),
+                commitment_denom: "commitment".to_string(),
+                investment_denom: "investment".to_string(),
+                capital_denom: String::from("stable_coin"),
+                capital_per_share: 100,
+            })
+            .unwrap();
+        singleton(&mut deps.storage, OUTSTANDING_REDEMPTIONS_KEY)
+            .save(&HashSet::from([RedemptionV1 {
+                subscription: Addr::unchecked("sub_1"),
+                asset: 10,
+                capital: 1_000,
+            }]))
+            .unwrap();
+        singleton(&mut deps.storage, OUTSTANDING_DISTRIBUTIONS_KEY)
+            .save(&HashSet::from([DistributionV1 {
+                subscription: Addr::unchecked("sub_1"),
+                amount: 500,
+            }]))
+            .unwrap();
+
+        migrate(
+            deps.as_mut(),
+            mock_env(),
+            MigrateMsg {
+                subscription_code_id: 2,
+                capital_denom: None,
+                required_capital_attribute: None,
+                due_grace_seconds: None,
+                denom_decimals: vec![],
+            },
+        )
+        .unwrap();
+
+        // verify legacy entries are converted into asset exchanges
+        let exchanges = asset_exchange_storage_read(&deps.storage)
+            .load(b"sub_1")
+            .unwrap();
+        assert_eq!(2, exchanges.len());
+        assert!(exchanges[0].is_redemption());
+        assert_eq!(Some(-10), exchanges[0].investment);
+        assert_eq!(Some(1_000), exchanges[0].capital);
+        assert!(exchanges[1].is_distribution());
+        assert_eq!(Some(500), exchanges[1].capital);
+        assert_eq!(
+            OutstandingObligations {
+                exchanges: 2,
+                capital: 1_500,
+            },
+            outstanding_obligations_read(&deps.storage).load().unwrap()
+        );
+
+        // verify legacy keys are removed
+        assert!(deps.storage.get(OUTSTANDING_REDEMPTIONS_KEY).is_none());
+        assert!(deps.storage.get(OUTSTANDING_DISTRIBUTIONS_KEY).is_none());
     }
 
     #[test]
@@ -150,10 +536,10 @@ mod tests {
         let mut deps = mock_dependencies(&[]);
         set_contract_version(&mut deps.storage, "TEST", "2.2.0").unwrap();
         singleton(&mut deps.storage, CONFIG_KEY)
-            .save(&State {
+            .save(&StateV2_2_0 {
                 subscription_code_id: 1,
                 recovery_admin: Addr::unchecked("marketpalace"),
-                required_attestations: vec![HashSet::from(["506c".to_string()])],
+                required_attestations: vec![BTreeSet::from(["506c".to_string()])],
                 gp: Addr::unchecked("gp"),
                 commitment_denom: String::from("commitment"),
                 investment_denom: String::from("investment"),
@@ -170,6 +556,8 @@ mod tests {
                 subscription_code_id: 2,
                 capital_denom: None,
                 required_capital_attribute: None,
+                due_grace_seconds: None,
+                denom_decimals: vec![],
             },
         )
         .unwrap();
@@ -178,15 +566,134 @@ mod tests {
             State {
                 subscription_code_id: 2,
                 recovery_admin: Addr::unchecked("marketpalace"),
-                required_attestations: vec![HashSet::from(["506c".to_string()])],
+                required_attestations: vec![BTreeSet::from(["506c".to_string()])],
                 gp: Addr::unchecked("gp"),
                 commitment_denom: String::from("commitment"),
                 investment_denom: String::from("investment"),
                 capital_denom: String::from("stable_coin"),
                 capital_per_share: 100,
                 required_capital_attribute: None,
+                due_grace_seconds: 0,
+                escrow_capital: false,
+                permissionless_eligibility: false,
+                paused: false,
+                delegates: BTreeSet::new(),
+                commitment_marker: None,
+                investment_marker: None,
+                max_receipts: None,
+                receipt_retention_seconds: None,
+                change_log_retention: None,
+                settlement_hours: None,
+                gp_committee: vec![],
+                approval_threshold: 0,
+                approval_capital_threshold: None,
+                compliance_contract: None,
+                treasury: None,
+                pending_treasury: None,
+                max_outstanding_exchanges: None,
+                max_outstanding_capital: None,
+                capital_exchange_rates: vec![],
+                capital_attributes: vec![],
+                metadata: FundMetadata::default(),
+                min_initial_commitment: None,
+                share_classes: vec![],
+                nav: None,
+                max_ownership_bps: None,
+                approved_destinations: None,
+                auto_accept: None,
+                performance_fee: None,
+                term_end: None,
+                batch_cancel_cosign_threshold: None,
+                management_fee: None,
+                memo_transfer_events: false,
+                denom_decimals: vec![],
+                tombstone_code_id: None,
+                accepting_proposals: true,
+                proposal_cooldown_blocks: None,
+                max_pending_per_lp: None,
             },
             singleton_read(&deps.storage, CONFIG_KEY).load().unwrap()
         );
     }
+
+    #[test]
+    fn migration_indexes_legacy_subscription_sets() {
+        let mut deps = mock_dependencies(&[]);
+        set_contract_version(&mut deps.storage, "TEST", "2.2.0").unwrap();
+        singleton(&mut deps.storage, CONFIG_KEY)
+            .save(&StateV2_2_0 {
+                subscription_code_id: 1,
+                recovery_admin: Addr::unchecked("marketpalace"),
+                required_attestations: vec![BTreeSet::from(["506c".to_string()])],
+                gp: Addr::unchecked("gp"),
+                commitment_denom: String::from("commitment"),
+                investment_denom: String::from("investment"),
+                capital_denom: String::from("stable_coin"),
+                capital_per_share: 100,
+                required_capital_attribute: None,
+            })
+            .unwrap();
+        let save_legacy = |storage: &mut dyn Storage, key: &[u8], subs: Vec<&str>| {
+            singleton(storage, key)
+                .save(
+                    &subs
+                        .into_iter()
+                        .map(Addr::unchecked)
+                        .collect::<BTreeSet<Addr>>(),
+                )
+                .unwrap();
+        };
+        save_legacy(
+            &mut deps.storage,
+            PENDING_SUBSCRIPTIONS_KEY,
+            vec!["sub_1", "sub_2", "sub_3"],
+        );
+        save_legacy(
+            &mut deps.storage,
+            ELIGIBLE_SUBSCRIPTIONS_KEY,
+            vec!["sub_2", "sub_4"],
+        );
+        save_legacy(
+            &mut deps.storage,
+            ACCEPTED_SUBSCRIPTIONS_KEY,
+            vec!["sub_3", "sub_4", "sub_5"],
+        );
+
+        migrate(
+            deps.as_mut(),
+            mock_env(),
+            MigrateMsg {
+                subscription_code_id: 2,
+                capital_denom: None,
+                required_capital_attribute: None,
+                due_grace_seconds: None,
+                denom_decimals: vec![],
+            },
+        )
+        .unwrap();
+
+        // verify each sub is indexed only under its furthest status
+        let status = |status| subscriptions_with_status(&deps.storage, status).unwrap();
+        assert_eq!(
+            vec![Addr::unchecked("sub_1")],
+            status(SubscriptionStatus::Pending)
+        );
+        assert_eq!(
+            vec![Addr::unchecked("sub_2")],
+            status(SubscriptionStatus::Eligible)
+        );
+        assert_eq!(
+            vec![
+                Addr::unchecked("sub_3"),
+                Addr::unchecked("sub_4"),
+                Addr::unchecked("sub_5")
+            ],
+            status(SubscriptionStatus::Accepted)
+        );
+
+        // verify legacy keys are removed
+        assert!(deps.storage.get(PENDING_SUBSCRIPTIONS_KEY).is_none());
+        assert!(deps.storage.get(ELIGIBLE_SUBSCRIPTIONS_KEY).is_none());
+        assert!(deps.storage.get(ACCEPTED_SUBSCRIPTIONS_KEY).is_none());
+    }
 }
@@ -5,6 +5,7 @@ use cosmwasm_std::BankMsg;
 use cosmwasm_std::CosmosMsg;
 use cosmwasm_std::Response;
 use cosmwasm_std::WasmMsg;
+use cosmwasm_std::{coins, Storage};
 use cosmwasm_std::{from_binary, Addr};
 use cosmwasm_std::{
     from_slice, Binary, Coin, ContractResult, OwnedDeps, Querier, QueryRequest, SystemError,
@@ -17,6 +18,12 @@ use provwasm_std::ProvenanceQuery;
 use provwasm_std::{Marker, MarkerMsgParams};
 use serde::de::DeserializeOwned;
 
+use crate::msg::{AssetExchange, SubscriptionStats};
+use crate::state::{
+    asset_exchange_storage, config_read, lp_subscription_storage, set_subscription_status,
+    subscription_lp_storage, subscription_stats_storage, SubscriptionStatus,
+};
+
 pub type MockWasmSmartHandler = fn(String, Binary) -> SystemResult<ContractResult<Binary>>;
 pub type MockBankBalanceHandler = fn(String, String) -> SystemResult<ContractResult<Binary>>;
 
@@ -27,21 +34,18 @@ pub struct MockContractQuerier {
 
 impl Querier for MockContractQuerier {
     fn raw_query(&self, bin_request: &[u8]) -> SystemResult<ContractResult<Binary>> {
-        return match from_slice::<QueryRequest<ProvenanceQuery>>(bin_request) {
+        match from_slice::<QueryRequest<ProvenanceQuery>>(bin_request) {
             Ok(value) => match value.clone() {
-                QueryRequest::Wasm(msg) => match msg {
-                    WasmQuery::Smart { contract_addr, msg } => {
-                        (self.wasm_smart_handler)(contract_addr, msg)
-                    }
-                    _ => self.base.handle_query(&value),
-                },
+                QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg }) => {
+                    (self.wasm_smart_handler)(contract_addr, msg)
+                }
                 _ => self.base.handle_query(&value),
             },
             Err(e) => SystemResult::Err(SystemError::InvalidRequest {
                 error: format!("Parsing query request: {}", e),
                 request: bin_request.into(),
             }),
-        };
+        }
     }
 }
 
@@ -198,3 +202,105 @@ pub fn load_markers(querier: &mut ProvenanceMockQuerier) {
         get_marker("restricted_capital"),
     ]);
 }
+
+// splitmix64, so generated scenarios are random but the same for a given seed
+pub struct SeededRng(u64);
+
+impl SeededRng {
+    pub fn new(seed: u64) -> Self {
+        SeededRng(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // uniform enough for test data within [low, high]
+    pub fn between(&mut self, low: u64, high: u64) -> u64 {
+        low + self.next_u64() % (high - low + 1)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct LargeRaise {
+    // sorted, matching the order subs are stored in
+    pub subscriptions: Vec<Addr>,
+    pub commitments: Vec<u64>,
+    pub capital_called: u128,
+}
+
+// accepts `count` subs, each with an lp, a commitment, commitment shares held and
+// an outstanding capital call for some of them, all drawn from the seed
+pub fn seed_large_raise(
+    storage: &mut dyn Storage,
+    querier: &mut ProvenanceMockQuerier,
+    count: usize,
+    seed: u64,
+) -> LargeRaise {
+    let state = config_read(storage).load().unwrap();
+    let mut rng = SeededRng::new(seed);
+    let mut raise = LargeRaise {
+        subscriptions: vec![],
+        commitments: vec![],
+        capital_called: 0,
+    };
+
+    for i in 0..count {
+        let subscription = Addr::unchecked(format!("sub_{:06}", i));
+        let lp = Addr::unchecked(format!("lp_{:06}", i));
+        let shares = rng.between(1, 10_000);
+        let commitment = shares * state.capital_per_share;
+
+        set_subscription_status(storage, &subscription, Some(SubscriptionStatus::Accepted))
+            .unwrap();
+        subscription_lp_storage(storage)
+            .save(subscription.as_bytes(), &lp)
+            .unwrap();
+        lp_subscription_storage(storage, &lp)
+            .save(subscription.as_bytes(), &subscription)
+            .unwrap();
+        subscription_stats_storage(storage)
+            .save(
+                subscription.as_bytes(),
+                &SubscriptionStats {
+                    capital_contributed: 0,
+                    capital_distributed: 0,
+                    shares_redeemed: 0,
+                    commitment,
+                    capital_contributed_by_denom: vec![],
+                },
+            )
+            .unwrap();
+        querier.base.update_balance(
+            subscription.clone(),
+            coins(shares.into(), &state.commitment_denom),
+        );
+
+        // roughly a third of subs have part of their commitment called
+        if rng.between(0, 2) == 0 {
+            let called = rng.between(1, shares) as i64;
+            let capital = called * state.capital_per_share as i64;
+            asset_exchange_storage(storage)
+                .save(
+                    subscription.as_bytes(),
+                    &vec![AssetExchange {
+                        investment: Some(called),
+                        commitment_in_shares: Some(-called),
+                        capital: Some(-capital),
+                        ..AssetExchange::default()
+                    }],
+                )
+                .unwrap();
+            raise.capital_called += capital as u128;
+        }
+
+        raise.subscriptions.push(subscription);
+        raise.commitments.push(commitment);
+    }
+
+    raise
+}
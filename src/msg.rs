@@ -1,19 +1,39 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::BTreeSet;
 
-use cosmwasm_std::Addr;
+use cosmwasm_std::{Addr, Coin, CosmosMsg, Uint128};
+use cw2::ContractVersion;
+use provwasm_std::{AccessGrant, MarkerAccess, ProvenanceMsg};
 
-use crate::state::State;
+use crate::state::ManagementFee;
+use crate::state::{AutoAcceptConfig, CapitalExchangeRate, DenomDecimals, FundMetadata};
+use crate::state::{PerformanceFee, SettlementHours, State, SubscriptionStatus};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
     pub subscription_code_id: u64,
     pub recovery_admin: Addr,
-    pub required_attestations: Vec<HashSet<String>>,
+    pub required_attestations: Vec<BTreeSet<String>>,
     pub capital_denom: String,
     pub capital_per_share: u64,
     pub required_capital_attribute: Option<String>,
+    pub due_grace_seconds: Option<u64>,
+    #[serde(default)]
+    pub escrow_capital: bool,
+    #[serde(default)]
+    pub permissionless_eligibility: bool,
+    #[serde(default)]
+    pub share_classes: Vec<String>,
+    // subscriptions already accepted by a legacy system
+    #[serde(default)]
+    pub initial_accepted_subscriptions: Vec<AcceptSubscription>,
+    #[serde(default)]
+    pub metadata: FundMetadata,
+    #[serde(default)]
+    pub min_initial_commitment: Option<u64>,
+    #[serde(default)]
+    pub denom_decimals: Vec<DenomDecimals>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -22,6 +42,9 @@ pub struct MigrateMsg {
     pub subscription_code_id: u64,
     pub capital_denom: Option<String>,
     pub required_capital_attribute: Option<String>,
+    pub due_grace_seconds: Option<u64>,
+    #[serde(default)]
+    pub denom_decimals: Vec<DenomDecimals>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -37,40 +60,273 @@ pub enum HandleMsg {
     Recover {
         gp: Addr,
     },
+    SetPause {
+        paused: bool,
+    },
+    SetAcceptingProposals {
+        accepting_proposals: bool,
+    },
+    RetryMarkerSetup {},
+    AddDelegate {
+        delegate: Addr,
+    },
+    RemoveDelegate {
+        delegate: Addr,
+    },
     UpdateRequiredAttestations {
-        required_attestations: Vec<HashSet<String>>,
+        required_attestations: Vec<BTreeSet<String>>,
+    },
+    UpdateComplianceContract {
+        compliance_contract: Option<Addr>,
+    },
+    UpdateMetadata {
+        metadata: FundMetadata,
+    },
+    UpdateMinInitialCommitment {
+        min_initial_commitment: Option<u64>,
+    },
+    PostNav {
+        per_share: u64,
+    },
+    UpdateMaxOwnership {
+        max_ownership_bps: Option<u16>,
+    },
+    UpdateAutoAccept {
+        auto_accept: Option<AutoAcceptConfig>,
+    },
+    UpdatePerformanceFee {
+        performance_fee: Option<PerformanceFee>,
+    },
+    CrystallizePerformanceFees {},
+    UpdateTreasury {
+        treasury: Option<Addr>,
+    },
+    ApplyTreasuryUpdate {},
+    CancelTreasuryUpdate {},
+    UpdateCapitalExchangeRates {
+        capital_exchange_rates: Vec<CapitalExchangeRate>,
+    },
+    UpdateApprovedDestinations {
+        approved_destinations: Option<BTreeSet<Addr>>,
+    },
+    UpdateObligationLimits {
+        max_outstanding_exchanges: Option<u64>,
+        max_outstanding_capital: Option<u64>,
+    },
+    UpdateMemoTransferEvents {
+        memo_transfer_events: bool,
+    },
+    UpdateTombstoneCodeId {
+        tombstone_code_id: Option<u64>,
+    },
+    UpdateProposalLimits {
+        proposal_cooldown_blocks: Option<u64>,
+        max_pending_per_lp: Option<u64>,
+    },
+    UpdateBatchCancelThreshold {
+        batch_cancel_cosign_threshold: Option<u64>,
+    },
+    UpdateDueGracePeriod {
+        due_grace_seconds: u64,
+    },
+    UpdateSettlementHours {
+        settlement_hours: Option<SettlementHours>,
+    },
+    UpdateGpCommittee {
+        gp_committee: Vec<Addr>,
+        approval_threshold: u32,
+        approval_capital_threshold: Option<u64>,
+    },
+    ApproveOperation {
+        id: u64,
+    },
+    OpenConsentProposal {
+        amendment_hash: String,
+        voting_seconds: u64,
+        threshold_bps: u16,
+        change: Option<Box<HandleMsg>>,
+    },
+    VoteConsent {
+        id: u64,
+        subscription: Addr,
+        approve: bool,
+    },
+    CloseConsentProposal {
+        id: u64,
+    },
+    UpdateManagementFee {
+        management_fee: Option<ManagementFee>,
+    },
+    ExtendFundTerm {
+        term_end: u64,
+    },
+    UpdateReceiptRetention {
+        max_receipts: Option<u64>,
+    },
+    UpdateHistoryRetention {
+        receipt_retention_seconds: Option<u64>,
+        change_log_retention: Option<u64>,
+    },
+    ArchiveHistory {
+        subscriptions: Vec<Addr>,
+    },
+    UpdateRequiredCapitalAttribute {
+        denom: String,
+        required_capital_attribute: Option<String>,
+    },
+    SetSubscriptionNotifications {
+        subscription: Addr,
+        enabled: bool,
     },
     MigrateSubscriptions {
-        subscriptions: HashSet<Addr>,
+        subscriptions: BTreeSet<Addr>,
     },
+    RetryFailedMigrations {},
     ProposeSubscription {
         initial_commitment: Option<u64>,
+        #[serde(default)]
+        owner: Option<Addr>,
+        #[serde(default)]
+        forward_funds: bool,
     },
     CloseSubscriptions {
-        subscriptions: HashSet<Addr>,
+        subscriptions: BTreeSet<Addr>,
+        treasury: Option<Addr>,
+        #[serde(default)]
+        retirement: Option<SubscriptionRetirement>,
     },
     IssueAssetExchanges {
         asset_exchanges: Vec<IssueAssetExchange>,
+        #[serde(default)]
+        notify_subscriptions: bool,
+        #[serde(default)]
+        verify_capital_denom: bool,
     },
     CancelAssetExchanges {
         cancellations: Vec<IssueAssetExchange>,
     },
+    CancelAssetExchange {
+        subscription: Addr,
+        exchange: ExchangeReference,
+    },
+    CancelBatch {
+        batch_id: u64,
+    },
     CompleteAssetExchange {
         exchanges: Vec<AssetExchange>,
         to: Option<Addr>,
         memo: Option<String>,
     },
+    QueueAssetExchange {
+        exchanges: Vec<AssetExchange>,
+        to: Option<Addr>,
+        memo: Option<String>,
+    },
+    ProcessQueue {
+        limit: u32,
+    },
+    SettleQueued {
+        sequence: u64,
+    },
+    // sent by a sub, with any funds settlement needs attached, so the gp can complete
+    // the matching exchanges for lps whose custodians can't execute contracts
+    AuthorizeGpSettlement {
+        exchanges_hash: String,
+        expires: u64,
+    },
+    RevokeGpSettlement {},
+    CompleteAssetExchangeFor {
+        subscription: Addr,
+        exchanges: Vec<AssetExchange>,
+        to: Option<Addr>,
+    },
+    PayCapitalCall {
+        exchange: AssetExchange,
+    },
+    RefreshEligibility {
+        subscription: Addr,
+    },
     UpdateEligibleSubscriptions {
         subscriptions: Vec<Addr>,
     },
     AcceptSubscriptions {
         subscriptions: Vec<AcceptSubscription>,
     },
+    TransferCommitment {
+        from_subscription: Addr,
+        to_subscription: Addr,
+        shares: u64,
+        #[serde(default)]
+        investment: Option<u64>,
+    },
     IssueWithdrawal {
         to: Addr,
         amount: u64,
         memo: Option<String>,
+        // a like capital denom to withdraw instead of the capital denom
+        #[serde(default)]
+        denom: Option<String>,
     },
+    ExpireCapitalCalls {},
+}
+
+// a closed sub's contract stays live unless it is retired as it closes
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SubscriptionRetirement {
+    // migrate it to the tombstone code id
+    Tombstone,
+    // ask it to disable itself
+    Disable,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct ClosedSubscription {
+    pub subscription: Addr,
+    pub closed_at: u64,
+    pub retirement: Option<SubscriptionRetirement>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MarkerSetupStep {
+    Create,
+    Grant,
+    Finalize,
+    Activate,
+}
+
+// in the order each marker is set up
+pub const MARKER_SETUP_STEPS: [MarkerSetupStep; 4] = [
+    MarkerSetupStep::Create,
+    MarkerSetupStep::Grant,
+    MarkerSetupStep::Finalize,
+    MarkerSetupStep::Activate,
+];
+
+// how far setup of one of the raise's markers got, and the first failure since
+// it was last attempted
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct MarkerSetup {
+    pub denom: String,
+    pub completed: Vec<MarkerSetupStep>,
+    pub failure: Option<String>,
+}
+
+impl MarkerSetup {
+    pub fn new(denom: String) -> Self {
+        MarkerSetup {
+            denom,
+            completed: vec![],
+            failure: None,
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        MARKER_SETUP_STEPS
+            .iter()
+            .all(|step| self.completed.contains(step))
+    }
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
@@ -79,13 +335,21 @@ pub struct AcceptSubscription {
     pub commitment_in_capital: u64,
 }
 
+// addresses an outstanding exchange without reproducing it
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExchangeReference {
+    Index(u32),
+    ExchangeId(String),
+}
+
 #[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct IssueAssetExchange {
     pub subscription: Addr,
     pub exchanges: Vec<AssetExchange>,
 }
 
-#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[derive(Deserialize, Serialize, Clone, Debug, Default, Eq, JsonSchema)]
 pub struct AssetExchange {
     #[serde(rename = "inv")]
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -102,6 +366,140 @@ pub struct AssetExchange {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
     pub date: Option<ExchangeDate>,
+    #[serde(rename = "att")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub required_attestation: Option<String>,
+    #[serde(rename = "id")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub exchange_id: Option<String>,
+    #[serde(rename = "fee")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub late_fee_bps: Option<u16>,
+    #[serde(rename = "cls")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub class: Option<String>,
+    #[serde(rename = "iss")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub provenance: Option<ExchangeProvenance>,
+    #[serde(rename = "prc")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub price: Option<ExchangePrice>,
+    // distributions pay out in this like capital denom instead of the capital denom
+    #[serde(rename = "dnm")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub denom: Option<String>,
+    // set once the late fee on a partly paid capital call is collected, so the
+    // remainder can still be paid late without being charged again
+    #[serde(rename = "lfp")]
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    #[serde(default)]
+    pub late_fee_paid: bool,
+}
+
+// provenance and a collected late fee are recorded by the raise, so exchanges
+// submitted without them still match the issued exchange
+impl PartialEq for AssetExchange {
+    fn eq(&self, other: &Self) -> bool {
+        self.investment == other.investment
+            && self.commitment_in_shares == other.commitment_in_shares
+            && self.capital == other.capital
+            && self.date == other.date
+            && self.required_attestation == other.required_attestation
+            && self.exchange_id == other.exchange_id
+            && self.late_fee_bps == other.late_fee_bps
+            && self.class == other.class
+            && self.price == other.price
+            && self.denom == other.denom
+    }
+}
+
+// capital left unset at issuance and worked out when the exchange completes
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub enum ExchangePrice {
+    #[serde(rename = "nav")]
+    Nav,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct ExchangeProvenance {
+    pub issued_by: Addr,
+    pub issued_at: u64,
+    // issuance call that created the exchange, so an erroneous batch can be found and cancelled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch: Option<u64>,
+}
+
+impl AssetExchange {
+    pub fn available_from(&self) -> Option<u64> {
+        match self.date {
+            Some(ExchangeDate::Available(available)) => Some(available),
+            Some(ExchangeDate::Window(available, _)) => Some(available),
+            _ => None,
+        }
+    }
+
+    pub fn due_by(&self) -> Option<u64> {
+        match self.date {
+            Some(ExchangeDate::Due(due)) => Some(due),
+            Some(ExchangeDate::Window(_, due)) => Some(due),
+            _ => None,
+        }
+    }
+
+    pub fn is_past_due(&self, epoch_seconds: u64, grace_seconds: u64) -> bool {
+        self.due_by()
+            .is_some_and(|due| due.saturating_add(grace_seconds) < epoch_seconds)
+    }
+
+    pub fn is_not_yet_available(&self, epoch_seconds: u64) -> bool {
+        self.available_from()
+            .is_some_and(|available| available > epoch_seconds)
+    }
+
+    pub fn is_available(&self, epoch_seconds: u64, grace_seconds: u64) -> bool {
+        (!self.is_past_due(epoch_seconds, grace_seconds) || self.accepts_late_payment())
+            && !self.is_not_yet_available(epoch_seconds)
+    }
+
+    // a capital call with a late fee can still be paid once past due, with the fee
+    pub fn accepts_late_payment(&self) -> bool {
+        self.is_capital_call() && self.late_fee_bps.is_some()
+    }
+
+    // additional capital owed on a capital call settled after its due date and grace period
+    pub fn late_fee(&self, epoch_seconds: u64, grace_seconds: u64) -> u64 {
+        match self.late_fee_bps {
+            Some(bps)
+                if !self.late_fee_paid
+                    && self.is_capital_call()
+                    && self.is_past_due(epoch_seconds, grace_seconds) =>
+            {
+                let capital = u128::from(self.capital.unwrap_or_default().unsigned_abs());
+                (capital * u128::from(bps)).div_ceil(10_000) as u64
+            }
+            _ => 0,
+        }
+    }
+
+    pub fn is_capital_call(&self) -> bool {
+        self.investment.unwrap_or_default() > 0 && self.capital.unwrap_or_default() < 0
+    }
+
+    pub fn is_redemption(&self) -> bool {
+        self.investment.unwrap_or_default() < 0
+            && (self.capital.unwrap_or_default() > 0 || self.price.is_some())
+    }
+
+    pub fn is_distribution(&self) -> bool {
+        self.investment.unwrap_or_default() == 0 && self.capital.unwrap_or_default() > 0
+    }
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
@@ -110,6 +508,31 @@ pub enum ExchangeDate {
     Due(u64),
     #[serde(rename = "avl")]
     Available(u64),
+    #[serde(rename = "win")]
+    Window(u64, u64),
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct CompletedAssetExchange {
+    pub exchange: AssetExchange,
+    pub to: Option<Addr>,
+    pub height: u64,
+    pub time: u64,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct QueuedSettlement {
+    pub subscription: Addr,
+    pub exchanges: Vec<AssetExchange>,
+    pub to: Option<Addr>,
+    pub memo: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SettlementAuthorization {
+    pub exchanges_hash: String,
+    pub expires: u64,
+    pub funds: Vec<Coin>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -117,13 +540,385 @@ pub enum ExchangeDate {
 pub enum QueryMsg {
     GetState {},
     GetAllAssetExchanges {},
-    GetAssetExchangesForSubscription { subscription: Addr },
+    GetAssetExchangesForSubscription {
+        subscription: Addr,
+    },
+    GetCompletedExchanges {
+        subscription: Addr,
+    },
+    HasClaimables {
+        subscription: Addr,
+    },
+    GetInstantiateTerms {},
+    GetSubscriptionVersions {},
+    GetPrepayment {
+        subscription: Addr,
+    },
+    GetSettlementWindows {},
+    GetDueCapitalCalls {
+        within_seconds: u64,
+    },
+    GetBatch {
+        batch_id: u64,
+    },
+    GetClaimables {
+        subscription: Addr,
+    },
+    CheckCapitalTransfer {
+        from: Addr,
+        to: Addr,
+        denom: String,
+    },
+    GetEscrow {
+        subscription: Addr,
+    },
+    GetMigrationFailures {},
+    GetChanges {
+        since_revision: u64,
+    },
+    GetRaiseTotals {},
+    GetReceipts {
+        subscription: Addr,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    GetArchiveCheckpoint {
+        subscription: Option<Addr>,
+    },
+    AuditMarkerPermissions {},
+    AuditSubscription {
+        subscription: Addr,
+    },
+    GetPendingOperations {},
+    GetConsentProposal {
+        id: u64,
+    },
+    GetHoldings {},
+    GetSubscriptionStats {
+        subscription: Addr,
+    },
+    GetSubscriptionCodeIds {},
+    GetInfo {},
+    GetSubscriptions {
+        status: SubscriptionStatus,
+        start_after: Option<Addr>,
+        limit: Option<u32>,
+    },
+    GetSubscriptionForLp {
+        lp: Addr,
+    },
+    GetLpForSubscription {
+        subscription: Addr,
+    },
+    GetClosedSubscriptions {
+        start_after: Option<Addr>,
+        limit: Option<u32>,
+    },
+    GetMarkerSetup {},
+    // scoped to a sub and checked against its recorded lp, for lp self-service
+    GetOwnCapitalAccount {
+        lp: Addr,
+        subscription: Addr,
+    },
+    GetOwnReceipts {
+        lp: Addr,
+        subscription: Addr,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    GetNavHistory {
+        from: Option<u64>,
+        to: Option<u64>,
+        limit: Option<u32>,
+    },
+    GetPerformanceFees {
+        from: Option<u64>,
+        limit: Option<u32>,
+    },
+    Batch {
+        queries: Vec<QueryMsg>,
+    },
+    SimulateCompleteAssetExchange {
+        subscription: Addr,
+        exchanges: Vec<AssetExchange>,
+        to: Option<Addr>,
+        #[serde(default)]
+        funds: Vec<Coin>,
+    },
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CompletionSimulation {
+    pub messages: Vec<CosmosMsg<ProvenanceMsg>>,
+    pub error: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingOperation {
+    pub id: u64,
+    pub operation: HandleMsg,
+    pub proposed_by: Addr,
+    pub approvals: Vec<Addr>,
+}
+
+// a request for lp consent to an amendment, identified by the hash of its text
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ConsentProposal {
+    pub id: u64,
+    pub amendment_hash: String,
+    pub closes_at: u64,
+    pub threshold_bps: u16,
+    // applied as the gp once consent is given
+    pub change: Option<HandleMsg>,
+    pub shares_for: Uint128,
+    pub shares_against: Uint128,
+    pub result: Option<ConsentResult>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsentResult {
+    Passed,
+    Rejected,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct ConsentVote {
+    pub approve: bool,
+    pub shares: Uint128,
+}
+
+// performance fee owed to the gp for a class's nav gain above its prior high-water mark
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct PerformanceFeeAccrual {
+    pub investment_denom: String,
+    pub high_water_mark: u64,
+    pub nav_per_share: u64,
+    pub shares: u64,
+    pub fee: u64,
+    pub crystallized_at: u64,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Receipt {
+    pub sequence: u64,
+    pub exchange_id: Option<String>,
+    pub height: u64,
+    pub time: u64,
+    pub tx_index: Option<u32>,
+    pub investment: i64,
+    pub commitment_in_shares: i64,
+    pub capital: i64,
+    pub destination: Addr,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq, Eq, JsonSchema)]
+pub struct ArchiveCheckpoint {
+    pub hash: String,
+    pub archived: u64,
+    pub through: u64,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Claimables {
+    pub exchange: bool,
+    pub distribution: bool,
+    pub redemption: bool,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct ClaimableItem {
+    pub exchange: AssetExchange,
+    pub available: bool,
+    pub past_due: bool,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct ClaimableItems {
+    pub capital_calls: Vec<ClaimableItem>,
+    pub distributions: Vec<ClaimableItem>,
+    pub redemptions: Vec<ClaimableItem>,
+    pub asset_exchanges: Vec<ClaimableItem>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MarkerPermissionAudit {
+    pub intact: bool,
+    pub markers: Vec<MarkerAudit>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MarkerAudit {
+    pub denom: String,
+    pub found: bool,
+    pub missing: Vec<MarkerAccess>,
+    pub unexpected: Vec<AccessGrant>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct SubscriptionAudit {
+    pub subscription: Addr,
+    pub found: bool,
+    pub consistent: bool,
+    pub mismatches: Vec<SubscriptionMismatch>,
+}
+
+// a sub field that no longer agrees with the raise, with both sides' values
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct SubscriptionMismatch {
+    pub field: String,
+    pub raise: String,
+    pub subscription: String,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct CapitalTransferCheck {
+    pub from: bool,
+    pub to: bool,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct SettlementWindow {
+    pub exchange: AssetExchange,
+    pub earliest_settle: Option<u64>,
+    pub latest_settle: Option<u64>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct SubscriptionSettlementWindows {
+    #[serde(rename = "sub")]
+    pub subscription: Addr,
+    pub windows: Vec<SettlementWindow>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct DueCapitalCall {
+    #[serde(rename = "sub")]
+    pub subscription: Addr,
+    pub exchange_id: Option<String>,
+    pub capital: u64,
+    pub due: u64,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangedEntity {
+    State,
+    Subscription,
+    AssetExchange,
+    CompletedExchange,
+    Prepayment,
+    Escrow,
+    MigrationFailure,
+    Receipt,
+    SettlementQueue,
+    PendingOperation,
+    SubscriptionStats,
+    PerformanceFee,
+    ConsentProposal,
+    SettlementAuthorization,
+    MarkerSetup,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Change {
+    pub revision: u64,
+    pub entity: ChangedEntity,
+    pub key: String,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Changes {
+    pub revision: u64,
+    pub changes: Vec<Change>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct RaiseTotals {
+    pub commitment_shares: Uint128,
+    pub investment_shares: Uint128,
+    pub unclaimed_capital_called: Uint128,
+    pub pending_subscriptions: u64,
+    pub eligible_subscriptions: u64,
+    pub accepted_subscriptions: u64,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Holdings {
+    pub height: u64,
+    pub subscriptions: Vec<SubscriptionHoldings>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct SubscriptionHoldings {
+    pub subscription: Addr,
+    pub commitment: Uint128,
+    pub investment: Uint128,
+    pub ownership_bps: u64,
+}
+
+// enough to identify the raise and its denoms without loading subscription sets
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RaiseInfo {
+    pub contract: ContractVersion,
+    pub commitment_denom: String,
+    pub investment_denoms: Vec<String>,
+    pub capital_denoms: Vec<String>,
+    pub capital_per_share: u64,
+}
+
+// the block height a code id was activated at, unknown for ids that predate tracking
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct SubscriptionCodeId {
+    pub code_id: u64,
+    pub activated_at: Option<u64>,
+}
+
+// lifetime totals of the exchanges a subscription has completed
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct SubscriptionStats {
+    pub capital_contributed: u64,
+    pub capital_distributed: u64,
+    pub shares_redeemed: u64,
+    pub commitment: u64,
+    // what was actually paid in, so funds accepting like denoms can report by currency
+    #[serde(default)]
+    pub capital_contributed_by_denom: Vec<Coin>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CapitalAccount {
+    #[serde(rename = "sub")]
+    pub subscription: Addr,
+    pub stats: SubscriptionStats,
+    pub escrow: u64,
+    pub prepayment: u64,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct MigrationFailure {
+    pub subscription: Addr,
+    pub error: String,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct SubscriptionVersion {
+    pub subscription: Addr,
+    pub version: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct SubscriptionAssetExchanges {
+    #[serde(rename = "sub")]
+    pub subscription: Addr,
+    pub exchanges: Vec<AssetExchange>,
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct RaiseState {
     pub general: State,
-    pub pending_subscriptions: HashSet<Addr>,
-    pub eligible_subscriptions: HashSet<Addr>,
-    pub accepted_subscriptions: HashSet<Addr>,
+    // sorted by address
+    pub pending_subscriptions: Vec<Addr>,
+    pub eligible_subscriptions: Vec<Addr>,
+    pub accepted_subscriptions: Vec<Addr>,
 }
@@ -0,0 +1,113 @@
+use cosmwasm_std::{Addr, Deps, StdResult, Uint128};
+use provwasm_std::{ProvenanceQuerier, ProvenanceQuery};
+
+use crate::error::ContractError;
+use crate::state::State;
+
+// commitment converts into investment one for one, so a position counts both
+// and capital calls leave it unchanged
+pub fn position(
+    deps: Deps<ProvenanceQuery>,
+    state: &State,
+    subscription: &Addr,
+) -> StdResult<u128> {
+    let mut shares = deps
+        .querier
+        .query_balance(subscription, &state.commitment_denom)?
+        .amount
+        .u128();
+    for denom in state.investment_denoms() {
+        shares += deps
+            .querier
+            .query_balance(subscription, denom)?
+            .amount
+            .u128();
+    }
+    Ok(shares)
+}
+
+pub fn total_shares(deps: Deps<ProvenanceQuery>, state: &State) -> StdResult<u128> {
+    let querier = ProvenanceQuerier::new(&deps.querier);
+    let supply = |denom: &str| -> StdResult<u128> {
+        Ok((Uint128::new(1) * querier.get_marker_by_denom(denom)?.total_supply).u128())
+    };
+
+    let mut shares = supply(&state.commitment_denom)?;
+    for denom in state.investment_denoms() {
+        shares += supply(denom)?;
+    }
+    Ok(shares)
+}
+
+pub fn ownership_bps(position: u128, total: u128) -> u64 {
+    if total == 0 {
+        return 0;
+    }
+    (position * 10_000 / total) as u64
+}
+
+// checks each sub's position against the cap once all of the increases have landed
+pub fn check_ownership(
+    deps: Deps<ProvenanceQuery>,
+    state: &State,
+    increases: &[(&Addr, u128)],
+) -> Result<(), ContractError> {
+    if state.max_ownership_bps.is_none() {
+        return Ok(());
+    }
+
+    let total =
+        total_shares(deps, state)? + increases.iter().map(|(_, shares)| shares).sum::<u128>();
+    check_positions(deps, state, increases, total)
+}
+
+// shares moved between subs leave the fund's total unchanged
+pub fn check_transfer_ownership(
+    deps: Deps<ProvenanceQuery>,
+    state: &State,
+    to: &Addr,
+    shares: u128,
+) -> Result<(), ContractError> {
+    if state.max_ownership_bps.is_none() {
+        return Ok(());
+    }
+
+    let total = total_shares(deps, state)?;
+    check_positions(deps, state, &[(to, shares)], total)
+}
+
+fn check_positions(
+    deps: Deps<ProvenanceQuery>,
+    state: &State,
+    increases: &[(&Addr, u128)],
+    total: u128,
+) -> Result<(), ContractError> {
+    let max_ownership_bps = match state.max_ownership_bps {
+        Some(max_ownership_bps) => max_ownership_bps,
+        None => return Ok(()),
+    };
+
+    for (subscription, shares) in increases {
+        let ownership_bps = ownership_bps(position(deps, state, subscription)? + shares, total);
+        if ownership_bps > u64::from(max_ownership_bps) {
+            return Err(ContractError::OwnershipLimitExceeded {
+                subscription: (*subscription).clone(),
+                ownership_bps,
+                max_ownership_bps,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ownership_bps_of_total() {
+        assert_eq!(0, ownership_bps(100, 0));
+        assert_eq!(2_500, ownership_bps(210, 840));
+    }
+}
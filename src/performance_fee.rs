@@ -0,0 +1,153 @@
+use cosmwasm_std::{DepsMut, Env, MessageInfo, Response, Uint128};
+use provwasm_std::{ProvenanceQuerier, ProvenanceQuery};
+use std::convert::TryFrom;
+
+use crate::contract::ContractResponse;
+use crate::error::ContractError;
+use crate::msg::{ChangedEntity, PerformanceFeeAccrual};
+use crate::state::{config_read, record_change, HighWaterMark};
+use crate::state::{
+    high_water_mark_storage, high_water_mark_storage_read, performance_fee_storage,
+};
+
+pub fn try_crystallize_performance_fees(
+    deps: DepsMut<ProvenanceQuery>,
+    env: Env,
+    info: MessageInfo,
+) -> ContractResponse {
+    let state = config_read(deps.storage).load()?;
+
+    if info.sender != state.gp {
+        return Err(ContractError::Unauthorized {
+            required: "gp",
+            action: "crystallize performance fees",
+        });
+    }
+
+    let performance_fee = state
+        .performance_fee
+        .clone()
+        .ok_or(ContractError::InvalidRequest {
+            reason: "performance fee not configured",
+        })?;
+    let nav = state.nav.clone().ok_or(ContractError::NavNotPosted {})?;
+    let now = env.block.time.seconds();
+    let querier = ProvenanceQuerier::new(&deps.querier);
+    let mark = HighWaterMark {
+        nav_per_share: nav.per_share,
+        crystallized_at: now,
+    };
+
+    let mut response = Response::new();
+    for denom in state.investment_denoms() {
+        let high_water_mark =
+            match high_water_mark_storage_read(deps.storage).may_load(denom.as_bytes())? {
+                Some(high_water_mark) => high_water_mark,
+                // a class's first crystallization only sets the mark it's measured against
+                None => {
+                    high_water_mark_storage(deps.storage).save(denom.as_bytes(), &mark)?;
+                    continue;
+                }
+            };
+
+        if performance_fee
+            .min_interval_seconds
+            .is_some_and(|interval| now < high_water_mark.crystallized_at.saturating_add(interval))
+            || nav.per_share <= high_water_mark.nav_per_share
+        {
+            continue;
+        }
+
+        let shares = (Uint128::new(1) * querier.get_marker_by_denom(denom)?.total_supply).u128();
+        let fee = u128::from(nav.per_share - high_water_mark.nav_per_share)
+            * shares
+            * u128::from(performance_fee.fee_bps)
+            / 10_000;
+        let accrual = PerformanceFeeAccrual {
+            investment_denom: denom.clone(),
+            high_water_mark: high_water_mark.nav_per_share,
+            nav_per_share: nav.per_share,
+            shares: u64::try_from(shares)?,
+            fee: u64::try_from(fee)?,
+            crystallized_at: now,
+        };
+
+        let key = [&now.to_be_bytes(), denom.as_bytes()].concat();
+        performance_fee_storage(deps.storage).save(&key, &accrual)?;
+        record_change(deps.storage, ChangedEntity::PerformanceFee, denom)?;
+        high_water_mark_storage(deps.storage).save(denom.as_bytes(), &mark)?;
+
+        response = response.add_attribute("performance_fee", format!("{denom}:{fee}"));
+    }
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::execute;
+    use crate::contract::tests::default_deps;
+    use crate::mock::load_markers;
+    use crate::msg::HandleMsg;
+    use crate::state::{performance_fee_storage_read, PerformanceFee};
+    use cosmwasm_std::testing::{mock_env, mock_info};
+    use cosmwasm_std::Order;
+
+    #[test]
+    fn crystallize_above_high_water_mark() {
+        let mut deps = default_deps(Some(|state| {
+            state.performance_fee = Some(PerformanceFee {
+                fee_bps: 2_000,
+                min_interval_seconds: Some(86_400),
+            });
+        }));
+        load_markers(&mut deps.querier);
+        let mut env = mock_env();
+        let mut post_and_crystallize = |env: &Env, per_share| {
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("gp", &[]),
+                HandleMsg::PostNav { per_share },
+            )
+            .unwrap();
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("gp", &[]),
+                HandleMsg::CrystallizePerformanceFees {},
+            )
+            .unwrap()
+        };
+
+        // verify the first crystallization sets the mark without a fee
+        let res = post_and_crystallize(&env, 100);
+        assert!(res.attributes.is_empty());
+
+        // verify gains within the interval wait for it to pass
+        env.block.time = env.block.time.plus_seconds(3_600);
+        let res = post_and_crystallize(&env, 110);
+        assert!(res.attributes.is_empty());
+
+        env.block.time = env.block.time.plus_seconds(86_400);
+        let res = post_and_crystallize(&env, 110);
+        assert_eq!("investment_coin:840", res.attributes[0].value);
+
+        // verify a recovery back to the mark owes nothing
+        env.block.time = env.block.time.plus_seconds(86_400);
+        post_and_crystallize(&env, 90);
+        env.block.time = env.block.time.plus_seconds(86_400);
+        let res = post_and_crystallize(&env, 110);
+        assert!(res.attributes.is_empty());
+
+        let accruals = performance_fee_storage_read(&deps.storage)
+            .range(None, None, Order::Ascending)
+            .map(|item| item.unwrap().1)
+            .collect::<Vec<PerformanceFeeAccrual>>();
+        assert_eq!(1, accruals.len());
+        assert_eq!(100, accruals[0].high_water_mark);
+        assert_eq!(420, accruals[0].shares);
+        assert_eq!(840, accruals[0].fee);
+    }
+}
@@ -1,34 +1,110 @@
-use cosmwasm_std::{entry_point, to_binary, Addr, Binary, Deps, Env, StdResult};
-use provwasm_std::ProvenanceQuery;
-use schemars::JsonSchema;
-use serde::Serialize;
+use cosmwasm_std::{
+    from_slice, to_binary, Addr, Binary, Deps, Env, MessageInfo, Order, StdError, StdResult,
+    Uint128,
+};
+use cw2::{get_contract_version, ContractVersion, CONTRACT};
+use provwasm_std::{AccessGrant, MarkerAccess, ProvenanceQuerier, ProvenanceQuery};
 
-use crate::msg::{AssetExchange, QueryMsg, RaiseState};
+use crate::exchange_asset::{batch_exchanges, plan_completion};
+use crate::msg::{
+    AssetExchange, CapitalAccount, CapitalTransferCheck, Change, Changes, ClaimableItem,
+    ClaimableItems, Claimables, ClosedSubscription, CompletionSimulation, DueCapitalCall, Holdings,
+    MarkerAudit, MarkerPermissionAudit, MigrationFailure, PendingOperation, PerformanceFeeAccrual,
+    QueryMsg, RaiseInfo, RaiseState, RaiseTotals, Receipt, SettlementWindow,
+    SubscriptionAssetExchanges, SubscriptionAudit, SubscriptionHoldings, SubscriptionMismatch,
+    SubscriptionSettlementWindows, SubscriptionVersion,
+};
+use crate::ownership::{ownership_bps, position, total_shares};
+use crate::settlement::has_capital_attribute;
 use crate::state::{
-    accepted_subscriptions_read, asset_exchange_storage_read, config_read,
-    eligible_subscriptions_read, pending_subscriptions_read,
+    asset_exchange_storage_read, change_log_checkpoint_read, change_log_read, change_revision_read,
+    closed_subscription_storage_read, completed_exchange_storage_read, config_read,
+    consent_proposal_storage_read, escrow_storage_read, instantiate_terms_read,
+    lp_subscription_storage_read, marker_setup_read, migration_failure_storage_read,
+    nav_history_read, pending_operation_storage_read, performance_fee_storage_read,
+    prepayment_storage_read, receipt_checkpoint_storage_read, receipt_storage_read,
+    subscription_code_ids_read, subscription_index_read, subscription_lp_storage_read,
+    subscription_stats_storage_read, subscription_status, subscriptions_with_status, NetAssetValue,
+    SubscriptionStatus, DEFAULT_NAV_HISTORY_LIMIT, DEFAULT_PERFORMANCE_FEE_LIMIT,
+    DEFAULT_RECEIPT_LIMIT, DEFAULT_SUBSCRIPTION_LIMIT, MAX_BATCH_QUERIES, MAX_CHANGES,
+    MAX_NAV_HISTORY_LIMIT, MAX_PERFORMANCE_FEE_LIMIT, MAX_RECEIPT_LIMIT, MAX_SUBSCRIPTION_LIMIT,
 };
+use crate::sub_msg::{SubQueryMsg, SubState};
 
-#[entry_point]
-pub fn query(deps: Deps<ProvenanceQuery>, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+#[cfg_attr(not(feature = "library"), cosmwasm_std::entry_point)]
+pub fn query(deps: Deps<ProvenanceQuery>, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::GetState {} => to_binary(&RaiseState {
             general: config_read(deps.storage).load()?,
-            pending_subscriptions: pending_subscriptions_read(deps.storage)
-                .may_load()?
-                .unwrap_or_default(),
-            eligible_subscriptions: eligible_subscriptions_read(deps.storage)
-                .may_load()?
-                .unwrap_or_default(),
-            accepted_subscriptions: accepted_subscriptions_read(deps.storage)
+            pending_subscriptions: subscriptions_with_status(
+                deps.storage,
+                SubscriptionStatus::Pending,
+            )?,
+            eligible_subscriptions: subscriptions_with_status(
+                deps.storage,
+                SubscriptionStatus::Eligible,
+            )?,
+            accepted_subscriptions: subscriptions_with_status(
+                deps.storage,
+                SubscriptionStatus::Accepted,
+            )?,
+        }),
+        QueryMsg::GetSubscriptions {
+            status,
+            start_after,
+            limit,
+        } => {
+            let limit = limit
+                .unwrap_or(DEFAULT_SUBSCRIPTION_LIMIT)
+                .min(MAX_SUBSCRIPTION_LIMIT) as usize;
+            // a trailing zero byte sorts the start just after the given address
+            let start = start_after.map(|subscription| {
+                let mut key = subscription.as_bytes().to_vec();
+                key.push(0);
+                key
+            });
+            let subscriptions = subscription_index_read(deps.storage, status)
+                .range(start.as_deref(), None, Order::Ascending)
+                .take(limit)
+                .map(|item| item.map(|(_, subscription)| subscription))
+                .collect::<StdResult<Vec<Addr>>>()?;
+            to_binary(&subscriptions)
+        }
+        // an lp may hold more than one sub, so every sub proposed by the lp is returned
+        QueryMsg::GetSubscriptionForLp { lp } => {
+            let subscriptions = lp_subscription_storage_read(deps.storage, &lp)
+                .range(None, None, Order::Ascending)
+                .map(|item| item.map(|(_, subscription)| subscription))
+                .collect::<StdResult<Vec<Addr>>>()?;
+            to_binary(&subscriptions)
+        }
+        QueryMsg::GetMarkerSetup {} => to_binary(
+            &marker_setup_read(deps.storage)
                 .may_load()?
                 .unwrap_or_default(),
-        }),
+        ),
+        QueryMsg::GetClosedSubscriptions { start_after, limit } => {
+            let limit = limit
+                .unwrap_or(DEFAULT_SUBSCRIPTION_LIMIT)
+                .min(MAX_SUBSCRIPTION_LIMIT) as usize;
+            let start = start_after.map(|subscription| {
+                let mut key = subscription.as_bytes().to_vec();
+                key.push(0);
+                key
+            });
+            let closed = closed_subscription_storage_read(deps.storage)
+                .range(start.as_deref(), None, Order::Ascending)
+                .take(limit)
+                .map(|item| item.map(|(_, closed)| closed))
+                .collect::<StdResult<Vec<ClosedSubscription>>>()?;
+            to_binary(&closed)
+        }
+        QueryMsg::GetLpForSubscription { subscription } => to_binary(
+            &subscription_lp_storage_read(deps.storage).may_load(subscription.as_bytes())?,
+        ),
         QueryMsg::GetAllAssetExchanges {} => {
             let all_asset_exchanges: Vec<SubscriptionAssetExchanges> =
-                accepted_subscriptions_read(deps.storage)
-                    .may_load()?
-                    .unwrap_or_default()
+                subscriptions_with_status(deps.storage, SubscriptionStatus::Accepted)?
                     .into_iter()
                     .map(|subscription| SubscriptionAssetExchanges {
                         subscription: subscription.clone(),
@@ -44,26 +120,763 @@ pub fn query(deps: Deps<ProvenanceQuery>, _env: Env, msg: QueryMsg) -> StdResult
         QueryMsg::GetAssetExchangesForSubscription { subscription } => {
             to_binary(&asset_exchange_storage_read(deps.storage).may_load(subscription.as_bytes())?)
         }
+        QueryMsg::GetCompletedExchanges { subscription } => to_binary(
+            &completed_exchange_storage_read(deps.storage)
+                .may_load(subscription.as_bytes())?
+                .unwrap_or_default(),
+        ),
+        QueryMsg::GetInstantiateTerms {} => {
+            to_binary(&instantiate_terms_read(deps.storage).may_load()?)
+        }
+        QueryMsg::GetSubscriptionVersions {} => {
+            let versions: Vec<SubscriptionVersion> =
+                subscriptions_with_status(deps.storage, SubscriptionStatus::Accepted)?
+                    .into_iter()
+                    .map(|subscription| SubscriptionVersion {
+                        version: subscription_version(deps, &subscription),
+                        subscription,
+                    })
+                    .collect();
+
+            to_binary(&versions)
+        }
+        QueryMsg::GetPrepayment { subscription } => to_binary(
+            &prepayment_storage_read(deps.storage)
+                .may_load(subscription.as_bytes())?
+                .unwrap_or_default(),
+        ),
+        QueryMsg::GetRaiseTotals {} => {
+            let state = config_read(deps.storage).load()?;
+            let querier = ProvenanceQuerier::new(&deps.querier);
+            let supply = |denom: &str| -> StdResult<Uint128> {
+                Ok(Uint128::new(1) * querier.get_marker_by_denom(denom)?.total_supply)
+            };
+            let pending = subscriptions_with_status(deps.storage, SubscriptionStatus::Pending)?;
+            let eligible = subscriptions_with_status(deps.storage, SubscriptionStatus::Eligible)?;
+            let accepted = subscriptions_with_status(deps.storage, SubscriptionStatus::Accepted)?;
+
+            let mut unclaimed_capital_called: u128 = 0;
+            for subscription in &accepted {
+                unclaimed_capital_called += asset_exchange_storage_read(deps.storage)
+                    .may_load(subscription.as_bytes())?
+                    .unwrap_or_default()
+                    .iter()
+                    .filter(|exchange| exchange.is_capital_call())
+                    .map(|exchange| u128::from(exchange.capital.unwrap_or_default().unsigned_abs()))
+                    .sum::<u128>();
+            }
+
+            to_binary(&RaiseTotals {
+                commitment_shares: supply(&state.commitment_denom)?,
+                investment_shares: supply(&state.investment_denom)?,
+                unclaimed_capital_called: Uint128::new(unclaimed_capital_called),
+                pending_subscriptions: pending.len() as u64,
+                eligible_subscriptions: eligible.len() as u64,
+                accepted_subscriptions: accepted.len() as u64,
+            })
+        }
+        QueryMsg::GetSubscriptionCodeIds {} => to_binary(
+            &subscription_code_ids_read(deps.storage)
+                .may_load()?
+                .unwrap_or_default(),
+        ),
+        QueryMsg::GetInfo {} => {
+            let state = config_read(deps.storage).load()?;
+
+            // the capital denom comes first, followed by like capital denoms
+            let capital_denoms = std::iter::once(&state.capital_denom)
+                .chain(state.capital_exchange_rates.iter().map(|rate| &rate.denom))
+                .cloned()
+                .collect();
+
+            to_binary(&RaiseInfo {
+                contract: get_contract_version(deps.storage)?,
+                commitment_denom: state.commitment_denom.clone(),
+                investment_denoms: state.investment_denoms().into_iter().cloned().collect(),
+                capital_denoms,
+                capital_per_share: state.capital_per_share,
+            })
+        }
+        QueryMsg::GetNavHistory { from, to, limit } => {
+            let limit = limit
+                .unwrap_or(DEFAULT_NAV_HISTORY_LIMIT)
+                .min(MAX_NAV_HISTORY_LIMIT) as usize;
+            let start = from.map(|from| from.to_be_bytes());
+            let end = to.map(|to| to.saturating_add(1).to_be_bytes());
+            let history = nav_history_read(deps.storage)
+                .range(
+                    start.as_ref().map(|s| &s[..]),
+                    end.as_ref().map(|e| &e[..]),
+                    Order::Ascending,
+                )
+                .take(limit)
+                .map(|item| item.map(|(_, nav)| nav))
+                .collect::<StdResult<Vec<NetAssetValue>>>()?;
+            to_binary(&history)
+        }
+        QueryMsg::GetPerformanceFees { from, limit } => {
+            let limit = limit
+                .unwrap_or(DEFAULT_PERFORMANCE_FEE_LIMIT)
+                .min(MAX_PERFORMANCE_FEE_LIMIT) as usize;
+            let start = from.map(|from| from.to_be_bytes());
+            let accruals = performance_fee_storage_read(deps.storage)
+                .range(start.as_ref().map(|s| &s[..]), None, Order::Ascending)
+                .take(limit)
+                .map(|item| item.map(|(_, accrual)| accrual))
+                .collect::<StdResult<Vec<PerformanceFeeAccrual>>>()?;
+            to_binary(&accruals)
+        }
+        QueryMsg::GetSubscriptionStats { subscription } => to_binary(
+            &subscription_stats_storage_read(deps.storage)
+                .may_load(subscription.as_bytes())?
+                .unwrap_or_default(),
+        ),
+        QueryMsg::GetHoldings {} => {
+            let state = config_read(deps.storage).load()?;
+            let total = total_shares(deps, &state)?;
+            let subscriptions =
+                subscriptions_with_status(deps.storage, SubscriptionStatus::Accepted)?
+                    .into_iter()
+                    .map(|subscription| {
+                        Ok(SubscriptionHoldings {
+                            commitment: deps
+                                .querier
+                                .query_balance(&subscription, &state.commitment_denom)?
+                                .amount,
+                            investment: deps
+                                .querier
+                                .query_balance(&subscription, &state.investment_denom)?
+                                .amount,
+                            ownership_bps: ownership_bps(
+                                position(deps, &state, &subscription)?,
+                                total,
+                            ),
+                            subscription,
+                        })
+                    })
+                    .collect::<StdResult<Vec<SubscriptionHoldings>>>()?;
+
+            to_binary(&Holdings {
+                height: env.block.height,
+                subscriptions,
+            })
+        }
+        QueryMsg::GetChanges { since_revision } => {
+            let changes = change_log_read(deps.storage)
+                .range(
+                    Some(&since_revision.saturating_add(1).to_be_bytes()),
+                    None,
+                    Order::Ascending,
+                )
+                .take(MAX_CHANGES)
+                .map(|item| item.map(|(_, change)| change))
+                .collect::<StdResult<Vec<Change>>>()?;
+
+            to_binary(&Changes {
+                revision: change_revision_read(deps.storage)
+                    .may_load()?
+                    .unwrap_or_default(),
+                changes,
+            })
+        }
+        QueryMsg::GetReceipts {
+            subscription,
+            start_after,
+            limit,
+        } => to_binary(&receipts(deps, &subscription, start_after, limit)?),
+        QueryMsg::GetOwnCapitalAccount { lp, subscription } => {
+            check_lp(deps, &lp, &subscription)?;
+            to_binary(&CapitalAccount {
+                stats: subscription_stats_storage_read(deps.storage)
+                    .may_load(subscription.as_bytes())?
+                    .unwrap_or_default(),
+                escrow: escrow_storage_read(deps.storage)
+                    .may_load(subscription.as_bytes())?
+                    .unwrap_or_default(),
+                prepayment: prepayment_storage_read(deps.storage)
+                    .may_load(subscription.as_bytes())?
+                    .unwrap_or_default(),
+                subscription,
+            })
+        }
+        QueryMsg::GetOwnReceipts {
+            lp,
+            subscription,
+            start_after,
+            limit,
+        } => {
+            check_lp(deps, &lp, &subscription)?;
+            to_binary(&receipts(deps, &subscription, start_after, limit)?)
+        }
+        QueryMsg::GetArchiveCheckpoint { subscription } => to_binary(
+            &match subscription {
+                Some(subscription) => receipt_checkpoint_storage_read(deps.storage)
+                    .may_load(subscription.as_bytes())?,
+                None => change_log_checkpoint_read(deps.storage).may_load()?,
+            }
+            .unwrap_or_default(),
+        ),
+        QueryMsg::GetMigrationFailures {} => {
+            let failures = migration_failure_storage_read(deps.storage)
+                .range(None, None, Order::Ascending)
+                .map(|item| {
+                    item.map(|(key, error)| MigrationFailure {
+                        subscription: Addr::unchecked(String::from_utf8_lossy(&key)),
+                        error,
+                    })
+                })
+                .collect::<StdResult<Vec<MigrationFailure>>>()?;
+
+            to_binary(&failures)
+        }
+        QueryMsg::GetEscrow { subscription } => to_binary(
+            &escrow_storage_read(deps.storage)
+                .may_load(subscription.as_bytes())?
+                .unwrap_or_default(),
+        ),
+        QueryMsg::GetSettlementWindows {} => {
+            let all_windows: Vec<SubscriptionSettlementWindows> =
+                subscriptions_with_status(deps.storage, SubscriptionStatus::Accepted)?
+                    .into_iter()
+                    .map(|subscription| SubscriptionSettlementWindows {
+                        windows: asset_exchange_storage_read(deps.storage)
+                            .may_load(subscription.as_bytes())
+                            .unwrap()
+                            .unwrap_or_default()
+                            .into_iter()
+                            .map(|exchange| SettlementWindow {
+                                earliest_settle: exchange.available_from(),
+                                latest_settle: exchange.due_by(),
+                                exchange,
+                            })
+                            .collect(),
+                        subscription,
+                    })
+                    .collect();
+
+            to_binary(&all_windows)
+        }
+        QueryMsg::GetDueCapitalCalls { within_seconds } => {
+            let now = env.block.time.seconds();
+            let until = now.saturating_add(within_seconds);
+            let mut due_calls = vec![];
+            for subscription in
+                subscriptions_with_status(deps.storage, SubscriptionStatus::Accepted)?
+            {
+                for exchange in asset_exchange_storage_read(deps.storage)
+                    .may_load(subscription.as_bytes())?
+                    .unwrap_or_default()
+                {
+                    match exchange.due_by() {
+                        Some(due) if exchange.is_capital_call() && (now..=until).contains(&due) => {
+                            due_calls.push(DueCapitalCall {
+                                subscription: subscription.clone(),
+                                exchange_id: exchange.exchange_id,
+                                capital: exchange.capital.unwrap_or_default().unsigned_abs(),
+                                due,
+                            })
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            // soonest first so reminders can go out in order
+            due_calls.sort_by_key(|call| call.due);
+
+            to_binary(&due_calls)
+        }
+        QueryMsg::GetBatch { batch_id } => to_binary(&batch_exchanges(deps.storage, batch_id)?),
+        QueryMsg::HasClaimables { subscription } => {
+            let state = config_read(deps.storage).load()?;
+            let available: Vec<AssetExchange> = asset_exchange_storage_read(deps.storage)
+                .may_load(subscription.as_bytes())?
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|exchange| {
+                    exchange.is_available(env.block.time.seconds(), state.due_grace_seconds)
+                })
+                .collect();
+
+            to_binary(&Claimables {
+                exchange: !available.is_empty(),
+                distribution: available.iter().any(|e| e.is_distribution()),
+                redemption: available.iter().any(|e| e.is_redemption()),
+            })
+        }
+        QueryMsg::GetClaimables { subscription } => {
+            let state = config_read(deps.storage).load()?;
+            let now = env.block.time.seconds();
+            let mut claimables = ClaimableItems {
+                capital_calls: vec![],
+                distributions: vec![],
+                redemptions: vec![],
+                asset_exchanges: vec![],
+            };
+
+            for exchange in asset_exchange_storage_read(deps.storage)
+                .may_load(subscription.as_bytes())?
+                .unwrap_or_default()
+            {
+                let item = ClaimableItem {
+                    available: exchange.is_available(now, state.due_grace_seconds),
+                    past_due: exchange.is_past_due(now, state.due_grace_seconds),
+                    exchange,
+                };
+
+                if item.exchange.is_capital_call() {
+                    claimables.capital_calls.push(item);
+                } else if item.exchange.is_distribution() {
+                    claimables.distributions.push(item);
+                } else if item.exchange.is_redemption() {
+                    claimables.redemptions.push(item);
+                } else {
+                    claimables.asset_exchanges.push(item);
+                }
+            }
+
+            to_binary(&claimables)
+        }
+        QueryMsg::CheckCapitalTransfer { from, to, denom } => {
+            let state = config_read(deps.storage).load()?;
+
+            to_binary(&CapitalTransferCheck {
+                from: has_capital_attribute(deps, &state, &from, &denom),
+                to: has_capital_attribute(deps, &state, &to, &denom),
+            })
+        }
+        QueryMsg::GetPendingOperations {} => {
+            let operations: Vec<PendingOperation> = pending_operation_storage_read(deps.storage)
+                .range(None, None, Order::Ascending)
+                .map(|item| item.map(|(_, operation)| operation))
+                .collect::<StdResult<_>>()?;
+
+            to_binary(&operations)
+        }
+        QueryMsg::GetConsentProposal { id } => {
+            to_binary(&consent_proposal_storage_read(deps.storage).load(&id.to_be_bytes())?)
+        }
+        QueryMsg::AuditMarkerPermissions {} => {
+            let state = config_read(deps.storage).load()?;
+
+            let mut denoms = vec![&state.commitment_denom];
+            denoms.append(&mut state.investment_denoms());
+            let markers: Vec<MarkerAudit> = denoms
+                .into_iter()
+                .map(|denom| audit_marker(deps, &env.contract.address, denom))
+                .collect();
+
+            to_binary(&MarkerPermissionAudit {
+                intact: markers.iter().all(|marker| {
+                    marker.found && marker.missing.is_empty() && marker.unexpected.is_empty()
+                }),
+                markers,
+            })
+        }
+        QueryMsg::AuditSubscription { subscription } => {
+            let state = config_read(deps.storage).load()?;
+            if subscription_status(deps.storage, &subscription)?.is_none() {
+                return Err(StdError::not_found(format!("subscription {subscription}")));
+            }
+
+            // a sub that can't answer is reported rather than failing the audit
+            let sub_state: SubState = match deps
+                .querier
+                .query_wasm_smart(subscription.clone(), &SubQueryMsg::GetState {})
+            {
+                Ok(sub_state) => sub_state,
+                Err(_) => {
+                    return to_binary(&SubscriptionAudit {
+                        subscription,
+                        found: false,
+                        consistent: false,
+                        mismatches: vec![],
+                    })
+                }
+            };
+
+            let expected = vec![
+                (
+                    "admin",
+                    state.recovery_admin.to_string(),
+                    sub_state.admin.to_string(),
+                ),
+                (
+                    "raise",
+                    env.contract.address.to_string(),
+                    sub_state.raise.to_string(),
+                ),
+                (
+                    "commitment_denom",
+                    state.commitment_denom,
+                    sub_state.commitment_denom,
+                ),
+                (
+                    "investment_denom",
+                    state.investment_denom,
+                    sub_state.investment_denom,
+                ),
+                (
+                    "capital_denom",
+                    state.capital_denom,
+                    sub_state.capital_denom,
+                ),
+                (
+                    "capital_per_share",
+                    state.capital_per_share.to_string(),
+                    sub_state.capital_per_share.to_string(),
+                ),
+            ];
+            let mismatches: Vec<SubscriptionMismatch> = expected
+                .into_iter()
+                .filter(|(_, raise, sub)| raise != sub)
+                .map(|(field, raise, sub)| SubscriptionMismatch {
+                    field: String::from(field),
+                    raise,
+                    subscription: sub,
+                })
+                .collect();
+
+            to_binary(&SubscriptionAudit {
+                subscription,
+                found: true,
+                consistent: mismatches.is_empty(),
+                mismatches,
+            })
+        }
+        QueryMsg::SimulateCompleteAssetExchange {
+            subscription,
+            exchanges,
+            to,
+            funds,
+        } => {
+            let state = config_read(deps.storage).load()?;
+            let info = MessageInfo {
+                sender: subscription,
+                funds,
+            };
+
+            to_binary(
+                &match plan_completion(deps, &env, &state, &info, &exchanges, to) {
+                    Ok(plan) => CompletionSimulation {
+                        messages: plan.messages,
+                        error: None,
+                    },
+                    Err(error) => CompletionSimulation {
+                        messages: vec![],
+                        error: Some(error.to_string()),
+                    },
+                },
+            )
+        }
+        QueryMsg::Batch { queries } => {
+            if queries.len() > MAX_BATCH_QUERIES {
+                return Err(StdError::generic_err(format!(
+                    "batch may contain at most {MAX_BATCH_QUERIES} queries"
+                )));
+            }
+            if queries
+                .iter()
+                .any(|query| matches!(query, QueryMsg::Batch { .. }))
+            {
+                return Err(StdError::generic_err("batch queries can't be nested"));
+            }
+
+            // each result is the binary the query would have returned on its own
+            let results = queries
+                .into_iter()
+                .map(|msg| query(deps, env.clone(), msg))
+                .collect::<StdResult<Vec<Binary>>>()?;
+
+            to_binary(&results)
+        }
+    }
+}
+
+fn receipts(
+    deps: Deps<ProvenanceQuery>,
+    subscription: &Addr,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<Receipt>> {
+    let limit = limit
+        .unwrap_or(DEFAULT_RECEIPT_LIMIT)
+        .min(MAX_RECEIPT_LIMIT) as usize;
+    let start = start_after.map(|sequence| sequence.saturating_add(1).to_be_bytes());
+    receipt_storage_read(deps.storage, subscription)
+        .range(start.as_ref().map(|s| &s[..]), None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, receipt)| receipt))
+        .collect()
+}
+
+// a sub may ask for its own records, as may the lp recorded when it was proposed
+fn check_lp(deps: Deps<ProvenanceQuery>, lp: &Addr, subscription: &Addr) -> StdResult<()> {
+    let recorded = subscription_lp_storage_read(deps.storage).may_load(subscription.as_bytes())?;
+    if lp == subscription && subscription_status(deps.storage, subscription)?.is_some()
+        || recorded.as_ref() == Some(lp)
+    {
+        Ok(())
+    } else {
+        Err(StdError::generic_err(format!(
+            "{lp} is not the lp of subscription {subscription}"
+        )))
+    }
+}
+
+// only the raise may move supply, so any other holder of these grants
+// means the marker was altered out of band
+const SUPPLY_PERMISSIONS: [MarkerAccess; 3] = [
+    MarkerAccess::Mint,
+    MarkerAccess::Burn,
+    MarkerAccess::Withdraw,
+];
+
+fn audit_marker(deps: Deps<ProvenanceQuery>, contract: &Addr, denom: &str) -> MarkerAudit {
+    let marker = match ProvenanceQuerier::new(&deps.querier).get_marker_by_denom(denom) {
+        Ok(marker) => marker,
+        Err(_) => {
+            return MarkerAudit {
+                denom: String::from(denom),
+                found: false,
+                missing: SUPPLY_PERMISSIONS.to_vec(),
+                unexpected: vec![],
+            }
+        }
+    };
+
+    let granted: Vec<MarkerAccess> = marker
+        .permissions
+        .iter()
+        .filter(|grant| &grant.address == contract)
+        .flat_map(|grant| grant.permissions.clone())
+        .collect();
+
+    MarkerAudit {
+        denom: String::from(denom),
+        found: true,
+        missing: SUPPLY_PERMISSIONS
+            .iter()
+            .filter(|permission| !granted.contains(permission))
+            .cloned()
+            .collect(),
+        unexpected: marker
+            .permissions
+            .into_iter()
+            .filter(|grant| &grant.address != contract)
+            .map(|grant| AccessGrant {
+                permissions: grant
+                    .permissions
+                    .into_iter()
+                    .filter(|permission| SUPPLY_PERMISSIONS.contains(permission))
+                    .collect(),
+                address: grant.address,
+            })
+            .filter(|grant| !grant.permissions.is_empty())
+            .collect(),
     }
 }
 
-#[derive(Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
-struct SubscriptionAssetExchanges {
-    #[serde(rename = "sub")]
-    subscription: Addr,
-    exchanges: Vec<AssetExchange>,
+// a sub that can't be queried or has no cw2 info reports no version
+fn subscription_version(deps: Deps<ProvenanceQuery>, subscription: &Addr) -> Option<String> {
+    deps.querier
+        .query_wasm_raw(subscription, CONTRACT.as_slice())
+        .ok()
+        .flatten()
+        .and_then(|raw| from_slice::<ContractVersion>(&raw).ok())
+        .map(|info| info.version)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use crate::contract::execute;
+    use crate::contract::tests::capital_coin_deps;
+    use crate::error::ContractError;
+    use crate::mock::load_markers;
+    use crate::mock::seed_large_raise;
+    use crate::msg::{HandleMsg, SubscriptionCodeId, SubscriptionStats};
     use crate::{
+        msg::{ChangedEntity, CompletedAssetExchange, ExchangeDate},
         query::query,
-        state::{asset_exchange_storage, tests::set_accepted},
+        state::{
+            asset_exchange_storage, completed_exchange_storage, config, escrow_storage,
+            migration_failure_storage, receipt_storage, record_change, record_nav,
+            record_subscription_code_id, subscription_lp_storage, tests::set_accepted,
+            tests::set_pending, CapitalExchangeRate, State, MAX_NAV_HISTORY,
+        },
     };
-    use cosmwasm_std::testing::mock_env;
-    use provwasm_mocks::mock_dependencies;
+    use cosmwasm_schema::schema_for;
+    use cosmwasm_std::testing::mock_info;
+    use cosmwasm_std::testing::{mock_env, MOCK_CONTRACT_ADDR};
+    use cosmwasm_std::{coin, coins, from_binary, Coin};
+    use cosmwasm_std::{ContractResult, SystemError, SystemResult, WasmQuery};
+    use cw2::set_contract_version;
+    use provwasm_mocks::{mock_dependencies, must_read_binary_file};
+    use provwasm_std::Marker;
+    use schemars::JsonSchema;
+
+    #[test]
+    fn audit_marker_permissions() {
+        let mut deps = mock_dependencies(&[]);
+        config(&mut deps.storage)
+            .save(&State::test_default())
+            .unwrap();
+        let marker = |name: &str, permissions: Vec<AccessGrant>| -> Marker {
+            let bin = must_read_binary_file(&format!("testdata/{}_marker.json", name));
+            Marker {
+                permissions,
+                ..from_binary(&bin).unwrap()
+            }
+        };
+        let raise_grant = AccessGrant {
+            permissions: vec![
+                MarkerAccess::Admin,
+                MarkerAccess::Mint,
+                MarkerAccess::Burn,
+                MarkerAccess::Withdraw,
+            ],
+            address: Addr::unchecked(MOCK_CONTRACT_ADDR),
+        };
+        deps.querier.with_markers(vec![
+            marker("commitment", vec![raise_grant.clone()]),
+            marker(
+                "investment",
+                vec![
+                    raise_grant,
+                    AccessGrant {
+                        permissions: vec![MarkerAccess::Deposit, MarkerAccess::Mint],
+                        address: Addr::unchecked("rogue"),
+                    },
+                ],
+            ),
+        ]);
+
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::AuditMarkerPermissions {},
+        )
+        .unwrap();
+        let audit: MarkerPermissionAudit = from_binary(&res).unwrap();
+
+        // verify only the out of band mint grant is reported
+        assert!(!audit.intact);
+        assert_eq!(
+            vec![
+                MarkerAudit {
+                    denom: String::from("commitment_coin"),
+                    found: true,
+                    missing: vec![],
+                    unexpected: vec![],
+                },
+                MarkerAudit {
+                    denom: String::from("investment_coin"),
+                    found: true,
+                    missing: vec![],
+                    unexpected: vec![AccessGrant {
+                        permissions: vec![MarkerAccess::Mint],
+                        address: Addr::unchecked("rogue"),
+                    }],
+                },
+            ],
+            audit.markers
+        );
+    }
+
+    #[test]
+    fn audit_missing_marker() {
+        let mut deps = mock_dependencies(&[]);
+        config(&mut deps.storage)
+            .save(&State::test_default())
+            .unwrap();
+
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::AuditMarkerPermissions {},
+        )
+        .unwrap();
+        let audit: MarkerPermissionAudit = from_binary(&res).unwrap();
+
+        assert!(!audit.intact);
+        assert!(audit.markers.iter().all(|marker| !marker.found));
+    }
+
+    #[test]
+    fn audit_subscription() {
+        let mut deps = mock_dependencies(&[]);
+        config(&mut deps.storage)
+            .save(&State::test_default())
+            .unwrap();
+        set_accepted(&mut deps.storage, vec!["sub_1", "sub_2"]);
+
+        // sub_1 agrees with the raise, sub_2 has drifted on its capital terms
+        deps.querier.base.update_wasm(|query| match query {
+            WasmQuery::Smart { contract_addr, .. } if contract_addr != "sub_3" => {
+                let drifted = contract_addr == "sub_2";
+                SystemResult::Ok(ContractResult::Ok(
+                    to_binary(&SubState {
+                        admin: Addr::unchecked("marketpalace"),
+                        lp: Addr::unchecked("lp"),
+                        raise: Addr::unchecked(MOCK_CONTRACT_ADDR),
+                        commitment_denom: String::from("commitment_coin"),
+                        investment_denom: String::from("investment_coin"),
+                        capital_denom: String::from(if drifted {
+                            "other_coin"
+                        } else {
+                            "stable_coin"
+                        }),
+                        capital_per_share: if drifted { 1 } else { 100 },
+                        required_capital_attribute: None,
+                        like_capital_denoms: vec![],
+                    })
+                    .unwrap(),
+                ))
+            }
+            _ => SystemResult::Err(SystemError::NoSuchContract {
+                addr: String::from("sub_3"),
+            }),
+        });
+        let audit = |deps: Deps<ProvenanceQuery>, subscription: &str| {
+            query(
+                deps,
+                mock_env(),
+                QueryMsg::AuditSubscription {
+                    subscription: Addr::unchecked(subscription),
+                },
+            )
+            .map(|res| from_binary::<SubscriptionAudit>(&res).unwrap())
+        };
+
+        let consistent = audit(deps.as_ref(), "sub_1").unwrap();
+        assert!(consistent.found && consistent.consistent);
+        assert!(consistent.mismatches.is_empty());
+
+        let drifted = audit(deps.as_ref(), "sub_2").unwrap();
+        assert!(!drifted.consistent);
+        assert_eq!(
+            vec![
+                SubscriptionMismatch {
+                    field: String::from("capital_denom"),
+                    raise: String::from("stable_coin"),
+                    subscription: String::from("other_coin"),
+                },
+                SubscriptionMismatch {
+                    field: String::from("capital_per_share"),
+                    raise: String::from("100"),
+                    subscription: String::from("1"),
+                },
+            ],
+            drifted.mismatches
+        );
+
+        // a tracked sub that can't be queried is reported, an unknown one is an error
+        set_pending(&mut deps.storage, vec!["sub_3"]);
+        let unreachable = audit(deps.as_ref(), "sub_3").unwrap();
+        assert!(!unreachable.found && !unreachable.consistent);
+        assert!(audit(deps.as_ref(), "sub_4").is_err());
+    }
 
     #[test]
     fn get_all_asset_exchanges() {
@@ -74,10 +887,8 @@ mod tests {
                 .save(
                     Addr::unchecked("sub_1").as_bytes(),
                     &vec![AssetExchange {
-                        investment: None,
                         commitment_in_shares: Some(1_000),
-                        capital: None,
-                        date: None,
+                        ..AssetExchange::default()
                     }],
                 )
                 .unwrap();
@@ -87,6 +898,82 @@ mod tests {
         println!("{}", std::str::from_utf8(res.as_slice()).unwrap());
     }
 
+    #[test]
+    fn get_subscriptions_paged() {
+        let mut deps = mock_dependencies(&[]);
+        set_pending(&mut deps.storage, vec!["sub_4"]);
+        set_accepted(&mut deps.storage, vec!["sub_1", "sub_2", "sub_3"]);
+        let page = |start_after: Option<&str>| -> Vec<Addr> {
+            from_binary(
+                &query(
+                    deps.as_ref(),
+                    mock_env(),
+                    QueryMsg::GetSubscriptions {
+                        status: SubscriptionStatus::Accepted,
+                        start_after: start_after.map(Addr::unchecked),
+                        limit: Some(2),
+                    },
+                )
+                .unwrap(),
+            )
+            .unwrap()
+        };
+
+        // verify pages continue after the last sub returned and skip other statuses
+        assert_eq!(
+            vec![Addr::unchecked("sub_1"), Addr::unchecked("sub_2")],
+            page(None)
+        );
+        assert_eq!(vec![Addr::unchecked("sub_3")], page(Some("sub_2")));
+        assert!(page(Some("sub_3")).is_empty());
+    }
+
+    #[test]
+    fn large_raise_pages_and_totals() {
+        let mut deps = capital_coin_deps(None);
+        load_markers(&mut deps.querier);
+        let raise = seed_large_raise(&mut deps.storage, &mut deps.querier, 5_000, 42);
+
+        // verify the same seed always generates the same raise
+        let mut other = capital_coin_deps(None);
+        assert_eq!(
+            raise,
+            seed_large_raise(&mut other.storage, &mut other.querier, 5_000, 42)
+        );
+
+        // verify paging visits every sub once and in order
+        let mut paged: Vec<Addr> = vec![];
+        loop {
+            let page: Vec<Addr> = from_binary(
+                &query(
+                    deps.as_ref(),
+                    mock_env(),
+                    QueryMsg::GetSubscriptions {
+                        status: SubscriptionStatus::Accepted,
+                        start_after: paged.last().cloned(),
+                        limit: Some(MAX_SUBSCRIPTION_LIMIT),
+                    },
+                )
+                .unwrap(),
+            )
+            .unwrap();
+            if page.is_empty() {
+                break;
+            }
+            paged.extend(page);
+        }
+        assert_eq!(raise.subscriptions, paged);
+
+        let totals: RaiseTotals =
+            from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::GetRaiseTotals {}).unwrap())
+                .unwrap();
+        assert_eq!(5_000, totals.accepted_subscriptions);
+        assert_eq!(
+            Uint128::new(raise.capital_called),
+            totals.unclaimed_capital_called
+        );
+    }
+
     #[test]
     fn get_asset_exchanges_for_subscription() {
         let mut deps = mock_dependencies(&[]);
@@ -94,10 +981,8 @@ mod tests {
             .save(
                 Addr::unchecked("sub_1").as_bytes(),
                 &vec![AssetExchange {
-                    investment: None,
                     commitment_in_shares: Some(1_000),
-                    capital: None,
-                    date: None,
+                    ..AssetExchange::default()
                 }],
             )
             .unwrap();
@@ -105,4 +990,921 @@ mod tests {
         let res = query(deps.as_ref(), mock_env(), QueryMsg::GetAllAssetExchanges {}).unwrap();
         println!("{}", std::str::from_utf8(res.as_slice()).unwrap());
     }
+
+    #[test]
+    fn get_completed_exchanges() {
+        let mut deps = mock_dependencies(&[]);
+        completed_exchange_storage(&mut deps.storage)
+            .save(
+                Addr::unchecked("sub_1").as_bytes(),
+                &vec![CompletedAssetExchange {
+                    exchange: AssetExchange {
+                        investment: Some(1_000),
+                        commitment_in_shares: Some(-1_000),
+                        capital: Some(-1_000),
+                        ..AssetExchange::default()
+                    },
+                    to: None,
+                    height: 12_345,
+                    time: 1672531200,
+                }],
+            )
+            .unwrap();
+
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetCompletedExchanges {
+                subscription: Addr::unchecked("sub_1"),
+            },
+        )
+        .unwrap();
+        let completed: Vec<CompletedAssetExchange> = from_binary(&res).unwrap();
+        assert_eq!(1, completed.len());
+        assert_eq!(12_345, completed.first().unwrap().height);
+    }
+
+    #[test]
+    fn has_claimables() {
+        let mut deps = mock_dependencies(&[]);
+        let env = mock_env();
+        config(&mut deps.storage)
+            .save(&State::test_default())
+            .unwrap();
+        asset_exchange_storage(&mut deps.storage)
+            .save(
+                Addr::unchecked("sub_1").as_bytes(),
+                &vec![
+                    AssetExchange {
+                        capital: Some(1_000),
+                        date: Some(ExchangeDate::Available(env.block.time.seconds())),
+                        ..AssetExchange::default()
+                    },
+                    AssetExchange {
+                        investment: Some(-1_000),
+                        capital: Some(1_000),
+                        date: Some(ExchangeDate::Available(env.block.time.seconds() + 1)),
+                        ..AssetExchange::default()
+                    },
+                ],
+            )
+            .unwrap();
+
+        let res = query(
+            deps.as_ref(),
+            env,
+            QueryMsg::HasClaimables {
+                subscription: Addr::unchecked("sub_1"),
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            Claimables {
+                exchange: true,
+                distribution: true,
+                redemption: false,
+            },
+            from_binary::<Claimables>(&res).unwrap()
+        );
+    }
+
+    #[test]
+    fn get_claimables() {
+        let mut deps = mock_dependencies(&[]);
+        let env = mock_env();
+        let now = env.block.time.seconds();
+        config(&mut deps.storage)
+            .save(&State::test_default())
+            .unwrap();
+        asset_exchange_storage(&mut deps.storage)
+            .save(
+                Addr::unchecked("sub_1").as_bytes(),
+                &vec![
+                    AssetExchange {
+                        investment: Some(1_000),
+                        commitment_in_shares: Some(-1_000),
+                        capital: Some(-1_000),
+                        date: Some(ExchangeDate::Due(now - 1)),
+                        ..AssetExchange::default()
+                    },
+                    AssetExchange {
+                        capital: Some(1_000),
+                        date: Some(ExchangeDate::Available(now + 1)),
+                        ..AssetExchange::default()
+                    },
+                    AssetExchange {
+                        investment: Some(-1_000),
+                        capital: Some(1_000),
+                        ..AssetExchange::default()
+                    },
+                    AssetExchange {
+                        commitment_in_shares: Some(1_000),
+                        ..AssetExchange::default()
+                    },
+                ],
+            )
+            .unwrap();
+
+        let res = query(
+            deps.as_ref(),
+            env,
+            QueryMsg::GetClaimables {
+                subscription: Addr::unchecked("sub_1"),
+            },
+        )
+        .unwrap();
+        let claimables: ClaimableItems = from_binary(&res).unwrap();
+
+        assert_eq!(1, claimables.capital_calls.len());
+        assert!(claimables.capital_calls[0].past_due);
+        assert!(!claimables.capital_calls[0].available);
+        assert_eq!(1, claimables.distributions.len());
+        assert!(!claimables.distributions[0].available);
+        assert!(!claimables.distributions[0].past_due);
+        assert_eq!(1, claimables.redemptions.len());
+        assert!(claimables.redemptions[0].available);
+        assert_eq!(1, claimables.asset_exchanges.len());
+        assert!(claimables.asset_exchanges[0].available);
+    }
+
+    #[test]
+    fn check_capital_transfer() {
+        let mut deps = mock_dependencies(&[]);
+        config(&mut deps.storage)
+            .save(&State {
+                capital_denom: String::from("restricted_capital_coin"),
+                required_capital_attribute: Some(String::from("capital.test")),
+                ..State::test_default()
+            })
+            .unwrap();
+        deps.querier
+            .with_attributes("sub_1", &[("capital.test", "", "")]);
+
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::CheckCapitalTransfer {
+                from: Addr::unchecked("sub_1"),
+                to: Addr::unchecked("destination"),
+                denom: String::from("restricted_capital_coin"),
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            CapitalTransferCheck {
+                from: true,
+                to: false,
+            },
+            from_binary(&res).unwrap()
+        );
+
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::CheckCapitalTransfer {
+                from: Addr::unchecked("sub_1"),
+                to: Addr::unchecked("destination"),
+                denom: String::from("other_coin"),
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            CapitalTransferCheck {
+                from: true,
+                to: true,
+            },
+            from_binary(&res).unwrap()
+        );
+    }
+
+    #[test]
+    fn get_receipts() {
+        let mut deps = mock_dependencies(&[]);
+        let subscription = Addr::unchecked("sub_1");
+        for sequence in 1..=3u64 {
+            receipt_storage(&mut deps.storage, &subscription)
+                .save(
+                    &sequence.to_be_bytes(),
+                    &Receipt {
+                        sequence,
+                        exchange_id: None,
+                        height: 12_345,
+                        time: 1_571_797_419,
+                        tx_index: None,
+                        investment: 0,
+                        commitment_in_shares: 0,
+                        capital: 100,
+                        destination: subscription.clone(),
+                    },
+                )
+                .unwrap();
+        }
+
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetReceipts {
+                subscription: subscription.clone(),
+                start_after: Some(1),
+                limit: Some(1),
+            },
+        )
+        .unwrap();
+        let receipts: Vec<Receipt> = from_binary(&res).unwrap();
+        assert_eq!(1, receipts.len());
+        assert_eq!(2, receipts[0].sequence);
+
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetReceipts {
+                subscription,
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+        let receipts: Vec<Receipt> = from_binary(&res).unwrap();
+        assert_eq!(3, receipts.len());
+    }
+
+    #[test]
+    fn get_own_records() {
+        let mut deps = mock_dependencies(&[]);
+        set_accepted(&mut deps.storage, vec!["sub_1"]);
+        subscription_lp_storage(&mut deps.storage)
+            .save(b"sub_1", &Addr::unchecked("lp_1"))
+            .unwrap();
+        escrow_storage(&mut deps.storage)
+            .save(b"sub_1", &500)
+            .unwrap();
+        let capital_account = |lp: &str| {
+            query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::GetOwnCapitalAccount {
+                    lp: Addr::unchecked(lp),
+                    subscription: Addr::unchecked("sub_1"),
+                },
+            )
+        };
+
+        // verify the recorded lp and the sub itself can read the account
+        let account: CapitalAccount = from_binary(&capital_account("lp_1").unwrap()).unwrap();
+        assert_eq!(500, account.escrow);
+        assert!(capital_account("sub_1").is_ok());
+
+        // verify anyone else is refused
+        assert!(capital_account("lp_2").is_err());
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetOwnReceipts {
+                lp: Addr::unchecked("lp_2"),
+                subscription: Addr::unchecked("sub_1"),
+                start_after: None,
+                limit: None,
+            },
+        );
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn get_raise_totals() {
+        let mut deps = mock_dependencies(&[]);
+        load_markers(&mut deps.querier);
+        config(&mut deps.storage)
+            .save(&State::test_default())
+            .unwrap();
+        set_pending(&mut deps.storage, vec!["sub_1"]);
+        set_accepted(&mut deps.storage, vec!["sub_2", "sub_3"]);
+        let capital_call = AssetExchange {
+            investment: Some(1_000),
+            commitment_in_shares: Some(-1_000),
+            capital: Some(-1_000),
+            ..AssetExchange::default()
+        };
+        let distribution = AssetExchange {
+            investment: None,
+            commitment_in_shares: None,
+            capital: Some(500),
+            ..capital_call.clone()
+        };
+        asset_exchange_storage(&mut deps.storage)
+            .save(b"sub_2", &vec![capital_call.clone(), distribution])
+            .unwrap();
+        asset_exchange_storage(&mut deps.storage)
+            .save(b"sub_3", &vec![capital_call])
+            .unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetRaiseTotals {}).unwrap();
+        assert_eq!(
+            RaiseTotals {
+                commitment_shares: Uint128::new(420),
+                investment_shares: Uint128::new(420),
+                unclaimed_capital_called: Uint128::new(2_000),
+                pending_subscriptions: 1,
+                eligible_subscriptions: 0,
+                accepted_subscriptions: 2,
+            },
+            from_binary(&res).unwrap()
+        );
+    }
+
+    // every field the published schema requires must be in the serialized response
+    fn assert_described_by_schema<T: JsonSchema>(res: &Binary) {
+        let schema = schema_for!(T);
+        let json = std::str::from_utf8(res.as_slice()).unwrap();
+        for field in &schema.schema.object.as_ref().unwrap().required {
+            assert!(
+                json.contains(&format!("\"{}\":", field)),
+                "{} is missing from the response",
+                field
+            );
+        }
+    }
+
+    #[test]
+    fn responses_described_by_schemas() {
+        let mut deps = mock_dependencies(&[]);
+        load_markers(&mut deps.querier);
+        config(&mut deps.storage)
+            .save(&State::test_default())
+            .unwrap();
+        set_accepted(&mut deps.storage, vec!["sub_1"]);
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetState {}).unwrap();
+        assert_described_by_schema::<RaiseState>(&res);
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetRaiseTotals {}).unwrap();
+        assert_described_by_schema::<RaiseTotals>(&res);
+    }
+
+    #[test]
+    fn get_state_is_canonical() {
+        let state_binary = |accepted: Vec<&str>| {
+            let mut deps = mock_dependencies(&[]);
+            let mut state = State::test_default();
+            state.delegates = accepted.iter().map(|addr| Addr::unchecked(*addr)).collect();
+            config(&mut deps.storage).save(&state).unwrap();
+            set_accepted(&mut deps.storage, accepted);
+            query(deps.as_ref(), mock_env(), QueryMsg::GetState {}).unwrap()
+        };
+
+        // verify insertion order doesn't change the serialized response
+        assert_eq!(
+            state_binary(vec!["sub_1", "sub_2", "sub_3"]),
+            state_binary(vec!["sub_3", "sub_1", "sub_2"])
+        );
+        let state: RaiseState = from_binary(&state_binary(vec!["sub_2", "sub_1"])).unwrap();
+        assert_eq!(
+            vec![Addr::unchecked("sub_1"), Addr::unchecked("sub_2")],
+            state.accepted_subscriptions
+        );
+    }
+
+    #[test]
+    fn simulate_complete_asset_exchange() {
+        let mut deps = capital_coin_deps(None);
+        load_markers(&mut deps.querier);
+        let redemption = AssetExchange {
+            investment: Some(-1_000),
+            capital: Some(1_000),
+            ..AssetExchange::default()
+        };
+        asset_exchange_storage(&mut deps.storage)
+            .save(b"sub_1", &vec![redemption.clone()])
+            .unwrap();
+        let simulate = |deps: Deps<ProvenanceQuery>, exchange: &AssetExchange| {
+            from_binary::<CompletionSimulation>(
+                &query(
+                    deps,
+                    mock_env(),
+                    QueryMsg::SimulateCompleteAssetExchange {
+                        subscription: Addr::unchecked("sub_1"),
+                        exchanges: vec![exchange.clone()],
+                        to: None,
+                        funds: coins(1_000, "investment_coin"),
+                    },
+                )
+                .unwrap(),
+            )
+            .unwrap()
+        };
+
+        // verify the simulation matches what the completion emits
+        let simulation = simulate(deps.as_ref(), &redemption);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("sub_1", &coins(1_000, "investment_coin")),
+            HandleMsg::CompleteAssetExchange {
+                exchanges: vec![redemption.clone()],
+                to: None,
+                memo: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(None, simulation.error);
+        assert_eq!(
+            res.messages
+                .into_iter()
+                .map(|msg| msg.msg)
+                .collect::<Vec<_>>(),
+            simulation.messages
+        );
+
+        // verify the error is reported once the exchange is gone
+        let simulation = simulate(deps.as_ref(), &redemption);
+        assert_eq!(
+            Some(
+                ContractError::ExchangeNotFound {
+                    subscription: Addr::unchecked("sub_1")
+                }
+                .to_string()
+            ),
+            simulation.error
+        );
+        assert!(simulation.messages.is_empty());
+    }
+
+    #[test]
+    fn get_subscription_stats() {
+        let mut deps = capital_coin_deps(None);
+        load_markers(&mut deps.querier);
+        let capital_call = AssetExchange {
+            investment: Some(1_000),
+            commitment_in_shares: Some(-1_000),
+            capital: Some(-1_000),
+            ..AssetExchange::default()
+        };
+        let redemption = AssetExchange {
+            investment: Some(-400),
+            commitment_in_shares: None,
+            capital: Some(500),
+            ..capital_call.clone()
+        };
+        asset_exchange_storage(&mut deps.storage)
+            .save(
+                b"sub_1",
+                &vec![
+                    AssetExchange {
+                        investment: None,
+                        commitment_in_shares: Some(2_000),
+                        capital: None,
+                        ..capital_call.clone()
+                    },
+                    capital_call.clone(),
+                    redemption.clone(),
+                ],
+            )
+            .unwrap();
+
+        let mut complete = |exchange: AssetExchange, funds: Vec<Coin>| {
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("sub_1", &funds),
+                HandleMsg::CompleteAssetExchange {
+                    exchanges: vec![exchange],
+                    to: None,
+                    memo: None,
+                },
+            )
+            .unwrap();
+        };
+        complete(
+            AssetExchange {
+                investment: None,
+                commitment_in_shares: Some(2_000),
+                capital: None,
+                ..capital_call.clone()
+            },
+            vec![],
+        );
+        complete(
+            capital_call,
+            vec![coin(1_000, "capital_coin"), coin(1_000, "commitment_coin")],
+        );
+        complete(redemption, vec![coin(400, "investment_coin")]);
+
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetSubscriptionStats {
+                subscription: Addr::unchecked("sub_1"),
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            SubscriptionStats {
+                capital_contributed: 1_000,
+                capital_distributed: 500,
+                shares_redeemed: 400,
+                commitment: 1_000,
+                capital_contributed_by_denom: vec![coin(1_000, "capital_coin")],
+            },
+            from_binary(&res).unwrap()
+        );
+    }
+
+    #[test]
+    fn batch() {
+        let mut deps = mock_dependencies(&[]);
+        config(&mut deps.storage)
+            .save(&State::test_default())
+            .unwrap();
+        set_accepted(&mut deps.storage, vec!["sub_1"]);
+
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::Batch {
+                queries: vec![
+                    QueryMsg::GetState {},
+                    QueryMsg::GetPrepayment {
+                        subscription: Addr::unchecked("sub_1"),
+                    },
+                ],
+            },
+        )
+        .unwrap();
+
+        // verify results come back in order as each query would return them
+        let results: Vec<Binary> = from_binary(&res).unwrap();
+        assert_eq!(
+            query(deps.as_ref(), mock_env(), QueryMsg::GetState {}).unwrap(),
+            results[0]
+        );
+        assert_eq!(0_u64, from_binary::<u64>(&results[1]).unwrap());
+
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::Batch {
+                queries: vec![QueryMsg::Batch { queries: vec![] }],
+            },
+        );
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn get_holdings() {
+        let mut deps = mock_dependencies(&[]);
+        load_markers(&mut deps.querier);
+        config(&mut deps.storage)
+            .save(&State::test_default())
+            .unwrap();
+        set_accepted(&mut deps.storage, vec!["sub_1", "sub_2"]);
+        deps.querier.base.update_balance(
+            Addr::unchecked("sub_1"),
+            vec![coin(100, "commitment_coin"), coin(50, "investment_coin")],
+        );
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetHoldings {}).unwrap();
+        let mut holdings: Holdings = from_binary(&res).unwrap();
+        holdings
+            .subscriptions
+            .sort_by(|a, b| a.subscription.cmp(&b.subscription));
+        assert_eq!(
+            Holdings {
+                height: mock_env().block.height,
+                subscriptions: vec![
+                    SubscriptionHoldings {
+                        subscription: Addr::unchecked("sub_1"),
+                        commitment: Uint128::new(100),
+                        investment: Uint128::new(50),
+                        ownership_bps: 1_785,
+                    },
+                    SubscriptionHoldings {
+                        subscription: Addr::unchecked("sub_2"),
+                        commitment: Uint128::zero(),
+                        investment: Uint128::zero(),
+                        ownership_bps: 0,
+                    },
+                ],
+            },
+            holdings
+        );
+    }
+
+    #[test]
+    fn get_info() {
+        let mut deps = mock_dependencies(&[]);
+        config(&mut deps.storage)
+            .save(&State {
+                capital_exchange_rates: vec![CapitalExchangeRate {
+                    denom: String::from("other_coin"),
+                    numerator: 1,
+                    denominator: 1,
+                }],
+                ..State::test_default()
+            })
+            .unwrap();
+        set_contract_version(&mut deps.storage, "marketpalace-raise-contract", "1.0.0").unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetInfo {}).unwrap();
+        assert_eq!(
+            RaiseInfo {
+                contract: ContractVersion {
+                    contract: String::from("marketpalace-raise-contract"),
+                    version: String::from("1.0.0"),
+                },
+                commitment_denom: String::from("commitment_coin"),
+                investment_denoms: vec![String::from("investment_coin")],
+                capital_denoms: vec![String::from("stable_coin"), String::from("other_coin")],
+                capital_per_share: 100,
+            },
+            from_binary(&res).unwrap()
+        );
+    }
+
+    #[test]
+    fn get_nav_history() {
+        let mut deps = mock_dependencies(&[]);
+        for posted_at in 100..=100 + MAX_NAV_HISTORY as u64 {
+            record_nav(
+                &mut deps.storage,
+                &NetAssetValue {
+                    per_share: posted_at * 2,
+                    posted_at,
+                },
+            )
+            .unwrap();
+        }
+        let history = |from: Option<u64>, to: Option<u64>, limit: Option<u32>| {
+            let res = query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::GetNavHistory { from, to, limit },
+            )
+            .unwrap();
+            from_binary::<Vec<NetAssetValue>>(&res).unwrap()
+        };
+
+        // verify the oldest nav was pruned
+        assert_eq!(101, history(None, None, Some(1))[0].posted_at);
+        assert_eq!(
+            DEFAULT_NAV_HISTORY_LIMIT as usize,
+            history(None, None, None).len()
+        );
+
+        // verify the range is inclusive of both ends
+        assert_eq!(
+            vec![200, 201, 202],
+            history(Some(200), Some(202), None)
+                .iter()
+                .map(|nav| nav.posted_at)
+                .collect::<Vec<u64>>()
+        );
+    }
+
+    #[test]
+    fn get_changes() {
+        let mut deps = mock_dependencies(&[]);
+        record_change(&mut deps.storage, ChangedEntity::State, "config").unwrap();
+        record_change(&mut deps.storage, ChangedEntity::Subscription, "sub_1").unwrap();
+        record_change(&mut deps.storage, ChangedEntity::AssetExchange, "sub_1").unwrap();
+
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetChanges { since_revision: 1 },
+        )
+        .unwrap();
+        assert_eq!(
+            Changes {
+                revision: 3,
+                changes: vec![
+                    Change {
+                        revision: 2,
+                        entity: ChangedEntity::Subscription,
+                        key: String::from("sub_1"),
+                    },
+                    Change {
+                        revision: 3,
+                        entity: ChangedEntity::AssetExchange,
+                        key: String::from("sub_1"),
+                    },
+                ],
+            },
+            from_binary(&res).unwrap()
+        );
+    }
+
+    #[test]
+    fn get_migration_failures() {
+        let mut deps = mock_dependencies(&[]);
+        migration_failure_storage(&mut deps.storage)
+            .save(b"sub_1", &String::from("incompatible version"))
+            .unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetMigrationFailures {}).unwrap();
+        assert_eq!(
+            vec![MigrationFailure {
+                subscription: Addr::unchecked("sub_1"),
+                error: String::from("incompatible version"),
+            }],
+            from_binary::<Vec<MigrationFailure>>(&res).unwrap()
+        );
+    }
+
+    #[test]
+    fn has_claimables_none_issued() {
+        let mut deps = mock_dependencies(&[]);
+        config(&mut deps.storage)
+            .save(&State::test_default())
+            .unwrap();
+
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::HasClaimables {
+                subscription: Addr::unchecked("sub_1"),
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            Claimables {
+                exchange: false,
+                distribution: false,
+                redemption: false,
+            },
+            from_binary::<Claimables>(&res).unwrap()
+        );
+    }
+
+    #[test]
+    fn get_subscription_versions() {
+        let mut deps = mock_dependencies(&[]);
+        set_accepted(&mut deps.storage, vec!["sub_1", "sub_2"]);
+        deps.querier.base.update_wasm(|query| match query {
+            WasmQuery::Raw { contract_addr, .. } if contract_addr == "sub_1" => {
+                SystemResult::Ok(ContractResult::Ok(
+                    to_binary(&ContractVersion {
+                        contract: String::from("marketpalace-subscription-contract"),
+                        version: String::from("2.1.0"),
+                    })
+                    .unwrap(),
+                ))
+            }
+            _ => SystemResult::Err(SystemError::NoSuchContract {
+                addr: String::from("sub_2"),
+            }),
+        });
+
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetSubscriptionVersions {},
+        )
+        .unwrap();
+        let mut versions: Vec<SubscriptionVersion> = from_binary(&res).unwrap();
+        versions.sort_by(|a, b| a.subscription.cmp(&b.subscription));
+        assert_eq!(
+            vec![
+                SubscriptionVersion {
+                    subscription: Addr::unchecked("sub_1"),
+                    version: Some(String::from("2.1.0")),
+                },
+                SubscriptionVersion {
+                    subscription: Addr::unchecked("sub_2"),
+                    version: None,
+                },
+            ],
+            versions
+        );
+    }
+
+    #[test]
+    fn get_subscription_code_ids() {
+        let mut deps = mock_dependencies(&[]);
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetSubscriptionCodeIds {},
+        )
+        .unwrap();
+        assert!(from_binary::<Vec<SubscriptionCodeId>>(&res)
+            .unwrap()
+            .is_empty());
+
+        record_subscription_code_id(&mut deps.storage, 1, None).unwrap();
+        record_subscription_code_id(&mut deps.storage, 2, Some(12_345)).unwrap();
+
+        // verify the active code id isn't recorded again
+        record_subscription_code_id(&mut deps.storage, 2, Some(12_346)).unwrap();
+
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetSubscriptionCodeIds {},
+        )
+        .unwrap();
+        assert_eq!(
+            vec![
+                SubscriptionCodeId {
+                    code_id: 1,
+                    activated_at: None,
+                },
+                SubscriptionCodeId {
+                    code_id: 2,
+                    activated_at: Some(12_345),
+                },
+            ],
+            from_binary::<Vec<SubscriptionCodeId>>(&res).unwrap()
+        );
+    }
+
+    #[test]
+    fn get_settlement_windows() {
+        let mut deps = mock_dependencies(&[]);
+        set_accepted(&mut deps.storage, vec!["sub_1"]);
+        let exchange = |date| AssetExchange {
+            investment: Some(1_000),
+            commitment_in_shares: Some(-1_000),
+            capital: Some(-1_000),
+            date,
+            ..AssetExchange::default()
+        };
+        asset_exchange_storage(&mut deps.storage)
+            .save(
+                Addr::unchecked("sub_1").as_bytes(),
+                &vec![
+                    exchange(None),
+                    exchange(Some(ExchangeDate::Due(200))),
+                    exchange(Some(ExchangeDate::Available(100))),
+                    exchange(Some(ExchangeDate::Window(100, 200))),
+                ],
+            )
+            .unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetSettlementWindows {}).unwrap();
+        let all_windows: Vec<SubscriptionSettlementWindows> = from_binary(&res).unwrap();
+        let windows: Vec<(Option<u64>, Option<u64>)> = all_windows
+            .first()
+            .unwrap()
+            .windows
+            .iter()
+            .map(|window| (window.earliest_settle, window.latest_settle))
+            .collect();
+        assert_eq!(
+            vec![
+                (None, None),
+                (None, Some(200)),
+                (Some(100), None),
+                (Some(100), Some(200)),
+            ],
+            windows
+        );
+    }
+
+    #[test]
+    fn get_due_capital_calls() {
+        let mut deps = mock_dependencies(&[]);
+        set_accepted(&mut deps.storage, vec!["sub_1", "sub_2"]);
+        let now = mock_env().block.time.seconds();
+        let exchange = |capital: i64, due: u64| AssetExchange {
+            investment: Some(capital.abs()),
+            commitment_in_shares: Some(-capital.abs()),
+            capital: Some(capital),
+            date: Some(ExchangeDate::Due(due)),
+            ..AssetExchange::default()
+        };
+        asset_exchange_storage(&mut deps.storage)
+            .save(
+                b"sub_1",
+                &vec![
+                    exchange(-1_000, now + 7_200),
+                    exchange(-2_000, now + 172_800),
+                    exchange(-3_000, now - 1),
+                    exchange(4_000, now + 60),
+                ],
+            )
+            .unwrap();
+        asset_exchange_storage(&mut deps.storage)
+            .save(b"sub_2", &vec![exchange(-5_000, now + 60)])
+            .unwrap();
+
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetDueCapitalCalls {
+                within_seconds: 86_400,
+            },
+        )
+        .unwrap();
+
+        // verify only calls due within the window are returned, soonest first
+        let calls: Vec<DueCapitalCall> = from_binary(&res).unwrap();
+        assert_eq!(
+            vec![
+                (Addr::unchecked("sub_2"), 5_000),
+                (Addr::unchecked("sub_1"), 1_000)
+            ],
+            calls
+                .into_iter()
+                .map(|call| (call.subscription, call.capital))
+                .collect::<Vec<(Addr, u64)>>()
+        );
+    }
 }
@@ -0,0 +1,317 @@
+use cosmwasm_std::{Addr, Binary, Event, StdError, StdResult, SubMsgResponse};
+
+// reply ids are partitioned into ranges so each subsystem only sees its own replies
+pub const REPLY_NAMESPACE_SIZE: u64 = 1_000_000;
+pub const SUBSCRIPTION_REPLY_NAMESPACE: u64 = REPLY_NAMESPACE_SIZE;
+pub const MIGRATION_REPLY_NAMESPACE: u64 = 2 * REPLY_NAMESPACE_SIZE;
+pub const NOTIFICATION_REPLY_NAMESPACE: u64 = 3 * REPLY_NAMESPACE_SIZE;
+pub const QUEUE_REPLY_NAMESPACE: u64 = 4 * REPLY_NAMESPACE_SIZE;
+pub const MARKER_SETUP_REPLY_NAMESPACE: u64 = 5 * REPLY_NAMESPACE_SIZE;
+
+pub const PENDING_SUBSCRIPTION_REPLY_ID: u64 = SUBSCRIPTION_REPLY_NAMESPACE;
+pub const ELIGIBLE_SUBSCRIPTION_REPLY_ID: u64 = SUBSCRIPTION_REPLY_NAMESPACE + 1;
+// sub migrations reply with this offset plus the sub's index in the migration batch
+pub const MIGRATE_SUBSCRIPTION_REPLY_ID_OFFSET: u64 = MIGRATION_REPLY_NAMESPACE;
+// issuance notifications reply with this offset plus the index in the notification batch
+pub const NOTIFY_SUBSCRIPTION_REPLY_ID_OFFSET: u64 = NOTIFICATION_REPLY_NAMESPACE;
+// queued settlements reply with this offset plus the index in the processed batch
+pub const SETTLE_QUEUED_REPLY_ID_OFFSET: u64 = QUEUE_REPLY_NAMESPACE;
+// marker setup replies with this offset plus the marker's index times the step count,
+// plus the step's index
+pub const MARKER_SETUP_REPLY_ID_OFFSET: u64 = MARKER_SETUP_REPLY_NAMESPACE;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReplyNamespace {
+    Subscription,
+    Migration,
+    Notification,
+    Queue,
+    MarkerSetup,
+}
+
+pub fn reply_namespace(id: u64) -> Option<ReplyNamespace> {
+    match id - id % REPLY_NAMESPACE_SIZE {
+        SUBSCRIPTION_REPLY_NAMESPACE => Some(ReplyNamespace::Subscription),
+        MIGRATION_REPLY_NAMESPACE => Some(ReplyNamespace::Migration),
+        NOTIFICATION_REPLY_NAMESPACE => Some(ReplyNamespace::Notification),
+        QUEUE_REPLY_NAMESPACE => Some(ReplyNamespace::Queue),
+        MARKER_SETUP_REPLY_NAMESPACE => Some(ReplyNamespace::MarkerSetup),
+        _ => None,
+    }
+}
+
+// wire types used by protobuf encoding
+const WIRE_TYPE_VARINT: u64 = 0;
+const WIRE_TYPE_64BIT: u64 = 1;
+const WIRE_TYPE_LENGTH_DELIMITED: u64 = 2;
+const WIRE_TYPE_32BIT: u64 = 5;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct InstantiateResponse {
+    pub contract_address: String,
+    pub data: Option<Binary>,
+}
+
+// decodes the MsgInstantiateContractResponse protobuf returned as submessage data
+pub fn parse_instantiate_response(data: &[u8]) -> StdResult<InstantiateResponse> {
+    let mut contract_address = None;
+    let mut response_data = None;
+    let mut remaining = data;
+
+    while !remaining.is_empty() {
+        let key = read_varint(&mut remaining)?;
+        let field = key >> 3;
+        match key & 0x7 {
+            WIRE_TYPE_LENGTH_DELIMITED => {
+                let len = read_varint(&mut remaining)? as usize;
+                if remaining.len() < len {
+                    return Err(parse_error("field length exceeds data"));
+                }
+                let (value, rest) = remaining.split_at(len);
+                remaining = rest;
+
+                match field {
+                    1 => {
+                        contract_address = Some(
+                            String::from_utf8(value.to_vec())
+                                .map_err(|_| parse_error("contract address is not utf8"))?,
+                        )
+                    }
+                    2 => response_data = Some(Binary::from(value)),
+                    _ => {}
+                }
+            }
+            WIRE_TYPE_VARINT => {
+                read_varint(&mut remaining)?;
+            }
+            WIRE_TYPE_64BIT => remaining = skip(remaining, 8)?,
+            WIRE_TYPE_32BIT => remaining = skip(remaining, 4)?,
+            _ => return Err(parse_error("unsupported wire type")),
+        }
+    }
+
+    Ok(InstantiateResponse {
+        contract_address: contract_address
+            .filter(|address| !address.is_empty())
+            .ok_or_else(|| parse_error("missing contract address"))?,
+        data: response_data,
+    })
+}
+
+// prefers the submessage data and falls back to the events, which may hold several
+// instantiations in any order, so only events for the expected code id are considered
+pub fn instantiated_contract_address(response: &SubMsgResponse, code_id: u64) -> Option<Addr> {
+    response
+        .data
+        .as_ref()
+        .and_then(|data| parse_instantiate_response(data.as_slice()).ok())
+        .map(|instantiated| Addr::unchecked(instantiated.contract_address))
+        .or_else(|| contract_address(&response.events, "instantiate", Some(code_id)))
+        .or_else(|| contract_address(&response.events, "wasm", None))
+}
+
+// an address is only trusted when every matching event agrees on it
+fn contract_address(events: &[Event], ty: &str, code_id: Option<u64>) -> Option<Addr> {
+    let mut addresses = events
+        .iter()
+        .filter(|event| event.ty == ty)
+        .filter(|event| match (code_id, attribute(event, "code_id")) {
+            (Some(code_id), Some(value)) => value == code_id.to_string(),
+            _ => true,
+        })
+        .filter_map(|event| attribute(event, "_contract_address"));
+    let address = addresses.next()?;
+
+    addresses
+        .all(|other| other == address)
+        .then(|| Addr::unchecked(address))
+}
+
+fn attribute<'a>(event: &'a Event, key: &str) -> Option<&'a str> {
+    event
+        .attributes
+        .iter()
+        .find(|attr| attr.key == key)
+        .map(|attr| attr.value.as_str())
+}
+
+fn read_varint(data: &mut &[u8]) -> StdResult<u64> {
+    let mut value: u64 = 0;
+    for (i, byte) in data.iter().enumerate().take(10) {
+        value |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            *data = &data[i + 1..];
+            return Ok(value);
+        }
+    }
+
+    Err(parse_error("invalid varint"))
+}
+
+fn skip(data: &[u8], len: usize) -> StdResult<&[u8]> {
+    if data.len() < len {
+        return Err(parse_error("field length exceeds data"));
+    }
+
+    Ok(&data[len..])
+}
+
+fn parse_error(msg: &str) -> StdError {
+    StdError::parse_err("MsgInstantiateContractResponse", msg)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    // encodes a MsgInstantiateContractResponse the way wasmd does
+    pub fn instantiate_response_data(contract_address: &str, data: Option<&[u8]>) -> Binary {
+        let mut encoded = vec![0x0a, contract_address.len() as u8];
+        encoded.extend_from_slice(contract_address.as_bytes());
+        if let Some(data) = data {
+            encoded.push(0x12);
+            encoded.push(data.len() as u8);
+            encoded.extend_from_slice(data);
+        }
+        Binary::from(encoded)
+    }
+
+    #[test]
+    fn parse_address_and_data() {
+        let parsed =
+            parse_instantiate_response(instantiate_response_data("sub_1", Some(b"{}")).as_slice())
+                .unwrap();
+
+        assert_eq!("sub_1", parsed.contract_address);
+        assert_eq!(Some(Binary::from(b"{}")), parsed.data);
+    }
+
+    #[test]
+    fn parse_skips_unknown_fields() {
+        let mut data = vec![0x18, 0x96, 0x01];
+        data.extend_from_slice(instantiate_response_data("sub_1", None).as_slice());
+
+        let parsed = parse_instantiate_response(&data).unwrap();
+
+        assert_eq!("sub_1", parsed.contract_address);
+        assert_eq!(None, parsed.data);
+    }
+
+    #[test]
+    fn parse_truncated() {
+        let data = instantiate_response_data("sub_1", None);
+
+        assert!(parse_instantiate_response(&data.as_slice()[..4]).is_err());
+        assert!(parse_instantiate_response(&[]).is_err());
+    }
+
+    #[test]
+    fn address_prefers_data_over_events() {
+        let response = SubMsgResponse {
+            events: vec![Event::new("instantiate").add_attribute("_contract_address", "sub_2")],
+            data: Some(instantiate_response_data("sub_1", None)),
+        };
+
+        assert_eq!(
+            Some(Addr::unchecked("sub_1")),
+            instantiated_contract_address(&response, 100)
+        );
+    }
+
+    #[test]
+    fn address_ignores_other_events() {
+        let response = SubMsgResponse {
+            events: vec![
+                Event::new("marker").add_attribute("_contract_address", "marker_1"),
+                Event::new("instantiate").add_attribute("_contract_address", "sub_2"),
+            ],
+            data: None,
+        };
+
+        assert_eq!(
+            Some(Addr::unchecked("sub_2")),
+            instantiated_contract_address(&response, 100)
+        );
+    }
+
+    #[test]
+    fn namespaces() {
+        assert_eq!(
+            Some(ReplyNamespace::Subscription),
+            reply_namespace(ELIGIBLE_SUBSCRIPTION_REPLY_ID)
+        );
+        assert_eq!(
+            Some(ReplyNamespace::Migration),
+            reply_namespace(MIGRATE_SUBSCRIPTION_REPLY_ID_OFFSET + 499)
+        );
+        assert_eq!(None, reply_namespace(0));
+    }
+
+    #[test]
+    fn address_falls_back_to_events() {
+        let response = SubMsgResponse {
+            events: vec![Event::new("instantiate").add_attribute("_contract_address", "sub_2")],
+            data: None,
+        };
+
+        assert_eq!(
+            Some(Addr::unchecked("sub_2")),
+            instantiated_contract_address(&response, 100)
+        );
+    }
+
+    #[test]
+    fn address_from_multiple_instantiations() {
+        // the sub instantiating a helper contract reports both, helper first
+        let response = SubMsgResponse {
+            events: vec![
+                Event::new("message").add_attribute("module", "wasm"),
+                Event::new("instantiate")
+                    .add_attribute("_contract_address", "helper_1")
+                    .add_attribute("code_id", "99"),
+                Event::new("wasm").add_attribute("_contract_address", "helper_1"),
+                Event::new("instantiate")
+                    .add_attribute("_contract_address", "sub_1")
+                    .add_attribute("code_id", "100"),
+                Event::new("wasm").add_attribute("_contract_address", "sub_1"),
+            ],
+            data: None,
+        };
+
+        assert_eq!(
+            Some(Addr::unchecked("sub_1")),
+            instantiated_contract_address(&response, 100)
+        );
+    }
+
+    #[test]
+    fn address_ambiguous_without_code_ids() {
+        let response = SubMsgResponse {
+            events: vec![
+                Event::new("instantiate").add_attribute("_contract_address", "helper_1"),
+                Event::new("instantiate").add_attribute("_contract_address", "sub_1"),
+            ],
+            data: None,
+        };
+
+        assert_eq!(None, instantiated_contract_address(&response, 100));
+    }
+
+    #[test]
+    fn address_falls_back_to_wasm_event() {
+        let response = SubMsgResponse {
+            events: vec![
+                Event::new("message").add_attribute("module", "wasm"),
+                Event::new("wasm")
+                    .add_attribute("_contract_address", "sub_1")
+                    .add_attribute("action", "init"),
+            ],
+            data: None,
+        };
+
+        assert_eq!(
+            Some(Addr::unchecked("sub_1")),
+            instantiated_contract_address(&response, 100)
+        );
+    }
+}
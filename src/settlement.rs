@@ -0,0 +1,404 @@
+use std::cmp::Ordering;
+
+use cosmwasm_std::{coin, coins, Addr, BankMsg, Coin, CosmosMsg, Deps, DepsMut, Event, StdResult};
+use provwasm_std::{
+    burn_marker_supply, mint_marker_supply, transfer_marker_coins, withdraw_coins, MarkerMsgParams,
+    ProvenanceMsg, ProvenanceMsgParams, ProvenanceQuerier, ProvenanceQuery,
+};
+
+use crate::error::ContractError;
+use crate::msg::ChangedEntity;
+use crate::state::{config, record_change, State};
+use crate::subscribe::attributes;
+
+// nets marker movements per denom and emits them in a canonical order:
+// mint → withdraw, then deposit → burn, then capital sends last
+pub struct Settlement {
+    recipient: Addr,
+    legs: Vec<(String, i64)>,
+    sends: Vec<(Addr, u128)>,
+    payouts: Vec<(Addr, Coin)>,
+}
+
+impl Settlement {
+    pub fn new(recipient: Addr) -> Settlement {
+        Settlement {
+            recipient,
+            legs: vec![],
+            sends: vec![],
+            payouts: vec![],
+        }
+    }
+
+    // positive legs are minted to the recipient, negative legs are deposited and burned
+    pub fn leg(mut self, denom: &str, amount: i64) -> Settlement {
+        match self.legs.iter_mut().find(|(d, _)| d == denom) {
+            Some((_, net)) => *net += amount,
+            None => self.legs.push((String::from(denom), amount)),
+        }
+        self
+    }
+
+    pub fn send_capital(mut self, to: Addr, amount: u128) -> Settlement {
+        if amount > 0 {
+            self.sends.push((to, amount));
+        }
+        self
+    }
+
+    // distributions paid in a like capital denom are sent as is
+    pub fn send_like_capital(mut self, to: Addr, coin: Coin) -> Settlement {
+        if !coin.amount.is_zero() {
+            self.payouts.push((to, coin));
+        }
+        self
+    }
+
+    pub fn messages(
+        self,
+        deps: Deps<ProvenanceQuery>,
+        state: &State,
+        contract: &Addr,
+    ) -> Result<Vec<CosmosMsg<ProvenanceMsg>>, ContractError> {
+        let mut mints = vec![];
+        let mut burns = vec![];
+
+        for (denom, net) in &self.legs {
+            let amount: u128 = net.unsigned_abs().into();
+            match net.cmp(&0) {
+                Ordering::Greater => {
+                    mints.push(mint_marker_supply(amount, denom)?);
+                    mints.push(withdraw_coins(
+                        denom,
+                        amount,
+                        denom,
+                        self.recipient.clone(),
+                    )?);
+                }
+                Ordering::Less => {
+                    let marker_address = match state.marker_address(denom) {
+                        Some(address) => address.clone(),
+                        None => {
+                            ProvenanceQuerier::new(&deps.querier)
+                                .get_marker_by_denom(denom)?
+                                .address
+                        }
+                    };
+                    burns.push(
+                        BankMsg::Send {
+                            to_address: marker_address.into_string(),
+                            amount: coins(amount, denom),
+                        }
+                        .into(),
+                    );
+                    burns.push(burn_marker_supply(amount, denom)?);
+                }
+                Ordering::Equal => {}
+            }
+        }
+
+        let mut messages = mints;
+        messages.append(&mut burns);
+        for (to, amount) in self.sends {
+            messages.push(send_capital(deps, state, amount, to, contract.clone())?);
+        }
+        for (to, coin) in self.payouts {
+            messages.push(send_coin(deps, state, coin, to, contract.clone())?);
+        }
+
+        Ok(messages)
+    }
+}
+
+// markers never move, so their addresses are looked up once and kept in state
+pub fn cache_marker_addresses(deps: DepsMut<ProvenanceQuery>, state: &mut State) -> StdResult<()> {
+    if state.commitment_marker.is_some() && state.investment_marker.is_some() {
+        return Ok(());
+    }
+
+    let querier = ProvenanceQuerier::new(&deps.querier);
+    let lookup = |denom: &str| querier.get_marker_by_denom(denom).ok().map(|m| m.address);
+    let commitment_marker = lookup(&state.commitment_denom);
+    let investment_marker = lookup(&state.investment_denom);
+
+    if commitment_marker.is_some() || investment_marker.is_some() {
+        state.commitment_marker = commitment_marker;
+        state.investment_marker = investment_marker;
+        config(deps.storage).save(state)?;
+        record_change(deps.storage, ChangedEntity::State, "config")?;
+    }
+
+    Ok(())
+}
+
+pub fn send_capital(
+    deps: Deps<ProvenanceQuery>,
+    state: &State,
+    amount: u128,
+    to: Addr,
+    contract: Addr,
+) -> Result<CosmosMsg<ProvenanceMsg>, ContractError> {
+    send_coin(
+        deps,
+        state,
+        coin(amount, &state.capital_denom),
+        to,
+        contract,
+    )
+}
+
+// restricted capital and like capital denoms are moved by their marker, and only
+// to holders of the attribute they require
+pub fn send_coin(
+    deps: Deps<ProvenanceQuery>,
+    state: &State,
+    coin: Coin,
+    to: Addr,
+    contract: Addr,
+) -> Result<CosmosMsg<ProvenanceMsg>, ContractError> {
+    match state.required_attribute_for(&coin.denom) {
+        None => Ok(BankMsg::Send {
+            to_address: to.into_string(),
+            amount: vec![coin],
+        }
+        .into()),
+        Some(attribute) => {
+            if !attributes(deps, &to).contains(attribute) {
+                return Err(ContractError::MissingCapitalAttribute {
+                    address: to,
+                    attribute: attribute.clone(),
+                });
+            }
+
+            Ok(transfer_marker_coins(
+                coin.amount.u128(),
+                &coin.denom,
+                to,
+                contract,
+            )?)
+        }
+    }
+}
+
+// bank and marker messages carry no memo, so custodians reconcile capital
+// transfers against an event per transfer instead
+pub fn memo_events(
+    state: &State,
+    messages: &[CosmosMsg<ProvenanceMsg>],
+    memo: &Option<String>,
+) -> Vec<Event> {
+    let memo = match memo {
+        Some(memo) if state.memo_transfer_events => memo,
+        _ => return vec![],
+    };
+
+    messages
+        .iter()
+        .filter_map(|msg| match msg {
+            CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => {
+                Some((to_address.clone(), amount.clone()))
+            }
+            CosmosMsg::Custom(ProvenanceMsg {
+                params:
+                    ProvenanceMsgParams::Marker(MarkerMsgParams::TransferMarkerCoins {
+                        coin, to, ..
+                    }),
+                ..
+            }) => Some((to.to_string(), vec![coin.clone()])),
+            _ => None,
+        })
+        .filter(|(_, amount)| {
+            amount
+                .iter()
+                .all(|coin| state.is_like_capital_denom(&coin.denom))
+        })
+        .map(|(recipient, amount)| {
+            Event::new("transfer_memo")
+                .add_attribute("recipient", recipient)
+                .add_attribute(
+                    "amount",
+                    amount
+                        .iter()
+                        .map(|coin| coin.to_string())
+                        .collect::<Vec<String>>()
+                        .join(","),
+                )
+                .add_attribute("memo", memo)
+        })
+        .collect()
+}
+
+pub fn has_capital_attribute(
+    deps: Deps<ProvenanceQuery>,
+    state: &State,
+    address: &Addr,
+    denom: &str,
+) -> bool {
+    match state.required_attribute_for(denom) {
+        None => true,
+        Some(attribute) => attributes(deps, address).contains(attribute),
+    }
+}
+
+pub fn check_capital_attribute(
+    deps: Deps<ProvenanceQuery>,
+    state: &State,
+    address: &Addr,
+    denom: &str,
+) -> Result<(), ContractError> {
+    match state.required_attribute_for(denom) {
+        Some(attribute) if !has_capital_attribute(deps, state, address, denom) => {
+            Err(ContractError::MissingCapitalAttribute {
+                address: address.clone(),
+                attribute: attribute.clone(),
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{burn_args, load_markers, mint_args, send_args, withdraw_args};
+    use crate::state::config_read;
+    use cosmwasm_std::testing::MOCK_CONTRACT_ADDR;
+    use provwasm_mocks::mock_dependencies;
+
+    #[test]
+    fn canonical_order() {
+        let mut deps = mock_dependencies(&[]);
+        load_markers(&mut deps.querier);
+
+        let messages = Settlement::new(Addr::unchecked("sub_1"))
+            .send_capital(Addr::unchecked("destination"), 1_000)
+            .leg("investment_coin", -1_000)
+            .leg("commitment_coin", 500)
+            .messages(
+                deps.as_ref(),
+                &State::test_capital_coin(),
+                &Addr::unchecked(MOCK_CONTRACT_ADDR),
+            )
+            .unwrap();
+
+        assert_eq!(5, messages.len());
+
+        // verify mint → withdraw comes first
+        let coin = mint_args(&messages[0]);
+        assert_eq!("commitment_coin", coin.denom);
+        assert_eq!(500, coin.amount.u128());
+        let (_, coin, recipient) = withdraw_args(&messages[1]);
+        assert_eq!("commitment_coin", coin.denom);
+        assert_eq!(&Addr::unchecked("sub_1"), recipient);
+
+        // verify deposit → burn comes next
+        let (to_address, coins) = send_args(&messages[2]);
+        assert_eq!("tp18vd8fpwxzck93qlwghaj6arh4p7c5n89x8kskz", to_address);
+        assert_eq!("investment_coin", coins.first().unwrap().denom);
+        let coin = burn_args(&messages[3]);
+        assert_eq!("investment_coin", coin.denom);
+        assert_eq!(1_000, coin.amount.u128());
+
+        // verify capital is sent last
+        let (to_address, coins) = send_args(&messages[4]);
+        assert_eq!("destination", to_address);
+        assert_eq!("capital_coin", coins.first().unwrap().denom);
+    }
+
+    #[test]
+    fn memo_events_for_capital_transfers() {
+        let mut state = State::test_capital_coin();
+        let memo = Some(String::from("wire 42"));
+        let messages = vec![
+            BankMsg::Send {
+                to_address: String::from("marker"),
+                amount: coins(1_000, "investment_coin"),
+            }
+            .into(),
+            BankMsg::Send {
+                to_address: String::from("destination"),
+                amount: coins(500, "capital_coin"),
+            }
+            .into(),
+        ];
+
+        // verify nothing is emitted until enabled
+        assert!(memo_events(&state, &messages, &memo).is_empty());
+
+        // verify only the capital transfer is tagged with the memo
+        state.memo_transfer_events = true;
+        let events = memo_events(&state, &messages, &memo);
+        assert_eq!(1, events.len());
+        assert_eq!(
+            vec![
+                ("recipient", "destination"),
+                ("amount", "500capital_coin"),
+                ("memo", "wire 42")
+            ],
+            events[0]
+                .attributes
+                .iter()
+                .map(|attr| (attr.key.as_str(), attr.value.as_str()))
+                .collect::<Vec<_>>()
+        );
+        assert!(memo_events(&state, &messages, &None).is_empty());
+    }
+
+    #[test]
+    fn nets_legs_per_denom() {
+        let mut deps = mock_dependencies(&[]);
+        load_markers(&mut deps.querier);
+
+        let messages = Settlement::new(Addr::unchecked("sub_1"))
+            .leg("investment_coin", 1_000)
+            .leg("investment_coin", -400)
+            .leg("commitment_coin", 200)
+            .leg("commitment_coin", -200)
+            .messages(
+                deps.as_ref(),
+                &State::test_capital_coin(),
+                &Addr::unchecked(MOCK_CONTRACT_ADDR),
+            )
+            .unwrap();
+
+        assert_eq!(2, messages.len());
+        assert_eq!(600, mint_args(&messages[0]).amount.u128());
+    }
+
+    #[test]
+    fn uses_cached_marker_address() {
+        // no markers loaded, so the query fallback would fail
+        let deps = mock_dependencies(&[]);
+
+        let messages = Settlement::new(Addr::unchecked("sub_1"))
+            .leg("investment_coin", -1_000)
+            .messages(
+                deps.as_ref(),
+                &State {
+                    investment_marker: Some(Addr::unchecked("investment_marker")),
+                    ..State::test_capital_coin()
+                },
+                &Addr::unchecked(MOCK_CONTRACT_ADDR),
+            )
+            .unwrap();
+
+        let (to_address, _) = send_args(&messages[0]);
+        assert_eq!("investment_marker", to_address);
+    }
+
+    #[test]
+    fn caches_marker_addresses() {
+        let mut deps = mock_dependencies(&[]);
+        load_markers(&mut deps.querier);
+        let mut state = State::test_capital_coin();
+
+        cache_marker_addresses(deps.as_mut(), &mut state).unwrap();
+
+        assert_eq!(
+            Some(Addr::unchecked("tp18vd8fpwxzck93qlwghaj6arh4p7c5n89x8kskz")),
+            state.investment_marker
+        );
+        assert!(state.commitment_marker.is_some());
+        assert_eq!(state, config_read(&deps.storage).load().unwrap());
+    }
+}
@@ -1,19 +1,105 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::BTreeSet;
+use std::convert::TryFrom;
 
-use cosmwasm_std::{Addr, Storage};
+use cosmwasm_std::{Addr, Binary, Coin, Order, StdResult, Storage};
 use cosmwasm_storage::{
     bucket, bucket_read, singleton, singleton_read, Bucket, ReadonlyBucket, ReadonlySingleton,
     Singleton,
 };
 
-use crate::msg::AssetExchange;
+use crate::error::ContractError;
+use crate::msg::{
+    ArchiveCheckpoint, AssetExchange, Change, ChangedEntity, ClosedSubscription,
+    CompletedAssetExchange, ConsentProposal, ConsentVote, ExchangePrice, MarkerSetup,
+    PendingOperation, PerformanceFeeAccrual, QueuedSettlement, Receipt, SettlementAuthorization,
+    SubscriptionCodeId, SubscriptionStats,
+};
 
 pub static CONFIG_KEY: &[u8] = b"config";
+pub static INSTANTIATE_TERMS_KEY: &[u8] = b"instantiate_terms";
+pub static SUBSCRIPTION_CODE_IDS_KEY: &[u8] = b"subscription_code_ids";
 
 pub static ASSET_EXCHANGE_NAMESPACE: &[u8] = b"asset_exchange";
+pub static OUTSTANDING_OBLIGATIONS_KEY: &[u8] = b"outstanding_obligations";
+pub static ESCROWED_CAPITAL_KEY: &[u8] = b"escrowed_capital";
+pub static COMPLETED_EXCHANGE_NAMESPACE: &[u8] = b"completed_exchange";
+pub static PREPAYMENT_NAMESPACE: &[u8] = b"prepayment";
+pub static ESCROW_NAMESPACE: &[u8] = b"escrow";
+pub static SUBSCRIPTION_STATS_NAMESPACE: &[u8] = b"subscription_stats";
+pub static NAV_HISTORY_NAMESPACE: &[u8] = b"nav_history";
+pub static HIGH_WATER_MARK_NAMESPACE: &[u8] = b"high_water_mark";
+pub static PERFORMANCE_FEE_NAMESPACE: &[u8] = b"performance_fee";
+pub static SUBSCRIPTION_LP_NAMESPACE: &[u8] = b"subscription_lp";
+pub static LP_SUBSCRIPTION_NAMESPACE: &[u8] = b"lp_subscription";
+pub static CLOSED_SUBSCRIPTION_NAMESPACE: &[u8] = b"closed_subscription";
+pub static LAST_PROPOSAL_NAMESPACE: &[u8] = b"last_proposal";
+pub static MARKER_SETUP_KEY: &[u8] = b"marker_setup";
+pub static PROPOSING_LP_KEY: &[u8] = b"proposing_lp";
+pub static PROPOSING_AGENT_KEY: &[u8] = b"proposing_agent";
+pub static PROPOSING_DEPOSIT_KEY: &[u8] = b"proposing_deposit";
+pub static PROPOSING_AUTO_ACCEPT_KEY: &[u8] = b"proposing_auto_accept";
+pub static AUTO_ACCEPTED_CAPITAL_KEY: &[u8] = b"auto_accepted_capital";
+pub static SUBSCRIPTION_AGENT_NAMESPACE: &[u8] = b"subscription_agent";
+pub static MIGRATION_BATCH_KEY: &[u8] = b"migration_batch";
+pub static NOTIFICATION_BATCH_KEY: &[u8] = b"notification_batch";
+pub static NOTIFICATIONS_DISABLED_NAMESPACE: &[u8] = b"notifications_disabled";
+pub static MIGRATION_FAILURE_NAMESPACE: &[u8] = b"migration_failures";
+pub static CHANGE_REVISION_KEY: &[u8] = b"change_revision";
+pub static CHANGE_LOG_NAMESPACE: &[u8] = b"change_log";
+pub static RECEIPT_NAMESPACE: &[u8] = b"receipt";
+pub static RECEIPT_SEQUENCE_NAMESPACE: &[u8] = b"receipt_sequence";
+pub static CHANGE_LOG_CHECKPOINT_KEY: &[u8] = b"change_log_checkpoint";
+pub static RECEIPT_CHECKPOINT_NAMESPACE: &[u8] = b"receipt_checkpoint";
+pub const MAX_ARCHIVE_BATCH: usize = 100;
+pub static SETTLEMENT_QUEUE_NAMESPACE: &[u8] = b"settlement_queue";
+pub static SETTLEMENT_QUEUE_SEQUENCE_KEY: &[u8] = b"settlement_queue_sequence";
+pub static SETTLEMENT_AUTHORIZATION_NAMESPACE: &[u8] = b"settlement_authorization";
+pub static AUTHORIZED_FUNDS_KEY: &[u8] = b"authorized_funds";
+pub static SETTLEMENT_BATCH_KEY: &[u8] = b"settlement_batch";
+pub const MAX_QUEUE_BATCH: u32 = 30;
+// number of subs whose exchanges are cancelled per batch cancellation
+pub const MAX_BATCH_CANCELLATIONS: usize = 30;
+pub static PENDING_OPERATION_NAMESPACE: &[u8] = b"pending_operation";
+pub static OPERATION_SEQUENCE_KEY: &[u8] = b"operation_sequence";
+pub static CONSENT_PROPOSAL_NAMESPACE: &[u8] = b"consent_proposal";
+pub static CONSENT_VOTE_NAMESPACE: &[u8] = b"consent_vote";
+pub static CONSENT_SEQUENCE_KEY: &[u8] = b"consent_sequence";
+pub static TERM_EXTENSION_KEY: &[u8] = b"term_extension";
+pub static MANAGEMENT_FEE_CHANGE_KEY: &[u8] = b"management_fee_change";
+pub static ISSUANCE_BATCH_NAMESPACE: &[u8] = b"issuance_batch";
+pub static ISSUANCE_SEQUENCE_KEY: &[u8] = b"issuance_sequence";
+pub static BATCH_CANCELLATION_NAMESPACE: &[u8] = b"batch_cancellation";
+pub const DEFAULT_RECEIPT_LIMIT: u32 = 10;
+pub const MAX_RECEIPT_LIMIT: u32 = 30;
+
+// number of posted navs retained, and returned per history query
+pub const MAX_NAV_HISTORY: usize = 500;
+pub const DEFAULT_NAV_HISTORY_LIMIT: u32 = 30;
+pub const MAX_NAV_HISTORY_LIMIT: u32 = 100;
+// number of crystallized performance fees returned per query
+pub const DEFAULT_PERFORMANCE_FEE_LIMIT: u32 = 30;
+pub const MAX_PERFORMANCE_FEE_LIMIT: u32 = 100;
+// number of completed exchanges retained per subscription
+pub const MAX_COMPLETED_EXCHANGES: usize = 100;
+// longest value accepted for any metadata field
+pub const MAX_METADATA_FIELD_LENGTH: usize = 256;
+// number of changes returned per change log query
+pub const MAX_CHANGES: usize = 100;
+// number of queries answered by a single batch query
+pub const MAX_BATCH_QUERIES: usize = 20;
+// delay before a change to an already registered treasury takes effect
+pub const TREASURY_TIMELOCK_SECONDS: u64 = 172_800;
+// largest decimal exponent a denom may be configured with
+pub const MAX_DENOM_DECIMALS: u32 = 18;
+
+pub static SUBSCRIPTION_STATUS_NAMESPACE: &[u8] = b"subscription_status";
+pub static SUBSCRIPTION_INDEX_NAMESPACE: &[u8] = b"subscription_index";
+pub const DEFAULT_SUBSCRIPTION_LIMIT: u32 = 30;
+pub const MAX_SUBSCRIPTION_LIMIT: u32 = 100;
 
+// whole-set singletons that held subs before they were indexed by status
 pub static PENDING_SUBSCRIPTIONS_KEY: &[u8] = b"pending_subscriptions";
 pub static ELIGIBLE_SUBSCRIPTIONS_KEY: &[u8] = b"eligible_subscriptions";
 pub static ACCEPTED_SUBSCRIPTIONS_KEY: &[u8] = b"accepted_subscriptions";
@@ -23,62 +109,1197 @@ pub struct State {
     pub subscription_code_id: u64,
     pub recovery_admin: Addr,
     pub gp: Addr,
-    pub required_attestations: Vec<HashSet<String>>,
+    pub required_attestations: Vec<BTreeSet<String>>,
     pub commitment_denom: String,
     pub investment_denom: String,
     pub capital_denom: String,
     pub capital_per_share: u64,
     pub required_capital_attribute: Option<String>,
+    #[serde(default)]
+    pub due_grace_seconds: u64,
+    #[serde(default)]
+    pub escrow_capital: bool,
+    #[serde(default)]
+    pub permissionless_eligibility: bool,
+    #[serde(default)]
+    pub paused: bool,
+    #[serde(default)]
+    pub delegates: BTreeSet<Addr>,
+    #[serde(default)]
+    pub commitment_marker: Option<Addr>,
+    #[serde(default)]
+    pub investment_marker: Option<Addr>,
+    #[serde(default)]
+    pub max_receipts: Option<u64>,
+    #[serde(default)]
+    pub receipt_retention_seconds: Option<u64>,
+    #[serde(default)]
+    pub change_log_retention: Option<u64>,
+    #[serde(default)]
+    pub settlement_hours: Option<SettlementHours>,
+    // committee members who must approve capital movements above the threshold
+    #[serde(default)]
+    pub gp_committee: Vec<Addr>,
+    #[serde(default)]
+    pub approval_threshold: u32,
+    #[serde(default)]
+    pub approval_capital_threshold: Option<u64>,
+    // when set, lp eligibility is decided by this contract instead of attestations
+    #[serde(default)]
+    pub compliance_contract: Option<Addr>,
+    // when set, withdrawals may only be sent here
+    #[serde(default)]
+    pub treasury: Option<Addr>,
+    #[serde(default)]
+    pub pending_treasury: Option<PendingTreasury>,
+    // ceilings that keep sweeps and queries over outstanding exchanges within gas limits
+    #[serde(default)]
+    pub max_outstanding_exchanges: Option<u64>,
+    #[serde(default)]
+    pub max_outstanding_capital: Option<u64>,
+    // like capital denoms accepted at a rate against the capital denom
+    #[serde(default)]
+    pub capital_exchange_rates: Vec<CapitalExchangeRate>,
+    // attributes holders of restricted like capital denoms must have
+    #[serde(default)]
+    pub capital_attributes: Vec<CapitalAttribute>,
+    #[serde(default)]
+    pub metadata: FundMetadata,
+    // proposals committing less than this are rejected before a sub is created
+    #[serde(default)]
+    pub min_initial_commitment: Option<u64>,
+    // share classes beyond the main fund, e.g. side pockets
+    #[serde(default)]
+    pub share_classes: Vec<ShareClass>,
+    // capital per investment share, used to price nav redemptions at completion
+    #[serde(default)]
+    pub nav: Option<NetAssetValue>,
+    // largest share of the fund's commitment and investment any one sub may hold
+    #[serde(default)]
+    pub max_ownership_bps: Option<u16>,
+    // when set, capital may only be sent to these addresses on request
+    #[serde(default)]
+    pub approved_destinations: Option<BTreeSet<Addr>>,
+    // attested lps within these limits are accepted without the gp
+    #[serde(default)]
+    pub auto_accept: Option<AutoAcceptConfig>,
+    #[serde(default)]
+    pub performance_fee: Option<PerformanceFee>,
+    // no new capital may be called once the term ends
+    #[serde(default)]
+    pub term_end: Option<u64>,
+    // cancelling a batch that touched more subs than this needs the admin's co-sign
+    #[serde(default)]
+    pub batch_cancel_cosign_threshold: Option<u64>,
+    // share of capital calls and distributions sent to the collector as they settle
+    #[serde(default)]
+    pub management_fee: Option<ManagementFee>,
+    // repeat a settlement's memo on an event for each transfer it makes
+    #[serde(default)]
+    pub memo_transfer_events: bool,
+    // decimal exponents of the capital and like capital denoms
+    #[serde(default)]
+    pub denom_decimals: Vec<DenomDecimals>,
+    // code that closed subs may be migrated to so they can no longer be used
+    #[serde(default)]
+    pub tombstone_code_id: Option<u64>,
+    // closes onboarding without pausing settlement, raises saved before it stay open
+    #[serde(default = "accepting_proposals_by_default")]
+    pub accepting_proposals: bool,
+    // blocks a sender waits between proposals
+    #[serde(default)]
+    pub proposal_cooldown_blocks: Option<u64>,
+    // pending or eligible subs each proposer may have at once, an agent's proposals
+    // count toward the agent rather than the lp they were made for
+    #[serde(default)]
+    pub max_pending_per_lp: Option<u64>,
+}
+
+fn accepting_proposals_by_default() -> bool {
+    true
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct ShareClass {
+    pub name: String,
+    pub investment_denom: String,
+}
+
+// descriptive details for wallets and explorers, not used by the contract itself
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, JsonSchema)]
+pub struct FundMetadata {
+    pub name: Option<String>,
+    pub entity_id: Option<String>,
+    pub terms_uri: Option<String>,
+    pub logo_uri: Option<String>,
+    pub logo_hash: Option<String>,
+    pub jurisdiction: Option<String>,
+    // seconds since epoch
+    pub closing_date: Option<u64>,
+}
+
+impl FundMetadata {
+    // state is loaded on every call, so each field is kept small
+    pub fn validate(&self) -> Result<(), ContractError> {
+        for (field, value) in [
+            ("name", &self.name),
+            ("entity_id", &self.entity_id),
+            ("terms_uri", &self.terms_uri),
+            ("logo_uri", &self.logo_uri),
+            ("logo_hash", &self.logo_hash),
+            ("jurisdiction", &self.jurisdiction),
+        ] {
+            if value
+                .as_ref()
+                .is_some_and(|value| value.len() > MAX_METADATA_FIELD_LENGTH)
+            {
+                return Err(ContractError::MetadataTooLong {
+                    field,
+                    max: MAX_METADATA_FIELD_LENGTH,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// amount of capital denom credited per like denom is numerator / denominator
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct CapitalExchangeRate {
+    pub denom: String,
+    pub numerator: u64,
+    pub denominator: u64,
+}
+
+// denoms without an entry share the capital denom's exponent
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct DenomDecimals {
+    pub denom: String,
+    pub decimals: u32,
+}
+
+// same rules as the cosmos sdk: [a-zA-Z][a-zA-Z0-9/:._-]{2,127}
+pub fn is_valid_denom(denom: &str) -> bool {
+    let mut chars = denom.chars();
+    (3..=128).contains(&denom.len())
+        && chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || "/:._-".contains(c))
+}
+
+// commitment limits in capital for subs accepted without the gp
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct AutoAcceptConfig {
+    pub max_commitment: u64,
+    pub total_cap: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct CapitalAttribute {
+    pub denom: String,
+    pub attribute: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct NetAssetValue {
+    pub per_share: u64,
+    pub posted_at: u64,
+}
+
+// fee on nav gains above each class's high-water mark, crystallized no more often
+// than the interval when one is set
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct PerformanceFee {
+    pub fee_bps: u16,
+    pub min_interval_seconds: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct HighWaterMark {
+    pub nav_per_share: u64,
+    pub crystallized_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct ManagementFee {
+    pub fee_bps: u16,
+    pub collector: Addr,
+}
+
+// a management fee change proposed by the gp and waiting on the admin
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct ManagementFeeChange {
+    pub management_fee: Option<ManagementFee>,
+    pub approvals: Vec<Addr>,
+}
+
+// an extension of the fund term waiting on both the gp and the admin
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct TermExtension {
+    pub term_end: u64,
+    pub approvals: Vec<Addr>,
+}
+
+// a treasury change that can't be applied until the timelock passes
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct PendingTreasury {
+    pub treasury: Option<Addr>,
+    pub effective_at: u64,
+}
+
+// running totals of every sub's outstanding exchanges, so obligation limits are
+// checked without loading each sub
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, JsonSchema)]
+pub struct OutstandingObligations {
+    pub exchanges: u64,
+    pub capital: u64,
+}
+
+impl OutstandingObligations {
+    fn add(&mut self, exchanges: &[AssetExchange]) {
+        self.exchanges += exchanges.len() as u64;
+        self.capital = self.capital.saturating_add(obligated_capital(exchanges));
+    }
+
+    fn subtract(&mut self, exchanges: &[AssetExchange]) {
+        self.exchanges = self.exchanges.saturating_sub(exchanges.len() as u64);
+        self.capital = self.capital.saturating_sub(obligated_capital(exchanges));
+    }
+}
+
+fn obligated_capital(exchanges: &[AssetExchange]) -> u64 {
+    exchanges
+        .iter()
+        .map(|exchange| exchange.capital.unwrap_or_default().unsigned_abs())
+        .fold(0, u64::saturating_add)
+}
+
+// utc days of the week (0 is monday) and seconds of the day when settlement may move funds
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct SettlementHours {
+    pub weekdays: BTreeSet<u8>,
+    pub start_seconds: u32,
+    pub end_seconds: u32,
+}
+
+impl SettlementHours {
+    pub fn is_valid(&self) -> bool {
+        !self.weekdays.is_empty()
+            && self.weekdays.iter().all(|day| *day < 7)
+            && self.start_seconds < self.end_seconds
+            && self.end_seconds <= 86_400
+    }
+
+    pub fn allows(&self, epoch_seconds: u64) -> bool {
+        // the epoch started on a thursday
+        let weekday = ((epoch_seconds / 86_400 + 3) % 7) as u8;
+        let second_of_day = (epoch_seconds % 86_400) as u32;
+        self.weekdays.contains(&weekday)
+            && second_of_day >= self.start_seconds
+            && second_of_day < self.end_seconds
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InstantiateTerms {
+    pub hash: String,
+    pub msg: Binary,
 }
 
 impl State {
+    pub fn investment_denom_for(&self, class: &Option<String>) -> Result<&String, ContractError> {
+        match class {
+            None => Ok(&self.investment_denom),
+            Some(class) => self
+                .share_classes
+                .iter()
+                .find(|share_class| &share_class.name == class)
+                .map(|share_class| &share_class.investment_denom)
+                .ok_or_else(|| ContractError::UnknownShareClass {
+                    class: class.clone(),
+                }),
+        }
+    }
+
+    pub fn investment_denoms(&self) -> Vec<&String> {
+        let mut denoms = vec![&self.investment_denom];
+        denoms.extend(
+            self.share_classes
+                .iter()
+                .map(|share_class| &share_class.investment_denom),
+        );
+        denoms
+    }
+
+    pub fn validate(&self) -> Result<(), ContractError> {
+        if self.capital_per_share == 0 {
+            return Err(ContractError::ZeroCapitalPerShare {});
+        }
+
+        if let Some(index) = self
+            .required_attestations
+            .iter()
+            .position(|attestations| attestations.is_empty())
+        {
+            return Err(ContractError::EmptyAttestationSet { index });
+        }
+
+        let mut denoms = BTreeSet::new();
+        for denom in self
+            .investment_denoms()
+            .into_iter()
+            .chain(vec![&self.commitment_denom, &self.capital_denom])
+        {
+            if !is_valid_denom(denom) {
+                return Err(ContractError::InvalidDenom {
+                    denom: denom.clone(),
+                });
+            }
+            if !denoms.insert(denom) {
+                return Err(ContractError::DuplicateDenom {
+                    denom: denom.clone(),
+                });
+            }
+        }
+
+        let mut decimal_denoms = BTreeSet::new();
+        for denom_decimals in &self.denom_decimals {
+            if !is_valid_denom(&denom_decimals.denom) {
+                return Err(ContractError::InvalidDenom {
+                    denom: denom_decimals.denom.clone(),
+                });
+            }
+            if !decimal_denoms.insert(&denom_decimals.denom) {
+                return Err(ContractError::DuplicateDenom {
+                    denom: denom_decimals.denom.clone(),
+                });
+            }
+            if denom_decimals.decimals > MAX_DENOM_DECIMALS {
+                return Err(ContractError::InvalidRequest {
+                    reason: "denom decimals can't exceed 18",
+                });
+            }
+        }
+
+        self.metadata.validate()
+    }
+
+    // value of attached funds in the capital denom, converting any like denoms
+    pub fn capital_value(&self, funds: &[Coin]) -> Result<u128, ContractError> {
+        funds.iter().try_fold(0u128, |total, coin| {
+            let overflow = || ContractError::ConversionOverflow {
+                from: coin.denom.clone(),
+                to: self.capital_denom.clone(),
+            };
+            let value = if coin.denom == self.capital_denom {
+                coin.amount.u128()
+            } else {
+                match self
+                    .capital_exchange_rates
+                    .iter()
+                    .find(|rate| rate.denom == coin.denom)
+                {
+                    Some(rate) => {
+                        self.normalize(coin.amount.u128(), &coin.denom, &self.capital_denom)?
+                            .checked_mul(u128::from(rate.numerator))
+                            .ok_or_else(overflow)?
+                            / u128::from(rate.denominator)
+                    }
+                    None => 0,
+                }
+            };
+            total.checked_add(value).ok_or_else(overflow)
+        })
+    }
+
+    fn decimals(&self, denom: &str) -> u32 {
+        let find = |denom: &str| {
+            self.denom_decimals
+                .iter()
+                .find(|decimals| decimals.denom == denom)
+                .map(|decimals| decimals.decimals)
+        };
+        find(denom)
+            .or_else(|| find(&self.capital_denom))
+            .unwrap_or_default()
+    }
+
+    // rescales an amount of one denom into the units of another with equal value
+    pub fn normalize(&self, amount: u128, from: &str, to: &str) -> Result<u128, ContractError> {
+        let overflow = || ContractError::ConversionOverflow {
+            from: String::from(from),
+            to: String::from(to),
+        };
+        let (from_decimals, to_decimals) = (self.decimals(from), self.decimals(to));
+        let scale = 10u128
+            .checked_pow(from_decimals.abs_diff(to_decimals))
+            .ok_or_else(overflow)?;
+        if to_decimals >= from_decimals {
+            amount.checked_mul(scale).ok_or_else(overflow)
+        } else {
+            Ok(amount / scale)
+        }
+    }
+
+    pub fn capital_funds(&self, funds: &[Coin]) -> Vec<Coin> {
+        funds
+            .iter()
+            .filter(|coin| self.is_like_capital_denom(&coin.denom))
+            .cloned()
+            .collect()
+    }
+
+    // the collector's share of capital moved by a settlement
+    pub fn management_fee_on(&self, capital: u64) -> u64 {
+        self.management_fee
+            .as_ref()
+            .map(|fee| (u128::from(capital) * u128::from(fee.fee_bps) / 10_000) as u64)
+            .unwrap_or_default()
+    }
+
+    // the capital denom's attribute is kept apart, as subs are told of it
+    pub fn required_attribute_for(&self, denom: &str) -> Option<&String> {
+        if denom == self.capital_denom {
+            self.required_capital_attribute.as_ref()
+        } else {
+            self.capital_attributes
+                .iter()
+                .find(|required| required.denom == denom)
+                .map(|required| &required.attribute)
+        }
+    }
+
+    pub fn is_like_capital_denom(&self, denom: &str) -> bool {
+        denom == self.capital_denom
+            || self
+                .capital_exchange_rates
+                .iter()
+                .any(|rate| rate.denom == denom)
+    }
+
+    // fixes the capital of a nav priced exchange from the latest posted nav
+    pub fn priced(&self, exchange: &AssetExchange) -> Result<AssetExchange, ContractError> {
+        match exchange.price {
+            Some(ExchangePrice::Nav) => {
+                let nav = self.nav.as_ref().ok_or(ContractError::NavNotPosted {})?;
+                let capital = u128::from(exchange.investment.unwrap_or_default().unsigned_abs())
+                    * u128::from(nav.per_share);
+                Ok(AssetExchange {
+                    capital: Some(i64::try_from(capital)?),
+                    ..exchange.clone()
+                })
+            }
+            None => Ok(exchange.clone()),
+        }
+    }
+
     pub fn not_evenly_divisble(&self, amount: u64) -> bool {
-        amount % self.capital_per_share > 0
+        !amount.is_multiple_of(self.capital_per_share)
     }
 
     pub fn capital_to_shares(&self, amount: u64) -> u64 {
         amount / self.capital_per_share
     }
+
+    pub fn marker_address(&self, denom: &str) -> Option<&Addr> {
+        if denom == self.commitment_denom {
+            self.commitment_marker.as_ref()
+        } else if denom == self.investment_denom {
+            self.investment_marker.as_ref()
+        } else {
+            None
+        }
+    }
+
+    pub fn is_gp_or_delegate(&self, sender: &Addr) -> bool {
+        *sender == self.gp || self.delegates.contains(sender)
+    }
+
+    pub fn auto_accepts(&self, commitment: u64, auto_accepted: u64) -> bool {
+        self.auto_accept.as_ref().is_some_and(|auto_accept| {
+            commitment > 0
+                && commitment <= auto_accept.max_commitment
+                && auto_accepted + commitment <= auto_accept.total_cap
+        })
+    }
+
+    pub fn check_destination(&self, destination: &Addr) -> Result<(), ContractError> {
+        match &self.approved_destinations {
+            Some(approved) if !approved.contains(destination) => {
+                Err(ContractError::DestinationNotApproved {
+                    destination: destination.clone(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn requires_approval(&self, capital: u64) -> bool {
+        self.approval_threshold > 0
+            && self
+                .approval_capital_threshold
+                .is_some_and(|threshold| capital > threshold)
+    }
 }
 
-pub fn config(storage: &mut dyn Storage) -> Singleton<State> {
+pub fn config(storage: &mut dyn Storage) -> Singleton<'_, State> {
     singleton(storage, CONFIG_KEY)
 }
 
-pub fn config_read(storage: &dyn Storage) -> ReadonlySingleton<State> {
+pub fn config_read(storage: &dyn Storage) -> ReadonlySingleton<'_, State> {
     singleton_read(storage, CONFIG_KEY)
 }
 
-pub fn asset_exchange_storage(storage: &mut dyn Storage) -> Bucket<Vec<AssetExchange>> {
+pub fn subscription_code_ids(storage: &mut dyn Storage) -> Singleton<'_, Vec<SubscriptionCodeId>> {
+    singleton(storage, SUBSCRIPTION_CODE_IDS_KEY)
+}
+
+pub fn subscription_code_ids_read(
+    storage: &dyn Storage,
+) -> ReadonlySingleton<'_, Vec<SubscriptionCodeId>> {
+    singleton_read(storage, SUBSCRIPTION_CODE_IDS_KEY)
+}
+
+// appends the code id to the history unless it is already the active one
+pub fn record_subscription_code_id(
+    storage: &mut dyn Storage,
+    code_id: u64,
+    activated_at: Option<u64>,
+) -> StdResult<()> {
+    let mut history = subscription_code_ids(storage)
+        .may_load()?
+        .unwrap_or_default();
+    if history.last().map(|last| last.code_id) != Some(code_id) {
+        history.push(SubscriptionCodeId {
+            code_id,
+            activated_at,
+        });
+        subscription_code_ids(storage).save(&history)?;
+    }
+    Ok(())
+}
+
+// keyed by posting time, so a nav reposted in the same block replaces the earlier one
+pub fn nav_history(storage: &mut dyn Storage) -> Bucket<'_, NetAssetValue> {
+    bucket(storage, NAV_HISTORY_NAMESPACE)
+}
+
+pub fn nav_history_read(storage: &dyn Storage) -> ReadonlyBucket<'_, NetAssetValue> {
+    bucket_read(storage, NAV_HISTORY_NAMESPACE)
+}
+
+// appends the nav and prunes the oldest entries beyond the retained history
+pub fn record_nav(storage: &mut dyn Storage, nav: &NetAssetValue) -> StdResult<()> {
+    let mut history = nav_history(storage);
+    history.save(&nav.posted_at.to_be_bytes(), nav)?;
+
+    let pruned = history
+        .range(None, None, Order::Descending)
+        .skip(MAX_NAV_HISTORY)
+        .map(|item| item.map(|(key, _)| key))
+        .collect::<StdResult<Vec<Vec<u8>>>>()?;
+    for key in pruned {
+        history.remove(&key);
+    }
+    Ok(())
+}
+
+// keyed by the class's investment denom so the main fund is tracked like any other class
+pub fn high_water_mark_storage(storage: &mut dyn Storage) -> Bucket<'_, HighWaterMark> {
+    bucket(storage, HIGH_WATER_MARK_NAMESPACE)
+}
+
+pub fn high_water_mark_storage_read(storage: &dyn Storage) -> ReadonlyBucket<'_, HighWaterMark> {
+    bucket_read(storage, HIGH_WATER_MARK_NAMESPACE)
+}
+
+// keyed by crystallization time then investment denom
+pub fn performance_fee_storage(storage: &mut dyn Storage) -> Bucket<'_, PerformanceFeeAccrual> {
+    bucket(storage, PERFORMANCE_FEE_NAMESPACE)
+}
+
+pub fn performance_fee_storage_read(
+    storage: &dyn Storage,
+) -> ReadonlyBucket<'_, PerformanceFeeAccrual> {
+    bucket_read(storage, PERFORMANCE_FEE_NAMESPACE)
+}
+
+pub fn instantiate_terms(storage: &mut dyn Storage) -> Singleton<'_, InstantiateTerms> {
+    singleton(storage, INSTANTIATE_TERMS_KEY)
+}
+
+pub fn instantiate_terms_read(storage: &dyn Storage) -> ReadonlySingleton<'_, InstantiateTerms> {
+    singleton_read(storage, INSTANTIATE_TERMS_KEY)
+}
+
+pub fn asset_exchange_storage(storage: &mut dyn Storage) -> Bucket<'_, Vec<AssetExchange>> {
     bucket(storage, ASSET_EXCHANGE_NAMESPACE)
 }
 
-pub fn asset_exchange_storage_read(storage: &dyn Storage) -> ReadonlyBucket<Vec<AssetExchange>> {
+pub fn asset_exchange_storage_read(
+    storage: &dyn Storage,
+) -> ReadonlyBucket<'_, Vec<AssetExchange>> {
     bucket_read(storage, ASSET_EXCHANGE_NAMESPACE)
 }
 
-pub fn pending_subscriptions(storage: &mut dyn Storage) -> Singleton<HashSet<Addr>> {
-    singleton(storage, PENDING_SUBSCRIPTIONS_KEY)
+pub fn outstanding_obligations(storage: &mut dyn Storage) -> Singleton<'_, OutstandingObligations> {
+    singleton(storage, OUTSTANDING_OBLIGATIONS_KEY)
+}
+
+pub fn outstanding_obligations_read(
+    storage: &dyn Storage,
+) -> ReadonlySingleton<'_, OutstandingObligations> {
+    singleton_read(storage, OUTSTANDING_OBLIGATIONS_KEY)
+}
+
+// every change to a sub's outstanding exchanges goes through here to keep the totals in step
+pub fn save_asset_exchanges(
+    storage: &mut dyn Storage,
+    subscription: &Addr,
+    exchanges: &[AssetExchange],
+) -> StdResult<()> {
+    let previous = asset_exchange_storage_read(storage)
+        .may_load(subscription.as_bytes())?
+        .unwrap_or_default();
+    let mut totals = outstanding_obligations_read(storage)
+        .may_load()?
+        .unwrap_or_default();
+    totals.subtract(&previous);
+    totals.add(exchanges);
+    outstanding_obligations(storage).save(&totals)?;
+
+    asset_exchange_storage(storage).save(subscription.as_bytes(), &exchanges.to_vec())
+}
+
+pub fn remove_asset_exchanges(storage: &mut dyn Storage, subscription: &Addr) -> StdResult<()> {
+    save_asset_exchanges(storage, subscription, &[])?;
+    asset_exchange_storage(storage).remove(subscription.as_bytes());
+
+    Ok(())
+}
+
+// tallies the totals from scratch for raises with exchanges issued before they were kept
+pub fn tally_outstanding_obligations(storage: &mut dyn Storage) -> StdResult<()> {
+    let mut totals = OutstandingObligations::default();
+    for item in asset_exchange_storage_read(storage).range(None, None, Order::Ascending) {
+        totals.add(&item?.1);
+    }
+
+    outstanding_obligations(storage).save(&totals)
+}
+
+pub fn completed_exchange_storage(
+    storage: &mut dyn Storage,
+) -> Bucket<'_, Vec<CompletedAssetExchange>> {
+    bucket(storage, COMPLETED_EXCHANGE_NAMESPACE)
+}
+
+pub fn completed_exchange_storage_read(
+    storage: &dyn Storage,
+) -> ReadonlyBucket<'_, Vec<CompletedAssetExchange>> {
+    bucket_read(storage, COMPLETED_EXCHANGE_NAMESPACE)
+}
+
+pub fn prepayment_storage(storage: &mut dyn Storage) -> Bucket<'_, u64> {
+    bucket(storage, PREPAYMENT_NAMESPACE)
+}
+
+pub fn prepayment_storage_read(storage: &dyn Storage) -> ReadonlyBucket<'_, u64> {
+    bucket_read(storage, PREPAYMENT_NAMESPACE)
+}
+
+pub fn subscription_stats_storage(storage: &mut dyn Storage) -> Bucket<'_, SubscriptionStats> {
+    bucket(storage, SUBSCRIPTION_STATS_NAMESPACE)
+}
+
+pub fn subscription_stats_storage_read(
+    storage: &dyn Storage,
+) -> ReadonlyBucket<'_, SubscriptionStats> {
+    bucket_read(storage, SUBSCRIPTION_STATS_NAMESPACE)
+}
+
+pub fn escrow_storage(storage: &mut dyn Storage) -> Bucket<'_, u64> {
+    bucket(storage, ESCROW_NAMESPACE)
+}
+
+pub fn escrow_storage_read(storage: &dyn Storage) -> ReadonlyBucket<'_, u64> {
+    bucket_read(storage, ESCROW_NAMESPACE)
+}
+
+// capital held for every sub, so withdrawals can leave it in place without loading each sub
+pub fn escrowed_capital(storage: &mut dyn Storage) -> Singleton<'_, u64> {
+    singleton(storage, ESCROWED_CAPITAL_KEY)
+}
+
+pub fn escrowed_capital_read(storage: &dyn Storage) -> ReadonlySingleton<'_, u64> {
+    singleton_read(storage, ESCROWED_CAPITAL_KEY)
+}
+
+// every change to a sub's escrow goes through here to keep the total in step
+pub fn save_escrow(storage: &mut dyn Storage, subscription: &Addr, escrowed: u64) -> StdResult<()> {
+    let previous = escrow_storage_read(storage)
+        .may_load(subscription.as_bytes())?
+        .unwrap_or_default();
+    let total = escrowed_capital_read(storage)
+        .may_load()?
+        .unwrap_or_default();
+    escrowed_capital(storage).save(&total.saturating_sub(previous).saturating_add(escrowed))?;
+
+    escrow_storage(storage).save(subscription.as_bytes(), &escrowed)
+}
+
+pub fn remove_escrow(storage: &mut dyn Storage, subscription: &Addr) -> StdResult<()> {
+    save_escrow(storage, subscription, 0)?;
+    escrow_storage(storage).remove(subscription.as_bytes());
+
+    Ok(())
+}
+
+// tallies the total from scratch for raises that escrowed capital before it was kept
+pub fn tally_escrowed_capital(storage: &mut dyn Storage) -> StdResult<()> {
+    let mut total: u64 = 0;
+    for item in escrow_storage_read(storage).range(None, None, Order::Ascending) {
+        total = total.saturating_add(item?.1);
+    }
+
+    escrowed_capital(storage).save(&total)
+}
+
+// receipts are keyed by sequence under each subscription
+pub fn receipt_storage<'a>(
+    storage: &'a mut dyn Storage,
+    subscription: &Addr,
+) -> Bucket<'a, Receipt> {
+    Bucket::multilevel(storage, &[RECEIPT_NAMESPACE, subscription.as_bytes()])
+}
+
+pub fn receipt_storage_read<'a>(
+    storage: &'a dyn Storage,
+    subscription: &Addr,
+) -> ReadonlyBucket<'a, Receipt> {
+    ReadonlyBucket::multilevel(storage, &[RECEIPT_NAMESPACE, subscription.as_bytes()])
+}
+
+pub fn receipt_sequence_storage(storage: &mut dyn Storage) -> Bucket<'_, u64> {
+    bucket(storage, RECEIPT_SEQUENCE_NAMESPACE)
+}
+
+// queued settlements are keyed by sequence so they are processed in submission order
+pub fn settlement_queue(storage: &mut dyn Storage) -> Bucket<'_, QueuedSettlement> {
+    bucket(storage, SETTLEMENT_QUEUE_NAMESPACE)
+}
+
+pub fn settlement_queue_read(storage: &dyn Storage) -> ReadonlyBucket<'_, QueuedSettlement> {
+    bucket_read(storage, SETTLEMENT_QUEUE_NAMESPACE)
+}
+
+pub fn settlement_queue_sequence(storage: &mut dyn Storage) -> Singleton<'_, u64> {
+    singleton(storage, SETTLEMENT_QUEUE_SEQUENCE_KEY)
+}
+
+pub fn settlement_authorization_storage(
+    storage: &mut dyn Storage,
+) -> Bucket<'_, SettlementAuthorization> {
+    bucket(storage, SETTLEMENT_AUTHORIZATION_NAMESPACE)
+}
+
+pub fn settlement_authorization_storage_read(
+    storage: &dyn Storage,
+) -> ReadonlyBucket<'_, SettlementAuthorization> {
+    bucket_read(storage, SETTLEMENT_AUTHORIZATION_NAMESPACE)
+}
+
+// funds held for every gp settlement authorization, so withdrawals can leave them in place
+pub fn authorized_funds(storage: &mut dyn Storage) -> Singleton<'_, Vec<Coin>> {
+    singleton(storage, AUTHORIZED_FUNDS_KEY)
+}
+
+pub fn authorized_funds_read(storage: &dyn Storage) -> ReadonlySingleton<'_, Vec<Coin>> {
+    singleton_read(storage, AUTHORIZED_FUNDS_KEY)
+}
+
+// every change to an authorization goes through here to keep the total in step
+pub fn save_settlement_authorization(
+    storage: &mut dyn Storage,
+    subscription: &Addr,
+    authorization: &SettlementAuthorization,
+) -> StdResult<()> {
+    remove_settlement_authorization(storage, subscription)?;
+    let mut total = authorized_funds_read(storage)
+        .may_load()?
+        .unwrap_or_default();
+    for coin in &authorization.funds {
+        match total.iter_mut().find(|held| held.denom == coin.denom) {
+            Some(held) => held.amount = held.amount.saturating_add(coin.amount),
+            None => total.push(coin.clone()),
+        }
+    }
+    authorized_funds(storage).save(&total)?;
+
+    settlement_authorization_storage(storage).save(subscription.as_bytes(), authorization)
+}
+
+pub fn remove_settlement_authorization(
+    storage: &mut dyn Storage,
+    subscription: &Addr,
+) -> StdResult<()> {
+    let previous =
+        match settlement_authorization_storage_read(storage).may_load(subscription.as_bytes())? {
+            Some(previous) => previous,
+            None => return Ok(()),
+        };
+    let mut total = authorized_funds_read(storage)
+        .may_load()?
+        .unwrap_or_default();
+    for coin in &previous.funds {
+        if let Some(held) = total.iter_mut().find(|held| held.denom == coin.denom) {
+            held.amount = held.amount.saturating_sub(coin.amount);
+        }
+    }
+    total.retain(|held| !held.amount.is_zero());
+    authorized_funds(storage).save(&total)?;
+    settlement_authorization_storage(storage).remove(subscription.as_bytes());
+
+    Ok(())
+}
+
+// tallies the total from scratch for raises that held authorized funds before it was kept
+pub fn tally_authorized_funds(storage: &mut dyn Storage) -> StdResult<()> {
+    let mut total: Vec<Coin> = vec![];
+    for item in settlement_authorization_storage_read(storage).range(None, None, Order::Ascending) {
+        for coin in item?.1.funds {
+            match total.iter_mut().find(|held| held.denom == coin.denom) {
+                Some(held) => held.amount = held.amount.saturating_add(coin.amount),
+                None => total.push(coin),
+            }
+        }
+    }
+
+    authorized_funds(storage).save(&total)
+}
+
+pub fn pending_operation_storage(storage: &mut dyn Storage) -> Bucket<'_, PendingOperation> {
+    bucket(storage, PENDING_OPERATION_NAMESPACE)
+}
+
+pub fn pending_operation_storage_read(
+    storage: &dyn Storage,
+) -> ReadonlyBucket<'_, PendingOperation> {
+    bucket_read(storage, PENDING_OPERATION_NAMESPACE)
+}
+
+pub fn operation_sequence(storage: &mut dyn Storage) -> Singleton<'_, u64> {
+    singleton(storage, OPERATION_SEQUENCE_KEY)
+}
+
+pub fn consent_proposal_storage(storage: &mut dyn Storage) -> Bucket<'_, ConsentProposal> {
+    bucket(storage, CONSENT_PROPOSAL_NAMESPACE)
+}
+
+pub fn consent_proposal_storage_read(storage: &dyn Storage) -> ReadonlyBucket<'_, ConsentProposal> {
+    bucket_read(storage, CONSENT_PROPOSAL_NAMESPACE)
+}
+
+// votes are keyed by subscription under each proposal
+pub fn consent_vote_storage<'a>(storage: &'a mut dyn Storage, id: u64) -> Bucket<'a, ConsentVote> {
+    Bucket::multilevel(storage, &[CONSENT_VOTE_NAMESPACE, &id.to_be_bytes()])
+}
+
+pub fn consent_vote_storage_read(
+    storage: &dyn Storage,
+    id: u64,
+) -> ReadonlyBucket<'_, ConsentVote> {
+    ReadonlyBucket::multilevel(storage, &[CONSENT_VOTE_NAMESPACE, &id.to_be_bytes()])
+}
+
+pub fn consent_sequence(storage: &mut dyn Storage) -> Singleton<'_, u64> {
+    singleton(storage, CONSENT_SEQUENCE_KEY)
+}
+
+pub fn term_extension(storage: &mut dyn Storage) -> Singleton<'_, TermExtension> {
+    singleton(storage, TERM_EXTENSION_KEY)
+}
+
+pub fn management_fee_change(storage: &mut dyn Storage) -> Singleton<'_, ManagementFeeChange> {
+    singleton(storage, MANAGEMENT_FEE_CHANGE_KEY)
+}
+
+// subs given exchanges by each issuance, keyed by the batch id tagged on their provenance
+pub fn issuance_batch_storage(storage: &mut dyn Storage) -> Bucket<'_, Vec<Addr>> {
+    bucket(storage, ISSUANCE_BATCH_NAMESPACE)
+}
+
+pub fn issuance_batch_storage_read(storage: &dyn Storage) -> ReadonlyBucket<'_, Vec<Addr>> {
+    bucket_read(storage, ISSUANCE_BATCH_NAMESPACE)
+}
+
+pub fn issuance_sequence(storage: &mut dyn Storage) -> Singleton<'_, u64> {
+    singleton(storage, ISSUANCE_SEQUENCE_KEY)
+}
+
+// approvals to cancel a batch large enough to need the admin's co-sign
+pub fn batch_cancellation_storage(storage: &mut dyn Storage) -> Bucket<'_, Vec<Addr>> {
+    bucket(storage, BATCH_CANCELLATION_NAMESPACE)
+}
+
+pub fn settlement_batch(storage: &mut dyn Storage) -> Singleton<'_, Vec<u64>> {
+    singleton(storage, SETTLEMENT_BATCH_KEY)
+}
+
+pub fn settlement_batch_read(storage: &dyn Storage) -> ReadonlySingleton<'_, Vec<u64>> {
+    singleton_read(storage, SETTLEMENT_BATCH_KEY)
+}
+
+pub fn subscription_lp_storage(storage: &mut dyn Storage) -> Bucket<'_, Addr> {
+    bucket(storage, SUBSCRIPTION_LP_NAMESPACE)
+}
+
+pub fn subscription_lp_storage_read(storage: &dyn Storage) -> ReadonlyBucket<'_, Addr> {
+    bucket_read(storage, SUBSCRIPTION_LP_NAMESPACE)
+}
+
+// the reverse of subscription_lp_storage, keyed by sub under each lp
+pub fn lp_subscription_storage<'a>(storage: &'a mut dyn Storage, lp: &Addr) -> Bucket<'a, Addr> {
+    Bucket::multilevel(storage, &[LP_SUBSCRIPTION_NAMESPACE, lp.as_bytes()])
+}
+
+pub fn lp_subscription_storage_read<'a>(
+    storage: &'a dyn Storage,
+    lp: &Addr,
+) -> ReadonlyBucket<'a, Addr> {
+    ReadonlyBucket::multilevel(storage, &[LP_SUBSCRIPTION_NAMESPACE, lp.as_bytes()])
+}
+
+// every sub closed by the raise, kept for audit after its other records are removed
+pub fn closed_subscription_storage(storage: &mut dyn Storage) -> Bucket<'_, ClosedSubscription> {
+    bucket(storage, CLOSED_SUBSCRIPTION_NAMESPACE)
+}
+
+pub fn closed_subscription_storage_read(
+    storage: &dyn Storage,
+) -> ReadonlyBucket<'_, ClosedSubscription> {
+    bucket_read(storage, CLOSED_SUBSCRIPTION_NAMESPACE)
+}
+
+// setup progress of every marker created at instantiation, absent for raises
+// instantiated before setup was tracked
+pub fn marker_setup(storage: &mut dyn Storage) -> Singleton<'_, Vec<MarkerSetup>> {
+    singleton(storage, MARKER_SETUP_KEY)
+}
+
+pub fn marker_setup_read(storage: &dyn Storage) -> ReadonlySingleton<'_, Vec<MarkerSetup>> {
+    singleton_read(storage, MARKER_SETUP_KEY)
+}
+
+// block height of each sender's latest proposal, for the proposal cooldown
+pub fn last_proposal_storage(storage: &mut dyn Storage) -> Bucket<'_, u64> {
+    bucket(storage, LAST_PROPOSAL_NAMESPACE)
+}
+
+pub fn last_proposal_storage_read(storage: &dyn Storage) -> ReadonlyBucket<'_, u64> {
+    bucket_read(storage, LAST_PROPOSAL_NAMESPACE)
+}
+
+pub fn proposing_lp(storage: &mut dyn Storage) -> Singleton<'_, Addr> {
+    singleton(storage, PROPOSING_LP_KEY)
+}
+
+pub fn proposing_agent(storage: &mut dyn Storage) -> Singleton<'_, Addr> {
+    singleton(storage, PROPOSING_AGENT_KEY)
+}
+
+// capital attached to a proposal, escrowed for the sub once it is instantiated
+pub fn proposing_deposit(storage: &mut dyn Storage) -> Singleton<'_, u64> {
+    singleton(storage, PROPOSING_DEPOSIT_KEY)
+}
+
+// commitment to accept for a proposed sub that qualifies for auto accept
+pub fn proposing_auto_accept(storage: &mut dyn Storage) -> Singleton<'_, u64> {
+    singleton(storage, PROPOSING_AUTO_ACCEPT_KEY)
+}
+
+// total commitment accepted without the gp, counted against the auto accept cap
+pub fn auto_accepted_capital(storage: &mut dyn Storage) -> Singleton<'_, u64> {
+    singleton(storage, AUTO_ACCEPTED_CAPITAL_KEY)
+}
+
+pub fn auto_accepted_capital_read(storage: &dyn Storage) -> ReadonlySingleton<'_, u64> {
+    singleton_read(storage, AUTO_ACCEPTED_CAPITAL_KEY)
+}
+
+// submitting agent for subs proposed on behalf of a different owner
+pub fn subscription_agent_storage(storage: &mut dyn Storage) -> Bucket<'_, Addr> {
+    bucket(storage, SUBSCRIPTION_AGENT_NAMESPACE)
+}
+
+pub fn subscription_agent_storage_read(storage: &dyn Storage) -> ReadonlyBucket<'_, Addr> {
+    bucket_read(storage, SUBSCRIPTION_AGENT_NAMESPACE)
+}
+
+pub fn notification_batch(storage: &mut dyn Storage) -> Singleton<'_, Vec<Addr>> {
+    singleton(storage, NOTIFICATION_BATCH_KEY)
+}
+
+pub fn notification_batch_read(storage: &dyn Storage) -> ReadonlySingleton<'_, Vec<Addr>> {
+    singleton_read(storage, NOTIFICATION_BATCH_KEY)
+}
+
+// subs on code versions that can't handle issuance notifications
+pub fn notifications_disabled_storage(storage: &mut dyn Storage) -> Bucket<'_, bool> {
+    bucket(storage, NOTIFICATIONS_DISABLED_NAMESPACE)
+}
+
+pub fn notifications_disabled_storage_read(storage: &dyn Storage) -> ReadonlyBucket<'_, bool> {
+    bucket_read(storage, NOTIFICATIONS_DISABLED_NAMESPACE)
+}
+
+pub fn migration_batch(storage: &mut dyn Storage) -> Singleton<'_, Vec<Addr>> {
+    singleton(storage, MIGRATION_BATCH_KEY)
+}
+
+pub fn migration_batch_read(storage: &dyn Storage) -> ReadonlySingleton<'_, Vec<Addr>> {
+    singleton_read(storage, MIGRATION_BATCH_KEY)
+}
+
+pub fn migration_failure_storage(storage: &mut dyn Storage) -> Bucket<'_, String> {
+    bucket(storage, MIGRATION_FAILURE_NAMESPACE)
 }
 
-pub fn pending_subscriptions_read(storage: &dyn Storage) -> ReadonlySingleton<HashSet<Addr>> {
-    singleton_read(storage, PENDING_SUBSCRIPTIONS_KEY)
+pub fn migration_failure_storage_read(storage: &dyn Storage) -> ReadonlyBucket<'_, String> {
+    bucket_read(storage, MIGRATION_FAILURE_NAMESPACE)
 }
 
-pub fn eligible_subscriptions(storage: &mut dyn Storage) -> Singleton<HashSet<Addr>> {
-    singleton(storage, ELIGIBLE_SUBSCRIPTIONS_KEY)
+pub fn change_revision_read(storage: &dyn Storage) -> ReadonlySingleton<'_, u64> {
+    singleton_read(storage, CHANGE_REVISION_KEY)
 }
 
-pub fn eligible_subscriptions_read(storage: &dyn Storage) -> ReadonlySingleton<HashSet<Addr>> {
-    singleton_read(storage, ELIGIBLE_SUBSCRIPTIONS_KEY)
+pub fn change_log_read(storage: &dyn Storage) -> ReadonlyBucket<'_, Change> {
+    bucket_read(storage, CHANGE_LOG_NAMESPACE)
 }
 
-pub fn accepted_subscriptions(storage: &mut dyn Storage) -> Singleton<HashSet<Addr>> {
-    singleton(storage, ACCEPTED_SUBSCRIPTIONS_KEY)
+// appends to the change log so indexers can sync incrementally
+pub fn change_log(storage: &mut dyn Storage) -> Bucket<'_, Change> {
+    bucket(storage, CHANGE_LOG_NAMESPACE)
 }
 
-pub fn accepted_subscriptions_read(storage: &dyn Storage) -> ReadonlySingleton<HashSet<Addr>> {
-    singleton_read(storage, ACCEPTED_SUBSCRIPTIONS_KEY)
+pub fn change_log_checkpoint(storage: &mut dyn Storage) -> Singleton<'_, ArchiveCheckpoint> {
+    singleton(storage, CHANGE_LOG_CHECKPOINT_KEY)
+}
+
+pub fn change_log_checkpoint_read(
+    storage: &dyn Storage,
+) -> ReadonlySingleton<'_, ArchiveCheckpoint> {
+    singleton_read(storage, CHANGE_LOG_CHECKPOINT_KEY)
+}
+
+pub fn receipt_checkpoint_storage(storage: &mut dyn Storage) -> Bucket<'_, ArchiveCheckpoint> {
+    bucket(storage, RECEIPT_CHECKPOINT_NAMESPACE)
+}
+
+pub fn receipt_checkpoint_storage_read(
+    storage: &dyn Storage,
+) -> ReadonlyBucket<'_, ArchiveCheckpoint> {
+    bucket_read(storage, RECEIPT_CHECKPOINT_NAMESPACE)
+}
+
+pub fn record_change(storage: &mut dyn Storage, entity: ChangedEntity, key: &str) -> StdResult<()> {
+    let revision = singleton_read::<u64>(storage, CHANGE_REVISION_KEY)
+        .may_load()?
+        .unwrap_or_default()
+        + 1;
+    singleton(storage, CHANGE_REVISION_KEY).save(&revision)?;
+    bucket(storage, CHANGE_LOG_NAMESPACE).save(
+        &revision.to_be_bytes(),
+        &Change {
+            revision,
+            entity,
+            key: String::from(key),
+        },
+    )
+}
+
+// subs are indexed by status so membership checks and status changes touch a single
+// entry, and a sub can never be left behind in more than one status
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SubscriptionStatus {
+    Pending,
+    Eligible,
+    Accepted,
+}
+
+impl SubscriptionStatus {
+    fn index_key(&self) -> &'static [u8] {
+        match self {
+            SubscriptionStatus::Pending => b"pending",
+            SubscriptionStatus::Eligible => b"eligible",
+            SubscriptionStatus::Accepted => b"accepted",
+        }
+    }
+}
+
+pub fn subscription_status_read(storage: &dyn Storage) -> ReadonlyBucket<'_, SubscriptionStatus> {
+    bucket_read(storage, SUBSCRIPTION_STATUS_NAMESPACE)
+}
+
+pub fn subscription_index_read(
+    storage: &dyn Storage,
+    status: SubscriptionStatus,
+) -> ReadonlyBucket<'_, Addr> {
+    ReadonlyBucket::multilevel(storage, &[SUBSCRIPTION_INDEX_NAMESPACE, status.index_key()])
+}
+
+fn subscription_index(storage: &mut dyn Storage, status: SubscriptionStatus) -> Bucket<'_, Addr> {
+    Bucket::multilevel(storage, &[SUBSCRIPTION_INDEX_NAMESPACE, status.index_key()])
+}
+
+pub fn subscription_status(
+    storage: &dyn Storage,
+    subscription: &Addr,
+) -> StdResult<Option<SubscriptionStatus>> {
+    subscription_status_read(storage).may_load(subscription.as_bytes())
+}
+
+pub fn has_subscription_status(
+    storage: &dyn Storage,
+    subscription: &Addr,
+    status: SubscriptionStatus,
+) -> StdResult<bool> {
+    Ok(subscription_status(storage, subscription)? == Some(status))
+}
+
+// moves the sub to the given status, or drops it from every status when None
+pub fn set_subscription_status(
+    storage: &mut dyn Storage,
+    subscription: &Addr,
+    status: Option<SubscriptionStatus>,
+) -> StdResult<()> {
+    if let Some(previous) = subscription_status(storage, subscription)? {
+        subscription_index(storage, previous).remove(subscription.as_bytes());
+    }
+    match status {
+        Some(status) => {
+            bucket(storage, SUBSCRIPTION_STATUS_NAMESPACE)
+                .save(subscription.as_bytes(), &status)?;
+            subscription_index(storage, status).save(subscription.as_bytes(), subscription)
+        }
+        None => {
+            bucket::<SubscriptionStatus>(storage, SUBSCRIPTION_STATUS_NAMESPACE)
+                .remove(subscription.as_bytes());
+            Ok(())
+        }
+    }
+}
+
+// every sub with the status in address order, for sweeps that must visit them all
+pub fn subscriptions_with_status(
+    storage: &dyn Storage,
+    status: SubscriptionStatus,
+) -> StdResult<Vec<Addr>> {
+    subscription_index_read(storage, status)
+        .range(None, None, Order::Ascending)
+        .map(|item| item.map(|(_, subscription)| subscription))
+        .collect()
 }
 
 #[cfg(test)]
@@ -99,48 +1320,148 @@ pub mod tests {
                 capital_denom: String::from("stable_coin"),
                 capital_per_share: 100,
                 required_capital_attribute: None,
+                due_grace_seconds: 0,
+                escrow_capital: false,
+                permissionless_eligibility: false,
+                paused: false,
+                delegates: BTreeSet::new(),
+                commitment_marker: None,
+                investment_marker: None,
+                max_receipts: None,
+                receipt_retention_seconds: None,
+                change_log_retention: None,
+                settlement_hours: None,
+                gp_committee: vec![],
+                approval_threshold: 0,
+                approval_capital_threshold: None,
+                compliance_contract: None,
+                treasury: None,
+                pending_treasury: None,
+                max_outstanding_exchanges: None,
+                max_outstanding_capital: None,
+                capital_exchange_rates: vec![],
+                capital_attributes: vec![],
+                metadata: FundMetadata::default(),
+                min_initial_commitment: None,
+                share_classes: vec![],
+                nav: None,
+                max_ownership_bps: None,
+                approved_destinations: None,
+                auto_accept: None,
+                performance_fee: None,
+                term_end: None,
+                batch_cancel_cosign_threshold: None,
+                management_fee: None,
+                memo_transfer_events: false,
+                denom_decimals: vec![],
+                tombstone_code_id: None,
+                accepting_proposals: true,
+                proposal_cooldown_blocks: None,
+                max_pending_per_lp: None,
             }
         }
     }
 
     pub fn asset_exchange_storage_read(
         storage: &dyn Storage,
-    ) -> ReadonlyBucket<Vec<AssetExchange>> {
+    ) -> ReadonlyBucket<'_, Vec<AssetExchange>> {
         bucket_read(storage, ASSET_EXCHANGE_NAMESPACE)
     }
 
-    pub fn to_addresses(addresses: Vec<&str>) -> HashSet<Addr> {
-        addresses
-            .into_iter()
-            .map(|addr| Addr::unchecked(addr))
-            .collect()
+    pub fn to_addresses(addresses: Vec<&str>) -> BTreeSet<Addr> {
+        addresses.into_iter().map(Addr::unchecked).collect()
+    }
+
+    fn set_status(storage: &mut dyn Storage, addresses: Vec<&str>, status: SubscriptionStatus) {
+        for subscription in to_addresses(addresses) {
+            set_subscription_status(storage, &subscription, Some(status)).unwrap();
+        }
     }
 
     pub fn set_pending(storage: &mut dyn Storage, addresses: Vec<&str>) {
-        pending_subscriptions(storage)
-            .save(&to_addresses(addresses))
-            .unwrap();
+        set_status(storage, addresses, SubscriptionStatus::Pending);
     }
 
     pub fn set_eligible(storage: &mut dyn Storage, addresses: Vec<&str>) {
-        eligible_subscriptions(storage)
-            .save(&to_addresses(addresses))
-            .unwrap();
+        set_status(storage, addresses, SubscriptionStatus::Eligible);
     }
 
     pub fn set_accepted(storage: &mut dyn Storage, addresses: Vec<&str>) {
-        accepted_subscriptions(storage)
-            .save(&to_addresses(addresses))
-            .unwrap();
+        set_status(storage, addresses, SubscriptionStatus::Accepted);
     }
 
     #[test]
     fn not_evenly_divisble() {
         let state = State::test_default();
 
-        assert_eq!(false, state.not_evenly_divisble(100));
+        assert!(!state.not_evenly_divisble(100));
         assert!(state.not_evenly_divisble(101));
-        assert_eq!(false, state.not_evenly_divisble(1_000));
+        assert!(!state.not_evenly_divisble(1_000));
         assert!(state.not_evenly_divisble(1_001));
     }
+
+    #[test]
+    fn capital_value_normalizes_decimals() {
+        let mut state = State::test_default();
+        state.capital_exchange_rates = vec![CapitalExchangeRate {
+            denom: String::from("wei_coin"),
+            numerator: 1,
+            denominator: 1,
+        }];
+        state.denom_decimals = vec![
+            DenomDecimals {
+                denom: state.capital_denom.clone(),
+                decimals: 6,
+            },
+            DenomDecimals {
+                denom: String::from("wei_coin"),
+                decimals: 18,
+            },
+        ];
+
+        // one whole unit of either denom is worth the same capital
+        assert_eq!(
+            1_000_000,
+            state
+                .capital_value(&[Coin::new(1_000_000_000_000_000_000, "wei_coin")])
+                .unwrap()
+        );
+        assert_eq!(
+            1_000_000_000_000_000_000,
+            state
+                .normalize(1_000_000, &state.capital_denom, "wei_coin")
+                .unwrap()
+        );
+
+        // denoms without decimals share the capital denom's exponent
+        assert_eq!(
+            500,
+            state
+                .normalize(500, &state.capital_denom, "other_coin")
+                .unwrap()
+        );
+
+        // amounts too large to convert or total are rejected
+        assert_eq!(
+            ContractError::ConversionOverflow {
+                from: state.capital_denom.clone(),
+                to: String::from("wei_coin"),
+            },
+            state
+                .normalize(u128::MAX, &state.capital_denom, "wei_coin")
+                .unwrap_err()
+        );
+        assert_eq!(
+            ContractError::ConversionOverflow {
+                from: String::from("wei_coin"),
+                to: state.capital_denom.clone(),
+            },
+            state
+                .capital_value(&[
+                    Coin::new(u128::MAX, &state.capital_denom),
+                    Coin::new(1_000_000_000_000_000_000, "wei_coin"),
+                ])
+                .unwrap_err()
+        );
+    }
 }
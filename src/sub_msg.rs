@@ -1,8 +1,11 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use cosmwasm_std::Addr;
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+use crate::msg::AssetExchange;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct SubInstantiateMsg {
     pub admin: Addr,
@@ -15,13 +18,22 @@ pub struct SubInstantiateMsg {
     pub required_capital_attribute: Option<String>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SubExecuteMsg {
+    IssueCapitalCall { capital_call: AssetExchange },
+    IssueDistribution { distribution: AssetExchange },
+    IssueRedemption { redemption: AssetExchange },
+    Disable {},
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum SubQueryMsg {
     GetState {},
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct SubState {
     pub admin: Addr,
     pub lp: Addr,
@@ -31,4 +43,112 @@ pub struct SubState {
     pub capital_denom: String,
     pub capital_per_share: u64,
     pub required_capital_attribute: Option<String>,
+    #[serde(default)]
+    pub like_capital_denoms: Vec<String>,
+}
+
+impl SubState {
+    pub fn can_settle(&self, denom: &str) -> bool {
+        self.capital_denom == denom || self.like_capital_denoms.iter().any(|like| like == denom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::msg::{ExchangeDate, ExchangeProvenance};
+    use cosmwasm_std::{from_slice, to_vec};
+    use provwasm_mocks::must_read_binary_file;
+    use serde::de::DeserializeOwned;
+
+    // fixtures hold the subscription contract's wire format, so any field change
+    // here that it wouldn't understand fails to round trip
+    fn assert_round_trip<T>(fixture: &str, expected: T)
+    where
+        T: Serialize + DeserializeOwned + PartialEq + std::fmt::Debug,
+    {
+        let bin = must_read_binary_file(&format!("testdata/subscription/{}.json", fixture));
+        assert_eq!(expected, from_slice::<T>(&bin).unwrap());
+        assert_eq!(
+            String::from_utf8(bin.to_vec()).unwrap(),
+            String::from_utf8(to_vec(&expected).unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    fn instantiate_msg_round_trip() {
+        assert_round_trip(
+            "instantiate_msg",
+            SubInstantiateMsg {
+                admin: Addr::unchecked("marketpalace"),
+                lp: Addr::unchecked("lp"),
+                commitment_denom: String::from("commitment_coin"),
+                investment_denom: String::from("investment_coin"),
+                capital_denom: String::from("stable_coin"),
+                capital_per_share: 100,
+                initial_commitment: Some(10_000),
+                required_capital_attribute: None,
+            },
+        );
+    }
+
+    #[test]
+    fn execute_msg_round_trip() {
+        let bin = must_read_binary_file("testdata/subscription/issue_capital_call.json");
+        let msg: SubExecuteMsg = from_slice(&bin).unwrap();
+        assert_round_trip(
+            "issue_capital_call",
+            SubExecuteMsg::IssueCapitalCall {
+                capital_call: AssetExchange {
+                    investment: Some(1_000),
+                    commitment_in_shares: Some(-1_000),
+                    capital: Some(-1_000),
+                    date: Some(ExchangeDate::Due(1_700_000_000)),
+                    exchange_id: Some(String::from("call_1")),
+                    provenance: Some(ExchangeProvenance {
+                        issued_by: Addr::unchecked("gp"),
+                        issued_at: 1_690_000_000,
+                        batch: None,
+                    }),
+                    ..AssetExchange::default()
+                },
+            },
+        );
+
+        // provenance isn't part of exchange equality, so check it separately
+        match msg {
+            SubExecuteMsg::IssueCapitalCall { capital_call } => assert_eq!(
+                Some(ExchangeProvenance {
+                    issued_by: Addr::unchecked("gp"),
+                    issued_at: 1_690_000_000,
+                    batch: None,
+                }),
+                capital_call.provenance
+            ),
+            _ => panic!("expected a capital call"),
+        }
+    }
+
+    #[test]
+    fn query_msg_round_trip() {
+        assert_round_trip("get_state", SubQueryMsg::GetState {});
+    }
+
+    #[test]
+    fn state_round_trip() {
+        assert_round_trip(
+            "state",
+            SubState {
+                admin: Addr::unchecked("marketpalace"),
+                lp: Addr::unchecked("lp"),
+                raise: Addr::unchecked("raise"),
+                commitment_denom: String::from("commitment_coin"),
+                investment_denom: String::from("investment_coin"),
+                capital_denom: String::from("stable_coin"),
+                capital_per_share: 100,
+                required_capital_attribute: Some(String::from("capital.test")),
+                like_capital_denoms: vec![String::from("other_coin")],
+            },
+        );
+    }
 }
@@ -1,36 +1,151 @@
+use crate::compliance_msg::{ComplianceQueryMsg, EligibilityResponse};
 use crate::contract::ContractResponse;
-use crate::error::contract_error;
-use crate::msg::{AcceptSubscription, AssetExchange};
-use crate::state::{accepted_subscriptions, config_read, pending_subscriptions, State};
-use crate::state::{asset_exchange_storage, eligible_subscriptions};
-use crate::sub_msg::{SubInstantiateMsg, SubQueryMsg, SubState};
-use cosmwasm_std::{to_binary, Addr, Env, SubMsg, WasmMsg};
+use crate::error::ContractError;
+use crate::exchange_asset::{record_receipts, record_subscription_stats};
+use crate::msg::{AcceptSubscription, AssetExchange, ChangedEntity, ExchangeProvenance};
+use crate::msg::{ClosedSubscription, SubscriptionRetirement};
+use crate::ownership::{check_ownership, check_transfer_ownership};
+use crate::reply::{ELIGIBLE_SUBSCRIPTION_REPLY_ID, PENDING_SUBSCRIPTION_REPLY_ID};
+use crate::settlement::send_capital;
+use crate::state::closed_subscription_storage;
+use crate::state::{auto_accepted_capital_read, proposing_auto_accept};
+use crate::state::{config_read, escrow_storage, record_change, State};
+use crate::state::{has_subscription_status, subscription_status};
+use crate::state::{last_proposal_storage, last_proposal_storage_read};
+use crate::state::{
+    lp_subscription_storage, lp_subscription_storage_read, subscription_agent_storage_read,
+    subscription_lp_storage, subscription_lp_storage_read,
+};
+use crate::state::{proposing_agent, proposing_deposit, proposing_lp};
+use crate::state::{remove_asset_exchanges, save_asset_exchanges};
+use crate::state::{remove_escrow, save_escrow};
+use crate::state::{set_subscription_status, SubscriptionStatus};
+use crate::sub_msg::{SubExecuteMsg, SubInstantiateMsg, SubQueryMsg, SubState};
+use cosmwasm_std::Response;
+use cosmwasm_std::{to_binary, Addr, Empty, Env, SubMsg, WasmMsg};
 use cosmwasm_std::{Deps, DepsMut};
-use cosmwasm_std::{MessageInfo, StdResult};
-use cosmwasm_std::{Response, StdError};
+use cosmwasm_std::{MessageInfo, Order, StdResult, Storage};
+use provwasm_std::transfer_marker_coins;
 use provwasm_std::ProvenanceQuerier;
 use provwasm_std::ProvenanceQuery;
-use std::collections::HashSet;
-use std::convert::TryInto;
+use std::collections::BTreeSet;
+use std::convert::{TryFrom, TryInto};
 
 pub fn try_propose_subscription(
     deps: DepsMut<ProvenanceQuery>,
     env: Env,
     info: MessageInfo,
     initial_commitment: Option<u64>,
+    owner: Option<Addr>,
+    forward_funds: bool,
 ) -> ContractResponse {
     let state = config_read(deps.storage).load()?;
 
-    let lp = || Ok(info.sender.clone());
+    if !state.accepting_proposals {
+        return Err(ContractError::ProposalsClosed {});
+    }
+
+    // each proposal instantiates a contract, so senders are throttled
+    if let Some(cooldown) = state.proposal_cooldown_blocks {
+        if let Some(last) =
+            last_proposal_storage_read(deps.storage).may_load(info.sender.as_bytes())?
+        {
+            let available_at = last + cooldown;
+            if env.block.height < available_at {
+                return Err(ContractError::ProposalCooldown { available_at });
+            }
+        }
+    }
+
+    if let Some(min_initial_commitment) = state.min_initial_commitment {
+        let initial_commitment = initial_commitment.unwrap_or_default();
+        if initial_commitment < min_initial_commitment {
+            return Err(ContractError::CommitmentBelowMinimum {
+                initial_commitment,
+                min_initial_commitment,
+            });
+        }
+    }
+    if let Some(initial_commitment) = initial_commitment {
+        if state.not_evenly_divisble(initial_commitment) {
+            return Err(ContractError::IndivisibleAmount {
+                amount: initial_commitment,
+                capital_per_share: state.capital_per_share,
+            });
+        }
+    }
+
+    // attached coins either seed the new sub or are escrowed as capital for it
+    let funds = if forward_funds {
+        proposing_deposit(deps.storage).remove();
+        info.funds.clone()
+    } else {
+        if info
+            .funds
+            .iter()
+            .any(|coin| coin.denom != state.capital_denom)
+        {
+            return Err(ContractError::InvalidRequest {
+                reason: "only capital can be escrowed for a proposed subscription",
+            });
+        }
+        let deposit: u128 = info.funds.iter().map(|coin| coin.amount.u128()).sum();
+        if deposit > 0 {
+            proposing_deposit(deps.storage).save(&u64::try_from(deposit)?)?;
+        } else {
+            proposing_deposit(deps.storage).remove();
+        }
+        vec![]
+    };
+
+    let owner = owner.unwrap_or_else(|| info.sender.clone());
+    if let Some(max) = state.max_pending_per_lp {
+        if pending_proposal_count(deps.storage, &owner, &info.sender)? >= max {
+            return Err(ContractError::PendingLimitExceeded {
+                proposer: info.sender,
+                max,
+            });
+        }
+    }
+    last_proposal_storage(deps.storage).save(info.sender.as_bytes(), &env.block.height)?;
+
+    let lp = || Ok(owner.clone());
     let eligible = verify_lp_eligibility(deps.as_ref(), &state, &lp).is_ok();
 
+    // attested lps within the auto accept limits skip gp acceptance, so long as any
+    // escrow requirement is already met by the attached deposit and the lp proposed
+    // the sub itself, an agent's proposals always wait for the gp
+    let deposit = proposing_deposit(deps.storage)
+        .may_load()?
+        .unwrap_or_default();
+    let auto_accepted = auto_accepted_capital_read(deps.storage)
+        .may_load()?
+        .unwrap_or_default();
+    match initial_commitment.filter(|commitment| {
+        eligible
+            && owner == info.sender
+            && (!state.escrow_capital || deposit == *commitment)
+            && state.auto_accepts(*commitment, auto_accepted)
+    }) {
+        Some(commitment) => proposing_auto_accept(deps.storage).save(&commitment)?,
+        None => proposing_auto_accept(deps.storage).remove(),
+    }
+
+    // remembered until the instantiate reply so the new sub can be linked to its lp
+    proposing_lp(deps.storage).save(&owner)?;
+    if owner != info.sender {
+        proposing_agent(deps.storage).save(&info.sender)?;
+    } else {
+        proposing_agent(deps.storage).remove();
+    }
+
     let create_sub = SubMsg::reply_always(
         WasmMsg::Instantiate {
             admin: Some(env.contract.address.into_string()),
             code_id: state.subscription_code_id,
             msg: to_binary(&SubInstantiateMsg {
                 admin: state.recovery_admin,
-                lp: info.sender,
+                lp: owner.clone(),
                 commitment_denom: state.commitment_denom,
                 investment_denom: state.investment_denom,
                 capital_denom: state.capital_denom,
@@ -38,66 +153,204 @@ pub fn try_propose_subscription(
                 initial_commitment,
                 required_capital_attribute: state.required_capital_attribute,
             })?,
-            funds: vec![],
+            funds,
             label: String::from("establish subscription"),
         },
-        if eligible { 1 } else { 0 },
+        if eligible {
+            ELIGIBLE_SUBSCRIPTION_REPLY_ID
+        } else {
+            PENDING_SUBSCRIPTION_REPLY_ID
+        },
     );
 
-    Ok(Response::new()
+    let mut response = Response::new()
         .add_submessage(create_sub)
-        .add_attribute("eligible", format!("{eligible}")))
+        .add_attribute("eligible", format!("{eligible}"));
+    if owner != info.sender {
+        response = response.add_attribute("agent", info.sender);
+    }
+
+    Ok(response)
+}
+
+// subs a proposer put forward that the gp hasn't accepted or closed yet, an lp's own
+// proposals are counted apart from an agent's so others can't use up the lp's limit
+fn pending_proposal_count(storage: &dyn Storage, lp: &Addr, proposer: &Addr) -> StdResult<u64> {
+    let awaiting_gp = |subscription: &Addr| -> StdResult<bool> {
+        Ok(matches!(
+            subscription_status(storage, subscription)?,
+            Some(SubscriptionStatus::Pending) | Some(SubscriptionStatus::Eligible)
+        ))
+    };
+
+    let mut count = 0;
+    if lp == proposer {
+        for item in lp_subscription_storage_read(storage, lp).range(None, None, Order::Ascending) {
+            let (_, subscription) = item?;
+            let agent =
+                subscription_agent_storage_read(storage).may_load(subscription.as_bytes())?;
+            if agent.is_none() && awaiting_gp(&subscription)? {
+                count += 1;
+            }
+        }
+    } else {
+        for item in subscription_agent_storage_read(storage).range(None, None, Order::Ascending) {
+            let (key, agent) = item?;
+            let subscription = Addr::unchecked(String::from_utf8_lossy(&key));
+            if &agent == proposer && awaiting_gp(&subscription)? {
+                count += 1;
+            }
+        }
+    }
+    Ok(count)
 }
 
 pub fn try_close_subscriptions(
     deps: DepsMut<ProvenanceQuery>,
+    env: Env,
     info: MessageInfo,
-    subscriptions: HashSet<Addr>,
+    subscriptions: BTreeSet<Addr>,
+    treasury: Option<Addr>,
+    retirement: Option<SubscriptionRetirement>,
 ) -> ContractResponse {
     let state = config_read(deps.storage).load()?;
-    let mut pending = pending_subscriptions(deps.storage)
-        .may_load()?
-        .unwrap_or_default();
-    let mut eligible = eligible_subscriptions(deps.storage)
-        .may_load()?
-        .unwrap_or_default();
-    let mut accepted = accepted_subscriptions(deps.storage)
-        .may_load()?
-        .unwrap_or_default();
 
     if info.sender != state.gp {
-        return contract_error("only gp can close subscriptions");
+        return Err(ContractError::Unauthorized {
+            required: "gp",
+            action: "close subscriptions",
+        });
     }
 
+    if retirement == Some(SubscriptionRetirement::Tombstone) && state.tombstone_code_id.is_none() {
+        return Err(ContractError::InvalidRequest {
+            reason: "no tombstone code id is configured",
+        });
+    }
+
+    if let Some(treasury) = &treasury {
+        if !has_subscription_status(deps.storage, treasury, SubscriptionStatus::Accepted)?
+            || subscriptions.contains(treasury)
+        {
+            return Err(ContractError::InvalidRequest {
+                reason: "treasury must be an accepted subscription that remains open",
+            });
+        }
+    }
+
+    let mut response = Response::new();
     for subscription in subscriptions {
-        if !pending.remove(&subscription) && !eligible.remove(&subscription) {
-            if accepted.contains(&subscription) {
+        record_change(
+            deps.storage,
+            ChangedEntity::Subscription,
+            subscription.as_str(),
+        )?;
+        match subscription_status(deps.storage, &subscription)? {
+            Some(SubscriptionStatus::Accepted) => {
                 let balances = deps.querier.query_all_balances(subscription.as_str())?;
+                let investment_denoms = state.investment_denoms();
+                let investments: Vec<_> = balances
+                    .iter()
+                    .filter(|coin| {
+                        investment_denoms.contains(&&coin.denom) && coin.amount.u128() > 0
+                    })
+                    .collect();
                 if balances
                     .iter()
                     .any(|coin| coin.denom == state.commitment_denom && coin.amount.u128() > 0)
                 {
-                    return contract_error("sub still has remaining commitment");
-                } else if balances
-                    .iter()
-                    .any(|coin| coin.denom == state.investment_denom && coin.amount.u128() > 0)
-                {
-                    return contract_error("sub still has remaining investment");
-                } else {
-                    accepted.remove(&subscription);
-                    asset_exchange_storage(deps.storage).remove(subscription.as_bytes());
+                    return Err(ContractError::RemainingBalance {
+                        subscription,
+                        denom: state.commitment_denom,
+                    });
+                } else if !investments.is_empty() {
+                    match &treasury {
+                        Some(treasury) => {
+                            for investment in investments {
+                                response = response.add_message(transfer_marker_coins(
+                                    investment.amount.u128(),
+                                    &investment.denom,
+                                    treasury.clone(),
+                                    subscription.clone(),
+                                )?);
+                            }
+                        }
+                        None => {
+                            return Err(ContractError::RemainingBalance {
+                                subscription,
+                                denom: investments[0].denom.clone(),
+                            })
+                        }
+                    }
                 }
-            } else {
-                return contract_error("no subscription pending or accepted to close");
+
+                remove_asset_exchanges(deps.storage, &subscription)?;
+                record_change(
+                    deps.storage,
+                    ChangedEntity::AssetExchange,
+                    subscription.as_str(),
+                )?;
+            }
+            Some(_) => {}
+            None => {
+                return Err(ContractError::SubscriptionNotFound {
+                    subscription,
+                    expected: "pending, eligible, or accepted",
+                });
             }
         }
-    }
+        set_subscription_status(deps.storage, &subscription, None)?;
+        if let Some(lp) =
+            subscription_lp_storage_read(deps.storage).may_load(subscription.as_bytes())?
+        {
+            lp_subscription_storage(deps.storage, &lp).remove(subscription.as_bytes());
+            subscription_lp_storage(deps.storage).remove(subscription.as_bytes());
+        }
 
-    pending_subscriptions(deps.storage).save(&pending)?;
-    eligible_subscriptions(deps.storage).save(&eligible)?;
-    accepted_subscriptions(deps.storage).save(&accepted)?;
+        // release any capital still held in escrow back to the sub
+        if let Some(escrowed) = escrow_storage(deps.storage).may_load(subscription.as_bytes())? {
+            if escrowed > 0 {
+                response = response.add_message(send_capital(
+                    deps.as_ref(),
+                    &state,
+                    escrowed.into(),
+                    subscription.clone(),
+                    env.contract.address.clone(),
+                )?);
+            }
+            remove_escrow(deps.storage, &subscription)?;
+            record_change(deps.storage, ChangedEntity::Escrow, subscription.as_str())?;
+        }
+
+        // sent as plain messages, so a sub that can't be retired keeps the close from landing
+        match (retirement, state.tombstone_code_id) {
+            (Some(SubscriptionRetirement::Tombstone), Some(tombstone_code_id)) => {
+                response = response.add_message(WasmMsg::Migrate {
+                    contract_addr: subscription.to_string(),
+                    new_code_id: tombstone_code_id,
+                    msg: to_binary(&Empty {})?,
+                });
+            }
+            (Some(SubscriptionRetirement::Disable), _) => {
+                response = response.add_message(WasmMsg::Execute {
+                    contract_addr: subscription.to_string(),
+                    msg: to_binary(&SubExecuteMsg::Disable {})?,
+                    funds: vec![],
+                });
+            }
+            _ => {}
+        }
+        closed_subscription_storage(deps.storage).save(
+            subscription.as_bytes(),
+            &ClosedSubscription {
+                subscription: subscription.clone(),
+                closed_at: env.block.time.seconds(),
+                retirement,
+            },
+        )?;
+    }
 
-    Ok(Response::new())
+    Ok(response)
 }
 
 pub fn try_upgrade_eligible_subscriptions(
@@ -106,117 +359,368 @@ pub fn try_upgrade_eligible_subscriptions(
     subcriptions: Vec<Addr>,
 ) -> ContractResponse {
     let state = config_read(deps.storage).load()?;
-    let mut pending = pending_subscriptions(deps.storage)
-        .may_load()?
-        .unwrap_or_default();
-    let mut eligible = eligible_subscriptions(deps.storage)
-        .may_load()?
-        .unwrap_or_default();
 
     if info.sender != state.gp && info.sender != state.recovery_admin {
-        return contract_error("only gp or admin can upgrade pending subs to eligible");
+        return Err(ContractError::Unauthorized {
+            required: "gp or admin",
+            action: "upgrade pending subs to eligible",
+        });
     }
 
     for sub in subcriptions {
-        if pending.contains(&sub) {
+        if has_subscription_status(deps.storage, &sub, SubscriptionStatus::Pending)? {
             let lp = || lp_for_sub(deps.as_ref(), &sub);
             verify_lp_eligibility(deps.as_ref(), &state, &lp)?;
 
-            pending.remove(&sub);
-            eligible.insert(sub);
+            record_change(deps.storage, ChangedEntity::Subscription, sub.as_str())?;
+            set_subscription_status(deps.storage, &sub, Some(SubscriptionStatus::Eligible))?;
         } else {
-            return contract_error("subscription must be pending");
+            return Err(ContractError::SubscriptionNotFound {
+                subscription: sub,
+                expected: "pending",
+            });
         }
     }
 
-    pending_subscriptions(deps.storage).save(&pending)?;
-    eligible_subscriptions(deps.storage).save(&eligible)?;
-
     Ok(Response::default())
 }
 
 pub fn try_accept_subscriptions(
     deps: DepsMut<ProvenanceQuery>,
+    env: Env,
     info: MessageInfo,
     accepts: Vec<AcceptSubscription>,
 ) -> ContractResponse {
     let state = config_read(deps.storage).load()?;
-    let mut pending = pending_subscriptions(deps.storage)
-        .may_load()?
-        .unwrap_or_default();
-    let mut eligible = eligible_subscriptions(deps.storage)
-        .may_load()?
-        .unwrap_or_default();
-    let mut accepted = accepted_subscriptions(deps.storage)
-        .may_load()?
-        .unwrap_or_default();
 
     if info.sender != state.gp {
-        return contract_error("only gp can accept subscriptions");
+        return Err(ContractError::Unauthorized {
+            required: "gp",
+            action: "accept subscriptions",
+        });
+    }
+
+    if state.escrow_capital {
+        let total_commitment: u64 = accepts.iter().map(|a| a.commitment_in_capital).sum();
+        let attached_capital: u128 = info
+            .funds
+            .iter()
+            .filter(|coin| coin.denom == state.capital_denom)
+            .map(|coin| coin.amount.u128())
+            .sum();
+        if attached_capital != total_commitment.into() {
+            return Err(ContractError::InvalidRequest {
+                reason: "accepted commitment must be escrowed in capital",
+            });
+        }
     }
 
+    // the batch is checked as a whole so an initial close can be accepted together
+    let increases: Vec<(&Addr, u128)> = accepts
+        .iter()
+        .map(|accept| {
+            (
+                &accept.subscription,
+                u128::from(state.capital_to_shares(accept.commitment_in_capital)),
+            )
+        })
+        .collect();
+    check_ownership(deps.as_ref(), &state, &increases)?;
+
     for accept in accepts.iter() {
         if state.not_evenly_divisble(accept.commitment_in_capital) {
-            return contract_error("accept amount must be evenly divisble by capital per share");
+            return Err(ContractError::IndivisibleAmount {
+                amount: accept.commitment_in_capital,
+                capital_per_share: state.capital_per_share,
+            });
         }
 
-        if eligible.contains(&accept.subscription) {
-            eligible.remove(&accept.subscription);
-        } else if pending.contains(&accept.subscription) {
-            let lp = || lp_for_sub(deps.as_ref(), &accept.subscription);
-            verify_lp_eligibility(deps.as_ref(), &state, &lp)?;
-
-            pending.remove(&accept.subscription);
-        } else {
-            return contract_error("subscription must either be pending or eligible");
+        if !matches!(
+            subscription_status(deps.storage, &accept.subscription)?,
+            Some(SubscriptionStatus::Pending) | Some(SubscriptionStatus::Eligible)
+        ) {
+            return Err(ContractError::SubscriptionNotFound {
+                subscription: accept.subscription.clone(),
+                expected: "pending or eligible",
+            });
         }
 
-        accepted.insert(accept.subscription.clone());
-        asset_exchange_storage(deps.storage).save(
-            accept.subscription.as_bytes(),
-            &vec![AssetExchange {
-                investment: None,
-                commitment_in_shares: Some(
-                    state
-                        .capital_to_shares(accept.commitment_in_capital)
-                        .try_into()?,
-                ),
-                capital: None,
-                date: None,
-            }],
+        // attestations may have been revoked since the sub was proposed or upgraded
+        let lp = || lp_for_sub(deps.as_ref(), &accept.subscription);
+        verify_lp_eligibility(deps.as_ref(), &state, &lp)?;
+
+        set_subscription_status(
+            deps.storage,
+            &accept.subscription,
+            Some(SubscriptionStatus::Accepted),
+        )?;
+        record_change(
+            deps.storage,
+            ChangedEntity::Subscription,
+            accept.subscription.as_str(),
         )?;
-    }
 
-    pending_subscriptions(deps.storage).save(&pending)?;
-    eligible_subscriptions(deps.storage).save(&eligible)?;
-    accepted_subscriptions(deps.storage).save(&accepted)?;
+        if state.escrow_capital {
+            let escrowed = escrow_storage(deps.storage)
+                .may_load(accept.subscription.as_bytes())?
+                .unwrap_or_default();
+            save_escrow(
+                deps.storage,
+                &accept.subscription,
+                escrowed + accept.commitment_in_capital,
+            )?;
+            record_change(
+                deps.storage,
+                ChangedEntity::Escrow,
+                accept.subscription.as_str(),
+            )?;
+        }
+        save_initial_commitment(
+            deps.storage,
+            &state,
+            &accept.subscription,
+            accept.commitment_in_capital,
+            ExchangeProvenance {
+                issued_by: info.sender.clone(),
+                issued_at: env.block.time.seconds(),
+                batch: None,
+            },
+        )?;
+    }
 
     Ok(Response::default())
 }
 
+// an accepted sub starts with its initial commitment as its only exchange
+pub fn save_initial_commitment(
+    storage: &mut dyn Storage,
+    state: &State,
+    subscription: &Addr,
+    commitment_in_capital: u64,
+    provenance: ExchangeProvenance,
+) -> Result<(), ContractError> {
+    save_asset_exchanges(
+        storage,
+        subscription,
+        &[AssetExchange {
+            investment: None,
+            commitment_in_shares: Some(state.capital_to_shares(commitment_in_capital).try_into()?),
+            capital: None,
+            date: None,
+            required_attestation: None,
+            exchange_id: None,
+            late_fee_bps: None,
+            class: None,
+            provenance: Some(provenance),
+            price: None,
+            denom: None,
+            late_fee_paid: false,
+        }],
+    )?;
+    record_change(storage, ChangedEntity::AssetExchange, subscription.as_str())?;
+
+    Ok(())
+}
+
+pub fn try_transfer_commitment(
+    deps: DepsMut<ProvenanceQuery>,
+    env: Env,
+    info: MessageInfo,
+    from_subscription: Addr,
+    to_subscription: Addr,
+    shares: u64,
+    investment: Option<u64>,
+) -> ContractResponse {
+    let state = config_read(deps.storage).load()?;
+
+    if info.sender != state.gp {
+        return Err(ContractError::Unauthorized {
+            required: "gp",
+            action: "transfer commitment",
+        });
+    }
+
+    for subscription in [&from_subscription, &to_subscription] {
+        if !has_subscription_status(deps.storage, subscription, SubscriptionStatus::Accepted)? {
+            return Err(ContractError::SubscriptionNotFound {
+                subscription: subscription.clone(),
+                expected: "accepted",
+            });
+        }
+    }
+    if from_subscription == to_subscription {
+        return Err(ContractError::InvalidRequest {
+            reason: "commitment must be transferred to a different subscription",
+        });
+    }
+
+    let investment = investment.unwrap_or_default();
+    if shares == 0 && investment == 0 {
+        return Err(ContractError::InvalidRequest {
+            reason: "nothing to transfer",
+        });
+    }
+
+    let mut response = Response::new();
+    for (amount, denom) in [
+        (shares, &state.commitment_denom),
+        (investment, &state.investment_denom),
+    ] {
+        if amount == 0 {
+            continue;
+        }
+        let balance = deps.querier.query_balance(&from_subscription, denom)?;
+        if balance.amount.u128() < u128::from(amount) {
+            return Err(ContractError::InsufficientBalance {
+                subscription: from_subscription,
+                denom: denom.clone(),
+                required: amount,
+            });
+        }
+        response = response.add_message(transfer_marker_coins(
+            amount.into(),
+            denom,
+            to_subscription.clone(),
+            from_subscription.clone(),
+        )?);
+    }
+
+    check_transfer_ownership(
+        deps.as_ref(),
+        &state,
+        &to_subscription,
+        u128::from(shares) + u128::from(investment),
+    )?;
+
+    // each side is recorded like a completed exchange so receipts and stats stay in step
+    let provenance = ExchangeProvenance {
+        issued_by: info.sender,
+        issued_at: env.block.time.seconds(),
+        batch: None,
+    };
+    let transfer = |sign: i64| -> Result<AssetExchange, ContractError> {
+        Ok(AssetExchange {
+            investment: Some(sign * i64::try_from(investment)?).filter(|_| investment > 0),
+            commitment_in_shares: Some(sign * i64::try_from(shares)?).filter(|_| shares > 0),
+            capital: None,
+            date: None,
+            required_attestation: None,
+            exchange_id: None,
+            late_fee_bps: None,
+            class: None,
+            provenance: Some(provenance.clone()),
+            price: None,
+            denom: None,
+            late_fee_paid: false,
+        })
+    };
+    for (subscription, exchange, destination) in [
+        (
+            &from_subscription,
+            transfer(-1)?,
+            Some(to_subscription.clone()),
+        ),
+        (&to_subscription, transfer(1)?, None),
+    ] {
+        let commitment = AssetExchange {
+            investment: None,
+            ..exchange.clone()
+        };
+        record_subscription_stats(deps.storage, subscription, &[commitment], 0, &[])?;
+        record_receipts(deps.storage, &env, subscription, &[exchange], &destination)?;
+    }
+
+    Ok(response
+        .add_attribute("from_subscription", from_subscription)
+        .add_attribute("to_subscription", to_subscription))
+}
+
+pub fn try_refresh_eligibility(
+    deps: DepsMut<ProvenanceQuery>,
+    subscription: Addr,
+) -> ContractResponse {
+    let state = config_read(deps.storage).load()?;
+
+    if !state.permissionless_eligibility {
+        return Err(ContractError::InvalidRequest {
+            reason: "eligibility refresh not enabled",
+        });
+    }
+
+    if !has_subscription_status(deps.storage, &subscription, SubscriptionStatus::Pending)? {
+        return Err(ContractError::SubscriptionNotFound {
+            subscription,
+            expected: "pending",
+        });
+    }
+
+    let lp = || lp_for_sub(deps.as_ref(), &subscription);
+    verify_lp_eligibility(deps.as_ref(), &state, &lp)?;
+
+    set_subscription_status(
+        deps.storage,
+        &subscription,
+        Some(SubscriptionStatus::Eligible),
+    )?;
+    record_change(
+        deps.storage,
+        ChangedEntity::Subscription,
+        subscription.as_str(),
+    )?;
+
+    Ok(Response::new().add_attribute("eligible", subscription))
+}
+
 fn verify_lp_eligibility(
     deps: Deps<ProvenanceQuery>,
     state: &State,
     lp: &dyn Fn() -> StdResult<Addr>,
-) -> StdResult<()> {
+) -> Result<(), ContractError> {
+    if let Some(compliance_contract) = &state.compliance_contract {
+        let address = lp()?;
+        let response: EligibilityResponse = deps.querier.query_wasm_smart(
+            compliance_contract,
+            &ComplianceQueryMsg::IsEligible {
+                address: address.clone(),
+            },
+        )?;
+        return if response.eligible {
+            Ok(())
+        } else {
+            Err(ContractError::NotEligible {
+                address,
+                compliance_contract: compliance_contract.clone(),
+            })
+        };
+    }
+
     if state.required_attestations.is_empty() {
         return Ok(());
     }
 
-    let attributes: HashSet<String> = attributes(deps, &lp()?);
+    let attributes: BTreeSet<String> = attributes(deps, &lp()?);
 
-    for acceptable in &state.required_attestations {
-        if attributes.intersection(acceptable).count() == 0 {
-            return Err(StdError::generic_err(
-                "subscription owner must have one of acceptable attestations",
-            ));
+    match missing_attestation_group(&attributes, &state.required_attestations) {
+        Some(acceptable) => {
+            let mut acceptable: Vec<String> = acceptable.iter().cloned().collect();
+            acceptable.sort();
+            Err(ContractError::MissingAcceptableAttestation { acceptable })
         }
+        None => Ok(()),
     }
+}
 
-    Ok(())
+// returns the first required attestation group the attributes don't satisfy
+pub fn missing_attestation_group<'a>(
+    attributes: &BTreeSet<String>,
+    required_attestations: &'a [BTreeSet<String>],
+) -> Option<&'a BTreeSet<String>> {
+    required_attestations
+        .iter()
+        .find(|acceptable| attributes.is_disjoint(acceptable))
 }
 
-fn lp_for_sub(deps: Deps<ProvenanceQuery>, sub: &Addr) -> StdResult<Addr> {
+pub fn lp_for_sub(deps: Deps<ProvenanceQuery>, sub: &Addr) -> StdResult<Addr> {
     let sub_state: SubState = deps
         .querier
         .query_wasm_smart(sub.clone(), &SubQueryMsg::GetState {})?;
@@ -224,7 +728,7 @@ fn lp_for_sub(deps: Deps<ProvenanceQuery>, sub: &Addr) -> StdResult<Addr> {
     Ok(sub_state.lp)
 }
 
-fn attributes(deps: Deps<ProvenanceQuery>, lp: &Addr) -> HashSet<String> {
+pub fn attributes(deps: Deps<ProvenanceQuery>, lp: &Addr) -> BTreeSet<String> {
     ProvenanceQuerier::new(&deps.querier)
         .get_attributes(lp.clone(), None as Option<String>)
         .unwrap()
@@ -235,24 +739,32 @@ fn attributes(deps: Deps<ProvenanceQuery>, lp: &Addr) -> HashSet<String> {
 }
 
 #[cfg(test)]
-mod tests {
+pub mod tests {
     use super::*;
     use crate::contract::execute;
     use crate::contract::tests::default_deps;
     use crate::mock::{
-        instantiate_args, msg_at_index, wasm_smart_mock_dependencies, MockContractQuerier,
+        execute_args, instantiate_args, load_markers, marker_transfer_msg, msg_at_index, send_args,
+        wasm_smart_mock_dependencies, MockContractQuerier,
     };
     use crate::msg::HandleMsg;
+    use crate::msg::IssueAssetExchange;
     use crate::msg::QueryMsg;
     use crate::msg::RaiseState;
     use crate::query::query;
+    use crate::state::change_log_read;
     use crate::state::config;
-    use crate::state::pending_subscriptions_read;
+    use crate::state::escrow_storage_read;
+    use crate::state::subscription_agent_storage;
+    use crate::state::subscriptions_with_status;
     use crate::state::tests::to_addresses;
     use crate::state::tests::{asset_exchange_storage_read, set_accepted};
     use crate::state::tests::{set_eligible, set_pending};
-    use crate::state::State;
-    use crate::state::{accepted_subscriptions_read, eligible_subscriptions_read};
+    use crate::state::SubscriptionStatus;
+    use crate::state::{asset_exchange_storage, State};
+    use crate::state::{auto_accepted_capital, AutoAcceptConfig};
+    use crate::state::{receipt_storage_read, subscription_stats_storage_read};
+    use cosmwasm_std::coin;
     use cosmwasm_std::coins;
     use cosmwasm_std::from_binary;
     use cosmwasm_std::testing::mock_env;
@@ -261,13 +773,15 @@ mod tests {
     use cosmwasm_std::to_binary;
     use cosmwasm_std::Addr;
     use cosmwasm_std::ContractResult;
+    use cosmwasm_std::CosmosMsg;
     use cosmwasm_std::MemoryStorage;
     use cosmwasm_std::OwnedDeps;
     use cosmwasm_std::SystemResult;
+    use provwasm_std::MarkerMsgParams;
 
     pub fn mock_sub_state(
     ) -> OwnedDeps<MemoryStorage, MockApi, MockContractQuerier, ProvenanceQuery> {
-        wasm_smart_mock_dependencies(&vec![], |_, _| {
+        wasm_smart_mock_dependencies(&[], |_, _| {
             SystemResult::Ok(ContractResult::Ok(
                 to_binary(&SubState {
                     admin: Addr::unchecked("marketpalace"),
@@ -278,6 +792,7 @@ mod tests {
                     capital_denom: String::from("stable_coin"),
                     capital_per_share: 1,
                     required_capital_attribute: None,
+                    like_capital_denoms: vec![],
                 })
                 .unwrap(),
             ))
@@ -295,6 +810,8 @@ mod tests {
             mock_info("lp", &[]),
             HandleMsg::ProposeSubscription {
                 initial_commitment: Some(100),
+                owner: None,
+                forward_funds: false,
             },
         )
         .unwrap();
@@ -330,6 +847,330 @@ mod tests {
         );
     }
 
+    #[test]
+    fn propose_subscription_below_minimum() {
+        let mut deps = default_deps(Some(|state| {
+            state.min_initial_commitment = Some(1_000);
+        }));
+        let propose = |initial_commitment| HandleMsg::ProposeSubscription {
+            initial_commitment,
+            owner: None,
+            forward_funds: false,
+        };
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("lp", &[]),
+            propose(None),
+        );
+        assert_eq!(
+            ContractError::CommitmentBelowMinimum {
+                initial_commitment: 0,
+                min_initial_commitment: 1_000
+            },
+            res.unwrap_err()
+        );
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("lp", &[]),
+            propose(Some(1_050)),
+        );
+        assert_eq!(
+            ContractError::IndivisibleAmount {
+                amount: 1_050,
+                capital_per_share: 100
+            },
+            res.unwrap_err()
+        );
+
+        // verify no sub is instantiated until the floor is met
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("lp", &[]),
+            propose(Some(1_000)),
+        )
+        .unwrap();
+        assert_eq!(1, res.messages.len());
+    }
+
+    #[test]
+    fn propose_while_not_accepting_proposals() {
+        let mut deps = default_deps(None);
+        let propose = HandleMsg::ProposeSubscription {
+            initial_commitment: Some(100),
+            owner: None,
+            forward_funds: false,
+        };
+        let set_accepting = |accepting_proposals| HandleMsg::SetAcceptingProposals {
+            accepting_proposals,
+        };
+
+        // only the gp can close onboarding
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("marketpalace", &[]),
+            set_accepting(false),
+        );
+        assert_eq!(
+            ContractError::Unauthorized {
+                required: "gp",
+                action: "open or close proposals",
+            },
+            res.unwrap_err()
+        );
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            set_accepting(false),
+        )
+        .unwrap();
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("lp", &[]),
+            propose.clone(),
+        );
+        assert_eq!(ContractError::ProposalsClosed {}, res.unwrap_err());
+
+        // closing proposals isn't a pause, so the rest of the raise keeps working
+        set_accepted(&mut deps.storage, vec!["sub_1"]);
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::IssueAssetExchanges {
+                asset_exchanges: vec![IssueAssetExchange {
+                    subscription: Addr::unchecked("sub_1"),
+                    exchanges: vec![AssetExchange {
+                        capital: Some(1_000),
+                        ..AssetExchange::default()
+                    }],
+                }],
+                notify_subscriptions: false,
+                verify_capital_denom: false,
+            },
+        )
+        .unwrap();
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            set_accepting(true),
+        )
+        .unwrap();
+        let res = execute(deps.as_mut(), mock_env(), mock_info("lp", &[]), propose).unwrap();
+        assert_eq!(1, res.messages.len());
+
+        // raises saved before the toggle existed keep accepting proposals
+        let saved = String::from_utf8(cosmwasm_std::to_vec(&State::test_default()).unwrap())
+            .unwrap()
+            .replace(",\"accepting_proposals\":true", "");
+        let state: State = cosmwasm_std::from_slice(saved.as_bytes()).unwrap();
+        assert!(state.accepting_proposals);
+    }
+
+    #[test]
+    fn propose_within_proposal_limits() {
+        let mut deps = default_deps(Some(|state| {
+            state.proposal_cooldown_blocks = Some(10);
+            state.max_pending_per_lp = Some(2);
+        }));
+        let propose = || HandleMsg::ProposeSubscription {
+            initial_commitment: Some(100),
+            owner: Some(Addr::unchecked("lp")),
+            forward_funds: false,
+        };
+        let at_height = |height| {
+            let mut env = mock_env();
+            env.block.height = height;
+            env
+        };
+
+        // a sender waits out the cooldown between proposals
+        execute(
+            deps.as_mut(),
+            at_height(100),
+            mock_info("lp", &[]),
+            propose(),
+        )
+        .unwrap();
+        let res = execute(
+            deps.as_mut(),
+            at_height(105),
+            mock_info("lp", &[]),
+            propose(),
+        );
+        assert_eq!(
+            ContractError::ProposalCooldown { available_at: 110 },
+            res.unwrap_err()
+        );
+        execute(
+            deps.as_mut(),
+            at_height(105),
+            mock_info("agent", &[]),
+            propose(),
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            at_height(110),
+            mock_info("lp", &[]),
+            propose(),
+        )
+        .unwrap();
+
+        // only subs still awaiting the gp count toward the lp's limit
+        for sub in ["sub_1", "sub_2", "sub_3"] {
+            lp_subscription_storage(&mut deps.storage, &Addr::unchecked("lp"))
+                .save(sub.as_bytes(), &Addr::unchecked(sub))
+                .unwrap();
+        }
+        set_pending(&mut deps.storage, vec!["sub_1"]);
+        set_eligible(&mut deps.storage, vec!["sub_2"]);
+        set_accepted(&mut deps.storage, vec!["sub_3"]);
+        let res = execute(
+            deps.as_mut(),
+            at_height(200),
+            mock_info("lp", &[]),
+            propose(),
+        );
+        assert_eq!(
+            ContractError::PendingLimitExceeded {
+                proposer: Addr::unchecked("lp"),
+                max: 2
+            },
+            res.unwrap_err()
+        );
+
+        set_accepted(&mut deps.storage, vec!["sub_2"]);
+        execute(
+            deps.as_mut(),
+            at_height(200),
+            mock_info("lp", &[]),
+            propose(),
+        )
+        .unwrap();
+
+        // an agent's proposals count toward the agent, not the lp they were made for
+        for sub in ["sub_4", "sub_5"] {
+            lp_subscription_storage(&mut deps.storage, &Addr::unchecked("lp"))
+                .save(sub.as_bytes(), &Addr::unchecked(sub))
+                .unwrap();
+            subscription_agent_storage(&mut deps.storage)
+                .save(sub.as_bytes(), &Addr::unchecked("agent"))
+                .unwrap();
+        }
+        set_pending(&mut deps.storage, vec!["sub_1", "sub_4", "sub_5"]);
+        execute(
+            deps.as_mut(),
+            at_height(300),
+            mock_info("lp", &[]),
+            propose(),
+        )
+        .unwrap();
+        let res = execute(
+            deps.as_mut(),
+            at_height(300),
+            mock_info("agent", &[]),
+            propose(),
+        );
+        assert_eq!(
+            ContractError::PendingLimitExceeded {
+                proposer: Addr::unchecked("agent"),
+                max: 2
+            },
+            res.unwrap_err()
+        );
+    }
+
+    #[test]
+    fn propose_subscription_with_funds() {
+        let mut deps = default_deps(None);
+        let propose = |forward_funds| HandleMsg::ProposeSubscription {
+            initial_commitment: Some(100),
+            owner: None,
+            forward_funds,
+        };
+
+        // verify forwarded funds seed the sub instantiation
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("lp", &coins(50, "nhash")),
+            propose(true),
+        )
+        .unwrap();
+        let (_, _, _, funds, _) = instantiate_args::<SubInstantiateMsg>(msg_at_index(&res, 0));
+        assert_eq!(&coins(50, "nhash"), funds);
+
+        // verify only capital can be held in escrow for the proposal
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("lp", &coins(50, "nhash")),
+            propose(false),
+        );
+        assert!(res.is_err());
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("lp", &coins(1_000, "stable_coin")),
+            propose(false),
+        )
+        .unwrap();
+        let (_, _, _, funds, _) = instantiate_args::<SubInstantiateMsg>(msg_at_index(&res, 0));
+        assert_eq!(0, funds.len());
+        assert_eq!(1_000, proposing_deposit(&mut deps.storage).load().unwrap());
+    }
+
+    #[test]
+    fn propose_subscription_with_compliance_contract() {
+        let mut deps = wasm_smart_mock_dependencies(&[], |_, msg| {
+            let ComplianceQueryMsg::IsEligible { address } = from_binary(&msg).unwrap();
+            SystemResult::Ok(ContractResult::Ok(
+                to_binary(&EligibilityResponse {
+                    eligible: address == "lp",
+                })
+                .unwrap(),
+            ))
+        });
+        // the compliance contract replaces the 506c attestation check
+        config(&mut deps.storage)
+            .save(&State {
+                compliance_contract: Some(Addr::unchecked("compliance")),
+                ..State::test_default()
+            })
+            .unwrap();
+        let mut propose = |sender| {
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(sender, &[]),
+                HandleMsg::ProposeSubscription {
+                    initial_commitment: None,
+                    owner: None,
+                    forward_funds: false,
+                },
+            )
+            .unwrap()
+            .attributes[0]
+                .value
+                .clone()
+        };
+
+        assert_eq!("true", propose("lp"));
+        assert_eq!("false", propose("other"));
+    }
+
     #[test]
     fn propose_eligible_subscription() {
         let mut deps = default_deps(None);
@@ -342,6 +1183,8 @@ mod tests {
             mock_info("lp", &[]),
             HandleMsg::ProposeSubscription {
                 initial_commitment: Some(100),
+                owner: None,
+                forward_funds: false,
             },
         )
         .unwrap();
@@ -377,6 +1220,85 @@ mod tests {
         );
     }
 
+    #[test]
+    fn propose_auto_accepted_subscription() {
+        let mut deps = default_deps(Some(|state| {
+            state.auto_accept = Some(AutoAcceptConfig {
+                max_commitment: 1_000,
+                total_cap: 1_500,
+            });
+        }));
+        deps.querier.with_attributes("lp", &[("506c", "", "")]);
+        auto_accepted_capital(&mut deps.storage).save(&800).unwrap();
+        let mut propose = |sender: &str, owner: Option<&str>, initial_commitment: u64| {
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(sender, &[]),
+                HandleMsg::ProposeSubscription {
+                    initial_commitment: Some(initial_commitment),
+                    owner: owner.map(Addr::unchecked),
+                    forward_funds: false,
+                },
+            )
+            .unwrap();
+            proposing_auto_accept(deps.as_mut().storage)
+                .may_load()
+                .unwrap()
+        };
+
+        // verify commitments past the remaining cap or from unattested lps wait for the gp
+        assert_eq!(None, propose("lp", None, 1_000));
+        assert_eq!(None, propose("other", None, 500));
+
+        // verify an agent proposing for an attested lp waits for the gp
+        assert_eq!(None, propose("agent", Some("lp"), 500));
+        assert_eq!(Some(500), propose("lp", Some("lp"), 500));
+    }
+
+    #[test]
+    fn propose_subscription_on_behalf_of_owner() {
+        let mut deps = default_deps(None);
+        deps.querier.with_attributes("owner", &[("506c", "", "")]);
+
+        // propose a sub from an operational wallet for the attested owner
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("ops_wallet", &[]),
+            HandleMsg::ProposeSubscription {
+                initial_commitment: None,
+                owner: Some(Addr::unchecked("owner")),
+                forward_funds: false,
+            },
+        )
+        .unwrap();
+
+        // verify owner is the sub lp and eligibility is based on the owner
+        let (_, _, msg, _, _) = instantiate_args::<SubInstantiateMsg>(msg_at_index(&res, 0));
+        assert_eq!(Addr::unchecked("owner"), msg.lp);
+        assert_eq!(
+            "true",
+            res.attributes
+                .iter()
+                .find(|attr| attr.key == "eligible")
+                .unwrap()
+                .value
+        );
+        assert_eq!(
+            "ops_wallet",
+            res.attributes
+                .iter()
+                .find(|attr| attr.key == "agent")
+                .unwrap()
+                .value
+        );
+        assert_eq!(
+            Addr::unchecked("ops_wallet"),
+            proposing_agent(&mut deps.storage).load().unwrap()
+        );
+    }
+
     #[test]
     fn close_pending_subscriptions() {
         let mut deps = default_deps(None);
@@ -389,6 +1311,8 @@ mod tests {
             mock_info("gp", &[]),
             HandleMsg::CloseSubscriptions {
                 subscriptions: to_addresses(vec!["sub_1"]),
+                treasury: None,
+                retirement: None,
             },
         )
         .unwrap();
@@ -396,13 +1320,43 @@ mod tests {
         // verify pending sub is removed
         assert_eq!(
             0,
-            pending_subscriptions_read(&deps.storage)
-                .load()
+            subscriptions_with_status(&deps.storage, SubscriptionStatus::Pending)
                 .unwrap()
                 .len()
         )
     }
 
+    #[test]
+    fn close_duplicate_subscription() {
+        let mut deps = default_deps(None);
+        set_pending(&mut deps.storage, vec!["sub_1"]);
+        set_eligible(&mut deps.storage, vec!["sub_1"]);
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::CloseSubscriptions {
+                subscriptions: to_addresses(vec!["sub_1"]),
+                treasury: None,
+                retirement: None,
+            },
+        )
+        .unwrap();
+
+        // verify the sub is removed from every set it was in
+        assert!(
+            subscriptions_with_status(&deps.storage, SubscriptionStatus::Pending)
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            subscriptions_with_status(&deps.storage, SubscriptionStatus::Eligible)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
     #[test]
     fn close_eligible_subscriptions() {
         let mut deps = default_deps(None);
@@ -414,19 +1368,96 @@ mod tests {
             mock_env(),
             mock_info("gp", &[]),
             HandleMsg::CloseSubscriptions {
-                subscriptions: to_addresses(vec!["sub_1"]),
+                subscriptions: to_addresses(vec!["sub_1"]),
+                treasury: None,
+                retirement: None,
+            },
+        )
+        .unwrap();
+
+        // verify pending sub is removed
+        assert_eq!(
+            0,
+            subscriptions_with_status(&deps.storage, SubscriptionStatus::Eligible)
+                .unwrap()
+                .len()
+        )
+    }
+
+    #[test]
+    fn close_subscriptions_with_retirement() {
+        let mut deps = default_deps(None);
+        set_eligible(&mut deps.storage, vec!["sub_1", "sub_2"]);
+        let mut close = |subscription: &str, retirement| {
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("gp", &[]),
+                HandleMsg::CloseSubscriptions {
+                    subscriptions: to_addresses(vec![subscription]),
+                    treasury: None,
+                    retirement: Some(retirement),
+                },
+            )
+        };
+
+        // verify a tombstone needs a code id to migrate to
+        assert_eq!(
+            ContractError::InvalidRequest {
+                reason: "no tombstone code id is configured"
+            },
+            close("sub_1", SubscriptionRetirement::Tombstone).unwrap_err()
+        );
+
+        let res = close("sub_1", SubscriptionRetirement::Disable).unwrap();
+        let (contract_addr, msg, _) = execute_args::<SubExecuteMsg>(msg_at_index(&res, 0));
+        assert_eq!("sub_1", contract_addr);
+        assert_eq!(SubExecuteMsg::Disable {}, msg);
+
+        let mut state = config(&mut deps.storage).load().unwrap();
+        state.tombstone_code_id = Some(9);
+        config(&mut deps.storage).save(&state).unwrap();
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::CloseSubscriptions {
+                subscriptions: to_addresses(vec!["sub_2"]),
+                treasury: None,
+                retirement: Some(SubscriptionRetirement::Tombstone),
             },
         )
         .unwrap();
-
-        // verify pending sub is removed
         assert_eq!(
-            0,
-            eligible_subscriptions_read(&deps.storage)
-                .load()
-                .unwrap()
-                .len()
+            &CosmosMsg::Wasm(WasmMsg::Migrate {
+                contract_addr: String::from("sub_2"),
+                new_code_id: 9,
+                msg: to_binary(&Empty {}).unwrap(),
+            }),
+            msg_at_index(&res, 0)
+        );
+
+        // verify closed subs are kept for audit
+        let closed: Vec<ClosedSubscription> = from_binary(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::GetClosedSubscriptions {
+                    start_after: Some(Addr::unchecked("sub_1")),
+                    limit: None,
+                },
+            )
+            .unwrap(),
         )
+        .unwrap();
+        assert_eq!(
+            vec![ClosedSubscription {
+                subscription: Addr::unchecked("sub_2"),
+                closed_at: mock_env().block.time.seconds(),
+                retirement: Some(SubscriptionRetirement::Tombstone),
+            }],
+            closed
+        );
     }
 
     #[test]
@@ -440,7 +1471,7 @@ mod tests {
                     investment: Some(1_000),
                     commitment_in_shares: Some(-1_000),
                     capital: Some(-1_000),
-                    date: None,
+                    ..AssetExchange::default()
                 }],
             )
             .unwrap();
@@ -452,6 +1483,8 @@ mod tests {
             mock_info("gp", &[]),
             HandleMsg::CloseSubscriptions {
                 subscriptions: to_addresses(vec!["sub_1"]),
+                treasury: None,
+                retirement: None,
             },
         )
         .unwrap();
@@ -459,8 +1492,7 @@ mod tests {
         // verify accepted sub is removed
         assert_eq!(
             0,
-            accepted_subscriptions_read(&deps.storage)
-                .load()
+            subscriptions_with_status(&deps.storage, SubscriptionStatus::Accepted)
                 .unwrap()
                 .len()
         );
@@ -472,11 +1504,42 @@ mod tests {
             .is_none());
     }
 
+    #[test]
+    fn close_subscriptions_releases_escrow() {
+        let mut deps = default_deps(None);
+        set_accepted(&mut deps.storage, vec!["sub_1"]);
+        escrow_storage(&mut deps.storage)
+            .save(Addr::unchecked("sub_1").as_bytes(), &5_000)
+            .unwrap();
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::CloseSubscriptions {
+                subscriptions: to_addresses(vec!["sub_1"]),
+                treasury: None,
+                retirement: None,
+            },
+        )
+        .unwrap();
+
+        // verify escrowed capital is returned to the sub
+        assert_eq!(1, res.messages.len());
+        let (to_address, coins) = send_args(msg_at_index(&res, 0));
+        assert_eq!("sub_1", to_address);
+        assert_eq!(5_000, coins.first().unwrap().amount.u128());
+        assert!(escrow_storage_read(&deps.storage)
+            .may_load(Addr::unchecked("sub_1").as_bytes())
+            .unwrap()
+            .is_none());
+    }
+
     #[test]
     fn close_subscriptions_accepted_commitment() {
         let mut deps = default_deps(None);
         config(&mut deps.storage)
-            .save(&&State::test_default())
+            .save(&State::test_default())
             .unwrap();
         set_accepted(&mut deps.storage, vec!["sub_1"]);
         deps.querier
@@ -490,6 +1553,8 @@ mod tests {
             mock_info("gp", &[]),
             HandleMsg::CloseSubscriptions {
                 subscriptions: to_addresses(vec!["sub_1"]),
+                treasury: None,
+                retirement: None,
             },
         );
 
@@ -501,7 +1566,7 @@ mod tests {
     fn close_subscriptions_accepted_investment() {
         let mut deps = default_deps(None);
         config(&mut deps.storage)
-            .save(&&State::test_default())
+            .save(&State::test_default())
             .unwrap();
         set_accepted(&mut deps.storage, vec!["sub_1"]);
         deps.querier
@@ -515,6 +1580,8 @@ mod tests {
             mock_info("gp", &[]),
             HandleMsg::CloseSubscriptions {
                 subscriptions: to_addresses(vec!["sub_1"]),
+                treasury: None,
+                retirement: None,
             },
         );
 
@@ -522,6 +1589,157 @@ mod tests {
         assert!(res.is_err());
     }
 
+    #[test]
+    fn close_subscriptions_accepted_investment_to_treasury() {
+        let mut deps = default_deps(None);
+        set_accepted(&mut deps.storage, vec!["sub_1", "treasury"]);
+        deps.querier
+            .base
+            .update_balance(Addr::unchecked("sub_1"), coins(100, "investment_coin"));
+
+        // close sub as gp, moving residual investment to treasury
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::CloseSubscriptions {
+                subscriptions: to_addresses(vec!["sub_1"]),
+                treasury: Some(Addr::unchecked("treasury")),
+                retirement: None,
+            },
+        )
+        .unwrap();
+
+        // verify investment is transferred to treasury
+        assert_eq!(1, res.messages.len());
+        assert_eq!(
+            &MarkerMsgParams::TransferMarkerCoins {
+                coin: coin(100, "investment_coin"),
+                to: Addr::unchecked("treasury"),
+                from: Addr::unchecked("sub_1"),
+            },
+            marker_transfer_msg(msg_at_index(&res, 0)),
+        );
+
+        // verify only the closed sub is removed
+        assert_eq!(
+            vec![Addr::unchecked("treasury")],
+            subscriptions_with_status(&deps.storage, SubscriptionStatus::Accepted).unwrap()
+        );
+    }
+
+    #[test]
+    fn transfer_commitment() {
+        let mut deps = default_deps(None);
+        set_accepted(&mut deps.storage, vec!["sub_1", "sub_2"]);
+        set_pending(&mut deps.storage, vec!["sub_3"]);
+        deps.querier.base.update_balance(
+            Addr::unchecked("sub_1"),
+            vec![coin(100, "commitment_coin"), coin(50, "investment_coin")],
+        );
+        let transfer = |to: &str, shares, investment| HandleMsg::TransferCommitment {
+            from_subscription: Addr::unchecked("sub_1"),
+            to_subscription: Addr::unchecked(to),
+            shares,
+            investment,
+        };
+
+        // verify both subs must be accepted
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            transfer("sub_3", 60, None),
+        );
+        assert_eq!(
+            ContractError::SubscriptionNotFound {
+                subscription: Addr::unchecked("sub_3"),
+                expected: "accepted",
+            },
+            res.unwrap_err()
+        );
+
+        // verify the sender must hold what is transferred
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            transfer("sub_2", 200, None),
+        );
+        assert_eq!(
+            ContractError::InsufficientBalance {
+                subscription: Addr::unchecked("sub_1"),
+                denom: String::from("commitment_coin"),
+                required: 200,
+            },
+            res.unwrap_err()
+        );
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            transfer("sub_2", 60, Some(20)),
+        )
+        .unwrap();
+
+        // verify commitment and investment move between the subs
+        assert_eq!(2, res.messages.len());
+        assert_eq!(
+            &MarkerMsgParams::TransferMarkerCoins {
+                coin: coin(60, "commitment_coin"),
+                to: Addr::unchecked("sub_2"),
+                from: Addr::unchecked("sub_1"),
+            },
+            marker_transfer_msg(msg_at_index(&res, 0)),
+        );
+        assert_eq!(
+            &MarkerMsgParams::TransferMarkerCoins {
+                coin: coin(20, "investment_coin"),
+                to: Addr::unchecked("sub_2"),
+                from: Addr::unchecked("sub_1"),
+            },
+            marker_transfer_msg(msg_at_index(&res, 1)),
+        );
+
+        // verify each side of the transfer is receipted
+        let receipt = receipt_storage_read(&deps.storage, &Addr::unchecked("sub_1"))
+            .load(&1u64.to_be_bytes())
+            .unwrap();
+        assert_eq!(-60, receipt.commitment_in_shares);
+        assert_eq!(-20, receipt.investment);
+        assert_eq!(Addr::unchecked("sub_2"), receipt.destination);
+        assert_eq!(
+            60,
+            subscription_stats_storage_read(&deps.storage)
+                .load(b"sub_2")
+                .unwrap()
+                .commitment
+        );
+    }
+
+    #[test]
+    fn close_subscriptions_treasury_not_accepted() {
+        let mut deps = default_deps(None);
+        set_accepted(&mut deps.storage, vec!["sub_1"]);
+        deps.querier
+            .base
+            .update_balance(Addr::unchecked("sub_1"), coins(100, "investment_coin"));
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::CloseSubscriptions {
+                subscriptions: to_addresses(vec!["sub_1"]),
+                treasury: Some(Addr::unchecked("treasury")),
+                retirement: None,
+            },
+        );
+
+        assert!(res.is_err());
+    }
+
     #[test]
     fn close_subscriptions_bad_actor() {
         let mut deps = default_deps(None);
@@ -534,6 +1752,8 @@ mod tests {
             mock_info("bad_actor", &[]),
             HandleMsg::CloseSubscriptions {
                 subscriptions: to_addresses(vec!["sub_1"]),
+                treasury: None,
+                retirement: None,
             },
         );
 
@@ -552,6 +1772,8 @@ mod tests {
             mock_info("gp", &[]),
             HandleMsg::CloseSubscriptions {
                 subscriptions: to_addresses(vec!["sub_2"]),
+                treasury: None,
+                retirement: None,
             },
         );
 
@@ -585,6 +1807,51 @@ mod tests {
         assert_eq!(1, state.eligible_subscriptions.len());
     }
 
+    #[test]
+    fn refresh_eligibility() {
+        let mut deps = mock_sub_state();
+        config(&mut deps.storage)
+            .save(&State::test_default())
+            .unwrap();
+        set_pending(&mut deps.storage, vec!["sub_1"]);
+
+        // verify refresh is rejected unless enabled for the raise
+        let refresh = HandleMsg::RefreshEligibility {
+            subscription: Addr::unchecked("sub_1"),
+        };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("anyone", &[]),
+            refresh.clone(),
+        );
+        assert!(res.is_err());
+
+        config(&mut deps.storage)
+            .save(&State {
+                permissionless_eligibility: true,
+                ..State::test_default()
+            })
+            .unwrap();
+
+        // verify a sub whose lp is not yet attested stays pending
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("anyone", &[]),
+            refresh.clone(),
+        );
+        assert!(res.is_err());
+
+        deps.querier.base.with_attributes("lp", &[("506c", "", "")]);
+        execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), refresh).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetState {}).unwrap();
+        let state: RaiseState = from_binary(&res).unwrap();
+        assert_eq!(0, state.pending_subscriptions.len());
+        assert_eq!(1, state.eligible_subscriptions.len());
+    }
+
     #[test]
     fn upgrade_eligible_subscriptions_as_admin() {
         let mut deps = mock_sub_state();
@@ -667,20 +1934,124 @@ mod tests {
         // verify asset exchange exists
         assert_eq!(
             &AssetExchange {
-                investment: None,
                 commitment_in_shares: Some(200),
-                capital: None,
-                date: None,
+                ..AssetExchange::default()
             },
-            asset_exchange_storage_read(&mut deps.storage)
+            asset_exchange_storage_read(&deps.storage)
                 .load(Addr::unchecked("sub_1").as_bytes())
                 .unwrap()
-                .iter()
-                .next()
+                .first()
                 .unwrap()
         )
     }
 
+    #[test]
+    fn accept_subscriptions_over_ownership_limit() {
+        let mut deps = mock_sub_state();
+        load_markers(&mut deps.querier.base);
+        deps.querier.base.with_attributes("lp", &[("506c", "", "")]);
+        config(&mut deps.storage)
+            .save(&State {
+                max_ownership_bps: Some(5_000),
+                ..State::test_default()
+            })
+            .unwrap();
+        set_pending(&mut deps.storage, vec!["sub_1", "sub_2"]);
+        let accept = |subscription: &str| AcceptSubscription {
+            subscription: Addr::unchecked(subscription),
+            commitment_in_capital: 100_000,
+        };
+
+        // verify a single large sub would own too much of the fund
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::AcceptSubscriptions {
+                subscriptions: vec![accept("sub_1")],
+            },
+        );
+        assert_eq!(
+            ContractError::OwnershipLimitExceeded {
+                subscription: Addr::unchecked("sub_1"),
+                ownership_bps: 5_434,
+                max_ownership_bps: 5_000,
+            },
+            res.unwrap_err()
+        );
+
+        // verify accepting alongside another sub keeps both under the cap
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::AcceptSubscriptions {
+                subscriptions: vec![accept("sub_1"), accept("sub_2")],
+            },
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn accept_subscription_with_escrow() {
+        let mut deps = mock_sub_state();
+        deps.querier.base.with_attributes("lp", &[("506c", "", "")]);
+        config(&mut deps.storage)
+            .save(&State {
+                escrow_capital: true,
+                ..State::test_default()
+            })
+            .unwrap();
+        set_pending(&mut deps.storage, vec!["sub_1"]);
+        let accepts = vec![AcceptSubscription {
+            subscription: Addr::unchecked("sub_1"),
+            commitment_in_capital: 20_000,
+        }];
+
+        // accept without forwarding the commitment
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &coins(10_000, "stable_coin")),
+            HandleMsg::AcceptSubscriptions {
+                subscriptions: accepts.clone(),
+            },
+        );
+        assert!(res.is_err());
+
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &coins(20_000, "stable_coin")),
+            HandleMsg::AcceptSubscriptions {
+                subscriptions: accepts,
+            },
+        )
+        .unwrap();
+
+        // verify the accept is recorded in the change log
+        let changes = change_log_read(&deps.storage)
+            .range(None, None, cosmwasm_std::Order::Ascending)
+            .map(|item| item.unwrap().1.entity)
+            .collect::<Vec<ChangedEntity>>();
+        assert_eq!(
+            vec![
+                ChangedEntity::Subscription,
+                ChangedEntity::Escrow,
+                ChangedEntity::AssetExchange
+            ],
+            changes
+        );
+
+        // verify commitment is escrowed for the sub
+        assert_eq!(
+            20_000,
+            escrow_storage_read(&deps.storage)
+                .load(Addr::unchecked("sub_1").as_bytes())
+                .unwrap()
+        );
+    }
+
     #[test]
     fn accept_eligible_subscription() {
         let mut deps = mock_sub_state();
@@ -715,20 +2086,75 @@ mod tests {
         // verify asset exchange exists
         assert_eq!(
             &AssetExchange {
-                investment: None,
                 commitment_in_shares: Some(200),
-                capital: None,
-                date: None,
+                ..AssetExchange::default()
             },
-            asset_exchange_storage_read(&mut deps.storage)
+            asset_exchange_storage_read(&deps.storage)
                 .load(Addr::unchecked("sub_1").as_bytes())
                 .unwrap()
-                .iter()
-                .next()
+                .first()
                 .unwrap()
         )
     }
 
+    #[test]
+    fn accept_eligible_subscription_lost_attestation() {
+        let mut deps = mock_sub_state();
+        deps.querier.base.with_attributes("lp", &[("506b", "", "")]);
+        config(&mut deps.storage)
+            .save(&State::test_default())
+            .unwrap();
+        set_eligible(&mut deps.storage, vec!["sub_1"]);
+
+        // accept eligible sub whose lp no longer has the attestation
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("gp", &[]),
+            HandleMsg::AcceptSubscriptions {
+                subscriptions: vec![AcceptSubscription {
+                    subscription: Addr::unchecked("sub_1"),
+                    commitment_in_capital: 20_000,
+                }],
+            },
+        );
+        assert_eq!(
+            ContractError::MissingAcceptableAttestation {
+                acceptable: vec![String::from("506c")],
+            },
+            res.unwrap_err()
+        );
+
+        // verify sub remains eligible
+        assert_eq!(
+            1,
+            subscriptions_with_status(&deps.storage, SubscriptionStatus::Eligible)
+                .unwrap()
+                .len()
+        );
+    }
+
+    #[test]
+    fn missing_attestation_group_reports_failed_group() {
+        let required = vec![
+            vec![String::from("506c"), String::from("506b")]
+                .into_iter()
+                .collect(),
+            vec![String::from("qp")].into_iter().collect(),
+        ];
+
+        let attributes: BTreeSet<String> = vec![String::from("506b")].into_iter().collect();
+        assert_eq!(
+            Some(&required[1]),
+            missing_attestation_group(&attributes, &required)
+        );
+
+        let attributes: BTreeSet<String> = vec![String::from("506c"), String::from("qp")]
+            .into_iter()
+            .collect();
+        assert_eq!(None, missing_attestation_group(&attributes, &required));
+    }
+
     #[test]
     fn accept_subscription_bad_actor() {
         let mut deps = mock_sub_state();
@@ -0,0 +1,583 @@
+// A small in-process chain for driving the raise end to end. It stands in for the
+// bank, marker and wasm modules: messages the raise emits are executed against a
+// balance ledger and marker registry, subscriptions are instantiated as stub
+// contracts, and replies are routed back to the raise as the chain would.
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::rc::Rc;
+
+use cosmwasm_std::testing::{mock_env, mock_info, MockApi, MockStorage, MOCK_CONTRACT_ADDR};
+use cosmwasm_std::{
+    from_binary, from_slice, to_binary, Addr, BankMsg, Binary, Coin, ContractResult, CosmosMsg,
+    Decimal, Env, Event, Order, OwnedDeps, Reply, ReplyOn, Response, Storage, SubMsg,
+    SubMsgResponse, SubMsgResult, SystemError, SystemResult, Uint128, WasmMsg, WasmQuery,
+};
+use provwasm_mocks::{mock_dependencies, ProvenanceMockQuerier};
+use provwasm_std::{
+    AccessGrant, Marker, MarkerAccess, MarkerMsgParams, MarkerStatus, ProvenanceMsg,
+    ProvenanceMsgParams, ProvenanceQuery,
+};
+
+use marketpalace_raise_contract::contract::{execute, reply};
+use marketpalace_raise_contract::instantiate::instantiate;
+use marketpalace_raise_contract::msg::AssetExchange;
+use marketpalace_raise_contract::query::query;
+use marketpalace_raise_contract::{
+    HandleMsg, InstantiateMsg, QueryMsg, SubExecuteMsg, SubInstantiateMsg, SubQueryMsg, SubState,
+};
+
+pub type ChainDeps = OwnedDeps<MockStorage, MockApi, ProvenanceMockQuerier, ProvenanceQuery>;
+
+pub const SUBSCRIPTION_CODE_ID: u64 = 7;
+
+// the minimal subscription contract: it keeps its state for the raise to query
+// and records what the raise sends it
+#[derive(Clone, Debug)]
+pub struct StubSubscription {
+    pub state: SubState,
+    pub initial_commitment: Option<u64>,
+    pub received: Vec<SubExecuteMsg>,
+    pub migrated_to: Option<u64>,
+}
+
+type Subscriptions = Rc<RefCell<BTreeMap<String, StubSubscription>>>;
+
+// everything a failed transaction has to roll back
+struct Snapshot {
+    storage: Vec<(Vec<u8>, Vec<u8>)>,
+    balances: BTreeMap<String, BTreeMap<String, u128>>,
+    markers: BTreeMap<String, Marker>,
+    subscriptions: BTreeMap<String, StubSubscription>,
+}
+
+pub struct Chain {
+    pub deps: ChainDeps,
+    pub env: Env,
+    pub raise: Addr,
+    balances: BTreeMap<String, BTreeMap<String, u128>>,
+    markers: BTreeMap<String, Marker>,
+    subscriptions: Subscriptions,
+    marker_outages: BTreeSet<String>,
+}
+
+impl Default for Chain {
+    fn default() -> Self {
+        Chain::new()
+    }
+}
+
+impl Chain {
+    pub fn new() -> Self {
+        let mut deps = mock_dependencies(&[]);
+        let subscriptions: Subscriptions = Rc::new(RefCell::new(BTreeMap::new()));
+
+        // the raise only ever asks a sub for its state
+        let stubs = subscriptions.clone();
+        deps.querier.base.update_wasm(move |query| match query {
+            WasmQuery::Smart { contract_addr, msg } => {
+                match (stubs.borrow().get(contract_addr), from_binary(msg)) {
+                    (Some(stub), Ok(SubQueryMsg::GetState {})) => {
+                        SystemResult::Ok(ContractResult::Ok(to_binary(&stub.state).unwrap()))
+                    }
+                    _ => SystemResult::Err(SystemError::NoSuchContract {
+                        addr: contract_addr.clone(),
+                    }),
+                }
+            }
+            _ => SystemResult::Err(SystemError::UnsupportedRequest {
+                kind: String::from("wasm"),
+            }),
+        });
+
+        Chain {
+            deps,
+            env: mock_env(),
+            raise: Addr::unchecked(MOCK_CONTRACT_ADDR),
+            balances: BTreeMap::new(),
+            markers: BTreeMap::new(),
+            subscriptions,
+            marker_outages: BTreeSet::new(),
+        }
+    }
+
+    pub fn instantiate(&mut self, sender: &str, msg: InstantiateMsg) -> Result<(), String> {
+        self.transaction(|chain| {
+            let response = instantiate(
+                chain.deps.as_mut(),
+                chain.env.clone(),
+                mock_info(sender, &[]),
+                msg,
+            )
+            .map_err(|e| e.to_string())?;
+            chain.process(&chain.raise.clone(), response)
+        })
+    }
+
+    // funds are moved to the raise before it executes, as the wasm module does
+    pub fn execute(&mut self, sender: &str, msg: HandleMsg, funds: &[Coin]) -> Result<(), String> {
+        let sender = Addr::unchecked(sender);
+        self.transaction(|chain| chain.execute_raise(&sender, msg, funds))
+    }
+
+    // has a stub sub settle exchanges with the raise, paying from its own balance
+    pub fn complete_asset_exchange(
+        &mut self,
+        subscription: &Addr,
+        exchanges: Vec<AssetExchange>,
+        funds: &[Coin],
+    ) -> Result<(), String> {
+        self.execute(
+            subscription.as_str(),
+            HandleMsg::CompleteAssetExchange {
+                exchanges,
+                to: None,
+                memo: None,
+            },
+            funds,
+        )
+    }
+
+    pub fn query<T: serde::de::DeserializeOwned>(&self, msg: QueryMsg) -> T {
+        from_binary(&query(self.deps.as_ref(), self.env.clone(), msg).unwrap()).unwrap()
+    }
+
+    // coins an account holds from outside the raise, like an lp's capital
+    pub fn fund(&mut self, address: &str, amount: u128, denom: &str) {
+        self.credit(address, amount, denom);
+    }
+
+    pub fn balance(&self, address: &Addr, denom: &str) -> u128 {
+        self.balances
+            .get(address.as_str())
+            .and_then(|balances| balances.get(denom))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn marker(&self, denom: &str) -> Option<&Marker> {
+        self.markers.get(denom)
+    }
+
+    pub fn supply(&self, denom: &str) -> u128 {
+        self.marker(denom)
+            .map(|marker| (Uint128::new(1) * marker.total_supply).u128())
+            .unwrap_or_default()
+    }
+
+    // the marker module rejects every message for the denom until the outage ends
+    pub fn marker_outage(&mut self, denom: &str, down: bool) {
+        if down {
+            self.marker_outages.insert(String::from(denom));
+        } else {
+            self.marker_outages.remove(denom);
+        }
+    }
+
+    pub fn subscription(&self, address: &Addr) -> StubSubscription {
+        self.subscriptions
+            .borrow()
+            .get(address.as_str())
+            .cloned()
+            .unwrap_or_else(|| panic!("no subscription at {}", address))
+    }
+
+    fn transaction(
+        &mut self,
+        f: impl FnOnce(&mut Chain) -> Result<(), String>,
+    ) -> Result<(), String> {
+        let snapshot = self.snapshot();
+        let result = f(self);
+        if result.is_err() {
+            self.restore(snapshot);
+        }
+        result
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            storage: self
+                .deps
+                .storage
+                .range(None, None, Order::Ascending)
+                .collect(),
+            balances: self.balances.clone(),
+            markers: self.markers.clone(),
+            subscriptions: self.subscriptions.borrow().clone(),
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.deps.storage = MockStorage::default();
+        for (key, value) in snapshot.storage {
+            self.deps.storage.set(&key, &value);
+        }
+        // accounts first credited after the snapshot are emptied too
+        let touched: Vec<String> = self.balances.keys().cloned().collect();
+        self.balances = snapshot.balances;
+        for address in touched {
+            self.deps
+                .querier
+                .base
+                .update_balance(address.as_str(), self.coins(&address));
+        }
+        self.markers = snapshot.markers;
+        self.deps
+            .querier
+            .with_markers(self.markers.values().cloned().collect());
+        *self.subscriptions.borrow_mut() = snapshot.subscriptions;
+    }
+
+    fn execute_raise(
+        &mut self,
+        sender: &Addr,
+        msg: HandleMsg,
+        funds: &[Coin],
+    ) -> Result<(), String> {
+        let raise = self.raise.clone();
+        for coin in funds {
+            self.transfer(sender.as_str(), raise.as_str(), coin)?;
+        }
+        let response = execute(
+            self.deps.as_mut(),
+            self.env.clone(),
+            mock_info(sender.as_str(), funds),
+            msg,
+        )
+        .map_err(|e| e.to_string())?;
+        self.process(&self.raise.clone(), response)
+    }
+
+    // runs a response's submessages in order, replying to the raise as requested
+    fn process(&mut self, sender: &Addr, response: Response<ProvenanceMsg>) -> Result<(), String> {
+        for SubMsg {
+            id, msg, reply_on, ..
+        } in response.messages
+        {
+            let snapshot = self.snapshot();
+            let result = self.dispatch(sender, msg);
+            if result.is_err() {
+                self.restore(snapshot);
+            }
+            let reply_requested = matches!(
+                (&result, &reply_on),
+                (_, ReplyOn::Always) | (Ok(_), ReplyOn::Success) | (Err(_), ReplyOn::Error)
+            );
+            if !reply_requested {
+                result?;
+                continue;
+            }
+
+            let result = match result {
+                Ok(events) => SubMsgResult::Ok(SubMsgResponse { events, data: None }),
+                Err(error) => SubMsgResult::Err(error),
+            };
+            let response = reply(self.deps.as_mut(), self.env.clone(), Reply { id, result })
+                .map_err(|e| e.to_string())?;
+            self.process(&self.raise.clone(), response)?;
+        }
+
+        Ok(())
+    }
+
+    fn dispatch(
+        &mut self,
+        sender: &Addr,
+        msg: CosmosMsg<ProvenanceMsg>,
+    ) -> Result<Vec<Event>, String> {
+        match msg {
+            CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => {
+                for coin in &amount {
+                    self.transfer(sender.as_str(), &to_address, coin)?;
+                }
+                Ok(vec![])
+            }
+            CosmosMsg::Custom(ProvenanceMsg {
+                params: ProvenanceMsgParams::Marker(params),
+                ..
+            }) => self.marker_msg(sender, params).map(|_| vec![]),
+            CosmosMsg::Wasm(WasmMsg::Instantiate {
+                code_id,
+                msg,
+                funds,
+                ..
+            }) => self.instantiate_subscription(sender, code_id, msg, funds),
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr,
+                msg,
+                funds,
+            }) => {
+                if contract_addr == self.raise.as_str() {
+                    let msg: HandleMsg = from_binary(&msg).map_err(|e| e.to_string())?;
+                    return self.execute_raise(sender, msg, &funds).map(|_| vec![]);
+                }
+                for coin in &funds {
+                    self.transfer(sender.as_str(), &contract_addr, coin)?;
+                }
+                let msg: SubExecuteMsg = from_binary(&msg).map_err(|e| e.to_string())?;
+                self.stub_mut(&contract_addr, |stub| stub.received.push(msg))?;
+                Ok(vec![])
+            }
+            CosmosMsg::Wasm(WasmMsg::Migrate {
+                contract_addr,
+                new_code_id,
+                ..
+            }) => {
+                self.stub_mut(&contract_addr, |stub| stub.migrated_to = Some(new_code_id))?;
+                Ok(vec![])
+            }
+            msg => Err(format!("unsupported message: {:?}", msg)),
+        }
+    }
+
+    fn instantiate_subscription(
+        &mut self,
+        sender: &Addr,
+        code_id: u64,
+        msg: Binary,
+        funds: Vec<Coin>,
+    ) -> Result<Vec<Event>, String> {
+        if code_id != SUBSCRIPTION_CODE_ID {
+            return Err(format!("no code stored for id {}", code_id));
+        }
+        let msg: SubInstantiateMsg = from_slice(&msg).map_err(|e| e.to_string())?;
+        let address = format!("sub_{}", self.subscriptions.borrow().len() + 1);
+        for coin in &funds {
+            self.transfer(sender.as_str(), &address, coin)?;
+        }
+        self.subscriptions.borrow_mut().insert(
+            address.clone(),
+            StubSubscription {
+                state: SubState {
+                    admin: msg.admin,
+                    lp: msg.lp,
+                    raise: sender.clone(),
+                    commitment_denom: msg.commitment_denom,
+                    investment_denom: msg.investment_denom,
+                    capital_denom: msg.capital_denom,
+                    capital_per_share: msg.capital_per_share,
+                    required_capital_attribute: msg.required_capital_attribute,
+                    like_capital_denoms: vec![],
+                },
+                initial_commitment: msg.initial_commitment,
+                received: vec![],
+                migrated_to: None,
+            },
+        );
+
+        Ok(vec![Event::new("instantiate")
+            .add_attribute("_contract_address", address)
+            .add_attribute("code_id", code_id.to_string())])
+    }
+
+    fn marker_msg(&mut self, sender: &Addr, params: MarkerMsgParams) -> Result<(), String> {
+        let denom = match &params {
+            MarkerMsgParams::CreateMarker { coin, .. }
+            | MarkerMsgParams::MintMarkerSupply { coin }
+            | MarkerMsgParams::BurnMarkerSupply { coin }
+            | MarkerMsgParams::TransferMarkerCoins { coin, .. } => coin.denom.as_str(),
+            MarkerMsgParams::GrantMarkerAccess { denom, .. }
+            | MarkerMsgParams::FinalizeMarker { denom }
+            | MarkerMsgParams::ActivateMarker { denom } => denom.as_str(),
+            MarkerMsgParams::WithdrawCoins { marker_denom, .. } => marker_denom.as_str(),
+            _ => "",
+        };
+        if self.marker_outages.contains(denom) {
+            return Err(format!("marker module unavailable for {}", denom));
+        }
+
+        match params {
+            MarkerMsgParams::CreateMarker { coin, marker_type } => {
+                if self.markers.contains_key(&coin.denom) {
+                    return Err(format!("marker {} already exists", coin.denom));
+                }
+                self.markers.insert(
+                    coin.denom.clone(),
+                    Marker {
+                        address: Addr::unchecked(format!("marker_{}", coin.denom)),
+                        coins: vec![],
+                        account_number: self.markers.len() as u64 + 1,
+                        sequence: 0,
+                        manager: sender.to_string(),
+                        permissions: vec![],
+                        status: MarkerStatus::Proposed,
+                        denom: coin.denom,
+                        total_supply: Decimal::zero(),
+                        marker_type,
+                        supply_fixed: false,
+                    },
+                );
+            }
+            MarkerMsgParams::GrantMarkerAccess {
+                denom,
+                address,
+                permissions,
+            } => {
+                self.marker_mut(&denom)?.permissions.push(AccessGrant {
+                    permissions,
+                    address,
+                });
+            }
+            MarkerMsgParams::FinalizeMarker { denom } => {
+                self.marker_mut(&denom)?.status = MarkerStatus::Finalized;
+            }
+            MarkerMsgParams::ActivateMarker { denom } => {
+                let marker = self.marker_mut(&denom)?;
+                if marker.status != MarkerStatus::Finalized {
+                    return Err(format!("marker {} isn't finalized", denom));
+                }
+                marker.status = MarkerStatus::Active;
+            }
+            MarkerMsgParams::MintMarkerSupply { coin } => {
+                let marker = self.active_marker(sender, &coin.denom, MarkerAccess::Mint)?;
+                self.credit(marker.address.as_str(), coin.amount.u128(), &coin.denom);
+                self.adjust_supply(&coin.denom, coin.amount.u128() as i128)?;
+            }
+            MarkerMsgParams::BurnMarkerSupply { coin } => {
+                let marker = self.active_marker(sender, &coin.denom, MarkerAccess::Burn)?;
+                self.debit(marker.address.as_str(), coin.amount.u128(), &coin.denom)?;
+                self.adjust_supply(&coin.denom, -(coin.amount.u128() as i128))?;
+            }
+            MarkerMsgParams::WithdrawCoins {
+                marker_denom,
+                coin,
+                recipient,
+            } => {
+                let marker = self.active_marker(sender, &marker_denom, MarkerAccess::Withdraw)?;
+                self.transfer(marker.address.as_str(), recipient.as_str(), &coin)?;
+            }
+            MarkerMsgParams::TransferMarkerCoins { coin, to, from } => {
+                self.transfer(from.as_str(), to.as_str(), &coin)?;
+            }
+            params => return Err(format!("unsupported marker message: {:?}", params)),
+        }
+        self.deps
+            .querier
+            .with_markers(self.markers.values().cloned().collect());
+
+        Ok(())
+    }
+
+    fn marker_mut(&mut self, denom: &str) -> Result<&mut Marker, String> {
+        self.markers
+            .get_mut(denom)
+            .ok_or_else(|| format!("no marker for {}", denom))
+    }
+
+    // supply changes need an active marker and a grant to the sender
+    fn active_marker(
+        &self,
+        sender: &Addr,
+        denom: &str,
+        access: MarkerAccess,
+    ) -> Result<Marker, String> {
+        let marker = self
+            .markers
+            .get(denom)
+            .ok_or_else(|| format!("no marker for {}", denom))?;
+        if marker.status != MarkerStatus::Active {
+            return Err(format!("marker {} isn't active", denom));
+        }
+        if !marker
+            .permissions
+            .iter()
+            .any(|grant| grant.address == *sender && grant.permissions.contains(&access))
+        {
+            return Err(format!("{} lacks {:?} on {}", sender, access, denom));
+        }
+        Ok(marker.clone())
+    }
+
+    fn adjust_supply(&mut self, denom: &str, change: i128) -> Result<(), String> {
+        let marker = self.marker_mut(denom)?;
+        let supply = (Uint128::new(1) * marker.total_supply).u128() as i128 + change;
+        marker.total_supply = Decimal::from_ratio(supply as u128, 1u128);
+        let address = marker.address.to_string();
+        self.sync_marker_coins(&address);
+        Ok(())
+    }
+
+    fn stub_mut(
+        &mut self,
+        address: &str,
+        f: impl FnOnce(&mut StubSubscription),
+    ) -> Result<(), String> {
+        match self.subscriptions.borrow_mut().get_mut(address) {
+            Some(stub) => {
+                f(stub);
+                Ok(())
+            }
+            None => Err(format!("no contract at {}", address)),
+        }
+    }
+
+    fn transfer(&mut self, from: &str, to: &str, coin: &Coin) -> Result<(), String> {
+        self.debit(from, coin.amount.u128(), &coin.denom)?;
+        self.credit(to, coin.amount.u128(), &coin.denom);
+        Ok(())
+    }
+
+    fn credit(&mut self, address: &str, amount: u128, denom: &str) {
+        *self
+            .balances
+            .entry(String::from(address))
+            .or_default()
+            .entry(String::from(denom))
+            .or_default() += amount;
+        self.sync_balance(address);
+    }
+
+    fn debit(&mut self, address: &str, amount: u128, denom: &str) -> Result<(), String> {
+        let balance = self
+            .balances
+            .entry(String::from(address))
+            .or_default()
+            .entry(String::from(denom))
+            .or_default();
+        if *balance < amount {
+            return Err(format!(
+                "{} has {}{} but needs {}{}",
+                address, balance, denom, amount, denom
+            ));
+        }
+        *balance -= amount;
+        self.sync_balance(address);
+        Ok(())
+    }
+
+    fn coins(&self, address: &str) -> Vec<Coin> {
+        self.balances
+            .get(address)
+            .map(|balances| {
+                balances
+                    .iter()
+                    .filter(|(_, amount)| **amount > 0)
+                    .map(|(denom, amount)| Coin::new(*amount, denom))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    // the raise reads balances and markers through its querier, so keep both current
+    fn sync_balance(&mut self, address: &str) {
+        self.deps
+            .querier
+            .base
+            .update_balance(address, self.coins(address));
+        self.sync_marker_coins(address);
+    }
+
+    fn sync_marker_coins(&mut self, address: &str) {
+        let coins = self.coins(address);
+        if let Some(marker) = self
+            .markers
+            .values_mut()
+            .find(|marker| marker.address.as_str() == address)
+        {
+            marker.coins = coins;
+            self.deps
+                .querier
+                .with_markers(self.markers.values().cloned().collect());
+        }
+    }
+}
@@ -0,0 +1,282 @@
+mod harness;
+
+use cosmwasm_std::{coin, Addr};
+use provwasm_std::{MarkerAccess, MarkerStatus};
+
+use harness::{Chain, SUBSCRIPTION_CODE_ID};
+use marketpalace_raise_contract::msg::{
+    AcceptSubscription, AssetExchange, IssueAssetExchange, MarkerSetup, RaiseState,
+    SubscriptionAudit,
+};
+use marketpalace_raise_contract::state::FundMetadata;
+use marketpalace_raise_contract::{HandleMsg, InstantiateMsg, QueryMsg, SubExecuteMsg};
+
+const CAPITAL: &str = "stable_coin";
+
+fn instantiate_msg() -> InstantiateMsg {
+    InstantiateMsg {
+        subscription_code_id: SUBSCRIPTION_CODE_ID,
+        recovery_admin: Addr::unchecked("marketpalace"),
+        required_attestations: vec![],
+        capital_denom: String::from(CAPITAL),
+        capital_per_share: 100,
+        required_capital_attribute: None,
+        due_grace_seconds: None,
+        escrow_capital: false,
+        permissionless_eligibility: false,
+        share_classes: vec![],
+        initial_accepted_subscriptions: vec![],
+        metadata: FundMetadata::default(),
+        min_initial_commitment: None,
+        denom_decimals: vec![],
+    }
+}
+
+fn exchange(
+    investment: Option<i64>,
+    commitment: Option<i64>,
+    capital: Option<i64>,
+) -> AssetExchange {
+    AssetExchange {
+        investment,
+        commitment_in_shares: commitment,
+        capital,
+        ..AssetExchange::default()
+    }
+}
+
+fn issue(chain: &mut Chain, subscription: &Addr, exchanges: Vec<AssetExchange>) {
+    chain
+        .execute(
+            "gp",
+            HandleMsg::IssueAssetExchanges {
+                asset_exchanges: vec![IssueAssetExchange {
+                    subscription: subscription.clone(),
+                    exchanges,
+                }],
+                notify_subscriptions: true,
+                verify_capital_denom: true,
+            },
+            &[],
+        )
+        .unwrap();
+}
+
+fn outstanding(chain: &Chain, subscription: &Addr) -> Vec<AssetExchange> {
+    chain.query(QueryMsg::GetAssetExchangesForSubscription {
+        subscription: subscription.clone(),
+    })
+}
+
+#[test]
+fn propose_accept_call_and_claim() {
+    let mut chain = Chain::new();
+    chain.instantiate("gp", instantiate_msg()).unwrap();
+    let state: RaiseState = chain.query(QueryMsg::GetState {});
+    let commitment_denom = state.general.commitment_denom.clone();
+    let investment_denom = state.general.investment_denom.clone();
+
+    // both markers are created, active and managed by the raise
+    for denom in [&commitment_denom, &investment_denom] {
+        let marker = chain.marker(denom).unwrap();
+        assert_eq!(MarkerStatus::Active, marker.status);
+        assert!(marker
+            .permissions
+            .iter()
+            .any(|grant| grant.address == chain.raise
+                && grant.permissions.contains(&MarkerAccess::Mint)
+                && grant.permissions.contains(&MarkerAccess::Burn)
+                && grant.permissions.contains(&MarkerAccess::Withdraw)));
+    }
+
+    // the proposal instantiates a sub, whose reply links it to the lp
+    chain
+        .execute(
+            "lp",
+            HandleMsg::ProposeSubscription {
+                initial_commitment: Some(10_000),
+                owner: None,
+                forward_funds: false,
+            },
+            &[],
+        )
+        .unwrap();
+    let subs: Vec<Addr> = chain.query(QueryMsg::GetSubscriptionForLp {
+        lp: Addr::unchecked("lp"),
+    });
+    assert_eq!(1, subs.len());
+    let sub = subs[0].clone();
+    let stub = chain.subscription(&sub);
+    assert_eq!(Addr::unchecked("lp"), stub.state.lp);
+    assert_eq!(chain.raise, stub.state.raise);
+    assert_eq!(commitment_denom, stub.state.commitment_denom);
+    assert_eq!(Some(10_000), stub.initial_commitment);
+    let state: RaiseState = chain.query(QueryMsg::GetState {});
+    assert_eq!(vec![sub.clone()], state.eligible_subscriptions);
+    let audit: SubscriptionAudit = chain.query(QueryMsg::AuditSubscription {
+        subscription: sub.clone(),
+    });
+    assert!(audit.consistent);
+
+    chain
+        .execute(
+            "gp",
+            HandleMsg::AcceptSubscriptions {
+                subscriptions: vec![AcceptSubscription {
+                    subscription: sub.clone(),
+                    commitment_in_capital: 10_000,
+                }],
+            },
+            &[],
+        )
+        .unwrap();
+    let state: RaiseState = chain.query(QueryMsg::GetState {});
+    assert_eq!(vec![sub.clone()], state.accepted_subscriptions);
+
+    // claiming the initial commitment mints it to the sub
+    let commitment = outstanding(&chain, &sub);
+    assert_eq!(vec![exchange(None, Some(100), None)], commitment);
+    chain
+        .complete_asset_exchange(&sub, commitment, &[])
+        .unwrap();
+    assert_eq!(100, chain.balance(&sub, &commitment_denom));
+    assert_eq!(100, chain.supply(&commitment_denom));
+
+    // a capital call is pushed to the sub, which pays it in capital and commitment
+    let call = exchange(Some(50), Some(-50), Some(-5_000));
+    issue(&mut chain, &sub, vec![call.clone()]);
+    assert_eq!(
+        vec![SubExecuteMsg::IssueCapitalCall {
+            capital_call: call.clone()
+        }],
+        chain.subscription(&sub).received
+    );
+
+    // the lp funds the sub, which burns commitment for investment as it pays
+    chain.fund(sub.as_str(), 5_000, CAPITAL);
+    chain
+        .complete_asset_exchange(
+            &sub,
+            vec![call],
+            &[coin(50, &commitment_denom), coin(5_000, CAPITAL)],
+        )
+        .unwrap();
+    assert_eq!(50, chain.balance(&sub, &investment_denom));
+    assert_eq!(50, chain.balance(&sub, &commitment_denom));
+    assert_eq!(50, chain.supply(&investment_denom));
+    assert_eq!(50, chain.supply(&commitment_denom));
+    assert_eq!(0, chain.balance(&sub, CAPITAL));
+    assert_eq!(5_000, chain.balance(&chain.raise, CAPITAL));
+
+    // a distribution and a redemption settled together
+    let distribution = exchange(None, None, Some(1_000));
+    let redemption = exchange(Some(-20), None, Some(2_000));
+    issue(
+        &mut chain,
+        &sub,
+        vec![distribution.clone(), redemption.clone()],
+    );
+    chain
+        .complete_asset_exchange(
+            &sub,
+            vec![distribution, redemption],
+            &[coin(20, &investment_denom)],
+        )
+        .unwrap();
+    assert_eq!(30, chain.balance(&sub, &investment_denom));
+    assert_eq!(30, chain.supply(&investment_denom));
+    assert_eq!(3_000, chain.balance(&sub, CAPITAL));
+    assert_eq!(2_000, chain.balance(&chain.raise, CAPITAL));
+    assert!(outstanding(&chain, &sub).is_empty());
+}
+
+#[test]
+fn failed_settlement_is_rolled_back() {
+    let mut chain = Chain::new();
+    chain.instantiate("gp", instantiate_msg()).unwrap();
+    chain
+        .execute(
+            "lp",
+            HandleMsg::ProposeSubscription {
+                initial_commitment: Some(10_000),
+                owner: None,
+                forward_funds: false,
+            },
+            &[],
+        )
+        .unwrap();
+    let sub = Addr::unchecked("sub_1");
+    chain
+        .execute(
+            "gp",
+            HandleMsg::AcceptSubscriptions {
+                subscriptions: vec![AcceptSubscription {
+                    subscription: sub.clone(),
+                    commitment_in_capital: 10_000,
+                }],
+            },
+            &[],
+        )
+        .unwrap();
+    let commitment = outstanding(&chain, &sub);
+    chain
+        .complete_asset_exchange(&sub, commitment, &[])
+        .unwrap();
+    let call = exchange(Some(50), Some(-50), Some(-5_000));
+    issue(&mut chain, &sub, vec![call.clone()]);
+
+    // the sub holds the capital but attaches no commitment, so the burn can't be funded
+    chain.fund(sub.as_str(), 5_000, CAPITAL);
+    let state: RaiseState = chain.query(QueryMsg::GetState {});
+    let commitment_denom = state.general.commitment_denom;
+    assert!(chain
+        .complete_asset_exchange(&sub, vec![call.clone()], &[coin(5_000, CAPITAL)])
+        .is_err());
+    assert_eq!(vec![call], outstanding(&chain, &sub));
+    assert_eq!(5_000, chain.balance(&sub, CAPITAL));
+    assert_eq!(0, chain.balance(&chain.raise, CAPITAL));
+    assert_eq!(100, chain.balance(&sub, &commitment_denom));
+    assert_eq!(100, chain.supply(&commitment_denom));
+}
+
+#[test]
+fn marker_setup_retried_after_failure() {
+    let mut chain = Chain::new();
+    let investment_denom = format!("{}.investment", chain.raise);
+    chain.marker_outage(&investment_denom, true);
+
+    // the raise still instantiates, with the failed marker recorded for a retry
+    chain.instantiate("gp", instantiate_msg()).unwrap();
+    assert!(chain.marker(&investment_denom).is_none());
+    let setups: Vec<MarkerSetup> = chain.query(QueryMsg::GetMarkerSetup {});
+    assert!(setups[0].is_complete());
+    assert!(setups[1].completed.is_empty());
+    assert!(setups[1].failure.is_some());
+    let propose = |chain: &mut Chain| {
+        chain.execute(
+            "lp",
+            HandleMsg::ProposeSubscription {
+                initial_commitment: Some(10_000),
+                owner: None,
+                forward_funds: false,
+            },
+            &[],
+        )
+    };
+    assert!(propose(&mut chain).is_err());
+
+    chain.marker_outage(&investment_denom, false);
+    chain
+        .execute("marketpalace", HandleMsg::RetryMarkerSetup {}, &[])
+        .unwrap();
+    assert_eq!(
+        MarkerStatus::Active,
+        chain.marker(&investment_denom).unwrap().status
+    );
+    let setups: Vec<MarkerSetup> = chain.query(QueryMsg::GetMarkerSetup {});
+    assert!(setups.iter().all(MarkerSetup::is_complete));
+    propose(&mut chain).unwrap();
+    assert!(chain
+        .execute("marketpalace", HandleMsg::RetryMarkerSetup {}, &[])
+        .is_err());
+}